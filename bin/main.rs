@@ -41,6 +41,18 @@ struct Args {
     /// development mode. The dev flag will show metrics on performance in
     /// the UI that can be helpful for debugging.
     dev: bool,
+
+    #[clap(long, global = true)]
+    /// The profile field is the name of the profile the UI opens, which is
+    /// created if it doesn't exist. The default profile is opened if it's not
+    /// set.
+    profile: Option<String>,
+
+    #[clap(long, global = true)]
+    /// The sandbox field is what runs the UI's sandbox, `anvil` or `arbiter`
+    /// to run it in process. If it's not set, anvil runs it when installed
+    /// and arbiter otherwise.
+    sandbox: Option<app::SandboxBackend>,
 }
 
 /// Defines available subcommands for the `Arbiter` tool.
@@ -91,8 +103,8 @@ fn main() -> Result<()> {
     match &args.command {
         Some(Commands::Simulate { config_path }) => sim::run(config_path, args.verbose)?,
         Some(Commands::Analyze) => todo!(),
-        Some(Commands::Ui) => app::run(args.dev)?,
-        None => app::run(args.dev)?,
+        Some(Commands::Ui) => app::run(args.dev, args.profile.clone(), args.sandbox)?,
+        None => app::run(args.dev, args.profile.clone(), args.sandbox)?,
     }
     Ok(())
 }
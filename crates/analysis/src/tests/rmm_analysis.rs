@@ -235,3 +235,64 @@ fn read_multiple_simulation() {
         figure.create().unwrap();
     });
 }
+
+#[test]
+fn plot_theta_returns() {
+    use cfmm_math::trading_functions::rmm::{compute_price_given_x_rust, decompose_value_change};
+
+    let file = "../../analysis/rmm/vol_targeting/static/0.json";
+    let data = SimulationData::new(file).unwrap();
+    let pool_stats = data.get_vectorized_events::<dfmm::LogPoolStatsFilter>("dfmm");
+
+    // Only LogNormal pools report a tau.
+    let pool_stats = pool_stats
+        .iter()
+        .filter(|event| !event.tau.is_zero())
+        .collect::<Vec<_>>();
+
+    let mut theta_returns = (vec![], vec![]);
+    let mut price_returns = (vec![], vec![]);
+    let (mut cumulative_theta, mut cumulative_price) = (0.0, 0.0);
+
+    for window in pool_stats.windows(2) {
+        let (prev, next) = (window[0], window[1]);
+        let liquidity = wad_to_float(prev.l);
+        let strike = wad_to_float(prev.strike);
+        let sigma = wad_to_float(prev.sigma);
+        let (tau_0, tau_1) = (wad_to_float(prev.tau), wad_to_float(next.tau));
+        let spot_0 =
+            compute_price_given_x_rust(wad_to_float(prev.rx), liquidity, strike, sigma, tau_0);
+        let spot_1 = compute_price_given_x_rust(
+            wad_to_float(next.rx),
+            wad_to_float(next.l),
+            strike,
+            sigma,
+            tau_1,
+        );
+
+        let (theta, price) = decompose_value_change(spot_0, spot_1, strike, sigma, tau_0, tau_1);
+        cumulative_theta += theta * liquidity;
+        cumulative_price += price * liquidity;
+
+        let timestamp = next.timestamp.as_u64().as_f64();
+        theta_returns.0.push(timestamp);
+        theta_returns.1.push(cumulative_theta);
+        price_returns.0.push(timestamp);
+        price_returns.1.push(cumulative_price);
+    }
+
+    let mut figure = Figure::new("theta_returns", Some((2000, 1000)));
+
+    let plot_settings = PlotSettings::new()
+        .title("Cumulative Theta Returns")
+        .labels("timestamp", "value");
+    let theta_plot = LinePlot::new(theta_returns.0, theta_returns.1).settings(plot_settings);
+    figure.add_plot(theta_plot);
+
+    let plot_settings = PlotSettings::new()
+        .title("Cumulative Price Returns")
+        .labels("timestamp", "value");
+    let price_plot = LinePlot::new(price_returns.0, price_returns.1).settings(plot_settings);
+    figure.add_plot(price_plot);
+    figure.create().unwrap();
+}
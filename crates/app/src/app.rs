@@ -36,9 +36,9 @@
 //! The Controller handles user input and updates the Model, the Presenter
 //! prepares data for the View, and the View handles rendering.
 
-use std::time::Duration;
+use std::{future::Future, time::Duration};
 
-use serde::{Deserialize, Serialize};
+use clients::rpc::RpcHealth;
 use tracing::Span;
 
 use super::{
@@ -50,8 +50,10 @@ use crate::{
     controller::{portfolio::PortfolioRoot, settings::SettingsScreen, State},
     middleware::ExcaliburMiddleware,
     model::{
-        contacts::{self, ContactValue},
-        rpcs::RPCValue,
+        contacts::{self, classify::classify_all, ContactValue, ImportedContact},
+        keystore::{Keystore, SignerEntry},
+        rpcs::{NetworkTarget, RPCValue},
+        sessions::{self, SandboxSession, SessionOrigin, SessionStore, DEFAULT_SESSION},
         user::Saveable,
     },
     view::sidebar::Sidebar,
@@ -79,25 +81,80 @@ pub enum Message {
     SwitchWindow(view::sidebar::Route),
     /// Updates the model after it has been fetched.
     ModelSyncResult(Result<Model, Arc<anyhow::Error>>),
+    /// Switches the client to a signer, with its id if it's from the
+    /// keystore.
+    ConnectSigner(Option<String>, ExcaliburSigner),
+    /// Returns the client after switching its signer, with the keystore id of
+    /// the new signer if it connected.
+    SignerConnected(
+        Arc<ExcaliburMiddleware<RpcTransport, ExcaliburSigner>>,
+        Result<Option<String>, Arc<anyhow::Error>>,
+    ),
+    /// Sends the stats of the connected endpoints to the rpc settings.
+    RefreshRpcHealth,
+    /// Switches the client and the model to another network.
+    SwitchNetwork(NetworkTarget),
+    /// Returns the client after switching networks, with the health of the
+    /// new network if it connected.
+    NetworkSwitched(
+        Arc<ExcaliburMiddleware<RpcTransport, ExcaliburSigner>>,
+        NetworkTarget,
+        Result<RpcHealth, Arc<anyhow::Error>>,
+    ),
+    /// Saves the profile, with its sandbox session, and opens the profile
+    /// with the name.
+    SwitchProfile(String),
+    /// Returns the session the sandbox was saved to before switching
+    /// profiles, if there is a sandbox.
+    ProfileSnapshotted(String, anyhow::Result<Option<String>>),
+    /// Caught by lib.rs, which reloads the app with the profile.
+    ProfileSaved(String),
+    /// Saves the sandbox to a new session with the name, which becomes the
+    /// active session.
+    SaveSession(String),
+    /// Returns the session the sandbox was saved to.
+    SessionSaved(Result<SandboxSession, Arc<anyhow::Error>>),
+    /// Saves the sandbox to the active session, then restores the session
+    /// with the name in a new sandbox.
+    RestoreSession(String),
+    /// Returns the client after restoring a session, with the session if it
+    /// was restored.
+    SessionRestored(
+        Arc<ExcaliburMiddleware<RpcTransport, ExcaliburSigner>>,
+        Result<(SandboxSession, RpcHealth), Arc<anyhow::Error>>,
+    ),
+    /// Classifies the contacts at the addresses from their code on the
+    /// connected network.
+    ClassifyContacts(Vec<Address>),
 }
 
 /// All messages for making modifications to the persistent user profile.
 #[derive(Debug)]
 pub enum UserProfileMessage {
-    /// Stores a stringified snapshot of an Anvil instance.
-    SaveAnvilSnapshot(anyhow::Result<AnvilSave>),
+    /// Sets the session the sandbox was saved to on exit as the active session.
+    SaveSession(anyhow::Result<SandboxSession>),
     /// Adds an address to the contacts list.
     AddAddress(String, Address, contacts::Category),
     /// Removes an address from the contacts list.
     RemoveAddress(String, contacts::Category),
     /// warning! Deletes all addresses from a category in the list.
     ClearAddresses(contacts::Category),
+    /// Adds contacts imported from an address book, then classifies them.
+    ImportContacts(Vec<ImportedContact>),
+    /// Sets the classes of contacts found on chain.
+    SetClasses(Vec<(Address, contacts::Class)>),
     /// Adds an RPC to the RPC list.
     AddRPC(RPCValue),
     /// Removes an RPC from the RPC list.
     RemoveRPC(String),
     /// warning! Deletes all RPCs from the list.
     ClearRPCs,
+    /// Adds an imported signer to the keystore.
+    AddSigner(SignerEntry),
+    /// Renames a signer in the keystore.
+    RenameSigner(String, String),
+    /// Removes a signer and its key from the keystore.
+    RemoveSigner(String),
 }
 
 pub type RootMessage = Message;
@@ -139,7 +196,7 @@ impl Windows {
 /// components will need.
 pub struct App {
     /// Connection to networks.
-    pub client: Arc<ExcaliburMiddleware<Ws, LocalWallet>>,
+    pub client: Arc<ExcaliburMiddleware<RpcTransport, ExcaliburSigner>>,
     /// Data module of the application.
     pub model: Model,
     /// State of the active window and sidebar the user is viewing.
@@ -195,11 +252,30 @@ impl App {
     ///   and a Command to load the application.
     pub fn new(
         model: Model,
-        client: Arc<ExcaliburMiddleware<Ws, LocalWallet>>,
+        client: Arc<ExcaliburMiddleware<RpcTransport, ExcaliburSigner>>,
     ) -> (Self, Command<Message>) {
-        let dashboard = PortfolioRoot::new(Some(client.clone()), model.clone()).into();
         let mut sidebar = Sidebar::new();
         sidebar.page = view::sidebar::Page::Portfolio;
+
+        // The app starts on the sandbox, so reconnect to the last used RPC.
+        // The dashboard holds on to the client, so it's only opened once the
+        // client is handed back from the switch.
+        let mut cmds = vec![Command::perform(async {}, |_| Message::Load)];
+        let dashboard = match model
+            .user
+            .network
+            .as_ref()
+            .and_then(|name| model.user.rpcs.target(name))
+        {
+            Some(target) => {
+                cmds.push(Command::perform(async {}, move |_| {
+                    Message::SwitchNetwork(target)
+                }));
+                EmptyScreen::new().into()
+            }
+            None => PortfolioRoot::new(Some(client.clone()), model.clone()).into(),
+        };
+
         (
             Self {
                 client,
@@ -207,7 +283,7 @@ impl App {
                 windows: Windows::new(dashboard, sidebar),
                 app_clock: AppClock::new(),
             },
-            Command::perform(async {}, |_| Message::Load),
+            Command::batch(cmds),
         )
     }
 
@@ -262,9 +338,13 @@ impl App {
 
                 // Propagate the model to the active screen.
                 // todo: remove side effects, @alex what did you mean by this?
-                self.windows
-                    .screen
-                    .update(Message::ModelSyncResult(Ok(model)))
+                Command::batch(vec![
+                    self.windows
+                        .screen
+                        .update(Message::ModelSyncResult(Ok(model))),
+                    // Syncing adds the contracts transacted with to the contacts.
+                    self.sync_contacts(),
+                ])
             }
             Message::ModelSyncResult(Err(e)) => {
                 tracing::error!(
@@ -274,6 +354,208 @@ impl App {
                 Command::none()
             }
             Message::UpdateUser(msg) => self.update_user(msg),
+            Message::ConnectSigner(id, signer) => self.connect_signer(id, signer),
+            Message::SignerConnected(client, result) => {
+                self.client = client;
+                let reopened = self.reopen_portfolio();
+                let handled = match result {
+                    Ok(id) => {
+                        tracing::info!("Connected signer {:?}", id);
+                        match id {
+                            Some(id) => {
+                                if let Err(e) = self.model.user.signers.set_active(&id) {
+                                    tracing::error!("Failed to set the active signer: {:?}", e);
+                                }
+                            }
+                            // Hardware signers aren't in the keystore.
+                            None => self.model.user.signers.active = None,
+                        }
+                        if let Err(e) = self.model.save() {
+                            tracing::error!("Failed to save profile to disk: {:?}", e);
+                        }
+                        Command::batch(vec![self.sync_signers(), self.sync_model()])
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to connect signer: {:?}", e);
+                        let feedback = settings::rpc::Feedback::Error(e.to_string());
+                        Command::perform(async {}, move |_| {
+                            view::Message::Settings(settings::Message::Signers(
+                                settings::signers::Message::Feedback(feedback),
+                            ))
+                        })
+                        .map(|x| x.into())
+                    }
+                };
+                Command::batch(vec![reopened, handled])
+            }
+            Message::RefreshRpcHealth => {
+                let health = self.client.rpc_health();
+                Command::perform(async {}, move |_| {
+                    view::Message::Settings(settings::Message::Rpc(settings::rpc::Message::Health(
+                        health,
+                    )))
+                })
+                .map(|x| x.into())
+            }
+            Message::SwitchNetwork(target) => self.switch_network(target),
+            Message::ClassifyContacts(addresses) => self.classify_contacts(addresses),
+            Message::NetworkSwitched(client, target, result) => {
+                self.client = client;
+                let feedback = match result {
+                    Ok(health) => {
+                        tracing::info!("Switched to {} at block {}", target, health.block_number);
+                        self.model.switch_network(health.chain_id);
+                        self.model.user.network = target.rpc_name();
+                        if let Err(e) = self.model.save() {
+                            tracing::error!("Failed to save profile to disk: {:?}", e);
+                        }
+                        settings::rpc::Feedback::Success(format!(
+                            "Connected to {} in {}ms",
+                            target,
+                            health.latency.as_millis()
+                        ))
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to switch to {}: {:?}", target, e);
+                        settings::rpc::Feedback::Error(format!(
+                            "Failed to connect to {}: {}",
+                            target, e
+                        ))
+                    }
+                };
+                let connected = self.model.user.network.clone();
+                Command::batch(vec![
+                    self.reopen_portfolio(),
+                    Command::perform(async {}, move |_| {
+                        view::Message::Settings(settings::Message::Rpc(
+                            settings::rpc::Message::Connected(connected),
+                        ))
+                    })
+                    .map(|x| x.into()),
+                    Command::perform(async {}, move |_| {
+                        view::Message::Settings(settings::Message::Rpc(
+                            settings::rpc::Message::Feedback(feedback),
+                        ))
+                    })
+                    .map(|x| x.into()),
+                    self.sync_model(),
+                ])
+            }
+            Message::SwitchProfile(name) => self.switch_profile(name),
+            Message::ProfileSnapshotted(name, snapshot) => {
+                match snapshot {
+                    Ok(Some(session)) => self.model.user.session = Some(session),
+                    Ok(None) => {}
+                    Err(e) => tracing::error!("Failed to save sandbox session: {:?}", e),
+                }
+
+                // Stay on this profile if it can't be saved.
+                if let Err(e) = self.model.save() {
+                    tracing::error!("Failed to save profile to disk: {:?}", e);
+                    let feedback = settings::rpc::Feedback::Error(format!(
+                        "Failed to save the profile before switching: {}",
+                        e
+                    ));
+                    return Command::perform(async {}, move |_| {
+                        view::Message::Settings(settings::Message::Profiles(
+                            settings::profiles::Message::Feedback(feedback),
+                        ))
+                    })
+                    .map(|x| x.into());
+                }
+
+                tracing::info!("Saved profile, switching to profile {}", name);
+                Command::perform(async {}, move |_| Message::ProfileSaved(name))
+            }
+            Message::ProfileSaved(_) => {
+                // Caught by the lib.rs and reloads the application.
+                Command::none()
+            }
+            Message::SaveSession(name) => {
+                if let Err(e) = sessions::validate_name(&name) {
+                    return self.session_feedback(e.into());
+                }
+                if self.client.sandbox_backend().is_none() {
+                    return self.session_feedback(settings::rpc::Feedback::Error(
+                        "Sessions can only be saved from the sandbox.".to_string(),
+                    ));
+                }
+
+                let client = self.client.clone();
+                let store = SessionStore::for_profile(&self.model.user);
+                Command::perform(
+                    async move {
+                        client
+                            .save_session(&store, &name, SessionOrigin::Manual)
+                            .await
+                            .map_err(Arc::new)
+                    },
+                    Message::SessionSaved,
+                )
+            }
+            Message::SessionSaved(result) => match result {
+                Ok(session) => {
+                    self.model.user.session = Some(session.name.clone());
+                    if let Err(e) = self.model.save() {
+                        tracing::error!("Failed to save profile to disk: {:?}", e);
+                    }
+                    Command::batch(vec![
+                        self.sync_sessions(),
+                        self.session_feedback(settings::rpc::Feedback::Success(format!(
+                            "Saved session {} at block {}",
+                            session.name, session.block_number
+                        ))),
+                    ])
+                }
+                Err(e) => {
+                    tracing::error!("Failed to save sandbox session: {:?}", e);
+                    self.session_feedback(settings::rpc::Feedback::Error(e.to_string()))
+                }
+            },
+            Message::RestoreSession(name) => self.restore_session(name),
+            Message::SessionRestored(client, result) => {
+                self.client = client;
+                let reopened = self.reopen_portfolio();
+                let handled = match result {
+                    Ok((session, health)) => {
+                        tracing::info!(
+                            "Restored session {} at block {}",
+                            session.name,
+                            health.block_number
+                        );
+                        self.model.switch_network(session.chain_id);
+                        self.model.user.session = Some(session.name.clone());
+                        self.model.user.network = None;
+                        self.model.user.contracts = self.client.contracts.clone();
+                        if let Err(e) = self.model.save() {
+                            tracing::error!("Failed to save profile to disk: {:?}", e);
+                        }
+
+                        Command::batch(vec![
+                            self.sync_sessions(),
+                            self.session_feedback(settings::rpc::Feedback::Success(format!(
+                                "Restored session {} at block {}",
+                                session.name, health.block_number
+                            ))),
+                            Command::perform(async {}, |_| {
+                                view::Message::Settings(settings::Message::Rpc(
+                                    settings::rpc::Message::Connected(None),
+                                ))
+                            })
+                            .map(|x| x.into()),
+                            self.sync_model(),
+                        ])
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to restore sandbox session: {:?}", e);
+                        self.session_feedback(settings::rpc::Feedback::Error(format!(
+                            "Failed to restore session: {}",
+                            e
+                        )))
+                    }
+                };
+                Command::batch(vec![reopened, handled])
+            }
             Message::View(view::Message::Root(msg)) => match msg {
                 view::RootMessage::ModelSyncRequest => self.sync_model(),
                 view::RootMessage::Route(route) => self.switch_window(&route),
@@ -334,7 +616,7 @@ impl App {
     /// application:
     /// 1. It saves the current profile to disk.
     /// 2. It calls the exit function on the currently opened window.
-    /// 3. If the development client is active, it saves a snapshot of the anvil
+    /// 3. If the development client is active, it saves a snapshot of the sandbox
     ///    state to the profile.
     ///
     /// # Returns
@@ -353,14 +635,11 @@ impl App {
         let cmd = self.windows.screen.exit();
         commands.push(cmd);
 
-        // If the dev client is Some, call the anvil client using `anvil_dumpState`, and
-        // set the profile's anvil snapshot to the result.
-        if self.client.anvil.is_some() {
-            let cmd = Command::perform(
-                save_snapshot(self.client.clone()),
-                UserProfileMessage::SaveAnvilSnapshot,
-            )
-            .map(Message::UpdateUser);
+        // If the dev client is Some, save the sandbox to the active session of the
+        // profile.
+        if self.client.sandbox_backend().is_some() {
+            let cmd = Command::perform(self.save_active_session(), UserProfileMessage::SaveSession)
+                .map(Message::UpdateUser);
             commands.push(cmd);
         }
 
@@ -379,8 +658,20 @@ impl App {
     /// * `Command<Message>` - A command containing the result of the model
     ///   synchronization.
     fn sync_model(&mut self) -> Command<Message> {
-        let model = self.model.clone();
-        let provider = self.client.get_client();
+        // The in-process sandbox has no network client, so the model is synced
+        // through the arbiter client.
+        match (self.client.client.clone(), self.client.arbiter_client()) {
+            (Some(provider), _) => Self::update_model(self.model.clone(), provider),
+            (None, Some(provider)) => Self::update_model(self.model.clone(), provider),
+            // The client is disconnected while its signer is being switched.
+            (None, None) => {
+                tracing::debug!("No client to sync the model with");
+                Command::none()
+            }
+        }
+    }
+
+    fn update_model<M: Middleware + 'static>(model: Model, provider: Arc<M>) -> Command<Message> {
         Command::perform(
             async move {
                 let mut model = model;
@@ -412,22 +703,28 @@ impl App {
     // #[allow(unused_assignments)]
     fn update_user(&mut self, message: UserProfileMessage) -> Command<Message> {
         let model = &mut self.model;
+        // Imported contacts are classified once they're saved.
+        let mut imported = vec![];
+        let mut classified = None;
+        // How a change to the signers went, reported to their screen once the
+        // profile is saved.
+        let mut signer_feedback = None;
         match message {
-            UserProfileMessage::SaveAnvilSnapshot(snapshot) => {
-                tracing::debug!("Saving anvil snapshot to profile");
-                match snapshot {
-                    Ok(snapshot) => {
-                        self.model.user.anvil_snapshot = Some(snapshot);
+            UserProfileMessage::SaveSession(session) => {
+                tracing::debug!("Saving sandbox session to profile");
+                match session {
+                    Ok(session) => {
+                        self.model.user.session = Some(session.name);
                         if let Err(e) = self.model.save() {
-                            tracing::error!("Failed to save anvil snapshot to file: {:?}", e);
+                            tracing::error!("Failed to save profile to file: {:?}", e);
                         } else {
-                            tracing::debug!("Saved anvil snapshot to file");
+                            tracing::debug!("Saved sandbox session to profile");
                         }
                     }
-                    Err(e) => tracing::error!("Failed to save anvil snapshot: {:?}", e),
+                    Err(e) => tracing::error!("Failed to save sandbox session: {:?}", e),
                 }
 
-                // Exits the application after saving the anvil snapshot.
+                // Exits the application after saving the sandbox session.
                 return Command::perform(async {}, |_| Message::QuitReady);
             }
             UserProfileMessage::AddAddress(name, address, category) => {
@@ -447,6 +744,15 @@ impl App {
             UserProfileMessage::ClearAddresses(category) => {
                 model.user.contacts.clear(category);
             }
+            UserProfileMessage::ImportContacts(contacts) => {
+                imported = model.user.contacts.merge(contacts);
+            }
+            UserProfileMessage::SetClasses(classes) => {
+                classified = Some(classes.len());
+                for (address, class) in classes {
+                    model.user.contacts.set_class(&address, class);
+                }
+            }
             UserProfileMessage::AddRPC(chain) => {
                 model.user.rpcs.add(chain);
             }
@@ -458,21 +764,324 @@ impl App {
             UserProfileMessage::ClearRPCs => {
                 model.user.rpcs.clear();
             }
+            UserProfileMessage::AddSigner(entry) => {
+                let id = entry.id.clone();
+                let name = entry.name.clone();
+                signer_feedback = Some(match model.user.signers.add(entry) {
+                    Ok(()) => settings::rpc::Feedback::Success(format!("Imported {}!", name)),
+                    Err(e) => {
+                        tracing::error!("Failed to add signer: {:?}", e);
+                        // Don't leave a key behind that the profile doesn't know about.
+                        if let Err(e) = std::fs::remove_file(Keystore::dir().join(id)) {
+                            tracing::error!("Failed to remove keystore file: {:?}", e);
+                        }
+                        settings::rpc::Feedback::Error(format!("Failed to import signer: {}", e))
+                    }
+                });
+            }
+            UserProfileMessage::RenameSigner(id, name) => {
+                signer_feedback = Some(match model.user.signers.rename(&id, &name) {
+                    Ok(()) => {
+                        settings::rpc::Feedback::Success(format!("Renamed signer to {}!", name))
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to rename signer: {:?}", e);
+                        settings::rpc::Feedback::Error(format!("Failed to rename signer: {}", e))
+                    }
+                });
+            }
+            UserProfileMessage::RemoveSigner(id) => {
+                tracing::debug!("Removing signer from keystore: {}", id);
+                signer_feedback = Some(match model.user.signers.delete(&Keystore::dir(), &id) {
+                    Ok(entry) => {
+                        settings::rpc::Feedback::Success(format!("Deleted {}!", entry.name))
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to remove signer: {:?}", e);
+                        settings::rpc::Feedback::Error(format!("Failed to delete signer: {}", e))
+                    }
+                });
+            }
         }
 
         let result = model.save();
         match result {
             Ok(_) => tracing::info!("Saved profile to disk"),
-            Err(e) => tracing::error!("Failed to save profile to disk: {:?}", e),
+            Err(e) => {
+                tracing::error!("Failed to save profile to disk: {:?}", e);
+                if signer_feedback.is_some() {
+                    signer_feedback = Some(settings::rpc::Feedback::Error(format!(
+                        "Failed to save profile: {}",
+                        e
+                    )));
+                }
+            }
         }
 
         let rpcs = model.user.rpcs.clone();
+        let mut commands = vec![
+            Command::perform(async {}, move |_| {
+                view::Message::Settings(settings::Message::Rpc(settings::rpc::Message::Sync(rpcs)))
+            })
+            .map(|x| x.into()),
+            self.sync_signers(),
+            self.sync_contacts(),
+        ];
+        if let Some(feedback) = signer_feedback {
+            commands.push(
+                Command::perform(async {}, move |_| {
+                    view::Message::Settings(settings::Message::Signers(
+                        settings::signers::Message::Feedback(feedback),
+                    ))
+                })
+                .map(|x| x.into()),
+            );
+        }
+        if !imported.is_empty() {
+            commands.push(self.classify_contacts(imported));
+        }
+        if let Some(count) = classified {
+            commands.push(
+                Command::perform(async {}, move |_| {
+                    view::Message::Settings(settings::Message::Contacts(
+                        settings::contacts::Message::Feedback(settings::rpc::Feedback::Success(
+                            format!("Classified {} contacts!", count),
+                        )),
+                    ))
+                })
+                .map(|x| x.into()),
+            );
+        }
+        Command::batch(commands)
+    }
+
+    /// Sends the keystore of the profile to the signers settings.
+    fn sync_signers(&self) -> Command<Message> {
+        let keystore = self.model.user.signers.clone();
+        Command::perform(async {}, move |_| {
+            view::Message::Settings(settings::Message::Signers(
+                settings::signers::Message::Sync(keystore),
+            ))
+        })
+        .map(|x| x.into())
+    }
+
+    /// Sends the contacts of the profile to the contacts settings.
+    fn sync_contacts(&self) -> Command<Message> {
+        let contacts = self.model.user.contacts.clone();
+        let chain_id = self.model.current;
         Command::perform(async {}, move |_| {
-            view::Message::Settings(settings::Message::Rpc(settings::rpc::Message::Sync(rpcs)))
+            view::Message::Settings(settings::Message::Contacts(
+                settings::contacts::Message::Sync(contacts, chain_id),
+            ))
         })
         .map(|x| x.into())
     }
 
+    /// Classifies the contacts at `addresses` on the connected network, or in
+    /// the in-process sandbox.
+    fn classify_contacts(&self, addresses: Vec<Address>) -> Command<Message> {
+        match (self.client.client.clone(), self.client.arbiter_client()) {
+            (Some(provider), _) => Self::set_classes(provider, addresses),
+            (None, Some(provider)) => Self::set_classes(provider, addresses),
+            (None, None) => Command::perform(async {}, |_| {
+                view::Message::Settings(settings::Message::Contacts(
+                    settings::contacts::Message::Feedback(
+                        anyhow::anyhow!("Connect to a network to classify contacts.").into(),
+                    ),
+                ))
+            })
+            .map(|x| x.into()),
+        }
+    }
+
+    fn set_classes<M: Middleware + 'static>(
+        provider: Arc<M>,
+        addresses: Vec<Address>,
+    ) -> Command<Message> {
+        Command::perform(classify_all(provider, addresses), |classes| {
+            UserProfileMessage::SetClasses(classes).into()
+        })
+    }
+
+    /// Switches the signer of the client to `signer`, the signer `id` of the
+    /// keystore if it has one.
+    ///
+    /// The client is shared with the screens, so it is taken out of the app
+    /// while the signer connects, and handed back with a
+    /// [`Message::SignerConnected`].
+    fn connect_signer(&mut self, id: Option<String>, signer: ExcaliburSigner) -> Command<Message> {
+        self.take_client(
+            |mut client| async move {
+                let result = client.connect_signer(signer).await.map(|_| id);
+                (client, result)
+            },
+            Message::SignerConnected,
+        )
+    }
+
+    /// Switches the client to another network, saving the sandbox to an
+    /// automatic session first. The model switches to the network once the
+    /// client has connected to it.
+    fn switch_network(&mut self, target: NetworkTarget) -> Command<Message> {
+        let target_result = target.clone();
+        let store = SessionStore::for_profile(&self.model.user);
+        self.take_client(
+            |mut client| async move {
+                if let Err(e) = client.auto_snapshot(&store, "switch network").await {
+                    tracing::warn!("Failed to save sandbox session: {:?}", e);
+                }
+                let result = client.connect_network(&target).await;
+                (client, result)
+            },
+            move |client, result| Message::NetworkSwitched(client, target_result, result),
+        )
+    }
+
+    /// Saves the sandbox to its session, if the client has one, so the profile
+    /// keeps its state when it's saved before switching to the profile `name`.
+    fn switch_profile(&mut self, name: String) -> Command<Message> {
+        if self.client.sandbox_backend().is_none() {
+            return Command::perform(async {}, move |_| {
+                Message::ProfileSnapshotted(name, Ok(None))
+            });
+        }
+
+        Command::perform(self.save_active_session(), move |session| {
+            Message::ProfileSnapshotted(name, session.map(|session| Some(session.name)))
+        })
+    }
+
+    /// Name of the session the sandbox is saved to when it's left.
+    fn active_session(&self) -> String {
+        self.model
+            .user
+            .session
+            .clone()
+            .unwrap_or(DEFAULT_SESSION.to_string())
+    }
+
+    /// Saves the sandbox to the active session of the profile.
+    fn save_active_session(&self) -> impl Future<Output = anyhow::Result<SandboxSession>> {
+        let client = self.client.clone();
+        let store = SessionStore::for_profile(&self.model.user);
+        let name = self.active_session();
+        async move {
+            client
+                .save_session(&store, &name, SessionOrigin::Manual)
+                .await
+        }
+    }
+
+    /// Saves the sandbox to the active session, then replaces it with a new
+    /// sandbox running the session `name`.
+    fn restore_session(&mut self, name: String) -> Command<Message> {
+        let store = SessionStore::for_profile(&self.model.user);
+        let active = self.active_session();
+        self.take_client(
+            |mut client| async move {
+                let result: anyhow::Result<(SandboxSession, RpcHealth)> = async {
+                    // Keep the work in the active session before leaving it.
+                    if client.sandbox_backend().is_some() && active != name {
+                        client
+                            .save_session(&store, &active, SessionOrigin::Manual)
+                            .await?;
+                    }
+                    let (session, state) = store.load(&name)?;
+                    let health = client.restore_session(&session, &state).await?;
+                    Ok((session, health))
+                }
+                .await;
+                (client, result)
+            },
+            Message::SessionRestored,
+        )
+    }
+
+    /// Sends the active session to the sessions settings, which lists the
+    /// sessions again.
+    fn sync_sessions(&self) -> Command<Message> {
+        let active = self.model.user.session.clone();
+        Command::perform(async {}, move |_| {
+            view::Message::Settings(settings::Message::Sessions(
+                settings::sessions::Message::Sync(active),
+            ))
+        })
+        .map(|x| x.into())
+    }
+
+    fn session_feedback(&self, feedback: settings::rpc::Feedback) -> Command<Message> {
+        Command::perform(async {}, move |_| {
+            view::Message::Settings(settings::Message::Sessions(
+                settings::sessions::Message::Feedback(feedback),
+            ))
+        })
+        .map(|x| x.into())
+    }
+
+    /// Takes the client out of the app to reconnect it, and hands it back in
+    /// the message built by `done`. The portfolio holds on to the client, so
+    /// it's closed until the client is handed back. Fails if a command is
+    /// still holding on to the client.
+    fn take_client<T, F, Fut>(
+        &mut self,
+        reconnect: F,
+        done: impl FnOnce(
+                Arc<ExcaliburMiddleware<RpcTransport, ExcaliburSigner>>,
+                Result<T, Arc<anyhow::Error>>,
+            ) -> Message
+            + Send
+            + 'static,
+    ) -> Command<Message>
+    where
+        T: Send + 'static,
+        F: FnOnce(ExcaliburMiddleware<RpcTransport, ExcaliburSigner>) -> Fut,
+        Fut: Future<
+                Output = (
+                    ExcaliburMiddleware<RpcTransport, ExcaliburSigner>,
+                    anyhow::Result<T>,
+                ),
+            > + Send
+            + 'static,
+    {
+        let mut cmds = Vec::new();
+        if self.windows.sidebar.page == view::sidebar::Page::Portfolio {
+            cmds.push(self.windows.screen.exit());
+            self.windows.screen = EmptyScreen::new().into();
+        }
+
+        let client = std::mem::replace(
+            &mut self.client,
+            Arc::new(ExcaliburMiddleware::disconnected()),
+        );
+        cmds.push(match Arc::try_unwrap(client) {
+            Ok(client) => Command::perform(reconnect(client), move |(client, result)| {
+                done(Arc::new(client), result.map_err(Arc::new))
+            }),
+            Err(client) => {
+                self.client = client.clone();
+                Command::perform(async {}, move |_| {
+                    done(
+                        client,
+                        Err(Arc::new(anyhow::anyhow!(
+                            "The client is in use, try again."
+                        ))),
+                    )
+                })
+            }
+        });
+        Command::batch(cmds)
+    }
+
+    /// Opens the portfolio again with the client handed back by
+    /// [`Self::take_client`], if it was open.
+    fn reopen_portfolio(&mut self) -> Command<Message> {
+        if self.windows.sidebar.page != view::sidebar::Page::Portfolio {
+            return Command::none();
+        }
+        self.switch_window(&view::sidebar::Route::Page(view::sidebar::Page::Portfolio))
+    }
+
     /// This function is responsible for switching between different windows in
     /// the application. It first creates an exit command for the current
     /// window and adds it to a command vector. Then, it checks the route
@@ -784,26 +1393,3 @@ impl AppClock {
         label(average).tertiary().caption2().into()
     }
 }
-
-// AnvilSave is a struct that represents a snapshot of the Anvil state at a
-// specific block number.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AnvilSave {
-    // snapshot is a string representation of the Anvil state.
-    pub snapshot: String,
-    // block_number is the block number at which the snapshot was taken.
-    pub block_number: u64,
-}
-
-// save_snapshot is an asynchronous function that attempts to save a snapshot of
-// the Anvil state. It takes an Arc of ExcaliburMiddleware with Ws and
-// LocalWallet as parameters. It returns a Result of AnvilSave.
-#[tracing::instrument(skip(client))]
-async fn save_snapshot(
-    client: Arc<ExcaliburMiddleware<Ws, LocalWallet>>,
-) -> anyhow::Result<AnvilSave> {
-    // Log a debug message indicating that a snapshot save attempt is being made.
-    tracing::debug!("Attempting to save anvil snapshot");
-    // Call the snapshot method on the client and await the result.
-    client.snapshot().await
-}
@@ -1567,6 +1567,12 @@ impl<'a, Message> ExcaliburInput<'a, Message> {
         self.width = width;
         self
     }
+
+    /// Hides the value, for passwords and keys.
+    pub fn secure(mut self, is_secure: bool) -> Self {
+        self.is_secure = is_secure;
+        self
+    }
 }
 
 impl<'a, Message> Component<Message, iced::Renderer> for ExcaliburInput<'a, Message>
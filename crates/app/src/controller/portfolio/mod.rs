@@ -41,11 +41,14 @@ impl From<Message> for view::Message {
 pub struct PortfolioRoot {
     pub page: Page,
     pub monolithic: monolithic::Monolithic,
-    pub client: Option<Arc<ExcaliburMiddleware<Ws, LocalWallet>>>,
+    pub client: Option<Arc<ExcaliburMiddleware<RpcTransport, ExcaliburSigner>>>,
 }
 
 impl PortfolioRoot {
-    pub fn new(client: Option<Arc<ExcaliburMiddleware<Ws, LocalWallet>>>, model: Model) -> Self {
+    pub fn new(
+        client: Option<Arc<ExcaliburMiddleware<RpcTransport, ExcaliburSigner>>>,
+        model: Model,
+    ) -> Self {
         Self {
             page: Page::default(),
             monolithic: monolithic::Monolithic::new(client.clone(), model.clone()),
@@ -33,6 +33,12 @@ pub struct Form {
     pub liquidity: Option<LiquidityTypes>,
     pub state: SubmitState,
     pub error: Option<String>,
+    /// Warning about the contract the position is sent to, from the contacts.
+    pub warning: Option<String>,
+    /// Estimated cost of creating the position, in ETH and USD.
+    pub fee: Option<String>,
+    /// Fees the position is created with, or the provider's if unset.
+    pub fees: Option<FeePreset>,
 }
 
 impl Form {
@@ -48,6 +54,9 @@ impl Form {
         self.end_price = None;
         self.liquidity = None;
         self.state = SubmitState::Empty;
+        self.error = None;
+        self.fee = None;
+        self.fees = None;
     }
 
     pub fn pending(&mut self) {
@@ -124,7 +133,9 @@ impl Form {
                     ),
                     submit,
                     state,
-                    &self.error
+                    &self.error,
+                    &self.warning,
+                    &self.fee
                 ),
                 FormView::chart_layout_histogram(
                     preview_chart,
@@ -146,7 +157,7 @@ pub enum LiquidityTypes {
     High,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct LiquidityTemplateParameters {
     pub strike_price_wad: f64,
     pub sigma_percent_wad: f64,
@@ -158,26 +169,36 @@ impl LiquidityTypes {
         vec![Self::Low, Self::Med, Self::High]
     }
 
-    // todo: work on this!
-    pub fn to_parameters(self, current_price: f64) -> LiquidityTemplateParameters {
+    /// Multiple of the current price spanned above and below it by each
+    /// template, i.e. Low spreads liquidity over [P / 4, 4P].
+    fn range_multiple(self) -> f64 {
         match self {
-            LiquidityTypes::Low => LiquidityTemplateParameters {
-                strike_price_wad: current_price,
-                sigma_percent_wad: 0.85,
-                time_remaining_years_wad: 1.0,
-            },
-            LiquidityTypes::Med => LiquidityTemplateParameters {
-                strike_price_wad: current_price,
-                sigma_percent_wad: 0.625,
-                time_remaining_years_wad: 1.0,
-            },
-            LiquidityTypes::High => LiquidityTemplateParameters {
-                strike_price_wad: current_price,
-                sigma_percent_wad: 0.1,
-                time_remaining_years_wad: 1.0,
-            },
+            LiquidityTypes::Low => 4.0,
+            LiquidityTypes::Med => 2.8,
+            LiquidityTypes::High => 1.18,
         }
     }
+
+    /// Fits a one year curve that trades 90% of its liquidity within the
+    /// template's price range around `current_price`, which must be positive.
+    pub fn to_parameters(self, current_price: f64) -> anyhow::Result<LiquidityTemplateParameters> {
+        if !current_price.is_finite() || current_price <= 0.0 {
+            anyhow::bail!("Cannot fit a curve around a price of {}", current_price);
+        }
+        let multiple = self.range_multiple();
+        let fit = cfmm_math::fitting::fit_price_range(
+            current_price / multiple,
+            current_price * multiple,
+            1.0,
+            0.9,
+        )?;
+
+        Ok(LiquidityTemplateParameters {
+            strike_price_wad: fit.strike,
+            sigma_percent_wad: fit.sigma,
+            time_remaining_years_wad: fit.tau,
+        })
+    }
 }
 
 impl std::fmt::Display for LiquidityTypes {
@@ -309,6 +330,8 @@ impl FormView {
         submit: Option<Message>,
         state: &SubmitState,
         error: &'a Option<String>,
+        warning: &'a Option<String>,
+        fee: &'a Option<String>,
     ) -> Container<'a, Message>
     where
         Message: 'a + Clone + Default,
@@ -328,6 +351,14 @@ impl FormView {
                     .push(Self::submit(submit, state).width(Length::FillPortion(2))),
             );
 
+        if let Some(warning) = warning {
+            row = row.push(Text::new(warning));
+        }
+
+        if let Some(fee) = fee {
+            row = row.push(Text::new(fee));
+        }
+
         // Add the error message to the row if it exists
         if let Some(error_message) = error {
             row = row.push(Text::new(error_message));
@@ -389,7 +420,7 @@ impl FormView {
                                         Some(on_select_liquidity(x.liquidity_type)),
                                         x.liquidity_type,
                                         chosen_liquidity == Some(x.liquidity_type),
-                                        x.liquidity_type.to_parameters(x.last_price),
+                                        x.parameters,
                                         format!(
                                             "${:.2} - ${:.2}",
                                             x.price_range.0, x.price_range.1
@@ -805,7 +836,7 @@ const LOADING_INDICATOR_SPEED_MS: u64 = 85;
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct LiquidityChoices {
     pub liquidity_type: LiquidityTypes,
-    pub last_price: f64,
+    pub parameters: LiquidityTemplateParameters,
     pub price_range: (f64, f64),
 }
 
@@ -8,22 +8,30 @@ use arbiter_bindings::bindings::liquid_exchange::LiquidExchange;
 use cfmm_math::trading_functions::rmm::{
     compute_value_function, compute_x_given_l_rust, compute_y_given_x_rust,
 };
-use clients::protocol::{LogNormalF64, PoolInitParamsF64};
+use clients::{
+    gas::{FeePreset, FeeSpeed, FeeSuggestion},
+    protocol::{LogNormalF64, PoolInitParamsF64, ProtocolClient},
+    transactions::{TrackedTransaction, TxEvent},
+};
 use datatypes::portfolio::coin::Coin;
-use iced::{futures::TryFutureExt, subscription, Padding};
+use iced::{subscription, Padding};
 use sim::{from_ethers_u256, to_ethers_address, to_ethers_u256};
 use RustQuant::stochastics::{GeometricBrownianMotion, StochasticProcess, Trajectories};
 
 use self::{
     create::{FormView, LiquidityTypes, Times},
     metrics::Metrics,
-    tx_history::TxHistory,
+    tx_history::{TxHistory, TxTracker},
     view::{MonolithicPresenter, MonolithicView},
 };
 use super::*;
 use crate::{
     components::system::{ExcaliburChart, ExcaliburContainer},
-    model::portfolio::{format_and_parse, AlloyAddress, ALLOY_WAD},
+    middleware::NetworkClient,
+    model::{
+        portfolio::{format_and_parse, AlloyAddress, ALLOY_WAD},
+        sessions::SessionStore,
+    },
     view::portfolio_view::PortfolioPresenter,
 };
 
@@ -35,6 +43,17 @@ pub enum Message {
     Form(FormMessage),
     SelectPosition(AlloyAddress),
     AllocateResult(anyhow::Result<Option<TransactionReceipt>, Arc<anyhow::Error>>),
+    /// Gas limit of the create position transaction and the fees suggested
+    /// for it.
+    FeeEstimate(anyhow::Result<(ethers::types::U256, FeeSuggestion), Arc<anyhow::Error>>),
+    /// A transaction sent from the app changed status.
+    TxEvent(TxEvent),
+    /// The transactions sent from the app, and where they are at.
+    Transactions(anyhow::Result<Vec<TrackedTransaction>, Arc<anyhow::Error>>),
+    SpeedUp(ethers::types::H256),
+    CancelTx(ethers::types::H256),
+    /// The replacement sent to speed up or cancel a transaction.
+    Replacement(anyhow::Result<ethers::types::H256, Arc<anyhow::Error>>),
 
     // todo: do we need these on all pages?? maybe just reference the  model.
     // I think we should use this pattern called the translator pattern:
@@ -80,7 +99,7 @@ impl From<Message> for <Message as MessageWrapper>::ParentMessage {
 
 #[derive(Debug, Clone, Default)]
 pub struct Monolithic {
-    client: Option<Arc<ExcaliburMiddleware<Ws, LocalWallet>>>,
+    client: Option<Arc<ExcaliburMiddleware<RpcTransport, ExcaliburSigner>>>,
     model: Model,
     presenter: MonolithicPresenter,
     chart_presenter: PortfolioPresenter,
@@ -89,10 +108,17 @@ pub struct Monolithic {
     view_position: Option<AlloyAddress>,
     create_status: create::SubmitState,
     price_process: Option<PriceProcess>,
+    /// Tracks the transactions sent on a live network.
+    tracker: Option<TxTracker>,
+    /// Transactions sent from the app, as of the last update from the tracker.
+    transactions: Vec<TrackedTransaction>,
 }
 
 impl Monolithic {
-    pub fn new(client: Option<Arc<ExcaliburMiddleware<Ws, LocalWallet>>>, model: Model) -> Self {
+    pub fn new(
+        client: Option<Arc<ExcaliburMiddleware<RpcTransport, ExcaliburSigner>>>,
+        model: Model,
+    ) -> Self {
         let presenter = MonolithicPresenter::new(model.clone());
         let chart_presenter = PortfolioPresenter::default();
 
@@ -104,6 +130,21 @@ impl Monolithic {
             max_steps: 1000,
         });
 
+        // The in-process sandbox has no network client to track transactions
+        // on, and read only connections send none.
+        let tracker = client
+            .as_ref()
+            .filter(|client| client.signer.is_some())
+            .and_then(|client| client.client.clone())
+            .zip(model.transactions_path())
+            .and_then(|(provider, path)| match TxTracker::new(provider, &path) {
+                Ok(tracker) => Some(tracker),
+                Err(e) => {
+                    tracing::warn!("Failed to load the transactions sent from the app: {:?}", e);
+                    None
+                }
+            });
+
         Self {
             client,
             model,
@@ -114,6 +155,8 @@ impl Monolithic {
             view_position: None,
             create_status: create::SubmitState::Empty,
             price_process: process,
+            tracker,
+            transactions: Vec::new(),
         }
     }
 
@@ -153,143 +196,130 @@ impl Monolithic {
         Command::none()
     }
 
+    /// Address of the protocol the create position transaction is sent to.
+    fn protocol_address(&self) -> Option<ethers::types::Address> {
+        self.client.as_ref()?.protocol_address()
+    }
+
     pub fn handle_submit_allocate(&mut self) -> anyhow::Result<Command<Message>> {
         if let Some(client) = self.client.clone() {
-            if let (Some(signer), Some(_)) = (client.signer.as_ref(), client.dfmm_client.as_ref()) {
+            // The in-process sandbox sends from its own account, without a
+            // signer.
+            if let (Some(sender), Some(protocol)) = (client.sender(), client.protocol_address()) {
                 if self.model.get_current().is_none() {
                     return Err(anyhow::anyhow!(
                         "Data model is not connected to any network."
                     ));
                 }
 
-                let parameters = self.create.liquidity;
-                let deposit_amount_dollars = self.create.amount.clone();
+                if self.model.user.contacts.is_blocked(&protocol) {
+                    return Err(anyhow::anyhow!(
+                        "The protocol {:?} is blocked in your contacts.",
+                        protocol
+                    ));
+                }
+
+                let request = PositionRequest::new(
+                    &self.model,
+                    self.create.liquidity,
+                    self.create.amount.as_deref(),
+                )?;
+                let fees = self.create.fees;
+                let tracker = self.tracker.clone();
                 let model_clone = self.model.clone();
                 let client = client.clone();
 
                 tracing::info!(
-                    "Sending create position transaction to contract: {:?} from signer: {:?}",
-                    client
-                        .dfmm_client
-                        .clone()
-                        .unwrap()
-                        .protocol
-                        .address()
-                        .clone(),
-                    signer.address()
+                    "Sending create position transaction to contract: {:?} from: {:?}",
+                    protocol,
+                    sender
                 );
                 return Ok(Command::perform(
                     async move {
-                        let data_model = model_clone.get_current().unwrap();
-                        let token_list = model_clone.user.coins.clone();
-                        // Find the asset token, which has a tag of "ether".
-                        let asset_token = token_list
-                            .tokens
-                            .clone()
-                            .into_iter()
-                            .find(|token| token.tags.contains(&"ether".to_string()))
-                            .map(|token| token.address);
-                        let asset_token = match asset_token {
-                            Some(x) => x,
-                            None => {
-                                return Err(anyhow::anyhow!("No asset token")).map_err(Arc::new)
-                            }
-                        };
-                        // Does not panic because it's caught in the above if statement.
-                        let asset_price = data_model.price_of_token(asset_token)?;
-                        let asset_price = format_and_parse(asset_price)?;
-
-                        let parameters: LiquidityTypes = match parameters {
-                            Some(x) => x,
-                            None => {
-                                return Err(anyhow::anyhow!("No liquidity parameters"))
-                                    .map_err(Arc::new)
-                            }
-                        };
-                        let parameters = parameters.to_parameters(asset_price);
-
-                        // Get the tokens from the user's data token list.
-                        let token_list = model_clone.user.coins.clone();
-
-                        // Find the quote token, which has a tag of "stablecoin".
-                        let quote_token = token_list
-                            .tokens
-                            .clone()
-                            .into_iter()
-                            .find(|token| token.tags.contains(&"stablecoin".to_string()))
-                            .map(|token| token.address);
-                        let quote_token = match quote_token {
-                            Some(x) => x,
-                            None => {
-                                return Err(anyhow::anyhow!("No quote token")).map_err(Arc::new)
-                            }
-                        };
-
-                        let deposit_amount_dollars = match deposit_amount_dollars {
-                            Some(x) => x.parse::<f64>().unwrap(),
-                            None => {
-                                return Err(anyhow::anyhow!("No deposit amount")).map_err(Arc::new)
-                            }
-                        };
-
-                        let (amount_x, _amount_y, _total_liquidity) = get_deposits_given_price(
-                            asset_price,
-                            deposit_amount_dollars,
-                            parameters.strike_price_wad,
-                            parameters.sigma_percent_wad,
-                            parameters.time_remaining_years_wad,
-                        );
-
-                        let payload_params = PoolInitParamsF64::LogNormal(LogNormalF64 {
-                            sigma: parameters.sigma_percent_wad,
-                            strike: parameters.strike_price_wad,
-                            tau: parameters.time_remaining_years_wad,
-                            swap_fee: 0.003,
-                        });
-
-                        let init_price_wad =
-                            alloy_primitives::utils::parse_ether(&format!("{}", asset_price))
-                                .map_err(|err| {
-                                    Arc::new(anyhow::anyhow!("Error parsing price: {:?}", err))
-                                })
-                                .unwrap();
-                        let init_price_wad = to_ethers_u256(init_price_wad);
-
-                        let init_reserve_x_wad = to_ethers_u256(
-                            alloy_primitives::utils::parse_ether(&format!("{}", amount_x))
-                                .map_err(|err| {
-                                    Arc::new(anyhow::anyhow!("Error parsing amount: {:?}", err))
-                                })
-                                .unwrap(),
-                        );
-
-                        let dfmm = client
-                            .dfmm_client
-                            .as_ref()
-                            .unwrap_or_else(|| panic!("No DFMM client in ExcaliburMiddleware"));
+                        // Keep the sandbox from before the position, to go back to it.
+                        let store = SessionStore::for_profile(&model_clone.user);
+                        if let Err(e) = client.auto_snapshot(&store, "create position").await {
+                            tracing::warn!("Failed to save sandbox session: {:?}", e);
+                        }
 
                         // todo: handle mutable update to the pools array in the protocol client
                         // separately.
-                        dfmm.create_position(
-                            to_ethers_address(asset_token),
-                            to_ethers_address(quote_token),
-                            init_reserve_x_wad,
-                            init_price_wad,
-                            payload_params,
-                        )
-                        .map_err(Arc::new)
-                        .await
+                        let receipt = match (client.dfmm_client.as_ref(), tracker) {
+                            (Some(dfmm), Some(tracker)) => {
+                                request.send_tracked(dfmm, &tracker, fees.as_ref()).await
+                            }
+                            (Some(dfmm), None) => request.send(dfmm, fees.as_ref()).await,
+                            (None, _) => {
+                                let dfmm = client.arbiter_protocol()?.ok_or(anyhow::anyhow!(
+                                    "No DFMM client in ExcaliburMiddleware"
+                                ))?;
+                                request.send(&dfmm, fees.as_ref()).await
+                            }
+                        };
+                        receipt.map_err(Arc::new)
                     },
                     Message::AllocateResult,
                 ));
             }
 
-            return Err(anyhow::anyhow!("No signer"));
+            return Err(anyhow::anyhow!("No signer or protocol on this network"));
         }
 
         Err(anyhow::anyhow!("No client"))
     }
 
+    /// Fetches the transactions sent from the app, checking the status of the
+    /// ones that aren't final if `poll` is set.
+    fn update_transactions(&self, poll: bool) -> Command<Message> {
+        let Some(tracker) = self.tracker.clone() else {
+            return Command::none();
+        };
+        Command::perform(
+            async move {
+                if poll {
+                    tracker.poll().await.map_err(Arc::new)
+                } else {
+                    Ok(tracker.transactions().await)
+                }
+            },
+            Message::Transactions,
+        )
+    }
+
+    /// Estimates what the create position transaction of the form costs, once
+    /// the form is filled in.
+    fn estimate_fees(&mut self) -> Command<Message> {
+        self.create.fee = None;
+        self.create.fees = None;
+
+        let Some(client) = self.client.clone() else {
+            return Command::none();
+        };
+        let Ok(request) = PositionRequest::new(
+            &self.model,
+            self.create.liquidity,
+            self.create.amount.as_deref(),
+        ) else {
+            return Command::none();
+        };
+
+        Command::perform(
+            async move {
+                let estimate = match client.dfmm_client.as_ref() {
+                    Some(dfmm) => request.estimate(dfmm).await,
+                    None => {
+                        let dfmm = client
+                            .arbiter_protocol()?
+                            .ok_or(anyhow::anyhow!("No DFMM client in ExcaliburMiddleware"))?;
+                        request.estimate(&dfmm).await
+                    }
+                };
+                estimate.map_err(Arc::new)
+            },
+            Message::FeeEstimate,
+        )
+    }
+
     pub fn handle_form_message(&mut self, message: FormMessage) -> Command<Message> {
         match message {
             FormMessage::Empty => Command::none(),
@@ -306,13 +336,16 @@ impl Monolithic {
                     Ok(command) => command,
                     Err(err) => {
                         tracing::error!("Error when submitting allocate transaction: {:?}", err);
+                        self.create.error = Some(err.to_string());
+                        self.create.failed();
+                        self.create_status = create::SubmitState::Failed;
                         Command::none()
                     }
                 }
             }
             FormMessage::Amount(amount) => {
                 self.create.amount = amount;
-                Command::none()
+                self.estimate_fees()
             }
             FormMessage::Asset(asset) => {
                 self.create.chosen_asset = Some(asset);
@@ -354,7 +387,10 @@ impl Monolithic {
                         Err(_) => return Command::none(),
                     };
 
-                    let parameters = liquidity.to_parameters(external_price);
+                    let parameters = match liquidity.to_parameters(external_price) {
+                        Ok(x) => x,
+                        Err(_) => return Command::none(),
+                    };
                     self.presenter.sync_strategy_preview(
                         external_price,
                         parameters.strike_price_wad,
@@ -362,7 +398,10 @@ impl Monolithic {
                         parameters.time_remaining_years_wad,
                     );
 
-                    Command::perform(async {}, |_| Message::Refresh)
+                    Command::batch(vec![
+                        self.estimate_fees(),
+                        Command::perform(async {}, |_| Message::Refresh),
+                    ])
                 } else {
                     Command::none()
                 }
@@ -371,6 +410,138 @@ impl Monolithic {
     }
 }
 
+/// Arguments of the transaction creating a position, from the form and the
+/// model's prices.
+#[derive(Debug, Clone)]
+struct PositionRequest {
+    asset_token: ethers::types::Address,
+    quote_token: ethers::types::Address,
+    init_reserve_x_wad: ethers::types::U256,
+    init_price_wad: ethers::types::U256,
+    params: PoolInitParamsF64,
+}
+
+impl PositionRequest {
+    fn new(
+        model: &Model,
+        liquidity: Option<LiquidityTypes>,
+        deposit_amount_dollars: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let data_model = model.get_current().ok_or(anyhow::anyhow!(
+            "Data model is not connected to any network."
+        ))?;
+        let token_list = model.user.coins.clone();
+        // Find the asset token, which has a tag of "ether".
+        let asset_token = token_list
+            .tokens
+            .iter()
+            .find(|token| token.tags.contains(&"ether".to_string()))
+            .map(|token| token.address)
+            .ok_or(anyhow::anyhow!("No asset token"))?;
+        let asset_price = data_model.price_of_token(asset_token)?;
+        let asset_price = format_and_parse(asset_price)?;
+
+        let parameters = liquidity
+            .ok_or(anyhow::anyhow!("No liquidity parameters"))?
+            .to_parameters(asset_price)?;
+
+        // Find the quote token, which has a tag of "stablecoin".
+        let quote_token = token_list
+            .tokens
+            .iter()
+            .find(|token| token.tags.contains(&"stablecoin".to_string()))
+            .map(|token| token.address)
+            .ok_or(anyhow::anyhow!("No quote token"))?;
+
+        let deposit_amount_dollars = deposit_amount_dollars
+            .ok_or(anyhow::anyhow!("No deposit amount"))?
+            .parse::<f64>()?;
+
+        let (amount_x, _amount_y, _total_liquidity) = get_deposits_given_price(
+            asset_price,
+            deposit_amount_dollars,
+            parameters.strike_price_wad,
+            parameters.sigma_percent_wad,
+            parameters.time_remaining_years_wad,
+        );
+
+        let params = PoolInitParamsF64::LogNormal(LogNormalF64 {
+            sigma: parameters.sigma_percent_wad,
+            strike: parameters.strike_price_wad,
+            tau: parameters.time_remaining_years_wad,
+            swap_fee: 0.003,
+        });
+
+        let init_price_wad = alloy_primitives::utils::parse_ether(&format!("{}", asset_price))
+            .map_err(|err| anyhow::anyhow!("Error parsing price: {:?}", err))?;
+        let init_reserve_x_wad = alloy_primitives::utils::parse_ether(&format!("{}", amount_x))
+            .map_err(|err| anyhow::anyhow!("Error parsing amount: {:?}", err))?;
+
+        Ok(Self {
+            asset_token: to_ethers_address(asset_token),
+            quote_token: to_ethers_address(quote_token),
+            init_reserve_x_wad: to_ethers_u256(init_reserve_x_wad),
+            init_price_wad: to_ethers_u256(init_price_wad),
+            params,
+        })
+    }
+
+    async fn send<C: Middleware + 'static>(
+        self,
+        dfmm: &ProtocolClient<C>,
+        fees: Option<&FeePreset>,
+    ) -> anyhow::Result<Option<TransactionReceipt>> {
+        dfmm.create_position(
+            self.asset_token,
+            self.quote_token,
+            self.init_reserve_x_wad,
+            self.init_price_wad,
+            self.params,
+            fees,
+        )
+        .await
+    }
+
+    /// Sends the transaction through `tracker`, so it is tracked until it is
+    /// final, and waits for it to be mined.
+    async fn send_tracked(
+        self,
+        dfmm: &ProtocolClient<NetworkClient<RpcTransport, ExcaliburSigner>>,
+        tracker: &TxTracker,
+        fees: Option<&FeePreset>,
+    ) -> anyhow::Result<Option<TransactionReceipt>> {
+        let tx = dfmm
+            .create_position_transaction(
+                self.asset_token,
+                self.quote_token,
+                self.init_reserve_x_wad,
+                self.init_price_wad,
+                self.params,
+                fees,
+            )
+            .await?;
+        let hash = tracker.send(tx).await?;
+        let receipt = PendingTransaction::new(hash, dfmm.client.provider())
+            .interval(std::time::Duration::from_millis(100))
+            .await?;
+        Ok(receipt)
+    }
+
+    async fn estimate<C: Middleware + 'static>(
+        self,
+        dfmm: &ProtocolClient<C>,
+    ) -> anyhow::Result<(ethers::types::U256, FeeSuggestion)> {
+        dfmm.estimate_create_position(
+            self.asset_token,
+            self.quote_token,
+            self.init_reserve_x_wad,
+            self.init_price_wad,
+            self.params,
+        )
+        .await
+    }
+}
+
 impl State for Monolithic {
     type AppMessage = Message;
     type ViewMessage = Message;
@@ -395,13 +566,14 @@ impl State for Monolithic {
             update_base_asset,
             update_quote_asset,
             update_data_model,
+            self.update_transactions(false),
         ])
     }
 
     fn update(&mut self, message: Self::AppMessage) -> Command<Self::AppMessage> {
         match message {
             Self::AppMessage::Refresh => Command::none(),
-            Self::AppMessage::SyncModel(_block) => Command::none(),
+            Self::AppMessage::SyncModel(_block) => self.update_transactions(true),
             Self::AppMessage::UpdateDataModel(result) => match result {
                 Ok(updated_model) => self.handle_updated_model(updated_model),
                 Err(err) => {
@@ -416,6 +588,9 @@ impl State for Monolithic {
             }
             Self::AppMessage::StartAllocate => {
                 self.allocate = true;
+                self.create.warning = self
+                    .protocol_address()
+                    .and_then(|protocol| self.model.user.contacts.warning(&protocol));
                 Command::none()
             }
             Self::AppMessage::Form(form_message) => self.handle_form_message(form_message),
@@ -433,6 +608,53 @@ impl State for Monolithic {
                     Command::none()
                 }
             },
+            Self::AppMessage::FeeEstimate(result) => {
+                match result {
+                    Ok((gas, suggestion)) => {
+                        let preset = suggestion.preset(FeeSpeed::Normal).copied();
+                        self.create.fee = preset.map(|preset| {
+                            let cost = preset.cost(gas, suggestion.base_fee);
+                            format!("Network fee: {}", self.model.describe_gas_cost(&cost))
+                        });
+                        self.create.fees = preset;
+                    }
+                    Err(err) => {
+                        tracing::debug!("Failed to estimate the position's fees: {:?}", err)
+                    }
+                }
+                Command::none()
+            }
+            Self::AppMessage::TxEvent(event) => {
+                tracing::debug!("Transaction {:?}: {}", event.hash, event.status);
+                self.update_transactions(false)
+            }
+            Self::AppMessage::Transactions(result) => {
+                match result {
+                    Ok(transactions) => self.transactions = transactions,
+                    Err(err) => tracing::warn!("Failed to check transactions: {:?}", err),
+                }
+                Command::none()
+            }
+            Self::AppMessage::SpeedUp(hash) => match self.tracker.clone() {
+                Some(tracker) => Command::perform(
+                    async move { tracker.speed_up(hash).await.map_err(Arc::new) },
+                    Message::Replacement,
+                ),
+                None => Command::none(),
+            },
+            Self::AppMessage::CancelTx(hash) => match self.tracker.clone() {
+                Some(tracker) => Command::perform(
+                    async move { tracker.cancel(hash).await.map_err(Arc::new) },
+                    Message::Replacement,
+                ),
+                None => Command::none(),
+            },
+            Self::AppMessage::Replacement(result) => {
+                if let Err(err) = result {
+                    tracing::error!("Failed to replace transaction: {:?}", err);
+                }
+                Command::none()
+            }
             Self::AppMessage::UpdatePriceProcess => {
                 if let (Some(_), Some(exchange)) = (
                     self.price_process.clone(),
@@ -516,6 +738,14 @@ impl State for Monolithic {
             );
         }
 
+        if self.transactions.iter().any(|tx| !tx.status.is_final()) {
+            content = content.push(TxHistory::layout(
+                "Pending Transactions",
+                "Portfolio",
+                TxHistory::pending_table(&self.transactions, Message::SpeedUp, Message::CancelTx),
+            ));
+        }
+
         content = content.push(TxHistory::layout(
             "Transaction History",
             "Portfolio",
@@ -538,11 +768,17 @@ impl State for Monolithic {
 
     fn subscription(&self) -> Subscription<Self::AppMessage> {
         if let Some(client) = self.client.clone() {
-            let provider = client.get_client();
             let mut subscriptions: Vec<Subscription<Message>> = vec![];
 
-            // Fetches the most recent block and updates the model.
-            subscriptions.push(listen_to_blocks(provider));
+            // Fetches the most recent block and updates the model. The
+            // in-process sandbox has no network client to listen to.
+            if let Some(provider) = client.client.clone() {
+                subscriptions.push(listen_to_blocks(provider));
+            }
+
+            if let Some(tracker) = &self.tracker {
+                subscriptions.push(tracker.subscription(Message::TxEvent));
+            }
 
             // Steps the price process forward.
             // todo: remove this in favor of a live price feed.
@@ -561,25 +797,48 @@ impl State for Monolithic {
 
 /// Fetches the most recent block and updates the model with the state in the
 /// new block.
+/// - Subscribes to new blocks over websockets, and polls for them over HTTP.
+/// - Keyed by chain id, so switching networks restarts the subscription.
 pub fn listen_to_blocks(
-    provider: Arc<SignerMiddleware<Provider<Ws>, LocalWallet>>,
+    provider: Arc<NetworkClient<RpcTransport, ExcaliburSigner>>,
 ) -> Subscription<Message> {
     struct Blocks;
 
     subscription::channel(
-        std::any::TypeId::of::<Blocks>(),
+        (
+            std::any::TypeId::of::<Blocks>(),
+            provider.signer().chain_id(),
+        ),
         0,
         |mut output| async move {
-            let mut subscription = provider.subscribe_blocks().await.unwrap();
+            if let Ok(mut subscription) = provider.subscribe_blocks().await {
+                loop {
+                    while let Some(block) = subscription.next().await {
+                        output.try_send(Message::SyncModel(block)).unwrap();
+                    }
+                }
+            }
+
+            let mut interval = tokio::time::interval(BLOCK_POLL_INTERVAL);
+            let mut last = None;
             loop {
-                while let Some(block) = subscription.next().await {
-                    output.try_send(Message::SyncModel(block)).unwrap();
+                interval.tick().await;
+                match provider.get_block(BlockNumber::Latest).await {
+                    Ok(Some(block)) if block.number != last => {
+                        last = block.number;
+                        output.try_send(Message::SyncModel(block)).unwrap();
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("Failed to poll for blocks: {:?}", e),
                 }
             }
         },
     )
 }
 
+/// How often to poll for new blocks when the network can't push them.
+const BLOCK_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(4);
+
 /// For testing the UI with a "live" price.
 pub struct PriceProcess {
     pub trajectories: Trajectories,
@@ -617,7 +876,7 @@ impl std::fmt::Debug for PriceProcess {
 fn price_process_update_after_step(
     process: PriceProcess,
     exchange: AlloyAddress,
-    client: Arc<ExcaliburMiddleware<Ws, LocalWallet>>,
+    client: Arc<ExcaliburMiddleware<RpcTransport, ExcaliburSigner>>,
 ) -> Command<Message> {
     let mut next_price = None;
 
@@ -628,8 +887,22 @@ fn price_process_update_after_step(
         }
     }
 
-    let client = client.get_client();
+    // The in-process sandbox has no network client, so its exchange is
+    // updated through the arbiter client.
+    match (client.client.clone(), client.arbiter_client()) {
+        (Some(client), _) => set_exchange_price(client, exchange, next_price),
+        (None, Some(client)) => set_exchange_price(client, exchange, next_price),
+        (None, None) => Command::none(),
+    }
+}
 
+/// Sets the price of the liquid exchange to `next_price`, or to a random
+/// price within 1% of its current price.
+fn set_exchange_price<M: Middleware + 'static>(
+    client: Arc<M>,
+    exchange: AlloyAddress,
+    next_price: Option<f64>,
+) -> Command<Message> {
     Command::perform(
         async move {
             let next_price = next_price.unwrap_or_default();
@@ -1,14 +1,84 @@
-use iced::widget::Space;
+use std::{fmt, path::Path};
+
+use clients::transactions::{TrackedTransaction, TransactionManager, TxEvent, TxKind};
+use ethers::types::{transaction::eip2718::TypedTransaction, H256};
+use iced::{futures::SinkExt, widget::Space};
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    Mutex,
+};
 
 use super::*;
 use crate::{
     components::{
-        system::{ExcaliburColor, ExcaliburContainer, ExcaliburTable},
+        system::{ExcaliburButton, ExcaliburColor, ExcaliburContainer, ExcaliburTable},
         tables::{builder::TableBuilder, cells::CellBuilder},
     },
     model::portfolio::HistoricalTx,
 };
 
+/// Tracks the transactions sent from the app on a network until they are
+/// final, saving them so they are still tracked after a restart.
+#[derive(Clone)]
+pub struct TxTracker {
+    manager: Arc<Mutex<TransactionManager<NetworkClient<RpcTransport, ExcaliburSigner>>>>,
+    events: broadcast::Sender<TxEvent>,
+}
+
+impl fmt::Debug for TxTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TxTracker").finish_non_exhaustive()
+    }
+}
+
+impl TxTracker {
+    pub fn new(
+        client: Arc<NetworkClient<RpcTransport, ExcaliburSigner>>,
+        path: &Path,
+    ) -> anyhow::Result<Self> {
+        let manager = TransactionManager::new(client).with_store(path)?;
+        let events = manager.events();
+        Ok(Self {
+            manager: Arc::new(Mutex::new(manager)),
+            events,
+        })
+    }
+
+    /// Sends `tx` and tracks it.
+    pub async fn send(&self, tx: TypedTransaction) -> anyhow::Result<H256> {
+        self.manager.lock().await.send(tx).await
+    }
+
+    pub async fn speed_up(&self, hash: H256) -> anyhow::Result<H256> {
+        self.manager.lock().await.speed_up(hash, None).await
+    }
+
+    pub async fn cancel(&self, hash: H256) -> anyhow::Result<H256> {
+        self.manager.lock().await.cancel(hash, None).await
+    }
+
+    /// Checks the status of the transactions that aren't final, returning all
+    /// of them.
+    pub async fn poll(&self) -> anyhow::Result<Vec<TrackedTransaction>> {
+        let mut manager = self.manager.lock().await;
+        manager.poll().await?;
+        Ok(manager.transactions().to_vec())
+    }
+
+    /// The transactions tracked, without checking their status.
+    pub async fn transactions(&self) -> Vec<TrackedTransaction> {
+        self.manager.lock().await.transactions().to_vec()
+    }
+
+    /// Forwards every change of status of the tracked transactions.
+    pub fn subscription<Message>(&self, on_event: fn(TxEvent) -> Message) -> Subscription<Message>
+    where
+        Message: 'static + Send,
+    {
+        TxHistory::subscription(self.events.clone(), on_event)
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct TxHistory;
 
@@ -89,4 +159,92 @@ impl TxHistory {
             ])
             .build_custom(cells)
     }
+
+    /// Table of the transactions sent from the app that aren't final yet,
+    /// with actions to speed them up or cancel them.
+    pub fn pending_table<Message>(
+        txs: &[TrackedTransaction],
+        on_speed_up: impl Fn(H256) -> Message,
+        on_cancel: impl Fn(H256) -> Message,
+    ) -> TableBuilder<Message>
+    where
+        Message: 'static + Clone + Default,
+    {
+        let mut cells: Vec<Vec<CellBuilder<Message>>> = Vec::new();
+
+        for tx in txs.iter().filter(|tx| !tx.status.is_final()) {
+            let kind = match tx.kind {
+                TxKind::Send => "Send",
+                TxKind::SpeedUp => "Speed up",
+                TxKind::Cancel => "Cancel",
+            };
+            cells.push(vec![
+                CellBuilder::new().child(label(kind).build()),
+                CellBuilder::new().child(label(tx.nonce).quantitative().build()),
+                CellBuilder::new().child(label(tx.status).build()),
+                CellBuilder::new().child(label(tx.hash).secondary().caption().build()),
+                CellBuilder::new().child(
+                    Row::new()
+                        .spacing(Sizes::Sm)
+                        .push(
+                            ExcaliburButton::new()
+                                .transparent()
+                                .build(label("Speed up").caption().build())
+                                .on_press(on_speed_up(tx.hash)),
+                        )
+                        .push(
+                            ExcaliburButton::new()
+                                .transparent()
+                                .build(label("Cancel").caption().build())
+                                .on_press(on_cancel(tx.hash)),
+                        ),
+                ),
+            ]);
+        }
+
+        // If the table is empty, add a placeholder row.
+        if cells.is_empty() {
+            cells.push(vec![CellBuilder::new().child(
+                label("No pending transactions")
+                    .secondary()
+                    .caption()
+                    .build(),
+            )]);
+        }
+
+        ExcaliburTable::new()
+            .headers(vec!["Action", "Nonce", "Status", "Tx hash", ""])
+            .build_custom(cells)
+    }
+
+    /// Forwards every change of status of the transactions tracked by a
+    /// `TransactionManager`, given its `events` sender.
+    pub fn subscription<Message>(
+        events: broadcast::Sender<TxEvent>,
+        on_event: fn(TxEvent) -> Message,
+    ) -> Subscription<Message>
+    where
+        Message: 'static + Send,
+    {
+        struct TxEvents;
+
+        subscription::channel(
+            std::any::TypeId::of::<TxEvents>(),
+            16,
+            move |mut output| async move {
+                let mut receiver = events.subscribe();
+                loop {
+                    match receiver.recv().await {
+                        Ok(event) => {
+                            let _ = output.send(on_event(event)).await;
+                        }
+                        // Missed events are superseded by later ones.
+                        Err(RecvError::Lagged(_)) => continue,
+                        // The manager is gone, so nothing else will come.
+                        Err(RecvError::Closed) => std::future::pending::<()>().await,
+                    }
+                }
+            },
+        )
+    }
 }
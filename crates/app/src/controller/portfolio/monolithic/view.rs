@@ -101,7 +101,11 @@ impl MonolithicPresenter {
 
         let mut choices = vec![];
         for liquidity_type in liquidity_types.iter() {
-            let params = liquidity_type.to_parameters(current_price);
+            // There is nothing to fit a curve around until a price is synced.
+            let params = match liquidity_type.to_parameters(current_price) {
+                Ok(params) => params,
+                Err(_) => return vec![],
+            };
             let (strike_price, volatility, time_remaining) = (
                 params.strike_price_wad,
                 params.sigma_percent_wad,
@@ -133,7 +137,7 @@ impl MonolithicPresenter {
 
             choices.push(LiquidityChoices {
                 liquidity_type: *liquidity_type,
-                last_price: current_price,
+                parameters: params,
                 price_range,
             });
         }
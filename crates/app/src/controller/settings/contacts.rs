@@ -1,12 +1,42 @@
 //! Contacts are all the addresses that can be interacted with in the app.
+//!
+//! Contacts are imported from and exported to CSV files and wallet address
+//! books, and classified from their code on the connected network.
 
-use super::*;
-use crate::components::system::label;
+use std::path::PathBuf;
+
+use iced::{widget::Button, Padding};
+
+use self::system::{ExcaliburContainer, ExcaliburInputBuilder, ExcaliburTable};
+use super::{
+    rpc::{Feedback, RpcManagement},
+    *,
+};
+use crate::{
+    components::{
+        select::excalibur_select,
+        system::{label, ExcaliburButton},
+        tables::{builder::TableBuilder, cells::CellBuilder},
+    },
+    model::contacts::{Category, Contacts, ImportedContact},
+};
+
+/// Chain id of exported wallet address books when no network is connected.
+const DEFAULT_EXPORT_CHAIN_ID: u64 = 1;
 
 #[derive(Debug, Clone, Default)]
 pub enum Message {
     #[default]
     Empty,
+    /// Contacts of the profile and the chain id of the connected network.
+    Sync(Contacts, Option<u64>),
+    ChangePath(Option<String>),
+    SelectCategory(Category),
+    Import,
+    Imported(Vec<ImportedContact>),
+    Export,
+    Classify,
+    Feedback(Feedback),
 }
 
 impl MessageWrapper for Message {
@@ -23,11 +53,115 @@ impl From<Message> for <Message as MessageWrapper>::ParentMessage {
     }
 }
 
-pub struct ContactsManagement;
+pub struct ContactsManagement {
+    pub contacts: Contacts,
+    pub chain_id: Option<u64>,
+    /// Path of the address book to import or export.
+    pub path: Option<String>,
+    /// Category contacts are imported into and exported from.
+    pub category: Category,
+    pub feedback: Option<Feedback>,
+}
 
 impl ContactsManagement {
-    pub fn new() -> Self {
-        Self
+    pub fn new(contacts: Contacts) -> Self {
+        Self {
+            contacts,
+            chain_id: None,
+            path: None,
+            category: Category::default(),
+            feedback: None,
+        }
+    }
+
+    pub fn contact_table(&self) -> TableBuilder<Message> {
+        let mut cells: Vec<Vec<CellBuilder<Message>>> = Vec::new();
+
+        for category in Category::all() {
+            for (address, contact) in self.contacts.list(category.clone()) {
+                cells.push(vec![
+                    CellBuilder::new().child(label(&contact.label).secondary().build()),
+                    CellBuilder::new().child(label(format!("{:?}", address)).secondary().build()),
+                    CellBuilder::new().child(label(&contact.class).secondary().build()),
+                    CellBuilder::new().child(label(&category).build()),
+                ]);
+            }
+        }
+
+        // If the table is empty, add a placeholder row.
+        if cells.is_empty() {
+            cells.push(vec![
+                CellBuilder::new().child(label("No contacts saved").secondary().caption().build())
+            ]);
+        }
+
+        ExcaliburTable::new()
+            .headers(vec![
+                "Name".to_string(),
+                "Address".to_string(),
+                "Class".to_string(),
+                "Category".to_string(),
+            ])
+            .build_custom(cells)
+    }
+
+    fn path_input(&self) -> Container<'_, Message> {
+        RpcManagement::form_item(
+            "Address book (.csv or .json)",
+            Column::new().push(
+                ExcaliburInputBuilder::new()
+                    .light_border()
+                    .border_radius(5.0.into())
+                    .placeholder("~/contacts.csv".to_string())
+                    .width(Length::Fill)
+                    .padding(Padding {
+                        top: Sizes::Sm.into(),
+                        bottom: Sizes::Sm.into(),
+                        left: Sizes::Md.into(),
+                        right: Sizes::Md.into(),
+                    })
+                    .size(system::Typography::Headline)
+                    .build(self.path.clone(), Message::ChangePath),
+            ),
+        )
+    }
+
+    fn category_input(&self) -> Container<'_, Message> {
+        RpcManagement::form_item(
+            "Category",
+            Column::new().push(
+                excalibur_select(
+                    Category::all(),
+                    Some(self.category.clone()),
+                    Message::SelectCategory,
+                    "Select category",
+                    Some(5.0.into()),
+                )
+                .padding(Sizes::Sm)
+                .width(Length::Fill),
+            ),
+        )
+    }
+
+    fn button<'a>(title: &str, on_press: Option<Message>) -> Button<'a, Message> {
+        let button = ExcaliburButton::new()
+            .primary()
+            .border_radius(5.0.into())
+            .build(label(title).build())
+            .padding(Sizes::Sm);
+        match on_press {
+            Some(message) => button.on_press(message),
+            None => button,
+        }
+    }
+
+    /// Path of the address book, with `~` expanded to the home directory.
+    fn path(&self) -> Option<PathBuf> {
+        let path = self.path.as_ref()?;
+        match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+            (Some(rest), Some(home)) => Some(PathBuf::from(home).join(rest)),
+            _ => Some(PathBuf::from(path)),
+        }
     }
 }
 
@@ -39,14 +173,112 @@ impl State for ContactsManagement {
         Command::none()
     }
 
-    fn update(&mut self, _message: Self::AppMessage) -> Command<Self::AppMessage> {
+    fn update(&mut self, message: Self::AppMessage) -> Command<Self::AppMessage> {
+        match message {
+            Message::Sync(contacts, chain_id) => {
+                self.contacts = contacts;
+                self.chain_id = chain_id;
+            }
+            Message::ChangePath(path) => self.path = path,
+            Message::SelectCategory(category) => self.category = category,
+            Message::Import => {
+                let Some(path) = self.path() else {
+                    return Command::none();
+                };
+                match Contacts::read_file(&path, self.category.clone()) {
+                    Ok(imported) => {
+                        self.feedback = Some(Feedback::Success(format!(
+                            "Importing {} contacts...",
+                            imported.len()
+                        )));
+                        return Command::perform(async {}, move |_| Message::Imported(imported));
+                    }
+                    Err(e) => self.feedback = Some(e.into()),
+                }
+            }
+            // The settings add the imported contacts to the profile.
+            Message::Imported(_) => {}
+            Message::Export => {
+                let Some(path) = self.path() else {
+                    return Command::none();
+                };
+                let chain_id = self.chain_id.unwrap_or(DEFAULT_EXPORT_CHAIN_ID);
+                self.feedback = Some(
+                    match self
+                        .contacts
+                        .export_file(&path, Some(self.category.clone()), chain_id)
+                    {
+                        Ok(_) => Feedback::Success(format!(
+                            "Exported {} contacts to {:?}!",
+                            self.category, path
+                        )),
+                        Err(e) => e.into(),
+                    },
+                );
+            }
+            Message::Classify => {
+                self.feedback = Some(Feedback::Success("Classifying contacts...".to_string()));
+            }
+            Message::Feedback(feedback) => self.feedback = Some(feedback),
+            Message::Empty => {}
+        }
+
         Command::none()
     }
 
     fn view(&self) -> Element<'_, Self::ViewMessage> {
-        Container::new(Column::new().push(label("Select an app to get started.").title2().build()))
+        let mut content = Column::new().spacing(Sizes::Lg).padding(Sizes::Lg);
+
+        let has_contacts = !self.contacts.list_all().is_empty();
+        let upper_half = Column::new()
+            .spacing(Sizes::Md)
+            .push(label("Manage Contacts").title2().primary().middle().build())
+            .push(Self::button(
+                "Classify Contacts",
+                has_contacts.then_some(Message::Classify),
+            ))
+            .push(
+                ExcaliburContainer::default()
+                    .light_border()
+                    .build(self.contact_table().build()),
+            );
+
+        let has_path = self.path.is_some();
+        let form = Row::new()
+            .spacing(Sizes::Sm)
+            .push(self.path_input().width(Length::FillPortion(2)))
+            .push(self.category_input().width(Length::FillPortion(1)))
+            .push(
+                Column::new()
+                    .spacing(Sizes::Md)
+                    .push(label("Into category").secondary().build())
+                    .push(Self::button("Import", has_path.then_some(Message::Import)))
+                    .width(Length::FillPortion(1)),
+            )
+            .push(
+                Column::new()
+                    .spacing(Sizes::Md)
+                    .push(label("From category").secondary().build())
+                    .push(Self::button("Export", has_path.then_some(Message::Export)))
+                    .width(Length::FillPortion(1)),
+            );
+
+        let mut lower_half = Column::new().spacing(Sizes::Md).push(form);
+
+        // if form error, push it as text.
+        if let Some(feedback) = &self.feedback {
+            let label = match feedback {
+                Feedback::Success(message) => label(message.clone()).style(GREEN_400).build(),
+                Feedback::Error(message) => label(message.clone()).style(RED_400).build(),
+            };
+
+            lower_half = lower_half.push(label);
+        }
+
+        content = content.push(upper_half);
+        content = content.push(lower_half);
+        Container::new(content)
             .center_x()
-            .center_y()
             .width(Length::Fill)
             .height(Length::Fill)
             .into()
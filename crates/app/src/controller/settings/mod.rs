@@ -2,7 +2,9 @@
 //! contacts management.
 
 pub mod contacts;
+pub mod profiles;
 pub mod rpc;
+pub mod sessions;
 pub mod signers;
 
 use anyhow::anyhow;
@@ -12,7 +14,9 @@ use iced_aw::Icon;
 use super::*;
 use crate::{
     app::{RootMessage, RootViewMessage, UserProfileMessage},
-    model::user::UserProfile,
+    model::{
+        profiles::DEFAULT_PROFILE, rpcs::NetworkTarget, sessions::SessionStore, user::UserProfile,
+    },
 };
 
 #[derive(Debug, Clone, Default)]
@@ -23,6 +27,8 @@ pub enum Message {
     Rpc(rpc::Message),
     Signers(signers::Message),
     Contacts(contacts::Message),
+    Profiles(profiles::Message),
+    Sessions(sessions::Message),
 }
 
 impl MessageWrapper for Message {
@@ -54,6 +60,8 @@ pub enum Pages {
     Rpc,
     Signers,
     Contacts,
+    Profiles,
+    Sessions,
 }
 
 pub struct SettingsScreen {
@@ -61,15 +69,24 @@ pub struct SettingsScreen {
     pub rpc: rpc::RpcManagement,
     pub signers: signers::SignerManagement,
     pub contacts: contacts::ContactsManagement,
+    pub profiles: profiles::ProfileManagement,
+    pub sessions: sessions::SessionManagement,
 }
 
 impl SettingsScreen {
     pub fn new(user: UserProfile) -> Self {
         Self {
             active: Pages::default(),
-            rpc: rpc::RpcManagement::new(user.rpcs.clone()),
-            signers: signers::SignerManagement::new(),
-            contacts: contacts::ContactsManagement::new(),
+            rpc: rpc::RpcManagement::new(user.rpcs.clone(), user.network.clone()),
+            signers: signers::SignerManagement::new(user.signers.clone()),
+            contacts: contacts::ContactsManagement::new(user.contacts.clone()),
+            profiles: profiles::ProfileManagement::new(
+                user.name.clone().unwrap_or(DEFAULT_PROFILE.to_string()),
+            ),
+            sessions: sessions::SessionManagement::new(
+                SessionStore::for_profile(&user),
+                user.session.clone(),
+            ),
         }
     }
 
@@ -89,6 +106,27 @@ impl SettingsScreen {
                 self.active == Pages::Signers,
                 false,
             ),
+            NavigationStep::new(
+                Icon::People,
+                "Contacts",
+                Message::Route(Pages::Contacts).into(),
+                self.active == Pages::Contacts,
+                false,
+            ),
+            NavigationStep::new(
+                Icon::Person,
+                "Profiles",
+                Message::Route(Pages::Profiles).into(),
+                self.active == Pages::Profiles,
+                false,
+            ),
+            NavigationStep::new(
+                Icon::ClockHistory,
+                "Sandbox",
+                Message::Route(Pages::Sessions).into(),
+                self.active == Pages::Sessions,
+                false,
+            ),
         ]
     }
 
@@ -112,6 +150,15 @@ impl State for SettingsScreen {
         Command::none()
     }
 
+    fn subscription(&self) -> Subscription<Self::AppMessage> {
+        // Endpoint stats are only shown on the rpc page.
+        match self.active {
+            Pages::Rpc => iced::time::every(std::time::Duration::from_secs(2))
+                .map(|_| Message::Rpc(rpc::Message::RefreshHealth).into()),
+            _ => Subscription::none(),
+        }
+    }
+
     fn update(&mut self, message: Self::AppMessage) -> Command<Self::AppMessage> {
         if let Self::AppMessage::View(view::Message::Settings(message)) = message {
             match message {
@@ -169,17 +216,146 @@ impl State for SettingsScreen {
                             }
                         }
                     }
+                    rpc::Message::RefreshHealth => {
+                        Command::perform(async {}, |_| app::Message::RefreshRpcHealth)
+                    }
+                    rpc::Message::Connect | rpc::Message::ConnectSandbox => {
+                        let target = match message {
+                            rpc::Message::Connect => self
+                                .rpc
+                                .selected_rpc()
+                                .and_then(|rpc| self.rpc.storage.target(&rpc.name)),
+                            _ => Some(NetworkTarget::Sandbox),
+                        };
+
+                        let mut commands = vec![];
+                        if let Some(target) = target {
+                            commands.push(Command::perform(async {}, move |_| {
+                                app::Message::SwitchNetwork(target)
+                            }));
+                        }
+                        commands.push(self.rpc.update(message).map(|x| Message::Rpc(x).into()));
+                        Command::batch(commands)
+                    }
                     _ => self.rpc.update(message).map(|x| Message::Rpc(x).into()),
                 },
-                Message::Signers(message) => self
-                    .signers
-                    .update(message)
-                    .map(|x| Message::Signers(x).into()),
-
-                Message::Contacts(message) => self
-                    .contacts
-                    .update(message)
-                    .map(|x| Message::Contacts(x).into()),
+                Message::Signers(message) => {
+                    // Changes to the keystore are made to the profile, which
+                    // syncs them back to the signers screen.
+                    let root: Option<RootMessage> = match &message {
+                        signers::Message::Imported(Ok(entry)) => {
+                            Some(UserProfileMessage::AddSigner(entry.clone()).into())
+                        }
+                        signers::Message::Rename => self
+                            .signers
+                            .selected
+                            .clone()
+                            .zip(self.signers.new_name.clone())
+                            .map(|(id, name)| UserProfileMessage::RenameSigner(id, name).into()),
+                        signers::Message::ConfirmDelete => self
+                            .signers
+                            .selected
+                            .clone()
+                            .map(|id| UserProfileMessage::RemoveSigner(id).into()),
+                        signers::Message::Unlocked(Ok((id, wallet))) => Some(
+                            app::Message::ConnectSigner(Some(id.clone()), wallet.clone().into()),
+                        ),
+                        signers::Message::UseLedger => match &self.signers.ledger {
+                            signers::LedgerConnection::Connected(ledger, _) => Some(
+                                app::Message::ConnectSigner(None, ledger.as_ref().clone().into()),
+                            ),
+                            _ => None,
+                        },
+                        _ => None,
+                    };
+
+                    let mut commands = vec![];
+                    if let Some(root) = root {
+                        commands.push(Command::perform(async {}, move |_| root));
+                    }
+                    commands.push(
+                        self.signers
+                            .update(message)
+                            .map(|x| Message::Signers(x).into()),
+                    );
+                    Command::batch(commands)
+                }
+
+                Message::Contacts(message) => {
+                    // Contacts are changed in the profile, which syncs them
+                    // back to the contacts screen.
+                    let root: Option<RootMessage> = match &message {
+                        contacts::Message::Imported(imported) => {
+                            Some(UserProfileMessage::ImportContacts(imported.clone()).into())
+                        }
+                        contacts::Message::Classify => {
+                            let addresses = self
+                                .contacts
+                                .contacts
+                                .list_all()
+                                .into_iter()
+                                .map(|(address, _)| *address)
+                                .collect();
+                            Some(app::Message::ClassifyContacts(addresses))
+                        }
+                        _ => None,
+                    };
+
+                    let mut commands = vec![];
+                    if let Some(root) = root {
+                        commands.push(Command::perform(async {}, move |_| root));
+                    }
+                    commands.push(
+                        self.contacts
+                            .update(message)
+                            .map(|x| Message::Contacts(x).into()),
+                    );
+                    Command::batch(commands)
+                }
+                Message::Profiles(message) => {
+                    let mut commands = vec![];
+                    if let (profiles::Message::Switch, Some(name)) =
+                        (&message, self.profiles.selected.clone())
+                    {
+                        commands.push(Command::perform(async {}, move |_| {
+                            app::Message::SwitchProfile(name)
+                        }));
+                    }
+                    commands.push(
+                        self.profiles
+                            .update(message)
+                            .map(|x| Message::Profiles(x).into()),
+                    );
+                    Command::batch(commands)
+                }
+                Message::Sessions(message) => {
+                    // Saving and restoring sessions need the client.
+                    let root = match &message {
+                        sessions::Message::Save => {
+                            self.sessions.name.clone().map(app::Message::SaveSession)
+                        }
+                        sessions::Message::Restore => self
+                            .sessions
+                            .selected_session()
+                            .cloned()
+                            .map(app::Message::RestoreSession),
+                        sessions::Message::Forked(name) => {
+                            Some(app::Message::RestoreSession(name.clone()))
+                        }
+                        _ => None,
+                    };
+
+                    let mut commands = vec![];
+                    if let Some(root) = root {
+                        commands.push(Command::perform(async {}, move |_| root));
+                    }
+                    commands.push(
+                        self.sessions
+                            .update(message)
+                            .map(|x| Message::Sessions(x).into()),
+                    );
+                    Command::batch(commands)
+                }
                 Message::Route(page) => self.switch_page(page).map(|x| x.into()),
                 _ => Command::none(),
             }
@@ -195,6 +371,18 @@ impl State for SettingsScreen {
         let nav_content = match self.active {
             Pages::Rpc => self.rpc.view().map(move |x| Message::Rpc(x).into()),
             Pages::Signers => self.signers.view().map(move |x| Message::Signers(x).into()),
+            Pages::Contacts => self
+                .contacts
+                .view()
+                .map(move |x| Message::Contacts(x).into()),
+            Pages::Profiles => self
+                .profiles
+                .view()
+                .map(move |x| Message::Profiles(x).into()),
+            Pages::Sessions => self
+                .sessions
+                .view()
+                .map(move |x| Message::Sessions(x).into()),
             _ => Column::new().into(),
         };
 
@@ -0,0 +1,300 @@
+//! Profiles are separate workspaces, each with its own contacts, RPCs, coins,
+//! portfolio and sandbox. Switching profiles saves the open one and loads the
+//! app again with the other.
+
+use iced::{widget::Button, Padding};
+
+use self::system::{ExcaliburContainer, ExcaliburInputBuilder, ExcaliburTable};
+use super::{
+    rpc::{Feedback, RpcManagement},
+    *,
+};
+use crate::{
+    components::{
+        system::{label, ExcaliburButton},
+        tables::{builder::TableBuilder, cells::CellBuilder},
+    },
+    model::profiles,
+};
+
+#[derive(Debug, Default, Clone)]
+pub enum Message {
+    #[default]
+    Empty,
+    Select(bool, String),
+    ChangeName(Option<String>),
+    Create,
+    Duplicate,
+    Delete,
+    Switch,
+    Feedback(Feedback),
+}
+
+impl MessageWrapper for Message {
+    type ParentMessage = super::Message;
+}
+
+impl MessageWrapperView for Message {
+    type ParentMessage = super::Message;
+}
+
+impl From<Message> for <Message as MessageWrapper>::ParentMessage {
+    fn from(message: Message) -> Self {
+        Self::Profiles(message)
+    }
+}
+
+pub struct ProfileManagement {
+    /// Name of the open profile.
+    pub active: String,
+    pub profiles: Vec<String>,
+    /// Name of the profile selected in the table.
+    pub selected: Option<String>,
+    pub name: Option<String>,
+    pub feedback: Option<Feedback>,
+}
+
+impl ProfileManagement {
+    pub fn new(active: String) -> Self {
+        let mut screen = Self {
+            active,
+            profiles: vec![],
+            selected: None,
+            name: None,
+            feedback: None,
+        };
+        screen.refresh();
+        screen
+    }
+
+    /// Lists the saved profiles again, after one is added or removed.
+    fn refresh(&mut self) {
+        match profiles::list() {
+            Ok(list) => self.profiles = list,
+            Err(e) => {
+                tracing::error!("Failed to list profiles: {:?}", e);
+                self.feedback = Some(e.into());
+            }
+        }
+        if let Some(selected) = &self.selected {
+            if !self.profiles.contains(selected) {
+                self.selected = None;
+            }
+        }
+    }
+
+    pub fn profile_table(&self) -> TableBuilder<Message> {
+        let mut cells: Vec<Vec<CellBuilder<Message>>> = Vec::new();
+
+        for profile in self.profiles.iter() {
+            let active = profile == &self.active;
+            let name = profile.clone();
+            cells.push(vec![
+                CellBuilder::new().child(label(profile).secondary().build()),
+                CellBuilder::new().child(label(if active { "Active" } else { "" }).build()),
+                CellBuilder::new()
+                    .checked(Some(self.selected.as_ref() == Some(profile)))
+                    .on_checkbox(move |x| Message::Select(x, name.clone())),
+            ]);
+        }
+
+        // If the table is empty, add a placeholder row.
+        if cells.is_empty() {
+            cells.push(vec![
+                CellBuilder::new().child(label("No profiles saved").secondary().caption().build())
+            ]);
+        }
+
+        ExcaliburTable::new()
+            .headers(vec![
+                "Name".to_string(),
+                "Active".to_string(),
+                "Select".to_string(),
+            ])
+            .build_custom(cells)
+    }
+
+    fn name_input(&self) -> Container<'_, Message> {
+        RpcManagement::form_item(
+            "Name",
+            Column::new().push(
+                ExcaliburInputBuilder::new()
+                    .light_border()
+                    .border_radius(5.0.into())
+                    .placeholder("research".to_string())
+                    .width(Length::Fill)
+                    .padding(Padding {
+                        top: Sizes::Sm.into(),
+                        bottom: Sizes::Sm.into(),
+                        left: Sizes::Md.into(),
+                        right: Sizes::Md.into(),
+                    })
+                    .size(system::Typography::Headline)
+                    .build(self.name.clone(), Message::ChangeName),
+            ),
+        )
+    }
+
+    fn button<'a>(title: &str, on_press: Option<Message>) -> Button<'a, Message> {
+        let button = ExcaliburButton::new()
+            .primary()
+            .border_radius(5.0.into())
+            .build(label(title).build())
+            .padding(Sizes::Sm);
+        match on_press {
+            Some(message) => button.on_press(message),
+            None => button,
+        }
+    }
+}
+
+impl State for ProfileManagement {
+    type AppMessage = Message;
+    type ViewMessage = Message;
+
+    fn load(&self) -> Command<Self::AppMessage> {
+        Command::none()
+    }
+
+    fn update(&mut self, message: Self::AppMessage) -> Command<Self::AppMessage> {
+        match message {
+            Message::Select(selected, name) => self.selected = selected.then_some(name),
+            Message::ChangeName(name) => self.name = name,
+            Message::Create => {
+                let Some(name) = self.name.clone() else {
+                    return Command::none();
+                };
+                self.feedback = Some(match profiles::create(&name) {
+                    Ok(_) => {
+                        self.name = None;
+                        Feedback::Success(format!("Created profile {}!", name))
+                    }
+                    Err(e) => e.into(),
+                });
+                self.refresh();
+            }
+            Message::Duplicate => {
+                let Some(name) = self.name.clone() else {
+                    return Command::none();
+                };
+                let from = self.selected.clone().unwrap_or(self.active.clone());
+                self.feedback = Some(match profiles::duplicate(&from, &name) {
+                    Ok(_) => {
+                        self.name = None;
+                        Feedback::Success(format!(
+                            "Copied {} to {}! Signers aren't copied.",
+                            from, name
+                        ))
+                    }
+                    Err(e) => e.into(),
+                });
+                self.refresh();
+            }
+            Message::Delete => {
+                let Some(name) = self.selected.clone() else {
+                    return Command::none();
+                };
+                self.feedback = Some(match profiles::delete(&name, &self.active) {
+                    Ok(_) => Feedback::Success(format!("Deleted profile {}!", name)),
+                    Err(e) => e.into(),
+                });
+                self.refresh();
+            }
+            Message::Switch => {
+                if let Some(name) = &self.selected {
+                    self.feedback = Some(Feedback::Success(format!(
+                        "Saving and switching to {}...",
+                        name
+                    )));
+                }
+            }
+            Message::Feedback(feedback) => self.feedback = Some(feedback),
+            Message::Empty => {}
+        }
+
+        Command::none()
+    }
+
+    fn view(&self) -> Element<'_, Self::ViewMessage> {
+        let mut content = Column::new().spacing(Sizes::Lg).padding(Sizes::Lg);
+
+        let other_selected = self
+            .selected
+            .as_ref()
+            .filter(|name| *name != &self.active)
+            .is_some();
+        let mut delete_button = ExcaliburButton::new()
+            .danger()
+            .build(label("Delete Profile").build())
+            .padding(Sizes::Sm);
+        if other_selected {
+            delete_button = delete_button.on_press(Message::Delete);
+        }
+
+        let actions = Row::new()
+            .spacing(Sizes::Md)
+            .push(Self::button(
+                "Switch to Profile",
+                other_selected.then_some(Message::Switch),
+            ))
+            .push(delete_button);
+
+        let upper_half = Column::new()
+            .spacing(Sizes::Md)
+            .push(
+                label(format!("Manage Profiles, using {}", self.active))
+                    .title2()
+                    .primary()
+                    .middle()
+                    .build(),
+            )
+            .push(actions)
+            .push(
+                ExcaliburContainer::default()
+                    .light_border()
+                    .build(self.profile_table().build()),
+            );
+
+        let has_name = self.name.is_some();
+        let form = Row::new()
+            .spacing(Sizes::Sm)
+            .push(self.name_input().width(Length::FillPortion(2)))
+            .push(
+                Column::new()
+                    .spacing(Sizes::Md)
+                    .push(label("New").secondary().build())
+                    .push(Self::button("Create", has_name.then_some(Message::Create)))
+                    .width(Length::FillPortion(1)),
+            )
+            .push(
+                Column::new()
+                    .spacing(Sizes::Md)
+                    .push(label("Copy selected").secondary().build())
+                    .push(Self::button(
+                        "Duplicate",
+                        has_name.then_some(Message::Duplicate),
+                    ))
+                    .width(Length::FillPortion(1)),
+            );
+
+        let mut lower_half = Column::new().spacing(Sizes::Md).push(form);
+
+        // if form error, push it as text.
+        if let Some(feedback) = &self.feedback {
+            let label = match feedback {
+                Feedback::Success(message) => label(message.clone()).style(GREEN_400).build(),
+                Feedback::Error(message) => label(message.clone()).style(RED_400).build(),
+            };
+
+            lower_half = lower_half.push(label);
+        }
+
+        content = content.push(upper_half);
+        content = content.push(lower_half);
+        Container::new(content)
+            .center_x()
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+}
@@ -3,6 +3,8 @@
 
 use std::collections::HashMap;
 
+use clients::rpc::EndpointHealth;
+
 use iced::Padding;
 
 use self::{
@@ -38,12 +40,22 @@ pub enum Message {
     ChangeName(Option<String>),
     ChangeChainId(Option<String>),
     ChangeUrl(Option<String>),
+    ChangeRateLimit(Option<String>),
     SelectedRPC(bool, Option<String>),
     Sync(RPCList),
     Feedback(Feedback),
     Delete,
     Submit,
     Reset,
+    /// Switches to the selected RPC.
+    Connect,
+    /// Switches to the local sandbox.
+    ConnectSandbox,
+    /// The app switched networks, to a saved RPC or the sandbox if `None`.
+    Connected(Option<String>),
+    /// Asks the app for the stats of the connected endpoints.
+    RefreshHealth,
+    Health(Vec<EndpointHealth>),
 }
 
 impl MessageWrapper for Message {
@@ -65,6 +77,7 @@ pub struct Form {
     pub name: Option<String>,
     pub chain_id: Option<String>,
     pub url: Option<String>,
+    pub rate_limit: Option<String>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -73,15 +86,29 @@ pub struct RpcManagement {
     pub chain_packet: Option<Form>,
     pub selected_rpcs: HashMap<String, bool>,
     pub form_feedback: Option<Feedback>,
+    /// Name of the connected RPC, or `None` for the sandbox.
+    pub connected: Option<String>,
+    /// Stats of the connected endpoints.
+    pub health: Vec<EndpointHealth>,
 }
 
 impl RpcManagement {
-    pub fn new(storage: RPCList) -> Self {
+    pub fn new(storage: RPCList, connected: Option<String>) -> Self {
         Self {
             storage,
             chain_packet: None,
             selected_rpcs: HashMap::new(),
             form_feedback: None,
+            connected,
+            health: Vec::new(),
+        }
+    }
+
+    /// The selected RPC, if exactly one is selected.
+    pub fn selected_rpc(&self) -> Option<&RPCValue> {
+        match self.selected_rpcs.keys().collect::<Vec<_>>().as_slice() {
+            [name] => self.storage.get(name),
+            _ => None,
         }
     }
 
@@ -98,6 +125,16 @@ impl RpcManagement {
             let name = chain_packet.name.clone();
             let chain_id = chain_packet.chain_id.clone();
             let url = chain_packet.url.clone();
+            let rate_limit = chain_packet
+                .rate_limit
+                .as_deref()
+                .filter(|rate_limit| !rate_limit.is_empty())
+                .map(|rate_limit| {
+                    rate_limit.parse::<u32>().map_err(|_| {
+                        anyhow::anyhow!("Rate limit must be a number!").context("Rate limit error")
+                    })
+                })
+                .transpose()?;
 
             if let (Some(name), Some(chain_id), Some(url)) = (name, chain_id, url) {
                 let chain_id = chain_id.parse::<u64>().map_err(|_| {
@@ -107,6 +144,7 @@ impl RpcManagement {
                     name,
                     chain_id,
                     url,
+                    rate_limit,
                 };
                 return Ok(chain_packet);
             }
@@ -184,10 +222,33 @@ impl RpcManagement {
         let mut cells: Vec<Vec<CellBuilder<Message>>> = Vec::new();
 
         for rpc in rpcs {
+            // Stats are only kept for the endpoints of the connected network.
+            let health = self.health.iter().find(|health| health.url == rpc.url);
+            let rate_limit = rpc
+                .rate_limit
+                .map_or("-".to_string(), |rate_limit| format!("{}/s", rate_limit));
+            let (latency, errors, score) = match health {
+                Some(health) => (
+                    health.latency.map_or("-".to_string(), |latency| {
+                        format!("{}ms", latency.as_millis())
+                    }),
+                    format!("{}/{}", health.errors, health.requests),
+                    match health.available {
+                        true => format!("{:.2}", health.score),
+                        false => "Cooling down".to_string(),
+                    },
+                ),
+                None => ("-".to_string(), "-".to_string(), "-".to_string()),
+            };
+
             cells.push(vec![
                 CellBuilder::new().child(label(&rpc.name.clone()).secondary().build()),
                 CellBuilder::new().child(label(&rpc.chain_id.to_string()).secondary().build()),
                 CellBuilder::new().child(label(&rpc.url.clone()).secondary().build()),
+                CellBuilder::new().child(label(rate_limit).secondary().build()),
+                CellBuilder::new().child(label(latency).secondary().build()),
+                CellBuilder::new().child(label(errors).secondary().build()),
+                CellBuilder::new().child(label(score).secondary().build()),
                 CellBuilder::new()
                     .checked(selected_rpcs.get(&rpc.name.clone()).cloned())
                     .on_checkbox(move |x| Message::SelectedRPC(x, Some(rpc.name.clone()))),
@@ -199,6 +260,10 @@ impl RpcManagement {
                 "Name".to_string(),
                 "Chain ID".to_string(),
                 "URL".to_string(),
+                "Rate Limit".to_string(),
+                "Latency".to_string(),
+                "Errors".to_string(),
+                "Score".to_string(),
                 "Select".to_string(),
             ])
             .build_custom(cells)
@@ -255,6 +320,11 @@ impl State for RpcManagement {
                     chain_packet.url = url;
                 }
             }
+            Message::ChangeRateLimit(rate_limit) => {
+                if let Some(chain_packet) = &mut self.chain_packet {
+                    chain_packet.rate_limit = rate_limit;
+                }
+            }
             Message::SelectedRPC(selected, name) => {
                 tracing::debug!("Selected RPC: {:?} {:?}", selected, name);
                 // Add to map if selected, else remove it.
@@ -296,6 +366,16 @@ impl State for RpcManagement {
                 tracing::debug!("Got form feedback: {:?}", feedback);
                 self.form_feedback = Some(feedback);
             }
+            Message::Connect | Message::ConnectSandbox => {
+                self.form_feedback = Some(Feedback::Success("Connecting...".to_string()));
+            }
+            Message::Connected(name) => {
+                self.connected = name;
+                self.selected_rpcs.clear();
+            }
+            Message::Health(health) => {
+                self.health = health;
+            }
             _ => {}
         }
 
@@ -316,6 +396,23 @@ impl State for RpcManagement {
             delete_button = delete_button.on_press(Message::Delete);
         }
 
+        // Only one RPC can be connected to at a time.
+        let mut connect_button = ExcaliburButton::new()
+            .primary()
+            .build(label("Connect").build())
+            .padding(Sizes::Sm);
+        if self.selected_rpc().is_some() {
+            connect_button = connect_button.on_press(Message::Connect);
+        }
+
+        let mut sandbox_button = ExcaliburButton::new()
+            .primary()
+            .build(label("Use Sandbox").build())
+            .padding(Sizes::Sm);
+        if self.connected.is_some() {
+            sandbox_button = sandbox_button.on_press(Message::ConnectSandbox);
+        }
+
         let actions = Row::new()
             .spacing(Sizes::Md)
             .push(
@@ -325,11 +422,19 @@ impl State for RpcManagement {
                     .padding(Sizes::Sm)
                     .on_press(Message::AddRpc),
             )
-            .push(delete_button);
+            .push(delete_button)
+            .push(connect_button)
+            .push(sandbox_button);
+
+        let connected = match &self.connected {
+            Some(name) => format!("Connected to {}", name),
+            None => "Connected to the sandbox".to_string(),
+        };
 
         let upper_half = Column::new()
             .spacing(Sizes::Md)
             .push(label("Manage RPC Settings").title2().build())
+            .push(label(connected).secondary().build())
             .push(actions)
             .push(
                 ExcaliburContainer::default()
@@ -396,6 +501,25 @@ impl State for RpcManagement {
                 ),
             );
 
+            let labeled_rate_limit_input = RpcManagement::form_item(
+                "Rate limit (requests/s)",
+                Column::new().push(
+                    ExcaliburInputBuilder::new()
+                        .light_border()
+                        .border_radius(5.0.into())
+                        .placeholder("Optional".to_string())
+                        .width(Length::Fill)
+                        .padding(Padding {
+                            top: Sizes::Sm.into(),
+                            bottom: Sizes::Sm.into(),
+                            left: Sizes::Md.into(),
+                            right: Sizes::Md.into(),
+                        })
+                        .size(system::Typography::Headline)
+                        .build(chain_packet.rate_limit.clone(), Message::ChangeRateLimit),
+                ),
+            );
+
             let submit_button = ExcaliburButton::new()
                 .primary()
                 .border_radius(5.0.into())
@@ -406,7 +530,8 @@ impl State for RpcManagement {
             let row_1 = Row::new()
                 .spacing(Sizes::Sm)
                 .push(labeled_name_input.width(Length::FillPortion(2)))
-                .push(labeled_chain_id_input.width(Length::FillPortion(2)));
+                .push(labeled_chain_id_input.width(Length::FillPortion(2)))
+                .push(labeled_rate_limit_input.width(Length::FillPortion(2)));
 
             let row_2 = Row::new()
                 .spacing(Sizes::Sm)
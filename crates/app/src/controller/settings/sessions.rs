@@ -0,0 +1,381 @@
+//! Sandbox sessions are saved states of the sandbox, which can be restored,
+//! forked and compared. The active session is where the sandbox is saved when
+//! it's left.
+
+use iced::{widget::Button, Padding};
+
+use self::system::{ExcaliburContainer, ExcaliburInputBuilder, ExcaliburTable};
+use super::{
+    rpc::{Feedback, RpcManagement},
+    *,
+};
+use crate::{
+    components::{
+        system::{label, ExcaliburButton},
+        tables::{builder::TableBuilder, cells::CellBuilder},
+    },
+    model::sessions::{self, SandboxSession, SessionStore},
+};
+
+#[derive(Debug, Default, Clone)]
+pub enum Message {
+    #[default]
+    Empty,
+    Sync(Option<String>),
+    Select(bool, String),
+    ChangeName(Option<String>),
+    Save,
+    Restore,
+    Fork,
+    Forked(String),
+    Diff,
+    Delete,
+    Feedback(Feedback),
+}
+
+impl MessageWrapper for Message {
+    type ParentMessage = super::Message;
+}
+
+impl MessageWrapperView for Message {
+    type ParentMessage = super::Message;
+}
+
+impl From<Message> for <Message as MessageWrapper>::ParentMessage {
+    fn from(message: Message) -> Self {
+        Self::Sessions(message)
+    }
+}
+
+pub struct SessionManagement {
+    pub store: SessionStore,
+    /// Name of the active session.
+    pub active: Option<String>,
+    pub sessions: Vec<SandboxSession>,
+    /// Names of the sessions selected in the table, two at most to compare
+    /// them.
+    pub selected: Vec<String>,
+    pub name: Option<String>,
+    /// Changes between the two selected sessions.
+    pub diff: Option<Vec<String>>,
+    pub feedback: Option<Feedback>,
+}
+
+impl SessionManagement {
+    pub fn new(store: SessionStore, active: Option<String>) -> Self {
+        let mut screen = Self {
+            store,
+            active,
+            sessions: vec![],
+            selected: vec![],
+            name: None,
+            diff: None,
+            feedback: None,
+        };
+        screen.refresh();
+        screen
+    }
+
+    /// Lists the saved sessions again, newest first.
+    fn refresh(&mut self) {
+        match self.store.list() {
+            Ok(mut list) => {
+                list.reverse();
+                self.sessions = list;
+            }
+            Err(e) => {
+                tracing::error!("Failed to list sessions: {:?}", e);
+                self.feedback = Some(e.into());
+            }
+        }
+        let sessions = &self.sessions;
+        self.selected
+            .retain(|name| sessions.iter().any(|session| &session.name == name));
+    }
+
+    /// The session selected in the table, if only one is.
+    pub fn selected_session(&self) -> Option<&String> {
+        match self.selected.as_slice() {
+            [name] => Some(name),
+            _ => None,
+        }
+    }
+
+    pub fn session_table(&self) -> TableBuilder<Message> {
+        let mut cells: Vec<Vec<CellBuilder<Message>>> = Vec::new();
+
+        for session in self.sessions.iter() {
+            let active = self.active.as_ref() == Some(&session.name);
+            let name = session.name.clone();
+            cells.push(vec![
+                CellBuilder::new().child(label(&session.name).secondary().build()),
+                CellBuilder::new().child(label(session.block_number).secondary().build()),
+                CellBuilder::new().child(label(session.backend).secondary().build()),
+                CellBuilder::new().child(label(&session.origin).secondary().build()),
+                CellBuilder::new().child(
+                    label(session.created_at.format("%Y-%m-%d %H:%M:%S"))
+                        .secondary()
+                        .build(),
+                ),
+                CellBuilder::new().child(label(if active { "Active" } else { "" }).build()),
+                CellBuilder::new()
+                    .checked(Some(self.selected.contains(&session.name)))
+                    .on_checkbox(move |x| Message::Select(x, name.clone())),
+            ]);
+        }
+
+        // If the table is empty, add a placeholder row.
+        if cells.is_empty() {
+            cells.push(vec![
+                CellBuilder::new().child(label("No sessions saved").secondary().caption().build())
+            ]);
+        }
+
+        ExcaliburTable::new()
+            .headers(vec![
+                "Name".to_string(),
+                "Block".to_string(),
+                "Sandbox".to_string(),
+                "Origin".to_string(),
+                "Saved".to_string(),
+                "Active".to_string(),
+                "Select".to_string(),
+            ])
+            .build_custom(cells)
+    }
+
+    fn name_input(&self) -> Container<'_, Message> {
+        RpcManagement::form_item(
+            "Name",
+            Column::new().push(
+                ExcaliburInputBuilder::new()
+                    .light_border()
+                    .border_radius(5.0.into())
+                    .placeholder("baseline".to_string())
+                    .width(Length::Fill)
+                    .padding(Padding {
+                        top: Sizes::Sm.into(),
+                        bottom: Sizes::Sm.into(),
+                        left: Sizes::Md.into(),
+                        right: Sizes::Md.into(),
+                    })
+                    .size(system::Typography::Headline)
+                    .build(self.name.clone(), Message::ChangeName),
+            ),
+        )
+    }
+
+    fn button<'a>(title: &str, on_press: Option<Message>) -> Button<'a, Message> {
+        let button = ExcaliburButton::new()
+            .primary()
+            .border_radius(5.0.into())
+            .build(label(title).build())
+            .padding(Sizes::Sm);
+        match on_press {
+            Some(message) => button.on_press(message),
+            None => button,
+        }
+    }
+}
+
+impl State for SessionManagement {
+    type AppMessage = Message;
+    type ViewMessage = Message;
+
+    fn load(&self) -> Command<Self::AppMessage> {
+        Command::none()
+    }
+
+    fn update(&mut self, message: Self::AppMessage) -> Command<Self::AppMessage> {
+        match message {
+            Message::Sync(active) => {
+                self.active = active;
+                self.refresh();
+            }
+            Message::Select(selected, name) => {
+                self.selected.retain(|selected| selected != &name);
+                if selected {
+                    // Keep the two latest selections to compare.
+                    if self.selected.len() == 2 {
+                        self.selected.remove(0);
+                    }
+                    self.selected.push(name);
+                }
+                self.diff = None;
+            }
+            Message::ChangeName(name) => self.name = name,
+            Message::Save => {
+                if let Some(name) = self.name.take() {
+                    self.feedback = Some(Feedback::Success(format!("Saving {}...", name)));
+                }
+            }
+            Message::Restore => {
+                if let Some(name) = self.selected_session().cloned() {
+                    self.feedback = Some(Feedback::Success(format!("Restoring {}...", name)));
+                }
+            }
+            Message::Fork => {
+                let (Some(from), Some(to)) = (self.selected_session().cloned(), self.name.clone())
+                else {
+                    return Command::none();
+                };
+                match self.store.fork(&from, &to) {
+                    Ok(_) => {
+                        self.name = None;
+                        self.feedback =
+                            Some(Feedback::Success(format!("Forked {} to {}...", from, to)));
+                        self.refresh();
+                        return Command::perform(async {}, move |_| Message::Forked(to));
+                    }
+                    Err(e) => self.feedback = Some(e.into()),
+                }
+            }
+            // The settings restore the fork.
+            Message::Forked(_) => {}
+            Message::Diff => {
+                let [a, b] = self.selected.as_slice() else {
+                    return Command::none();
+                };
+                match (self.store.get(a), self.store.get(b)) {
+                    (Ok(a), Ok(b)) => {
+                        // Compare the older session to the newer one.
+                        let (a, b) = match a.created_at <= b.created_at {
+                            true => (a, b),
+                            false => (b, a),
+                        };
+                        let mut lines = vec![format!("From {} to {}:", a.name, b.name)];
+                        let changes = sessions::diff(&a, &b);
+                        if changes.is_empty() {
+                            lines.push("No changes".to_string());
+                        }
+                        lines.extend(changes.iter().map(|change| change.to_string()));
+                        self.diff = Some(lines);
+                        self.feedback = None;
+                    }
+                    (Err(e), _) | (_, Err(e)) => self.feedback = Some(e.into()),
+                }
+            }
+            Message::Delete => {
+                let Some(name) = self.selected_session().cloned() else {
+                    return Command::none();
+                };
+                if self.active.as_ref() == Some(&name) {
+                    self.feedback = Some(Feedback::Error(
+                        "Restore another session before deleting the active one.".to_string(),
+                    ));
+                    return Command::none();
+                }
+                // Sessions are copied to an automatic session before they're
+                // deleted, so they can be restored until it's pruned.
+                let deleted = self.store.get(&name).and_then(|session| {
+                    if !session.is_auto() {
+                        let action = format!("delete session {}", name);
+                        self.store.auto_snapshot(&name, &action)?;
+                    }
+                    self.store.delete(&name)
+                });
+                self.feedback = Some(match deleted {
+                    Ok(_) => Feedback::Success(format!("Deleted session {}!", name)),
+                    Err(e) => e.into(),
+                });
+                self.refresh();
+            }
+            Message::Feedback(feedback) => self.feedback = Some(feedback),
+            Message::Empty => {}
+        }
+
+        Command::none()
+    }
+
+    fn view(&self) -> Element<'_, Self::ViewMessage> {
+        let mut content = Column::new().spacing(Sizes::Lg).padding(Sizes::Lg);
+
+        let one_selected = self.selected_session().is_some();
+        let mut delete_button = ExcaliburButton::new()
+            .danger()
+            .build(label("Delete Session").build())
+            .padding(Sizes::Sm);
+        if one_selected {
+            delete_button = delete_button.on_press(Message::Delete);
+        }
+
+        let actions = Row::new()
+            .spacing(Sizes::Md)
+            .push(Self::button(
+                "Restore Session",
+                one_selected.then_some(Message::Restore),
+            ))
+            .push(Self::button(
+                "Compare Sessions",
+                (self.selected.len() == 2).then_some(Message::Diff),
+            ))
+            .push(delete_button);
+
+        let active = self.active.as_deref().unwrap_or(sessions::DEFAULT_SESSION);
+        let upper_half = Column::new()
+            .spacing(Sizes::Md)
+            .push(
+                label(format!("Manage Sandbox Sessions, using {}", active))
+                    .title2()
+                    .primary()
+                    .middle()
+                    .build(),
+            )
+            .push(actions)
+            .push(
+                ExcaliburContainer::default()
+                    .light_border()
+                    .build(self.session_table().build()),
+            );
+
+        let has_name = self.name.is_some();
+        let form = Row::new()
+            .spacing(Sizes::Sm)
+            .push(self.name_input().width(Length::FillPortion(2)))
+            .push(
+                Column::new()
+                    .spacing(Sizes::Md)
+                    .push(label("Save sandbox").secondary().build())
+                    .push(Self::button("Save", has_name.then_some(Message::Save)))
+                    .width(Length::FillPortion(1)),
+            )
+            .push(
+                Column::new()
+                    .spacing(Sizes::Md)
+                    .push(label("Fork selected").secondary().build())
+                    .push(Self::button(
+                        "Fork",
+                        (has_name && one_selected).then_some(Message::Fork),
+                    ))
+                    .width(Length::FillPortion(1)),
+            );
+
+        let mut lower_half = Column::new().spacing(Sizes::Md).push(form);
+
+        if let Some(diff) = &self.diff {
+            let mut lines = Column::new().spacing(Sizes::Xs);
+            for line in diff {
+                lines = lines.push(label(line).secondary().build());
+            }
+            lower_half = lower_half.push(lines);
+        }
+
+        // if form error, push it as text.
+        if let Some(feedback) = &self.feedback {
+            let label = match feedback {
+                Feedback::Success(message) => label(message.clone()).style(GREEN_400).build(),
+                Feedback::Error(message) => label(message.clone()).style(RED_400).build(),
+            };
+
+            lower_half = lower_half.push(label);
+        }
+
+        content = content.push(upper_half);
+        content = content.push(lower_half);
+        Container::new(content)
+            .center_x()
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+}
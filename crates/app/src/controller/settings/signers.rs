@@ -1,26 +1,55 @@
 //! Signers are any entity that can sign and execute transactions.
 //! These signers can be used within the app.
+//!
+//! Local signers are imported from a private key, a mnemonic or a keystore
+//! file into the encrypted [`Keystore`], and unlocked with their password to
+//! become the active signer.
 
 use alloy_primitives::Address;
 use anyhow::Error;
 use clients::ledger::{types::DerivationType, *};
+use iced::Padding;
 
-use self::system::{ExcaliburContainer, ExcaliburTable};
-use super::*;
-use crate::components::{
-    system::{label, ExcaliburButton},
-    tables::{
-        builder::TableBuilder,
-        cells::{self, CellBuilder},
+use self::system::{ExcaliburContainer, ExcaliburInputBuilder, ExcaliburTable};
+use super::{
+    rpc::{Feedback, RpcManagement},
+    *,
+};
+use crate::{
+    components::{
+        system::{label, ExcaliburButton},
+        tables::{builder::TableBuilder, cells::CellBuilder},
     },
+    model::keystore::{Keystore, SignerEntry, SignerImport, SignerSource},
 };
 
 #[derive(Debug, Default, Clone)]
 pub enum Message {
     #[default]
-    NotConnected,
+    Empty,
     Connected(Result<(Arc<LedgerClient>, Address), Arc<Error>>),
     ConnectLedger,
+    UseLedger,
+    Sync(Keystore),
+    AddSigner,
+    ChangeImportKind(ImportKind),
+    ChangeName(Option<String>),
+    ChangeSecret(Option<String>),
+    ChangeDerivationPath(Option<String>),
+    ChangeImportPassword(Option<String>),
+    Submit,
+    Imported(Result<SignerEntry, Arc<Error>>),
+    Select(bool, String),
+    ChangeNewName(Option<String>),
+    Rename,
+    Delete,
+    /// Deletes the selected signer and its encrypted key, once confirmed.
+    ConfirmDelete,
+    CancelDelete,
+    ChangePassword(Option<String>),
+    Unlock,
+    Unlocked(Result<(String, LocalWallet), Arc<Error>>),
+    Feedback(Feedback),
 }
 
 impl MessageWrapper for Message {
@@ -37,12 +66,70 @@ impl From<Message> for <Message as MessageWrapper>::ParentMessage {
     }
 }
 
-pub enum SignerManagement {
+/// What a signer is imported from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImportKind {
+    #[default]
+    PrivateKey,
+    Mnemonic,
+    Keystore,
+}
+
+impl ImportKind {
+    pub const ALL: [ImportKind; 3] = [
+        ImportKind::PrivateKey,
+        ImportKind::Mnemonic,
+        ImportKind::Keystore,
+    ];
+}
+
+impl std::fmt::Display for ImportKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportKind::PrivateKey => write!(f, "Private key"),
+            ImportKind::Mnemonic => write!(f, "Mnemonic"),
+            ImportKind::Keystore => write!(f, "Keystore file"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ImportForm {
+    pub kind: ImportKind,
+    pub name: Option<String>,
+    /// Private key, mnemonic phrase or keystore path, depending on the kind.
+    pub secret: Option<String>,
+    pub derivation_path: Option<String>,
+    pub password: Option<String>,
+}
+
+impl ImportForm {
+    /// Converts the form input into a name, key material and password, if the
+    /// form is complete.
+    pub fn to_import(&self) -> anyhow::Result<(String, SignerImport, String)> {
+        let (Some(name), Some(secret), Some(password)) = (&self.name, &self.secret, &self.password)
+        else {
+            return Err(anyhow::anyhow!("Name, key and password are required!"));
+        };
+        let import = match self.kind {
+            ImportKind::PrivateKey => SignerImport::PrivateKey(secret.clone()),
+            ImportKind::Mnemonic => SignerImport::Mnemonic {
+                phrase: secret.clone(),
+                derivation_path: self.derivation_path.clone(),
+            },
+            ImportKind::Keystore => SignerImport::Keystore(secret.into()),
+        };
+        Ok((name.clone(), import, password.clone()))
+    }
+}
+
+pub enum LedgerConnection {
     NotConnected,
     Connected(Arc<LedgerClient>, Address),
     Connecting,
     Error,
 }
+
 pub async fn connect_to_ledger() -> Result<(Arc<LedgerClient>, Address), Arc<Error>> {
     let ledger = Arc::new(LedgerClient::new_connection(DerivationType::LedgerLive(0)).await?);
     let address = ledger.get_address().await.unwrap();
@@ -50,50 +137,330 @@ pub async fn connect_to_ledger() -> Result<(Arc<LedgerClient>, Address), Arc<Err
     Ok((ledger, address))
 }
 
-impl SignerManagement {
-    pub fn new() -> Self {
-        Self::NotConnected
-    }
+/// Encrypts the key of a new signer off the UI thread.
+pub async fn import_signer(
+    keystore: Keystore,
+    name: String,
+    import: SignerImport,
+    password: String,
+) -> Result<SignerEntry, Arc<Error>> {
+    tokio::task::spawn_blocking(move || keystore.import(&Keystore::dir(), &name, import, &password))
+        .await
+        .map_err(|e| Arc::new(anyhow::anyhow!(e)))?
+        .map_err(Arc::new)
+}
+
+/// Decrypts the key of a signer off the UI thread.
+pub async fn unlock_signer(
+    keystore: Keystore,
+    id: String,
+    password: String,
+) -> Result<(String, LocalWallet), Arc<Error>> {
+    tokio::task::spawn_blocking(move || {
+        let wallet = keystore.unlock(&Keystore::dir(), &id, &password)?;
+        Ok((id, wallet))
+    })
+    .await
+    .map_err(|e| Arc::new(anyhow::anyhow!(e)))?
+    .map_err(Arc::new)
+}
 
-    pub fn get_table(&self, headers: Vec<String>) -> TableBuilder<Message> {
-        let cells: Vec<Vec<CellBuilder<Message>>> = vec![vec![
-            CellBuilder::new().value(Some("Example Wallet".to_string())),
-            cells::CellBuilder::new().value(Some(
-                "0x0000000000000000000000000000000000000000".to_string(),
-            )),
-        ]];
+pub struct SignerManagement {
+    pub ledger: LedgerConnection,
+    pub keystore: Keystore,
+    pub form: Option<ImportForm>,
+    /// Id of the signer selected in the table.
+    pub selected: Option<String>,
+    pub new_name: Option<String>,
+    pub password: Option<String>,
+    /// Whether deleting the selected signer is waiting to be confirmed.
+    pub confirm_delete: bool,
+    pub feedback: Option<Feedback>,
+}
 
-        ExcaliburTable::new().headers(headers).build_custom(cells)
+impl SignerManagement {
+    pub fn new(keystore: Keystore) -> Self {
+        Self {
+            ledger: LedgerConnection::NotConnected,
+            keystore,
+            form: None,
+            selected: None,
+            new_name: None,
+            password: None,
+            confirm_delete: false,
+            feedback: None,
+        }
     }
 
     pub fn signer_table(&self) -> TableBuilder<Message> {
-        match self {
-            SignerManagement::NotConnected => {
-                self.get_table(vec!["Name".to_string(), "Primary Address".to_string()])
-            }
-            SignerManagement::Connecting => {
-                self.get_table(vec!["Name".to_string(), "Primary Address".to_string()])
-            }
-            SignerManagement::Connected(_ledger, _address) => {
-                self.get_table(vec!["Name".to_string(), _address.to_string()])
-            }
-            SignerManagement::Error => {
-                self.get_table(vec!["Name".to_string(), "Primary Address".to_string()])
-            }
+        let mut cells: Vec<Vec<CellBuilder<Message>>> = Vec::new();
+
+        if let LedgerConnection::Connected(_ledger, address) = &self.ledger {
+            cells.push(vec![
+                CellBuilder::new().child(label("Ledger").secondary().build()),
+                CellBuilder::new().child(label(address).secondary().build()),
+                CellBuilder::new().child(label("Hardware").secondary().build()),
+                CellBuilder::new().child(label("").build()),
+                CellBuilder::new().child(label("").build()),
+            ]);
         }
+
+        for signer in self.keystore.list() {
+            let source = match &signer.source {
+                SignerSource::PrivateKey => "Private key".to_string(),
+                SignerSource::Mnemonic { derivation_path } => {
+                    format!("Mnemonic ({})", derivation_path)
+                }
+                SignerSource::Keystore => "Keystore file".to_string(),
+            };
+            let active = self.keystore.active.as_deref() == Some(signer.id.as_str());
+            let id = signer.id.clone();
+            cells.push(vec![
+                CellBuilder::new().child(label(&signer.name).secondary().build()),
+                CellBuilder::new()
+                    .child(label(format!("{:?}", signer.address)).secondary().build()),
+                CellBuilder::new().child(label(source).secondary().build()),
+                CellBuilder::new().child(label(if active { "Active" } else { "" }).build()),
+                CellBuilder::new()
+                    .checked(Some(self.selected.as_ref() == Some(&signer.id)))
+                    .on_checkbox(move |x| Message::Select(x, id.clone())),
+            ]);
+        }
+
+        // If the table is empty, add a placeholder row.
+        if cells.is_empty() {
+            cells.push(vec![CellBuilder::new()
+                .child(label("No signers imported").secondary().caption().build())]);
+        }
+
+        ExcaliburTable::new()
+            .headers(vec![
+                "Name".to_string(),
+                "Address".to_string(),
+                "Source".to_string(),
+                "Active".to_string(),
+                "Select".to_string(),
+            ])
+            .build_custom(cells)
     }
 
-    pub fn singer_view() -> Row<'static, Message> {
+    fn input<'a>(
+        title: &str,
+        placeholder: &str,
+        value: Option<String>,
+        secure: bool,
+        on_change: impl Fn(Option<String>) -> Message + 'a,
+    ) -> Container<'a, Message> {
+        RpcManagement::form_item(
+            title,
+            Column::new().push(
+                ExcaliburInputBuilder::new()
+                    .light_border()
+                    .border_radius(5.0.into())
+                    .placeholder(placeholder.to_string())
+                    .width(Length::Fill)
+                    .padding(Padding {
+                        top: Sizes::Sm.into(),
+                        bottom: Sizes::Sm.into(),
+                        left: Sizes::Md.into(),
+                        right: Sizes::Md.into(),
+                    })
+                    .size(system::Typography::Headline)
+                    .build(value, on_change)
+                    .secure(secure),
+            ),
+        )
+    }
+
+    fn import_form(form: &ImportForm) -> Column<'_, Message> {
+        let secret = match form.kind {
+            ImportKind::PrivateKey => Self::input(
+                "Private key",
+                "0x...",
+                form.secret.clone(),
+                true,
+                Message::ChangeSecret,
+            ),
+            ImportKind::Mnemonic => Self::input(
+                "Mnemonic",
+                "Twelve or twenty-four words",
+                form.secret.clone(),
+                true,
+                Message::ChangeSecret,
+            ),
+            ImportKind::Keystore => Self::input(
+                "Keystore file",
+                "Path to a JSON keystore",
+                form.secret.clone(),
+                false,
+                Message::ChangeSecret,
+            ),
+        };
+
         let row_1 = Row::new()
             .spacing(Sizes::Sm)
-            .push(label("labeled_name_input").build());
-        let row_2 = Row::new().spacing(Sizes::Sm).push(
-            Column::new()
-                .spacing(Sizes::Md)
-                .push(label("Instructions").build())
+            .push(
+                Container::new(labeled_select(
+                    "Import from".to_string(),
+                    &ImportKind::ALL[..],
+                    Some(form.kind),
+                    Message::ChangeImportKind,
+                ))
+                .width(Length::FillPortion(2)),
+            )
+            .push(
+                Self::input(
+                    "Name",
+                    "Choose a label",
+                    form.name.clone(),
+                    false,
+                    Message::ChangeName,
+                )
                 .width(Length::FillPortion(2)),
-        );
-        Row::new().push(Column::new().push(row_1).push(row_2).spacing(Sizes::Md))
+            );
+
+        let mut row_2 = Row::new()
+            .spacing(Sizes::Sm)
+            .push(secret.width(Length::FillPortion(2)));
+        if form.kind == ImportKind::Mnemonic {
+            row_2 = row_2.push(
+                Self::input(
+                    "Derivation path",
+                    crate::model::keystore::DEFAULT_DERIVATION_PATH,
+                    form.derivation_path.clone(),
+                    false,
+                    Message::ChangeDerivationPath,
+                )
+                .width(Length::FillPortion(2)),
+            );
+        }
+
+        let submit_button = ExcaliburButton::new()
+            .primary()
+            .border_radius(5.0.into())
+            .build(label("Import signer").build())
+            .on_press(Message::Submit)
+            .padding(Sizes::Sm);
+        let row_3 = Row::new()
+            .spacing(Sizes::Sm)
+            .push(
+                Self::input(
+                    "Password",
+                    "Encrypts the key on this device",
+                    form.password.clone(),
+                    true,
+                    Message::ChangeImportPassword,
+                )
+                .width(Length::FillPortion(2)),
+            )
+            .push(
+                Column::new()
+                    .spacing(Sizes::Md)
+                    .push(label("Submit").secondary().build())
+                    .push(submit_button)
+                    .width(Length::FillPortion(2)),
+            );
+
+        Column::new()
+            .push(row_1)
+            .push(row_2)
+            .push(row_3)
+            .spacing(Sizes::Md)
+    }
+
+    /// Asks before deleting the selected signer, since its encrypted key can't
+    /// be recovered once deleted.
+    fn confirm_delete_prompt(&self) -> Row<'_, Message> {
+        let name = self
+            .selected
+            .as_deref()
+            .and_then(|id| self.keystore.get(id))
+            .map(|signer| signer.name.clone())
+            .unwrap_or_default();
+        Row::new()
+            .spacing(Sizes::Sm)
+            .align_items(alignment::Alignment::Center)
+            .push(
+                label(format!(
+                    "Delete {} and its encrypted key? Import it again from a backup to get it back.",
+                    name
+                ))
+                .style(RED_400)
+                .build(),
+            )
+            .push(
+                ExcaliburButton::new()
+                    .danger()
+                    .border_radius(5.0.into())
+                    .build(label("Delete").build())
+                    .on_press(Message::ConfirmDelete)
+                    .padding(Sizes::Sm),
+            )
+            .push(
+                ExcaliburButton::new()
+                    .primary()
+                    .border_radius(5.0.into())
+                    .build(label("Keep").build())
+                    .on_press(Message::CancelDelete)
+                    .padding(Sizes::Sm),
+            )
+    }
+
+    fn selected_actions(&self) -> Row<'_, Message> {
+        let mut rename_button = ExcaliburButton::new()
+            .primary()
+            .border_radius(5.0.into())
+            .build(label("Rename").build())
+            .padding(Sizes::Sm);
+        if self.new_name.is_some() {
+            rename_button = rename_button.on_press(Message::Rename);
+        }
+
+        let mut unlock_button = ExcaliburButton::new()
+            .primary()
+            .border_radius(5.0.into())
+            .build(label("Use signer").build())
+            .padding(Sizes::Sm);
+        if self.password.is_some() {
+            unlock_button = unlock_button.on_press(Message::Unlock);
+        }
+
+        Row::new()
+            .spacing(Sizes::Sm)
+            .push(
+                Self::input(
+                    "Rename",
+                    "New name",
+                    self.new_name.clone(),
+                    false,
+                    Message::ChangeNewName,
+                )
+                .width(Length::FillPortion(2)),
+            )
+            .push(
+                Column::new()
+                    .spacing(Sizes::Md)
+                    .push(label("Save").secondary().build())
+                    .push(rename_button)
+                    .width(Length::FillPortion(1)),
+            )
+            .push(
+                Self::input(
+                    "Password",
+                    "Unlocks the signer",
+                    self.password.clone(),
+                    true,
+                    Message::ChangePassword,
+                )
+                .width(Length::FillPortion(2)),
+            )
+            .push(
+                Column::new()
+                    .spacing(Sizes::Md)
+                    .push(label("Switch").secondary().build())
+                    .push(unlock_button)
+                    .width(Length::FillPortion(1)),
+            )
     }
 }
 
@@ -106,90 +473,216 @@ impl State for SignerManagement {
     }
 
     fn update(&mut self, message: Self::AppMessage) -> Command<Self::AppMessage> {
-        tracing::info!("Update message: {:?}", message);
         match message {
             Message::Connected(Ok(res)) => {
                 tracing::info!("Connected to ledger");
-                *self = SignerManagement::Connected(res.0, res.1);
-                Command::none()
+                self.ledger = LedgerConnection::Connected(res.0, res.1);
             }
             Message::Connected(Err(err)) => {
                 tracing::error!("Error connecting to ledger: {:?}", err);
-                *self = SignerManagement::Error;
-                Command::none()
+                self.ledger = LedgerConnection::Error;
             }
             Message::ConnectLedger => {
-                *self = SignerManagement::Connecting;
-                Command::perform(connect_to_ledger(), Message::Connected)
+                self.ledger = LedgerConnection::Connecting;
+                return Command::perform(connect_to_ledger(), Message::Connected);
+            }
+            Message::UseLedger => {
+                self.feedback = Some(Feedback::Success(
+                    "Switching to Ledger, confirm transactions on the device.".to_string(),
+                ));
+            }
+            Message::Sync(keystore) => {
+                tracing::debug!("Syncing signers in signer settings");
+                if let Some(id) = &self.selected {
+                    if keystore.get(id).is_none() {
+                        self.selected = None;
+                    }
+                }
+                self.keystore = keystore;
+            }
+            Message::AddSigner => {
+                self.form = Some(ImportForm::default());
+                self.feedback = None;
+            }
+            Message::ChangeImportKind(kind) => {
+                if let Some(form) = &mut self.form {
+                    form.kind = kind;
+                    form.secret = None;
+                }
+            }
+            Message::ChangeName(name) => {
+                if let Some(form) = &mut self.form {
+                    form.name = name;
+                }
+            }
+            Message::ChangeSecret(secret) => {
+                if let Some(form) = &mut self.form {
+                    form.secret = secret;
+                }
+            }
+            Message::ChangeDerivationPath(path) => {
+                if let Some(form) = &mut self.form {
+                    form.derivation_path = path;
+                }
+            }
+            Message::ChangeImportPassword(password) => {
+                if let Some(form) = &mut self.form {
+                    form.password = password;
+                }
+            }
+            Message::Submit => {
+                let Some(form) = &self.form else {
+                    return Command::none();
+                };
+                match form.to_import() {
+                    Ok((name, import, password)) => {
+                        self.feedback = Some(Feedback::Success("Encrypting key...".to_string()));
+                        return Command::perform(
+                            import_signer(self.keystore.clone(), name, import, password),
+                            Message::Imported,
+                        );
+                    }
+                    Err(e) => self.feedback = Some(e.into()),
+                }
+            }
+            Message::Imported(Ok(_)) => {
+                // The profile adds the signer, syncs it back and reports how
+                // it went.
+                self.form = None;
+                self.feedback = None;
+            }
+            Message::Imported(Err(e)) => {
+                tracing::error!("Failed to import signer: {:?}", e);
+                self.feedback = Some(Feedback::Error(e.to_string()));
+            }
+            Message::Select(selected, id) => {
+                self.selected = selected.then_some(id);
+                self.new_name = None;
+                self.password = None;
+                self.confirm_delete = false;
             }
-            _ => Command::none(),
+            Message::ChangeNewName(name) => self.new_name = name,
+            // The profile makes the change and reports how it went.
+            Message::Rename => {
+                self.new_name = None;
+                self.feedback = None;
+            }
+            Message::Delete => {
+                self.confirm_delete = true;
+                self.feedback = None;
+            }
+            Message::ConfirmDelete => {
+                self.selected = None;
+                self.confirm_delete = false;
+            }
+            Message::CancelDelete => self.confirm_delete = false,
+            Message::ChangePassword(password) => self.password = password,
+            Message::Unlock => {
+                let (Some(id), Some(password)) = (self.selected.clone(), self.password.take())
+                else {
+                    return Command::none();
+                };
+                return Command::perform(
+                    unlock_signer(self.keystore.clone(), id, password),
+                    Message::Unlocked,
+                );
+            }
+            Message::Unlocked(Ok((id, _))) => {
+                let name = self
+                    .keystore
+                    .get(&id)
+                    .map(|signer| signer.name.clone())
+                    .unwrap_or_default();
+                self.feedback = Some(Feedback::Success(format!("Switching to {}...", name)));
+            }
+            Message::Unlocked(Err(e)) => {
+                self.feedback = Some(Feedback::Error(e.to_string()));
+            }
+            Message::Feedback(feedback) => self.feedback = Some(feedback),
+            Message::Empty => {}
         }
+
+        Command::none()
     }
 
     fn view(&self) -> Element<'_, Self::ViewMessage> {
         let mut content = Column::new().spacing(Sizes::Lg).padding(Sizes::Lg);
 
-        let mut upper_half = Column::new().spacing(Sizes::Md).push(
-            label("Manage Signer Settings")
-                .title2()
-                .primary()
-                .middle()
-                .build(),
-        );
+        let ledger_label = match &self.ledger {
+            LedgerConnection::NotConnected => "Connect Ledger Device".to_string(),
+            LedgerConnection::Connecting => "Connecting".to_string(),
+            LedgerConnection::Connected(_ledger, address) => {
+                format!("Use Ledger wallet {}", address)
+            }
+            LedgerConnection::Error => "Error connecting. Is your ledger plugged in?".to_string(),
+        };
+
+        let mut delete_button = ExcaliburButton::new()
+            .danger()
+            .build(label("Delete Signer").build())
+            .padding(Sizes::Sm);
+        if self.selected.is_some() && !self.confirm_delete {
+            delete_button = delete_button.on_press(Message::Delete);
+        }
+
+        let actions = Row::new()
+            .spacing(Sizes::Md)
+            .push(
+                ExcaliburButton::new()
+                    .primary()
+                    .build(label("Import Signer").build())
+                    .padding(Sizes::Sm)
+                    .on_press(Message::AddSigner),
+            )
+            .push(
+                ExcaliburButton::new()
+                    .primary()
+                    .build(label(ledger_label).build())
+                    .padding(Sizes::Sm)
+                    .on_press(match self.ledger {
+                        LedgerConnection::Connected(..) => Message::UseLedger,
+                        _ => Message::ConnectLedger,
+                    }),
+            )
+            .push(delete_button);
+
+        let upper_half = Column::new()
+            .spacing(Sizes::Md)
+            .push(
+                label("Manage Signer Settings")
+                    .title2()
+                    .primary()
+                    .middle()
+                    .build(),
+            )
+            .push(actions)
+            .push(
+                ExcaliburContainer::default()
+                    .light_border()
+                    .build(self.signer_table().build()),
+            );
 
         let mut lower_half = Column::new().spacing(Sizes::Md);
+        if self.confirm_delete {
+            lower_half = lower_half.push(self.confirm_delete_prompt());
+        }
+        if self.selected.is_some() {
+            lower_half = lower_half.push(self.selected_actions());
+        }
+        if let Some(form) = &self.form {
+            lower_half = lower_half.push(Self::import_form(form));
+        }
+
+        // if form error, push it as text.
+        if let Some(feedback) = &self.feedback {
+            let label = match feedback {
+                Feedback::Success(message) => label(message.clone()).style(GREEN_400).build(),
+                Feedback::Error(message) => label(message.clone()).style(RED_400).build(),
+            };
+
+            lower_half = lower_half.push(label);
+        }
 
-        let (upper_content, lower_content) = match self {
-            SignerManagement::NotConnected => (
-                Row::new().spacing(Sizes::Md).push(
-                    ExcaliburButton::new()
-                        .primary()
-                        .build(label("Connect Ledger Device").build())
-                        .padding(Sizes::Sm)
-                        .on_press(Message::ConnectLedger),
-                ),
-                Row::<Message>::new(),
-            ),
-            SignerManagement::Connecting => (
-                Row::new().spacing(Sizes::Md).push(
-                    ExcaliburButton::new()
-                        .primary()
-                        .build(label("Connecting").build())
-                        .padding(Sizes::Sm)
-                        .on_press(Message::ConnectLedger),
-                ),
-                Row::<Message>::new(),
-            ),
-            SignerManagement::Connected(_ledger, address) => (
-                Row::new().spacing(Sizes::Md).push(
-                    ExcaliburButton::new()
-                        .primary()
-                        .build(
-                            label(format!("Connected to wallet with address : {}", address))
-                                .build(),
-                        )
-                        .padding(Sizes::Sm)
-                        .on_press(Message::ConnectLedger),
-                ),
-                SignerManagement::singer_view(),
-            ),
-            SignerManagement::Error => (
-                Row::new().spacing(Sizes::Md).push(
-                    ExcaliburButton::new()
-                        .primary()
-                        .build(label("Error connecting. Is your ledger plugged in?").build())
-                        .padding(Sizes::Sm)
-                        .on_press(Message::ConnectLedger),
-                ),
-                Row::<Message>::new().spacing(Sizes::Md),
-            ),
-        };
-        upper_half = upper_half.push(upper_content).push(
-            ExcaliburContainer::default()
-                .light_border()
-                .build(self.signer_table().build()),
-        );
-        lower_half = lower_half.push(lower_content);
         content = content.push(upper_half);
         content = content.push(lower_half);
         Container::new(content)
@@ -34,9 +34,11 @@ mod view;
 use std::sync::Arc;
 
 use app::App;
+use clients::{rpc::RpcTransport, signer::ExcaliburSigner};
 use components::{system::ExcaliburTheme, *};
 use controller::*;
 use loader::Loader;
+pub use model::sessions::SandboxBackend;
 use model::Model;
 use styles::*;
 
@@ -47,6 +49,7 @@ use styles::*;
 /// This is the outermost layer of the application.
 pub struct MVP {
     state: State,
+    flags: Flags,
     #[allow(dead_code)]
     tracer: tracer::Tracer,
 }
@@ -74,11 +77,15 @@ pub enum Message {
 }
 
 /// The `Flags` struct represents the flags that can be passed to the
-/// application. It contains a single flag `dev_mode` which indicates whether
-/// the application is running in development mode.
-#[derive(Debug, Clone, Copy)]
+/// application. `dev_mode` indicates whether the application is running in
+/// development mode, `profile` is the name of the profile to open, or the
+/// default profile if it's `None`, and `sandbox` is what runs the sandbox. If
+/// it's `None`, anvil runs it when installed and arbiter otherwise.
+#[derive(Debug, Clone)]
 pub struct Flags {
     pub dev_mode: bool,
+    pub profile: Option<String>,
+    pub sandbox: Option<SandboxBackend>,
 }
 
 /// The `Application` trait implementation for the `MVP` struct.
@@ -118,11 +125,15 @@ impl Application for MVP {
             std::env::set_var("DEV_MODE", "true");
         }
 
-        let (loader, command) = Loader::new(flags);
+        let (loader, command) = Loader::new(flags.clone());
         let state = State::Loader(loader);
 
         (
-            MVP { state, tracer },
+            MVP {
+                state,
+                flags,
+                tracer,
+            },
             command.map(|msg| Message::Load(Box::new(msg))),
         )
     }
@@ -198,15 +209,21 @@ impl Application for MVP {
                 // 2. Loader emits the Load message, update the loader state.
                 _ => l.update(*msg).map(|msg| Message::Load(Box::new(msg))),
             },
-            (State::App(app), Message::Update(msg)) => {
-                if let app::Message::QuitReady = *msg {
-                    return Command::perform(async {}, |()| Message::ForceQuit);
+            (State::App(app), Message::Update(msg)) => match *msg {
+                app::Message::QuitReady => Command::perform(async {}, |()| Message::ForceQuit),
+                app::Message::ProfileSaved(name) => {
+                    // The profile is saved, so load the app again with the next one.
+                    self.flags.profile = Some(name);
+                    let (loader, command) = Loader::new(self.flags.clone());
+                    self.state = State::Loader(loader);
+
+                    command.map(|msg| Message::Load(Box::new(msg)))
                 }
                 // 6. Arrived at main application loop.
                 // note: application loop is by mapping the result of update with Update
                 // message.
-                app.update(*msg).map(|msg| Message::Update(Box::new(msg)))
-            }
+                msg => app.update(msg).map(|msg| Message::Update(Box::new(msg))),
+            },
             _ => Command::none(),
         }
     }
@@ -299,14 +316,24 @@ impl Application for MVP {
 ///
 /// * `dev_mode` - A boolean indicating whether the application should run in
 ///   development mode.
+/// * `profile` - The name of the profile to open, created if it doesn't exist.
+/// * `sandbox` - What runs the sandbox, anvil if it's installed by default.
 ///
 /// # Returns
 ///
 /// * `iced::Result` - The result of running the application. If the application
 ///   runs successfully, it returns `Ok(())`. If an error occurs, it returns
 ///   `Err(e)` where `e` is the error.
-pub fn run(dev_mode: bool) -> iced::Result {
-    let mut settings = Settings::with_flags(Flags { dev_mode });
+pub fn run(
+    dev_mode: bool,
+    profile: Option<String>,
+    sandbox: Option<SandboxBackend>,
+) -> iced::Result {
+    let mut settings = Settings::with_flags(Flags {
+        dev_mode,
+        profile,
+        sandbox,
+    });
     settings.window.icon = Some(logos::excalibur_logo_2());
     settings.antialiasing = true;
     settings.exit_on_close_request = false;
@@ -4,7 +4,7 @@
 
 use std::time::Instant;
 
-use clients::{dev::DevClient, ledger::LedgerClient, protocol::ProtocolClient};
+use clients::{dev::DevClient, ledger::LedgerClient};
 use datatypes::portfolio::coin::Coin;
 use iced::{
     font,
@@ -16,17 +16,26 @@ use sim::from_ethers_address;
 
 use super::{middleware::*, model::contacts, *};
 use crate::{
-    app::AnvilSave,
     components::{
         logos::PhiLogo,
         progress::CustomProgressBar,
         system::{label, ExcaliburContainer},
     },
-    model::user::Saveable,
+    model::{
+        profiles,
+        sessions::{SandboxBackend, SessionStore, DEFAULT_SESSION},
+        storage,
+        user::Saveable,
+    },
 };
 
-type LoadResult =
-    anyhow::Result<(Model, Arc<middleware::ExcaliburMiddleware<Ws, LocalWallet>>), anyhow::Error>;
+type LoadResult = anyhow::Result<
+    (
+        Model,
+        Arc<middleware::ExcaliburMiddleware<RpcTransport, ExcaliburSigner>>,
+    ),
+    anyhow::Error,
+>;
 
 #[derive(Debug)]
 pub enum Message {
@@ -53,16 +62,26 @@ pub struct Loader {
 /// creates a new default model. It then logs the loaded model's user name and
 /// file path.
 #[tracing::instrument(level = "debug")]
-pub fn load_user_data() -> anyhow::Result<Model> {
+pub fn load_user_data(profile: Option<&str>) -> anyhow::Result<Model> {
     // first log we see on start up comes from here
-    let model = Model::load(None);
+    let model = match profile {
+        Some(name) => profiles::load_or_create(name),
+        None => Model::load(None),
+    };
     let model = match model {
         Ok(model) => model,
+        // The profile isn't broken, and is kept rather than replaced.
+        Err(e) if storage::is_intact(&e) => return Err(e),
         Err(e) => {
             tracing::warn!("Failed to load model: {:?}", e);
-            tracing::info!("Creating a new default model.");
+            tracing::info!("Creating a new model for profile {:?}.", profile);
 
-            Model::create_new(None)?
+            match profile {
+                Some(name) if name != profiles::DEFAULT_PROFILE => {
+                    Model::create_new(Some(name.to_string()))?
+                }
+                _ => Model::create_new(None)?,
+            }
         }
     };
 
@@ -75,6 +94,35 @@ pub fn load_user_data() -> anyhow::Result<Model> {
     Ok(model)
 }
 
+/// Imports the sandbox snapshot of a profile saved before sessions as its
+/// default session, unless the profile already has a default session. The
+/// snapshot is kept in the profile until it's imported.
+fn import_legacy_snapshot(model: &mut Model) -> anyhow::Result<()> {
+    let Some(snapshot) = model.user.legacy_snapshot.take() else {
+        return Ok(());
+    };
+
+    let store = SessionStore::for_profile(&model.user);
+    if store.exists(DEFAULT_SESSION) {
+        tracing::info!("Dropping the legacy snapshot, the default session already exists");
+    } else {
+        // Snapshots were only taken of the default sandbox.
+        let contracts = model
+            .user
+            .contracts
+            .get(&31337)
+            .cloned()
+            .unwrap_or_default();
+        if let Err(e) = store.import_legacy(snapshot.clone(), contracts) {
+            model.user.legacy_snapshot = Some(snapshot);
+            return Err(e);
+        }
+        tracing::info!("Imported the legacy snapshot as the default session");
+    }
+
+    model.save()
+}
+
 /// Contracts that we start up the client with
 /// ORDER MATTERS HERE WHICH IS VERY BIG BAD.
 pub const CONTRACT_NAMES: [&str; 6] = [
@@ -87,11 +135,25 @@ pub const CONTRACT_NAMES: [&str; 6] = [
 #[tracing::instrument(level = "debug")]
 pub async fn load_app(flags: super::Flags) -> LoadResult {
     // Load the user's save or create a new one.
-    let mut model = load_user_data()?;
+    let mut model = load_user_data(flags.profile.as_deref())?;
+    if let Err(e) = import_legacy_snapshot(&mut model) {
+        tracing::warn!("Failed to import the legacy sandbox snapshot: {:?}", e);
+    }
 
     // Create a new middleware client to make calls to the network.
     let mut exc_client = ExcaliburMiddleware::new(None, None, None).await?;
 
+    // Load the contract address books of every network the user has used.
+    exc_client.contracts = model.user.contracts.clone();
+
+    // Run the sandbox in process if anvil isn't installed.
+    let backend = flags.sandbox.unwrap_or_else(default_sandbox);
+    tracing::info!("Running the sandbox on {}", backend);
+    if backend == SandboxBackend::Arbiter {
+        load_arbiter_sandbox(&mut model, &mut exc_client).await?;
+        return Ok((model, Arc::new(exc_client)));
+    }
+
     // Start and connect to an anvil instance.
     let anvil = start_anvil(None)?;
     exc_client.connect_anvil(anvil).await?;
@@ -108,46 +170,30 @@ pub async fn load_app(flags: super::Flags) -> LoadResult {
     // Connect the model to the desired network.
     model.connect_to_network(client.clone()).await?;
 
-    // If profile has an anvil snapshot, load it.
-    let loaded_snapshot = if let Some(AnvilSave {
-        snapshot,
-        block_number,
-    }) = &model.user.anvil_snapshot
-    {
-        tracing::debug!(
-            "Attempting to load snapshot: {}",
-            &snapshot[..10.min(snapshot.len())],
-        );
-
-        let success = client
-            .clone()
-            .provider()
-            .request::<[String; 1], bool>("anvil_loadState", [snapshot.to_string()])
-            .await
-            .expect("Failed to load snapshot.");
-
-        tracing::info!("Loaded snapshot: {:?}", success);
-
-        if success {
-            tracing::info!("Syncing Anvil to block: {}", block_number);
-            client
-                .clone()
-                .provider()
-                .request::<[u64; 1], ()>("anvil_mine", [*block_number])
-                .await
-                .expect("Failed to sync to block.");
+    // If the profile has an active sandbox session, load it.
+    let store = SessionStore::for_profile(&model.user);
+    let session = store.session_for(model.user.session.as_deref(), SandboxBackend::Anvil);
+    model.user.session = Some(session.clone());
+    let loaded_session = match store.exists(&session) {
+        true => {
+            let (session, state) = store.load(&session)?;
+            tracing::debug!("Loading sandbox session {}", session.name);
+            exc_client.load_session(&session, &state).await?;
+            model.user.contracts = exc_client.contracts.clone();
+            tracing::info!("Loaded sandbox session {}", session.name);
+            true
         }
-
-        success
-    } else {
-        false
+        false => false,
     };
 
-    // To synchronize a loaded snapshot with the necessary state:
-    // 1. Get the "saved" addresses from the user's contact book.
-    // 2. Sync the addresses to the exc_client.
-    // 3. Sync the addresses to the model.
-    if loaded_snapshot {
+    // Sessions imported from profiles saved before sessions can be missing
+    // their contracts, which were saved in the user's contact book.
+    // todo: need better loading of contracts from storage.
+    if loaded_session
+        && exc_client
+            .current_contracts()
+            .map_or(true, |contracts| contracts.is_empty())
+    {
         for name in CONTRACT_NAMES.iter() {
             if let Some(contracts) = model
                 .user
@@ -155,7 +201,6 @@ pub async fn load_app(flags: super::Flags) -> LoadResult {
                 .get_class_list(contacts::Class::Contract)
             {
                 // If the contract value's label contains the name, add it to the exc_client.
-                // todo: need better loading of contracts from storage.
                 for (address, contact) in contracts.get_all() {
                     if contact.label == *name {
                         exc_client.add_contract(name, *address);
@@ -164,169 +209,246 @@ pub async fn load_app(flags: super::Flags) -> LoadResult {
             }
         }
 
-        // todo: better contract naming/storage management.
-        let protocol_client = ProtocolClient::from_deployed(
-            exc_client.get_client(),
-            *exc_client.contracts.get("protocol").unwrap(),
-            *exc_client.contracts.get("solver").unwrap(),
-            *exc_client.contracts.get("strategy").unwrap(),
-            Address::zero(),
-            Address::zero(),
-            Address::zero(),
-            Address::zero(),
-        )?;
-        exc_client.connect_dfmm(protocol_client).await?;
+        exc_client.connect_protocol()?;
     }
 
-    // Connect a signer to the client so we can send transactions.
-    let signer = exc_client.signer.clone().unwrap().with_chain_id(chain_id);
-    let sender = signer.address();
-    exc_client.connect_signer(signer).await?;
+    // The client signs with the sandbox's dev key.
+    let sender = exc_client
+        .address()
+        .ok_or(anyhow::anyhow!("No signer connected."))?;
 
     // If we are loading a fresh instance, deploy the contracts.
-    if !loaded_snapshot {
+    if !loaded_session {
         let client = exc_client.get_client();
 
         let dev_client = DevClient::deploy(client, sender).await?;
         exc_client.connect_dfmm(dev_client.protocol.clone()).await?;
 
-        let protocol = dev_client.protocol.protocol.address();
-        let strategy = dev_client.protocol.ln_strategy.address();
-        let token_x = dev_client.token_x.address();
-        let token_y = dev_client.token_y.address();
-        let solver = dev_client.solver.address();
-        let lex = dev_client.liquid_exchange.address();
-
-        exc_client.add_contract("protocol", protocol);
-        exc_client.add_contract("strategy", strategy);
-        exc_client.add_contract("solver", solver);
-        exc_client.add_contract("token_x", token_x);
-        exc_client.add_contract("token_y", token_y);
-        exc_client.add_contract("lex", lex);
-
-        model.user.contacts.add(
-            protocol,
-            contacts::ContactValue {
-                label: "protocol".to_string(),
-                class: contacts::Class::Contract,
-                ..Default::default()
-            },
-            contacts::Category::Untrusted,
-        );
+        record_dev_contracts(&mut model, &mut exc_client, &dev_client, sender, chain_id);
+        model.save()?;
+    }
 
+    // Add the default signer to the contacts book, if there is a signer.
+    if let Some(address) = exc_client.address() {
         model.user.contacts.add(
-            strategy,
+            address,
             contacts::ContactValue {
-                label: "strategy".to_string(),
-                class: contacts::Class::Contract,
+                label: "You".to_string(),
+                class: contacts::Class::EOA,
                 ..Default::default()
             },
             contacts::Category::Trusted,
         );
+        model.save()?;
+    }
 
-        model.user.contacts.add(
-            token_x,
-            contacts::ContactValue {
-                label: "token_x".to_string(),
-                class: contacts::Class::Contract,
-                ..Default::default()
-            },
-            contacts::Category::Untrusted,
-        );
+    Ok((model, Arc::new(exc_client)))
+}
 
-        model.user.contacts.add(
-            token_y,
-            contacts::ContactValue {
-                label: "token_y".to_string(),
-                class: contacts::Class::Contract,
-                ..Default::default()
-            },
-            contacts::Category::Untrusted,
-        );
+/// Starts the sandbox in process on an arbiter environment, loading the
+/// profile's session if it was saved from arbiter, or deploying the contracts
+/// in a fresh environment.
+async fn load_arbiter_sandbox(
+    model: &mut Model,
+    exc_client: &mut ExcaliburMiddleware<RpcTransport, ExcaliburSigner>,
+) -> anyhow::Result<()> {
+    model.switch_network(ARBITER_CHAIN_ID);
+
+    // Sessions saved from anvil can't be loaded in arbiter, so the sandbox is
+    // saved to a session of its own.
+    let store = SessionStore::for_profile(&model.user);
+    let session = store.session_for(model.user.session.as_deref(), SandboxBackend::Arbiter);
+    model.user.session = Some(session.clone());
+
+    if store.exists(&session) {
+        let (session, state) = store.load(&session)?;
+        tracing::debug!("Loading sandbox session {}", session.name);
+        exc_client.load_arbiter_session(&session, &state).await?;
+        model.user.contracts = exc_client.contracts.clone();
+        tracing::info!("Loaded sandbox session {}", session.name);
+    } else {
+        exc_client
+            .connect_arbiter(start_arbiter(None)?, Some(SANDBOX_LABEL))
+            .await?;
+        exc_client.chain_id = Some(ARBITER_CHAIN_ID);
 
-        model.user.contacts.add(
-            lex,
-            contacts::ContactValue {
-                label: "lex".to_string(),
-                class: contacts::Class::Contract,
-                ..Default::default()
-            },
-            contacts::Category::Untrusted,
-        );
+        let client = exc_client.arbiter_client().unwrap();
+        let sender = client.address();
+        let dev_client = DevClient::deploy(client, sender).await?;
+        record_dev_contracts(model, exc_client, &dev_client, sender, ARBITER_CHAIN_ID);
+    }
 
-        model.user.contacts.add(
-            solver,
-            contacts::ContactValue {
-                label: "solver".to_string(),
-                class: contacts::Class::Contract,
-                ..Default::default()
-            },
-            contacts::Category::Untrusted,
-        );
+    // The sandbox account stands in for the signer.
+    let sender = exc_client.arbiter_client().unwrap().address();
+    model.user.contacts.add(
+        sender,
+        contacts::ContactValue {
+            label: "You".to_string(),
+            class: contacts::Class::EOA,
+            ..Default::default()
+        },
+        contacts::Category::Trusted,
+    );
+    model.save()?;
 
-        tracing::info!("Loaded contacts: {:?}", model.user.contacts);
-        // TODO(matt): Create a shared type so that the order of the arguments isn't
-        // finicky
-        if let Some(connected_model) = model.get_current_mut() {
-            connected_model.setup(
-                from_ethers_address(exc_client.address().unwrap()),
-                from_ethers_address(lex),
-                from_ethers_address(protocol),
-                from_ethers_address(dev_client.solver.address()),
-                from_ethers_address(strategy),
-            );
-        }
+    Ok(())
+}
 
-        let token_x = alloy_primitives::Address::from(token_x.as_fixed_bytes());
-        let token_y = alloy_primitives::Address::from(token_y.as_fixed_bytes());
-        let tokens = model.user.coins.tokens.clone();
-        let coin_x = tokens.iter().find(|c| c.address == token_x);
-        let coin_y = tokens.iter().find(|c| c.address == token_y);
-
-        if coin_x.is_none() {
-            let coin: Coin = Coin {
-                name: "Token X".to_string(),
-                symbol: "TKNX".to_string(),
-                address: token_x,
-                decimals: 18,
-                chain_id,
-                logo_uri: "".to_string(),
-                tags: vec!["mock".to_string(), "ether".to_string()],
-            };
-            model.user.coins += coin;
+/// Adds the contracts deployed by `dev_client` to the address book of
+/// `chain_id`, the contacts and the coin list, and sets up the data model
+/// with them.
+fn record_dev_contracts<C: Middleware + 'static>(
+    model: &mut Model,
+    exc_client: &mut ExcaliburMiddleware<RpcTransport, ExcaliburSigner>,
+    dev_client: &DevClient<C>,
+    sender: Address,
+    chain_id: u64,
+) {
+    let protocol = dev_client.protocol.protocol.address();
+    let strategy = dev_client.protocol.ln_strategy.address();
+    let token_x = dev_client.token_x.address();
+    let token_y = dev_client.token_y.address();
+    let solver = dev_client.solver.address();
+    let lex = dev_client.liquid_exchange.address();
+
+    exc_client.add_contract("protocol", protocol);
+    exc_client.add_contract("strategy", strategy);
+    exc_client.add_contract("solver", solver);
+    exc_client.add_contract("token_x", token_x);
+    exc_client.add_contract("token_y", token_y);
+    exc_client.add_contract("lex", lex);
+    let client = &dev_client.protocol;
+    let optional = [
+        (
+            "helper",
+            client.ln_helper.as_ref().map(|helper| helper.address()),
+        ),
+        (
+            "g3m_strategy",
+            client.g_strategy.as_ref().map(|g3m| g3m.address()),
+        ),
+        (
+            "g3m_solver",
+            client.g_solver.as_ref().map(|solver| solver.address()),
+        ),
+        (
+            "g3m_helper",
+            client.g_helper.as_ref().map(|helper| helper.address()),
+        ),
+    ];
+    for (name, address) in optional {
+        if let Some(address) = address {
+            exc_client.add_contract(name, address);
         }
+    }
+    model.user.contracts = exc_client.contracts.clone();
+
+    // The contracts were just deployed by the sandbox account, so they're
+    // trusted.
+    model.user.contacts.add(
+        protocol,
+        contacts::ContactValue {
+            label: "protocol".to_string(),
+            class: contacts::Class::Contract,
+            ..Default::default()
+        },
+        contacts::Category::Trusted,
+    );
 
-        if coin_y.is_none() {
-            let coin: Coin = Coin {
-                name: "Token Y".to_string(),
-                symbol: "TKNY".to_string(),
-                address: token_y,
-                decimals: 18,
-                chain_id,
-                logo_uri: "".to_string(),
-                tags: vec!["mock".to_string(), "stablecoin".to_string()],
-            };
-            model.user.coins += coin;
-        }
+    model.user.contacts.add(
+        strategy,
+        contacts::ContactValue {
+            label: "strategy".to_string(),
+            class: contacts::Class::Contract,
+            ..Default::default()
+        },
+        contacts::Category::Trusted,
+    );
 
-        model.save()?;
-    }
+    model.user.contacts.add(
+        token_x,
+        contacts::ContactValue {
+            label: "token_x".to_string(),
+            class: contacts::Class::Contract,
+            ..Default::default()
+        },
+        contacts::Category::Trusted,
+    );
 
-    // Add the default signer to the contacts book, if there is a signer.
-    if let Some(address) = exc_client.address() {
-        model.user.contacts.add(
-            address,
-            contacts::ContactValue {
-                label: "You".to_string(),
-                class: contacts::Class::EOA,
-                ..Default::default()
-            },
-            contacts::Category::Trusted,
+    model.user.contacts.add(
+        token_y,
+        contacts::ContactValue {
+            label: "token_y".to_string(),
+            class: contacts::Class::Contract,
+            ..Default::default()
+        },
+        contacts::Category::Trusted,
+    );
+
+    model.user.contacts.add(
+        lex,
+        contacts::ContactValue {
+            label: "lex".to_string(),
+            class: contacts::Class::Contract,
+            ..Default::default()
+        },
+        contacts::Category::Trusted,
+    );
+
+    model.user.contacts.add(
+        solver,
+        contacts::ContactValue {
+            label: "solver".to_string(),
+            class: contacts::Class::Contract,
+            ..Default::default()
+        },
+        contacts::Category::Trusted,
+    );
+
+    tracing::info!("Loaded contacts: {:?}", model.user.contacts);
+    // TODO(matt): Create a shared type so that the order of the arguments isn't
+    // finicky
+    if let Some(connected_model) = model.get_current_mut() {
+        connected_model.setup(
+            from_ethers_address(sender),
+            from_ethers_address(lex),
+            from_ethers_address(protocol),
+            from_ethers_address(solver),
+            from_ethers_address(strategy),
         );
-        model.save()?;
     }
 
-    Ok((model, Arc::new(exc_client)))
+    let token_x = alloy_primitives::Address::from(token_x.as_fixed_bytes());
+    let token_y = alloy_primitives::Address::from(token_y.as_fixed_bytes());
+    let tokens = model.user.coins.tokens.clone();
+    let coin_x = tokens.iter().find(|c| c.address == token_x);
+    let coin_y = tokens.iter().find(|c| c.address == token_y);
+
+    if coin_x.is_none() {
+        let coin: Coin = Coin {
+            name: "Token X".to_string(),
+            symbol: "TKNX".to_string(),
+            address: token_x,
+            decimals: 18,
+            chain_id,
+            logo_uri: "".to_string(),
+            tags: vec!["mock".to_string(), "ether".to_string()],
+        };
+        model.user.coins += coin;
+    }
+
+    if coin_y.is_none() {
+        let coin: Coin = Coin {
+            name: "Token Y".to_string(),
+            symbol: "TKNY".to_string(),
+            address: token_y,
+            decimals: 18,
+            chain_id,
+            logo_uri: "".to_string(),
+            tags: vec!["mock".to_string(), "stablecoin".to_string()],
+        };
+        model.user.coins += coin;
+    }
 }
 
 /// Attempts to establish a new connection with the Ledger hardware wallet.
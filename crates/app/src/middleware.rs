@@ -1,57 +1,112 @@
-use std::{collections::HashMap, fmt};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    path::PathBuf,
+    sync::Mutex,
+    time::Duration,
+};
 
 use arbiter_core::{
-    environment::{builder::EnvironmentBuilder, Environment},
+    environment::{
+        builder::EnvironmentBuilder,
+        cheatcodes::{Cheatcodes, CheatcodesReturn},
+        Environment,
+    },
     middleware::RevmMiddleware,
 };
-use clients::{ledger::LedgerClient, protocol::ProtocolClient};
+use clients::{
+    ledger::LedgerClient,
+    protocol::ProtocolClient,
+    rpc::{check_health, EndpointConfig, EndpointHealth, FailoverConfig, RpcHealth, RpcTransport},
+};
 use ethers::utils::{Anvil, AnvilInstance};
+use revm::db::CacheDB;
+use revm_primitives::AccountInfo;
+use sim::{engine::SnapshotDB, from_ethers_address, to_ethers_u256};
 
 use super::*;
-use crate::{app::AnvilSave, model::portfolio::EthersAddress};
+use crate::model::{
+    portfolio::EthersAddress,
+    rpcs::{ContractBook, NetworkTarget, RPCValue},
+    sessions::{
+        auto_name, AccountSummary, SandboxBackend, SandboxSession, SessionOrigin, SessionStore,
+        MAX_AUTO_SESSIONS,
+    },
+};
 
 pub const SANDBOX_LABEL: &str = "sandbox";
 
+/// Chain id the address book and data model of the in-process sandbox are
+/// kept under, apart from the anvil sandbox and mainnet.
+pub const ARBITER_CHAIN_ID: u64 = 31338;
+
 /// Standard client that excalibur uses.
 pub type NetworkClient<P, S> = SignerMiddleware<Provider<P>, S>;
 
 /// Connects users to networks.
 /// - Anvil instance is optional and can be connected via `connect_anvil`.
-/// - Arbiter is optional and can be connected via `connect_arbiter`.
-/// - Ledger is optional and can be connected via `connect_ledger`.
+/// - Arbiter is optional and can be connected via `connect_arbiter`. It runs
+///   the sandbox in process, without a network client, and is locked since
+///   saving it restarts it.
+/// - Ledger is optional and can be connected via `connect_ledger`, then signed
+///   with via `use_ledger`.
 /// - Active client is the currently existing connection.
-/// - Active signer is the currently existing signer (if there is one).
-/// - Contracts are a stateful map of human readable contract identifiers to
-///   addresses.
+/// - Active signer is the currently existing signer (if there is one). The
+///   sandbox's dev key only signs on the sandbox; other networks are read only
+///   until the user connects a signer.
+/// - Contracts are address books of human readable contract identifiers to
+///   addresses, one per chain id.
 /// - note: if AnvilInstance is some, then the client is the client for Anvil.
 pub struct ExcaliburMiddleware<P: PubsubClient, S: Signer> {
     /// ACTIVE CLIENT CONNECTION
     pub client: Option<Arc<NetworkClient<P, S>>>,
     /// ACTIVE SIGNER
     pub signer: Option<S>,
-    /// ANY CONTRACTS
-    pub contracts: HashMap<String, EthersAddress>,
+    /// WHETHER THE ACTIVE SIGNER IS THE SANDBOX'S DEV KEY
+    pub dev_key: bool,
+    /// CHAIN ID OF THE ACTIVE CLIENT
+    pub chain_id: Option<u64>,
+    /// CONTRACTS BY CHAIN ID
+    pub contracts: HashMap<u64, ContractBook>,
     /// HARDWARE
     pub ledger: Option<LedgerClient>,
     /// ARBITER
-    pub arbiter: Option<Environment>,
+    pub arbiter: Mutex<Option<Environment>>,
     /// ARBITER CLIENT
-    pub arbiter_client: Option<Arc<RevmMiddleware>>,
+    pub arbiter_client: Mutex<Option<Arc<RevmMiddleware>>>,
     /// ANVIL
     pub anvil: Option<AnvilInstance>,
     /// PROTOCOL
     pub dfmm_client: Option<ProtocolClient<NetworkClient<P, S>>>,
 }
 
-impl fmt::Debug for ExcaliburMiddleware<Ws, LocalWallet> {
+impl fmt::Debug for ExcaliburMiddleware<RpcTransport, ExcaliburSigner> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ExcaliburMiddleware")
             .field("client", &self.client)
+            .field("chain_id", &self.chain_id)
             .finish()
     }
 }
 
 impl<P: PubsubClient, S: Signer> ExcaliburMiddleware<P, S> {
+    /// Creates a middleware connected to nothing, to stand in for one that is
+    /// being reconnected.
+    pub fn disconnected() -> Self {
+        Self {
+            client: None,
+            signer: None,
+            dev_key: false,
+            chain_id: None,
+            contracts: HashMap::new(),
+            ledger: None,
+            arbiter: Mutex::new(None),
+            arbiter_client: Mutex::new(None),
+            anvil: None,
+            dfmm_client: None,
+        }
+    }
+
     /// Returns a ref to the unwrapped client
     pub fn get_client(&self) -> Arc<NetworkClient<P, S>> {
         self.client.as_ref().unwrap().clone()
@@ -62,9 +117,104 @@ impl<P: PubsubClient, S: Signer> ExcaliburMiddleware<P, S> {
         self.signer.as_ref().map(|signer| signer.address())
     }
 
-    /// Adds a new contract to the contracts map.
+    /// Returns the client of the sandbox account in the arbiter environment,
+    /// if the sandbox runs in process.
+    pub fn arbiter_client(&self) -> Option<Arc<RevmMiddleware>> {
+        self.arbiter_client.lock().unwrap().clone()
+    }
+
+    /// Address transactions are sent from: the signer's, or the sandbox
+    /// account's when the in-process sandbox is the active network.
+    pub fn sender(&self) -> Option<Address> {
+        match self.client {
+            Some(_) => self.address(),
+            None => self.arbiter_client().map(|client| client.address()),
+        }
+    }
+
+    /// Protocol client of the sandbox account, when the in-process sandbox is
+    /// the active network and the protocol is deployed on it.
+    pub fn arbiter_protocol(&self) -> anyhow::Result<Option<ProtocolClient<RevmMiddleware>>> {
+        match (&self.client, self.arbiter_client()) {
+            (None, Some(client)) => self.protocol_client(client),
+            _ => Ok(None),
+        }
+    }
+
+    /// Address of the dfmm protocol on the active network.
+    pub fn protocol_address(&self) -> Option<Address> {
+        match (&self.dfmm_client, &self.client) {
+            (Some(dfmm), _) => Some(dfmm.protocol.address()),
+            // The in-process sandbox has no protocol client to keep.
+            (None, None) => self.contract("protocol"),
+            (None, Some(_)) => None,
+        }
+    }
+
+    /// Protocol client over `client` for the contracts in the address book of
+    /// the connected network, if they are deployed there.
+    fn protocol_client<C: Middleware + 'static>(
+        &self,
+        client: Arc<C>,
+    ) -> anyhow::Result<Option<ProtocolClient<C>>> {
+        let (Some(protocol), Some(strategy), Some(solver)) = (
+            self.contract("protocol"),
+            self.contract("strategy"),
+            self.contract("solver"),
+        ) else {
+            return Ok(None);
+        };
+
+        // Address books saved before the helpers and the G3M contracts were
+        // recorded don't have them.
+        Ok(Some(ProtocolClient::from_deployed(
+            client,
+            protocol,
+            strategy,
+            solver,
+            self.contract("helper"),
+            self.contract("g3m_strategy"),
+            self.contract("g3m_solver"),
+            self.contract("g3m_helper"),
+        )?))
+    }
+
+    /// What runs the sandbox, if there is one.
+    pub fn sandbox_backend(&self) -> Option<SandboxBackend> {
+        if self.anvil.is_some() {
+            Some(SandboxBackend::Anvil)
+        } else if self.arbiter.lock().unwrap().is_some() {
+            Some(SandboxBackend::Arbiter)
+        } else {
+            None
+        }
+    }
+
+    /// Adds a new contract to the address book of the connected network.
     pub fn add_contract(&mut self, name: &str, address: EthersAddress) {
-        self.contracts.insert(name.to_string(), address);
+        match self.chain_id {
+            Some(chain_id) => self.add_contract_on(chain_id, name, address),
+            None => tracing::warn!("Not connected to a network, can't add {}", name),
+        }
+    }
+
+    /// Adds a new contract to the address book of `chain_id`.
+    pub fn add_contract_on(&mut self, chain_id: u64, name: &str, address: EthersAddress) {
+        self.contracts
+            .entry(chain_id)
+            .or_default()
+            .insert(name.to_string(), address);
+    }
+
+    /// Gets the address of a contract on the connected network.
+    pub fn contract(&self, name: &str) -> Option<EthersAddress> {
+        self.current_contracts()?.get(name).copied()
+    }
+
+    /// Gets the address book of the connected network.
+    pub fn current_contracts(&self) -> Option<&ContractBook> {
+        self.chain_id
+            .and_then(|chain_id| self.contracts.get(&chain_id))
     }
 
     /// Creates a connection to a ledger device.
@@ -97,45 +247,153 @@ impl<P: PubsubClient, S: Signer> ExcaliburMiddleware<P, S> {
     ) -> anyhow::Result<()> {
         let arbiter_client = RevmMiddleware::new(&arbiter, seed)?;
 
-        self.arbiter = Some(arbiter);
-        self.arbiter_client = Some(arbiter_client.clone());
+        *self.arbiter.get_mut().unwrap() = Some(arbiter);
+        *self.arbiter_client.get_mut().unwrap() = Some(arbiter_client.clone());
 
         Ok(())
     }
 
-    /// Executes the `anvil_dumpState` rpc call on the anvil instance.
-    pub async fn snapshot(&self) -> anyhow::Result<AnvilSave> {
-        if self.anvil.is_some() {
-            let block_number = self
-                .client
-                .as_ref()
-                .unwrap()
-                .provider()
-                .get_block_number()
-                .await?
-                .as_u64();
-
-            let params: Vec<String> = vec![];
-            let snapshot = self
-                .client
-                .as_ref()
-                .unwrap()
-                .provider()
-                .request("anvil_dumpState", params)
-                .await
-                .expect("failed to get snapshot");
-
-            Ok(AnvilSave {
-                block_number,
-                snapshot,
-            })
-        } else {
-            Err(anyhow::anyhow!("No anvil instance set."))
+    /// Stops the arbiter environment and returns its state, if there is one.
+    pub fn stop_arbiter(&self) -> anyhow::Result<Option<SnapshotDB>> {
+        self.arbiter_client.lock().unwrap().take();
+        let Some(arbiter) = self.arbiter.lock().unwrap().take() else {
+            return Ok(None);
+        };
+        let db = arbiter
+            .stop()?
+            .ok_or(anyhow::anyhow!("Arbiter stopped without its db."))?;
+        Ok(Some(SnapshotDB::new(&db)))
+    }
+
+    /// Stops the arbiter environment to get its state, since it only hands
+    /// its db back when it stops, and starts it again from the state at the
+    /// same block, which isn't part of the state.
+    async fn restart_arbiter(
+        &self,
+        block_number: U256,
+        timestamp: U256,
+    ) -> anyhow::Result<SnapshotDB> {
+        let client = self
+            .arbiter_client()
+            .ok_or(anyhow::anyhow!("No arbiter environment set."))?;
+        let snapshot = self
+            .stop_arbiter()?
+            .ok_or(anyhow::anyhow!("No arbiter environment set."))?;
+
+        let sandbox = snapshot
+            .accounts
+            .get(&from_ethers_address(client.address()))
+            .cloned();
+        let arbiter = start_arbiter(Some(without_account(snapshot.clone(), client.address())))?;
+        let client = RevmMiddleware::new(&arbiter, Some(SANDBOX_LABEL))?;
+        client.update_block(block_number, timestamp)?;
+        reseed_account(&client, sandbox).await?;
+        *self.arbiter.lock().unwrap() = Some(arbiter);
+        *self.arbiter_client.lock().unwrap() = Some(client);
+        Ok(snapshot)
+    }
+
+    /// Health of the in-process sandbox, which answers without latency.
+    pub async fn arbiter_health(&self) -> anyhow::Result<RpcHealth> {
+        let client = self
+            .arbiter_client()
+            .ok_or(anyhow::anyhow!("No arbiter environment set."))?;
+        Ok(RpcHealth {
+            chain_id: ARBITER_CHAIN_ID,
+            block_number: client.get_block_number().await?.as_u64(),
+            latency: Duration::ZERO,
+        })
+    }
+
+    /// Returns the state of the arbiter environment as the session `name`,
+    /// with the state serialized as a [`SnapshotDB`].
+    pub async fn capture_arbiter_session(
+        &self,
+        name: &str,
+        origin: SessionOrigin,
+    ) -> anyhow::Result<(SandboxSession, String)> {
+        let client = self
+            .arbiter_client()
+            .ok_or(anyhow::anyhow!("No arbiter environment set."))?;
+        let block_number = client.get_block_number().await?;
+        let timestamp = client.get_block_timestamp().await?.as_u64();
+        let sandbox = client.address();
+        let snapshot = self
+            .restart_arbiter(block_number.as_u64().into(), timestamp.into())
+            .await?;
+
+        let contracts = self
+            .contracts
+            .get(&ARBITER_CHAIN_ID)
+            .cloned()
+            .unwrap_or_default();
+        let mut accounts = BTreeMap::new();
+        let sandbox = ("signer".to_string(), sandbox);
+        for (name, address) in contracts.clone().into_iter().chain(Some(sandbox)) {
+            let Some(info) = snapshot.accounts.get(&from_ethers_address(address)) else {
+                continue;
+            };
+            let code_hash = match info.code_hash == revm_primitives::KECCAK_EMPTY {
+                true => H256::zero(),
+                false => H256::from(info.code_hash.0),
+            };
+            let summary = AccountSummary {
+                address,
+                balance: to_ethers_u256(info.balance),
+                nonce: info.nonce.into(),
+                code_hash,
+            };
+            accounts.insert(name, summary);
         }
+
+        let session = SandboxSession {
+            name: name.to_string(),
+            origin,
+            created_at: chrono::Utc::now(),
+            chain_id: ARBITER_CHAIN_ID,
+            block_number: block_number.as_u64(),
+            backend: SandboxBackend::Arbiter,
+            contracts,
+            accounts,
+        };
+        Ok((session, serde_json::to_string(&snapshot)?))
+    }
+
+    /// Starts an arbiter environment running the session, replacing the one
+    /// running, and connects the sandbox account to it.
+    pub async fn load_arbiter_session(
+        &mut self,
+        session: &SandboxSession,
+        state: &str,
+    ) -> anyhow::Result<()> {
+        let mut snapshot: SnapshotDB = serde_json::from_str(state)?;
+        let mut sandbox = None;
+        if let Some(account) = session.accounts.get("signer") {
+            sandbox = snapshot
+                .accounts
+                .get(&from_ethers_address(account.address))
+                .cloned();
+            snapshot = without_account(snapshot, account.address);
+        }
+
+        self.stop_arbiter()?;
+        self.connect_arbiter(start_arbiter(Some(snapshot))?, Some(SANDBOX_LABEL))
+            .await?;
+
+        tracing::info!("Syncing arbiter to block: {}", session.block_number);
+        let client = self.arbiter_client().unwrap();
+        let timestamp = client.get_block_timestamp().await?.as_u64();
+        client.update_block(U256::from(session.block_number), U256::from(timestamp))?;
+        reseed_account(&client, sandbox).await?;
+
+        self.contracts
+            .insert(session.chain_id, session.contracts.clone());
+        self.chain_id = Some(session.chain_id);
+        Ok(())
     }
 }
 
-impl ExcaliburMiddleware<Ws, LocalWallet> {
+impl ExcaliburMiddleware<RpcTransport, ExcaliburSigner> {
     /// Creates a new Excalibur middleware instance, setting the anvil and/or
     /// arbiter instances if provided.
     /// - If anvil is available, then the client is automatically connected to
@@ -147,17 +405,14 @@ impl ExcaliburMiddleware<Ws, LocalWallet> {
     pub async fn new(
         anvil: Option<AnvilInstance>,
         arbiter: Option<Environment>,
-        signer: Option<LocalWallet>,
+        signer: Option<ExcaliburSigner>,
     ) -> anyhow::Result<Self> {
         let mut anvil_client = None;
         if let Some(anvil_instance) = anvil.as_ref() {
-            let signer = signer
-                .clone()
-                .unwrap_or_else(|| LocalWallet::from(anvil_instance.keys()[0].clone()));
+            let signer = signer.clone().unwrap_or_else(|| dev_signer(anvil_instance));
 
             anvil_client = Some(Arc::new(
-                Provider::<Ws>::connect(&anvil_instance.ws_endpoint())
-                    .await?
+                Provider::new(RpcTransport::connect(&anvil_instance.ws_endpoint()).await?)
                     .with_signer(signer.with_chain_id(anvil_instance.chain_id())),
             ));
         }
@@ -175,44 +430,315 @@ impl ExcaliburMiddleware<Ws, LocalWallet> {
         Ok(Self {
             client,
             signer,
+            dev_key: false,
+            chain_id: anvil.as_ref().map(|anvil| anvil.chain_id()),
             contracts: HashMap::new(),
             ledger: None,
-            arbiter,
-            arbiter_client,
+            arbiter: Mutex::new(arbiter),
+            arbiter_client: Mutex::new(arbiter_client),
             anvil,
             dfmm_client: None,
         })
     }
 
-    /// Connects to a network via a websocket endpoint.
+    /// Connects to a network via a websocket or HTTP endpoint, keeping the
+    /// signer the user connected. Fails if the endpoint is unhealthy or not on
+    /// `expected_chain_id`, leaving the current connection in place.
     #[tracing::instrument(skip(self), level = "debug")]
-    pub async fn connect_ws(&mut self, endpoint: &str, chain_id: u64) -> anyhow::Result<()> {
-        let signer = self.signer.clone().unwrap();
-        let client = Arc::new(
-            Provider::<Ws>::connect(endpoint)
-                .await?
-                .interval(std::time::Duration::from_millis(100))
-                .with_signer(signer.with_chain_id(chain_id)),
-        );
+    pub async fn connect_rpc(
+        &mut self,
+        endpoint: &str,
+        expected_chain_id: Option<u64>,
+    ) -> anyhow::Result<RpcHealth> {
+        let transport = RpcTransport::connect(endpoint).await?;
+        self.connect_transport(transport, expected_chain_id, false)
+            .await
+    }
 
-        self.client = Some(client);
+    /// Connects to several endpoints of one chain, failing over between them
+    /// in the order given when they can't be reached.
+    #[tracing::instrument(skip(self, rpcs), level = "debug")]
+    pub async fn connect_failover(&mut self, rpcs: &[RPCValue]) -> anyhow::Result<RpcHealth> {
+        let chain_id = rpcs
+            .first()
+            .ok_or(anyhow::anyhow!("No RPCs to connect to."))?
+            .chain_id;
+        if rpcs.iter().any(|rpc| rpc.chain_id != chain_id) {
+            return Err(anyhow::anyhow!("RPCs are on different chains."));
+        }
+
+        let endpoints = rpcs
+            .iter()
+            .map(|rpc| EndpointConfig {
+                url: rpc.url.clone(),
+                rate_limit: rpc.rate_limit,
+            })
+            .collect();
+        let transport = RpcTransport::failover(endpoints, FailoverConfig::default())?;
+        self.connect_transport(transport, Some(chain_id), false)
+            .await
+    }
+
+    /// Connects the client to `transport`. The sandbox falls back to its dev
+    /// key, and other networks are read only without a signer the user
+    /// connected.
+    async fn connect_transport(
+        &mut self,
+        transport: RpcTransport,
+        expected_chain_id: Option<u64>,
+        sandbox: bool,
+    ) -> anyhow::Result<RpcHealth> {
+        let provider = Provider::new(transport).interval(std::time::Duration::from_millis(100));
+        let health = check_health(&provider, expected_chain_id).await?;
+
+        let user_signer = self.user_signer();
+        let dev_key = user_signer.is_none() && sandbox;
+        let signer = match user_signer {
+            Some(signer) => Some(signer),
+            None if sandbox => self.anvil.as_ref().map(dev_signer),
+            None => None,
+        }
+        .map(|signer| signer.with_chain_id(health.chain_id));
+
+        let client_signer = signer
+            .clone()
+            .unwrap_or_else(|| ExcaliburSigner::read_only(health.chain_id));
+        self.client = Some(Arc::new(provider.with_signer(client_signer)));
+        self.dev_key = dev_key && signer.is_some();
+        self.signer = signer;
+        self.chain_id = Some(health.chain_id);
+        self.connect_protocol()?;
+
+        Ok(health)
+    }
+
+    /// The signer the user connected, as opposed to the sandbox's dev key.
+    pub fn user_signer(&self) -> Option<ExcaliburSigner> {
+        self.signer.clone().filter(|_| !self.dev_key)
+    }
+
+    /// Stats of the endpoints of the active client, if it fails over between
+    /// several.
+    pub fn rpc_health(&self) -> Vec<EndpointHealth> {
+        self.client
+            .as_ref()
+            .map(|client| client.provider().as_ref().health())
+            .unwrap_or_default()
+    }
+
+    /// Switches to a saved RPC or the sandbox, starting a sandbox if there is
+    /// none running.
+    #[tracing::instrument(skip(self), level = "debug")]
+    pub async fn connect_network(&mut self, target: &NetworkTarget) -> anyhow::Result<RpcHealth> {
+        match target {
+            NetworkTarget::Rpc(rpcs) => self.connect_failover(rpcs).await,
+            NetworkTarget::Sandbox => match self
+                .anvil
+                .as_ref()
+                .map(|anvil| (anvil.ws_endpoint(), anvil.chain_id()))
+            {
+                Some((endpoint, chain_id)) => {
+                    let transport = RpcTransport::connect(&endpoint).await?;
+                    self.connect_transport(transport, Some(chain_id), true)
+                        .await
+                }
+                // The in-process sandbox is called through the arbiter client.
+                None if self.arbiter_client().is_some() => {
+                    self.client = None;
+                    self.dfmm_client = None;
+                    self.chain_id = Some(ARBITER_CHAIN_ID);
+                    self.arbiter_health().await
+                }
+                None => {
+                    self.connect_anvil(start_anvil(None)?).await?;
+                    self.connect_protocol()?;
+                    check_health(self.get_client().provider(), None).await
+                }
+            },
+        }
+    }
+
+    /// Connects the dfmm protocol client to the contracts in the address book
+    /// of the connected network, or disconnects it if they are not deployed
+    /// there.
+    pub fn connect_protocol(&mut self) -> anyhow::Result<()> {
+        // The in-process sandbox is sent to with [`Self::arbiter_protocol`].
+        self.dfmm_client = match self.client.clone() {
+            Some(client) => self.protocol_client(client)?,
+            None => None,
+        };
         Ok(())
     }
 
-    /// Connects a signer to the client.
+    /// Provider of the anvil instance. The anvil instance is called directly,
+    /// since the active client can be connected to another network.
+    async fn anvil_provider(&self) -> anyhow::Result<Provider<RpcTransport>> {
+        let anvil = self
+            .anvil
+            .as_ref()
+            .ok_or(anyhow::anyhow!("No anvil instance set."))?;
+        Ok(Provider::new(
+            RpcTransport::connect(&anvil.ws_endpoint()).await?,
+        ))
+    }
+
+    /// Executes the `anvil_dumpState` rpc call on the anvil instance, and
+    /// returns it as the session `name` with the state.
+    pub async fn capture_session(
+        &self,
+        name: &str,
+        origin: SessionOrigin,
+    ) -> anyhow::Result<(SandboxSession, String)> {
+        let provider = self.anvil_provider().await?;
+        let chain_id = provider.get_chainid().await?.as_u64();
+        let block_number = provider.get_block_number().await?.as_u64();
+
+        let params: Vec<String> = vec![];
+        let state: String = provider.request("anvil_dumpState", params).await?;
+
+        let contracts = self.contracts.get(&chain_id).cloned().unwrap_or_default();
+        let mut accounts = BTreeMap::new();
+        let signer = self
+            .address()
+            .map(|address| ("signer".to_string(), address));
+        for (name, address) in contracts.clone().into_iter().chain(signer) {
+            let code = provider.get_code(address, None).await?;
+            let code_hash = match code.is_empty() {
+                true => H256::zero(),
+                false => H256::from(ethers::utils::keccak256(&code)),
+            };
+            let summary = AccountSummary {
+                address,
+                balance: provider.get_balance(address, None).await?,
+                nonce: provider.get_transaction_count(address, None).await?,
+                code_hash,
+            };
+            accounts.insert(name, summary);
+        }
+
+        let session = SandboxSession {
+            name: name.to_string(),
+            origin,
+            created_at: chrono::Utc::now(),
+            chain_id,
+            block_number,
+            backend: SandboxBackend::Anvil,
+            contracts,
+            accounts,
+        };
+        Ok((session, state))
+    }
+
+    /// Saves the sandbox to the session `name` in `store`. A session it
+    /// replaces keeps its origin.
+    pub async fn save_session(
+        &self,
+        store: &SessionStore,
+        name: &str,
+        origin: SessionOrigin,
+    ) -> anyhow::Result<SandboxSession> {
+        let origin = store.get(name).map_or(origin, |session| session.origin);
+        let (session, state) = match self.sandbox_backend() {
+            Some(SandboxBackend::Arbiter) => self.capture_arbiter_session(name, origin).await?,
+            _ => self.capture_session(name, origin).await?,
+        };
+        store.save(&session, &state)?;
+        tracing::info!(
+            "Saved sandbox session {} at block {}",
+            name,
+            session.block_number
+        );
+        Ok(session)
+    }
+
+    /// Saves the sandbox to a new automatic session before `action`, keeping
+    /// the latest [`MAX_AUTO_SESSIONS`]. Does nothing without a sandbox.
+    pub async fn auto_snapshot(&self, store: &SessionStore, action: &str) -> anyhow::Result<()> {
+        if self.sandbox_backend().is_none() {
+            return Ok(());
+        }
+
+        self.save_session(store, &auto_name(), SessionOrigin::Auto(action.to_string()))
+            .await?;
+        store.prune_auto(MAX_AUTO_SESSIONS)
+    }
+
+    /// Loads a session into the anvil instance, which should be fresh, and
+    /// connects the protocol client to its contracts.
+    pub async fn load_session(
+        &mut self,
+        session: &SandboxSession,
+        state: &str,
+    ) -> anyhow::Result<()> {
+        let provider = self.anvil_provider().await?;
+        let loaded: bool = provider
+            .request("anvil_loadState", [state.to_string()])
+            .await?;
+        if !loaded {
+            anyhow::bail!("Anvil failed to load session {}.", session.name);
+        }
+
+        tracing::info!("Syncing Anvil to block: {}", session.block_number);
+        provider
+            .request::<[u64; 1], ()>("anvil_mine", [session.block_number])
+            .await?;
+
+        self.contracts
+            .insert(session.chain_id, session.contracts.clone());
+        self.connect_protocol()
+    }
+
+    /// Replaces the sandbox with a new one running the session, and connects
+    /// the client to it with the signer the user connected, or the dev key.
+    /// Sessions from anvil replace an in-process sandbox with anvil, and the
+    /// other way around.
+    pub async fn restore_session(
+        &mut self,
+        session: &SandboxSession,
+        state: &str,
+    ) -> anyhow::Result<RpcHealth> {
+        if session.backend == SandboxBackend::Arbiter {
+            self.anvil = None;
+            self.load_arbiter_session(session, state).await?;
+            self.client = None;
+            self.dfmm_client = None;
+            return self.arbiter_health().await;
+        }
+
+        self.stop_arbiter()?;
+        self.connect_anvil(start_anvil(Some(session.chain_id))?)
+            .await?;
+        self.load_session(session, state).await?;
+        check_health(self.get_client().provider(), Some(session.chain_id)).await
+    }
+
+    /// Connects a signer to the client, either a local key or a Ledger.
     /// todo: replacing the client like this... are there side effects?
-    #[tracing::instrument(skip(self), level = "debug")]
-    pub async fn connect_signer(&mut self, signer: LocalWallet) -> anyhow::Result<()> {
-        let provider = self.client.as_ref().unwrap().provider().clone();
+    #[tracing::instrument(skip(self, signer), level = "debug")]
+    pub async fn connect_signer(
+        &mut self,
+        signer: impl Into<ExcaliburSigner>,
+    ) -> anyhow::Result<()> {
+        // Without a network client, e.g. on the in-process sandbox, the signer
+        // is kept for the next network connected to.
+        let Some(client) = self.client.as_ref() else {
+            self.signer = Some(signer.into());
+            self.dev_key = false;
+            return Ok(());
+        };
+        let provider = client.provider().clone();
         let chain_id = provider.get_chainid().await?.as_u64();
-        let signer = signer.with_chain_id(chain_id);
+        let signer = signer.into().with_chain_id(chain_id);
 
         let signer_client = Arc::new(
             provider
                 .interval(std::time::Duration::from_millis(10))
-                .with_signer(signer),
+                .with_signer(signer.clone()),
         );
         self.client = Some(signer_client.clone());
+        self.signer = Some(signer);
+        self.dev_key = false;
+        self.chain_id = Some(chain_id);
 
         // Override the dfmm_client if it exists with the new signer.
         if let Some(dfmm_client) = self.dfmm_client.as_ref() {
@@ -222,20 +748,34 @@ impl ExcaliburMiddleware<Ws, LocalWallet> {
         Ok(())
     }
 
-    /// Connects the middleware to a running anvil instance.
+    /// Signs with the connected Ledger instead of the active signer.
+    #[tracing::instrument(skip(self), level = "debug")]
+    pub async fn use_ledger(&mut self) -> anyhow::Result<()> {
+        let ledger = self
+            .ledger
+            .clone()
+            .ok_or(anyhow::anyhow!("No ledger connected."))?;
+        self.connect_signer(ledger).await
+    }
+
+    /// Connects the middleware to a running anvil instance, signing with the
+    /// signer the user connected or else anvil's dev key.
     #[tracing::instrument(skip(self, anvil), level = "debug")]
     pub async fn connect_anvil(&mut self, anvil: AnvilInstance) -> anyhow::Result<()> {
-        let signer = LocalWallet::from(anvil.keys()[0].clone());
+        let user_signer = self.user_signer();
+        let dev_key = user_signer.is_none();
+        let signer = user_signer.unwrap_or_else(|| dev_signer(&anvil));
         let client = Arc::new(
-            Provider::<Ws>::connect(&anvil.ws_endpoint())
-                .await?
+            Provider::new(RpcTransport::connect(&anvil.ws_endpoint()).await?)
                 .interval(std::time::Duration::from_millis(10))
                 .with_signer(signer.clone().with_chain_id(anvil.chain_id())),
         );
 
+        self.chain_id = Some(anvil.chain_id());
         self.anvil = Some(anvil);
         self.client = Some(client);
         self.signer = Some(signer);
+        self.dev_key = dev_key;
 
         Ok(())
     }
@@ -244,20 +784,43 @@ impl ExcaliburMiddleware<Ws, LocalWallet> {
     #[tracing::instrument(skip(self), level = "debug")]
     pub async fn connect_dfmm(
         &mut self,
-        client: ProtocolClient<NetworkClient<Ws, LocalWallet>>,
+        client: ProtocolClient<NetworkClient<RpcTransport, ExcaliburSigner>>,
     ) -> anyhow::Result<()> {
         self.dfmm_client = Some(client);
         Ok(())
     }
 }
 
-/// Spawns a new anvil instance.
-/// note: Requires anvil to be installed and available in the path.
-pub fn start_anvil(chain_id: Option<u64>) -> anyhow::Result<AnvilInstance> {
+/// First of anvil's dev keys, which are public.
+fn dev_signer(anvil: &AnvilInstance) -> ExcaliburSigner {
+    LocalWallet::from(anvil.keys()[0].clone()).into()
+}
+
+/// Path of the anvil binary, installed by foundryup or on the `$PATH`.
+pub fn anvil_path() -> Option<PathBuf> {
     let home_dir = std::env::var("HOME").unwrap_or_default();
-    let binary_path = format!("{}/.foundry/bin/anvil", home_dir);
+    let foundry = PathBuf::from(home_dir).join(".foundry/bin/anvil");
+    let path = std::env::var_os("PATH").unwrap_or_default();
+
+    std::iter::once(foundry)
+        .chain(std::env::split_paths(&path).map(|dir| dir.join("anvil")))
+        .find(|path| path.is_file())
+}
 
-    // todo: throw an error if the binary doesn't exist
+/// Runs the sandbox on anvil if it's installed, and in process otherwise.
+pub fn default_sandbox() -> SandboxBackend {
+    match anvil_path() {
+        Some(_) => SandboxBackend::Anvil,
+        None => SandboxBackend::Arbiter,
+    }
+}
+
+/// Spawns a new anvil instance.
+/// note: Requires anvil to be installed, see [`anvil_path`].
+pub fn start_anvil(chain_id: Option<u64>) -> anyhow::Result<AnvilInstance> {
+    let binary_path = anvil_path().ok_or(anyhow::anyhow!(
+        "Anvil is not installed, install foundry or use the arbiter sandbox."
+    ))?;
 
     let chain_id = chain_id.unwrap_or(31337_u64);
     let anvil = Anvil::default()
@@ -270,14 +833,73 @@ pub fn start_anvil(chain_id: Option<u64>) -> anyhow::Result<AnvilInstance> {
     Ok(anvil)
 }
 
-/// Spawns a new Arbiter instance.
-#[allow(dead_code)]
-pub fn start_arbiter() -> anyhow::Result<Environment> {
-    let arbiter = EnvironmentBuilder::new().build();
+/// Spawns a new Arbiter instance, starting from the state of `snapshot` if
+/// given.
+pub fn start_arbiter(snapshot: Option<SnapshotDB>) -> anyhow::Result<Environment> {
+    let mut builder = EnvironmentBuilder::new();
+    if let Some(snapshot) = snapshot {
+        builder = builder.db(CacheDB::from(snapshot));
+    }
+    let arbiter = builder.build();
 
     Ok(arbiter)
 }
 
+/// Removes the sandbox account from `snapshot`. Arbiter refuses to create an
+/// account that is in its db, so the account is created again from its label
+/// when the snapshot is loaded, and given back its ether and nonce with
+/// [`reseed_account`]. Its tokens are kept in the storage of their contracts.
+fn without_account(mut snapshot: SnapshotDB, address: Address) -> SnapshotDB {
+    let address = from_ethers_address(address);
+    snapshot.accounts.remove(&address);
+    snapshot.storage.remove(&address);
+    snapshot
+}
+
+/// Gives the sandbox account `client`, created again by arbiter, the ether
+/// and nonce it had in `account`. Arbiter has no cheatcode for the nonce, so
+/// it's raised with empty transactions to the account itself, which keeps the
+/// addresses of the contracts it deploys from colliding with earlier ones.
+async fn reseed_account(
+    client: &RevmMiddleware,
+    account: Option<AccountInfo>,
+) -> anyhow::Result<()> {
+    let Some(account) = account else {
+        return Ok(());
+    };
+
+    let address = client.address();
+    for _ in 0..account.nonce {
+        client
+            .send_transaction(TransactionRequest::new().to(address), None)
+            .await?
+            .await?;
+    }
+
+    let current = match client
+        .apply_cheatcode(Cheatcodes::Access {
+            address: from_ethers_address(address),
+        })
+        .await?
+    {
+        CheatcodesReturn::Access { info, .. } => info.balance,
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Arbiter didn't return the sandbox account."
+            ))
+        }
+    };
+    if let Some(missing) = account.balance.checked_sub(current) {
+        client
+            .apply_cheatcode(Cheatcodes::Deal {
+                address,
+                amount: to_ethers_u256(missing),
+            })
+            .await?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -351,4 +973,41 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn dev_key_stays_on_the_sandbox() -> anyhow::Result<()> {
+        let mut client = ExcaliburMiddleware::<RpcTransport, ExcaliburSigner>::disconnected();
+        client.connect_anvil(setup().await?).await?;
+        assert!(client.dev_key);
+        assert!(client.user_signer().is_none());
+
+        // Another network is read only without a signer the user connected.
+        let network = Anvil::default().chain_id(5_u64).spawn();
+        client.connect_rpc(&network.ws_endpoint(), Some(5)).await?;
+        assert!(client.signer.is_none());
+        assert!(!client.dev_key);
+        assert_eq!(client.get_client().signer().chain_id(), 5);
+        let tx = TransactionRequest::pay(Address::random(), 1);
+        assert!(client
+            .get_client()
+            .send_transaction(tx, None)
+            .await
+            .is_err());
+
+        // Back on the sandbox, the dev key signs again.
+        client.connect_network(&NetworkTarget::Sandbox).await?;
+        assert!(client.dev_key);
+        assert_eq!(
+            client.address(),
+            Some(LocalWallet::from(client.anvil.as_ref().unwrap().keys()[0].clone()).address())
+        );
+
+        // A signer the user connected is kept across networks.
+        let wallet = LocalWallet::from(network.keys()[1].clone());
+        client.connect_signer(wallet.clone()).await?;
+        client.connect_rpc(&network.ws_endpoint(), Some(5)).await?;
+        assert!(!client.dev_key);
+        assert_eq!(client.address(), Some(wallet.address()));
+        Ok(())
+    }
 }
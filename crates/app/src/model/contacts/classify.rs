@@ -0,0 +1,93 @@
+//! Classifies addresses from their code on chain.
+//!
+//! An address without code is an EOA, unless it's delegated to a contract
+//! with EIP-7702. Contracts that answer `entryPoint()` with an address are
+//! ERC-4337 smart accounts, and every other contract is a [`Class::Contract`].
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use ethers::types::{transaction::eip2718::TypedTransaction, Bytes, TransactionRequest};
+
+use super::*;
+
+/// Selector of `entryPoint()`, which ERC-4337 accounts implement to name the
+/// entry point they trust.
+pub const ENTRY_POINT_SELECTOR: [u8; 4] = [0xb0, 0xd6, 0x91, 0xfe];
+
+/// Code of EOAs that delegate to a contract with EIP-7702 starts with this.
+pub const DELEGATION_PREFIX: [u8; 3] = [0xef, 0x01, 0x00];
+
+/// Class of an address with `code`, if it can be told from the code alone.
+pub fn class_of_code(code: &[u8]) -> Option<Class> {
+    if code.is_empty() {
+        Some(Class::EOA)
+    } else if code.starts_with(&DELEGATION_PREFIX) {
+        Some(Class::AccountAbstraction)
+    } else {
+        None
+    }
+}
+
+/// Whether `output` of an `entryPoint()` call names an entry point.
+pub fn is_entry_point(output: &[u8]) -> bool {
+    output.len() == 32 && output[..12].iter().all(|byte| *byte == 0) && output[12..] != [0; 20]
+}
+
+/// Classifies `address` with `eth_getCode`, calling `entryPoint()` on
+/// contracts to find smart accounts.
+pub async fn classify<M: Middleware + 'static>(client: &M, address: Address) -> Result<Class> {
+    let code = client.get_code(address, None).await?;
+    if let Some(class) = class_of_code(&code) {
+        return Ok(class);
+    }
+
+    let call: TypedTransaction = TransactionRequest::new()
+        .to(address)
+        .data(Bytes::from(ENTRY_POINT_SELECTOR.to_vec()))
+        .into();
+    // Contracts without `entryPoint()` revert, which isn't an error here.
+    Ok(match client.call(&call, None).await {
+        Ok(output) if is_entry_point(&output) => Class::AccountAbstraction,
+        _ => Class::Contract,
+    })
+}
+
+/// Classifies each of `addresses`, leaving out the ones that fail.
+pub async fn classify_all<M: Middleware + 'static>(
+    client: Arc<M>,
+    addresses: Vec<Address>,
+) -> Vec<(Address, Class)> {
+    let mut classes = vec![];
+    for address in addresses {
+        match classify(client.as_ref(), address).await {
+            Ok(class) => classes.push((address, class)),
+            Err(error) => tracing::warn!("Failed to classify {:?}: {:?}", address, error),
+        }
+    }
+    classes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_code() {
+        assert_eq!(class_of_code(&[]), Some(Class::EOA));
+        let delegated = [&DELEGATION_PREFIX[..], &[0x11; 20]].concat();
+        assert_eq!(class_of_code(&delegated), Some(Class::AccountAbstraction));
+        assert_eq!(class_of_code(&[0x60, 0x80, 0x60, 0x40]), None);
+    }
+
+    #[test]
+    fn finds_entry_points() {
+        let mut output = [0u8; 32];
+        assert!(!is_entry_point(&output));
+        output[31] = 1;
+        assert!(is_entry_point(&output));
+        assert!(!is_entry_point(&output[..20]));
+        output[0] = 1;
+        assert!(!is_entry_point(&output));
+    }
+}
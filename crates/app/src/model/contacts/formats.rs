@@ -0,0 +1,437 @@
+//! Address books shared with other wallets.
+//!
+//! Contacts are imported from and exported to CSV, with a header naming the
+//! `address`, `name`, `chainId`, `class` and `category` columns, and to the
+//! JSON address book of browser wallets, which groups entries by chain id:
+//! `{ "0x1": { "0xabc...": { "address": "0xabc...", "name": "Alice", ... } } }`.
+//! Flat JSON lists of `{ "address", "name" }` entries are imported too.
+
+use std::{collections::BTreeMap, fmt, path::Path, str::FromStr};
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::*;
+
+/// Columns of exported CSV files, in order.
+const CSV_HEADER: [&str; 5] = ["address", "name", "chainId", "class", "category"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AddressBookFormat {
+    #[default]
+    Csv,
+    /// Address book JSON of browser wallets.
+    Wallet,
+}
+
+impl AddressBookFormat {
+    /// Format of the file at `path`, by its extension.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("csv") => Ok(Self::Csv),
+            Some(extension) if extension.eq_ignore_ascii_case("json") => Ok(Self::Wallet),
+            _ => Err(anyhow!(
+                "Address books must be .csv or .json files, got {:?}",
+                path
+            )),
+        }
+    }
+}
+
+impl fmt::Display for AddressBookFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AddressBookFormat::Csv => write!(f, "CSV"),
+            AddressBookFormat::Wallet => write!(f, "Wallet JSON"),
+        }
+    }
+}
+
+/// A contact read from an address book, in the category it's listed under.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedContact {
+    pub address: Address,
+    pub contact: ContactValue,
+    pub category: Category,
+}
+
+/// Entry of a wallet address book.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WalletEntry {
+    address: Address,
+    #[serde(default)]
+    name: String,
+    /// Hex string in browser wallets, but some tools write numbers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    chain_id: Option<Value>,
+    #[serde(default)]
+    memo: String,
+    #[serde(default)]
+    is_ens: bool,
+}
+
+impl Contacts {
+    /// Reads the contacts of an address book. Contacts without a category are
+    /// put in `category`.
+    pub fn parse(
+        format: AddressBookFormat,
+        contents: &str,
+        category: Category,
+    ) -> Result<Vec<ImportedContact>> {
+        match format {
+            AddressBookFormat::Csv => parse_csv(contents, category),
+            AddressBookFormat::Wallet => parse_wallet(contents, category),
+        }
+    }
+
+    /// Reads the contacts of the address book at `path`, in the format of its
+    /// extension.
+    pub fn read_file(path: &Path, category: Category) -> Result<Vec<ImportedContact>> {
+        let format = AddressBookFormat::from_path(path)?;
+        let contents = std::fs::read_to_string(path)
+            .map_err(|error| anyhow!("Failed to read {:?}: {}", path, error))?;
+        Self::parse(format, &contents, category)
+    }
+
+    /// Adds imported contacts, returning their addresses.
+    pub fn merge(&mut self, imported: Vec<ImportedContact>) -> Vec<Address> {
+        imported
+            .into_iter()
+            .map(|imported| {
+                self.add(imported.address, imported.contact, imported.category);
+                imported.address
+            })
+            .collect()
+    }
+
+    /// Writes the contacts of `category`, or of every category, as an address
+    /// book of the network `chain_id`.
+    pub fn export(
+        &self,
+        format: AddressBookFormat,
+        category: Option<Category>,
+        chain_id: u64,
+    ) -> Result<String> {
+        let books = match category {
+            Some(category) => vec![category],
+            None => Category::all(),
+        };
+        let mut contacts = vec![];
+        for book in books {
+            for (address, contact) in self.list(book.clone()) {
+                contacts.push((book.clone(), *address, contact));
+            }
+        }
+
+        match format {
+            AddressBookFormat::Csv => {
+                let mut lines = vec![CSV_HEADER.join(",")];
+                for (category, address, contact) in contacts {
+                    lines.push(
+                        [
+                            format!("{:?}", address),
+                            csv_field(&contact.label),
+                            chain_id.to_string(),
+                            contact.class.to_string(),
+                            category.to_string(),
+                        ]
+                        .join(","),
+                    );
+                }
+                Ok(lines.join("\n") + "\n")
+            }
+            AddressBookFormat::Wallet => {
+                let chain_id = format!("{:#x}", chain_id);
+                let entries: BTreeMap<String, WalletEntry> = contacts
+                    .into_iter()
+                    .map(|(_, address, contact)| {
+                        let entry = WalletEntry {
+                            address,
+                            name: contact.label.clone(),
+                            chain_id: Some(Value::String(chain_id.clone())),
+                            memo: String::new(),
+                            is_ens: false,
+                        };
+                        (format!("{:?}", address), entry)
+                    })
+                    .collect();
+                let book = BTreeMap::from([(chain_id, entries)]);
+                Ok(serde_json::to_string_pretty(&book)?)
+            }
+        }
+    }
+
+    /// Writes the contacts to the address book at `path`, in the format of
+    /// its extension.
+    pub fn export_file(
+        &self,
+        path: &Path,
+        category: Option<Category>,
+        chain_id: u64,
+    ) -> Result<()> {
+        let format = AddressBookFormat::from_path(path)?;
+        let contents = self.export(format, category, chain_id)?;
+        std::fs::write(path, contents)
+            .map_err(|error| anyhow!("Failed to write {:?}: {}", path, error))
+    }
+}
+
+fn parse_csv(contents: &str, default: Category) -> Result<Vec<ImportedContact>> {
+    let mut lines = contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    let Some((_, header)) = lines.next() else {
+        return Ok(vec![]);
+    };
+    let header = split_csv_line(header)?
+        .into_iter()
+        .map(|column| column.trim().to_lowercase())
+        .collect::<Vec<_>>();
+    let column = |names: &[&str]| {
+        header
+            .iter()
+            .position(|column| names.contains(&column.as_str()))
+    };
+    let address_column = column(&["address"]).ok_or(anyhow!("CSV has no address column."))?;
+    let name_column = column(&["name", "label"]);
+    let class_column = column(&["class", "type"]);
+    let category_column = column(&["category"]);
+
+    let mut contacts = vec![];
+    for (index, line) in lines {
+        let fields = split_csv_line(line)?;
+        let field = |column: Option<usize>| {
+            column
+                .and_then(|column| fields.get(column))
+                .map(|field| field.trim())
+                .filter(|field| !field.is_empty())
+        };
+
+        let address =
+            field(Some(address_column)).ok_or(anyhow!("Line {} has no address.", index + 1))?;
+        let address = address
+            .parse::<Address>()
+            .map_err(|error| anyhow!("Line {} has an invalid address: {}", index + 1, error))?;
+        let class = match field(class_column) {
+            Some(class) => Class::from_str(class)
+                .map_err(|_| anyhow!("Line {} has an unknown class: {}", index + 1, class))?,
+            None => Class::default(),
+        };
+        let category = match field(category_column) {
+            Some(category) => Category::from_str(category)
+                .map_err(|_| anyhow!("Line {} has an unknown category: {}", index + 1, category))?,
+            None => default.clone(),
+        };
+
+        contacts.push(ImportedContact {
+            address,
+            contact: ContactValue {
+                label: field(name_column).unwrap_or_default().to_string(),
+                class,
+                artifact: None,
+            },
+            category,
+        });
+    }
+
+    Ok(contacts)
+}
+
+fn parse_wallet(contents: &str, default: Category) -> Result<Vec<ImportedContact>> {
+    let mut value: Value = serde_json::from_str(contents)?;
+    // Wallet state dumps keep the address book under its own key.
+    if let Some(book) = value.get_mut("addressBook") {
+        value = book.take();
+    }
+
+    let entries: Vec<WalletEntry> = match value {
+        Value::Array(entries) => entries
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<Result<_, _>>()?,
+        Value::Object(chains) => {
+            let mut entries = vec![];
+            for (_, chain) in chains {
+                let Value::Object(chain) = chain else {
+                    bail!("Address book entries must be grouped by chain id.");
+                };
+                for (_, entry) in chain {
+                    entries.push(serde_json::from_value(entry)?);
+                }
+            }
+            entries
+        }
+        _ => bail!("Address book must be a list of contacts or contacts by chain id."),
+    };
+
+    Ok(entries
+        .into_iter()
+        .map(|entry| ImportedContact {
+            address: entry.address,
+            contact: ContactValue {
+                label: entry.name,
+                ..Default::default()
+            },
+            category: default.clone(),
+        })
+        .collect())
+}
+
+/// Splits a CSV line into its fields, unquoting quoted fields.
+fn split_csv_line(line: &str) -> Result<Vec<String>> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        bail!("Unclosed quote in CSV line: {}", line);
+    }
+    fields.push(field);
+
+    Ok(fields)
+}
+
+/// Quotes a field that has commas, quotes or surrounding spaces.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"']) || field.trim() != field {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contact(label: &str, class: Class) -> ContactValue {
+        ContactValue {
+            label: label.to_string(),
+            class,
+            artifact: None,
+        }
+    }
+
+    #[test]
+    fn round_trips_csv() -> Result<()> {
+        let mut contacts = Contacts::new();
+        contacts.add(
+            Address::repeat_byte(1),
+            contact("Alice, \"the\" trader", Class::EOA),
+            Category::Trusted,
+        );
+        contacts.add(
+            Address::repeat_byte(2),
+            contact("Vault", Class::Contract),
+            Category::Blocked,
+        );
+
+        let csv = contacts.export(AddressBookFormat::Csv, None, 1)?;
+        let imported = Contacts::parse(AddressBookFormat::Csv, &csv, Category::Untrusted)?;
+
+        let mut copy = Contacts::new();
+        copy.merge(imported);
+        assert_eq!(
+            copy.list(Category::Trusted),
+            contacts.list(Category::Trusted)
+        );
+        assert_eq!(
+            copy.list(Category::Blocked),
+            contacts.list(Category::Blocked)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reads_csv_of_other_wallets() -> Result<()> {
+        let csv = "name,address,chainId\n\
+                   Bob,0x0101010101010101010101010101010101010101,1\n\
+                   \n\
+                   \"Carol, Inc\",0x0202020202020202020202020202020202020202,1\n";
+
+        let imported = Contacts::parse(AddressBookFormat::Csv, csv, Category::Untrusted)?;
+
+        assert_eq!(imported.len(), 2);
+        assert_eq!(imported[0].address, Address::repeat_byte(1));
+        assert_eq!(imported[1].contact.label, "Carol, Inc");
+        assert!(imported
+            .iter()
+            .all(|contact| contact.category == Category::Untrusted));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_csv_rows() {
+        let csv = "address,name\n0x1234,Short\n";
+        let error = Contacts::parse(AddressBookFormat::Csv, csv, Category::Untrusted);
+        assert!(error.unwrap_err().to_string().contains("Line 2"));
+    }
+
+    #[test]
+    fn reads_wallet_address_books() -> Result<()> {
+        let by_chain = r#"{
+            "addressBook": {
+                "0x1": {
+                    "0x0101010101010101010101010101010101010101": {
+                        "address": "0x0101010101010101010101010101010101010101",
+                        "chainId": "0x1",
+                        "isEns": false,
+                        "memo": "",
+                        "name": "Alice"
+                    }
+                }
+            }
+        }"#;
+        let list = r#"[{ "address": "0x0202020202020202020202020202020202020202", "name": "Bob", "chainId": 1 }]"#;
+
+        let by_chain = Contacts::parse(AddressBookFormat::Wallet, by_chain, Category::Trusted)?;
+        let list = Contacts::parse(AddressBookFormat::Wallet, list, Category::Trusted)?;
+
+        assert_eq!(by_chain[0].address, Address::repeat_byte(1));
+        assert_eq!(by_chain[0].contact.label, "Alice");
+        assert_eq!(list[0].contact.label, "Bob");
+        Ok(())
+    }
+
+    #[test]
+    fn round_trips_wallet_address_books() -> Result<()> {
+        let mut contacts = Contacts::new();
+        contacts.add(
+            Address::repeat_byte(1),
+            contact("Alice", Class::EOA),
+            Category::Trusted,
+        );
+
+        let json = contacts.export(AddressBookFormat::Wallet, Some(Category::Trusted), 10)?;
+        assert!(json.contains("\"0xa\""));
+
+        let imported = Contacts::parse(AddressBookFormat::Wallet, &json, Category::Trusted)?;
+        let mut copy = Contacts::new();
+        copy.merge(imported);
+        assert_eq!(
+            copy.list(Category::Trusted),
+            contacts.list(Category::Trusted)
+        );
+        Ok(())
+    }
+}
@@ -38,6 +38,10 @@ impl ContactList {
         self.addresses.get(address)
     }
 
+    pub fn get_mut(&mut self, address: &Address) -> Option<&mut ContactValue> {
+        self.addresses.get_mut(address)
+    }
+
     pub fn remove(&mut self, address: &Address) -> Option<ContactValue> {
         self.addresses.remove(address)
     }
@@ -4,6 +4,8 @@
 //! `Category` enum.
 
 pub mod classification;
+pub mod classify;
+pub mod formats;
 pub mod list;
 
 use std::{
@@ -13,6 +15,7 @@ use std::{
 
 pub use classification::*;
 use ethers::prelude::*;
+pub use formats::*;
 pub use list::*;
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -37,7 +40,15 @@ impl Contacts {
         Self::default()
     }
 
+    /// Adds the contact at `address` to `category`, moving it out of the
+    /// category it was in, so every address is in one category.
     pub fn add(&mut self, address: Address, label: ContactValue, category: Category) {
+        if !self.books.contains_key(&category) {
+            return;
+        }
+        for book in self.books.values_mut() {
+            book.remove(&address);
+        }
         if let Some(book) = self.books.get_mut(&category) {
             book.add(address, label);
         }
@@ -99,6 +110,64 @@ impl Contacts {
             .unwrap_or_default()
     }
 
+    /// Category of the contact at `address`. An address listed in several
+    /// categories, by profiles saved before contacts were moved between them,
+    /// is blocked or untrusted before it's trusted.
+    pub fn category_of(&self, address: &Address) -> Option<Category> {
+        [
+            Category::Blocked,
+            Category::Untrusted,
+            Category::Trusted,
+            Category::Recent,
+        ]
+        .into_iter()
+        .find(|category| {
+            self.books
+                .get(category)
+                .is_some_and(|book| book.contains(address))
+        })
+    }
+
+    /// Warning to show before sending a transaction to `address`, if it's a
+    /// blocked or untrusted contact.
+    pub fn warning(&self, address: &Address) -> Option<String> {
+        let category = self.category_of(address)?;
+        let name = match self.find(address) {
+            Some(contact) if !contact.label.is_empty() => {
+                format!("{} ({:?})", contact.label, address)
+            }
+            _ => format!("{:?}", address),
+        };
+        match category {
+            Category::Blocked => Some(format!("{} is blocked in your contacts.", name)),
+            Category::Untrusted => Some(format!("{} is untrusted in your contacts.", name)),
+            _ => None,
+        }
+    }
+
+    pub fn is_blocked(&self, address: &Address) -> bool {
+        self.category_of(address) == Some(Category::Blocked)
+    }
+
+    /// Sets the class of the contact at `address` in every category.
+    pub fn set_class(&mut self, address: &Address, class: Class) {
+        for book in self.books.values_mut() {
+            if let Some(contact) = book.get_mut(address) {
+                contact.class = class.clone();
+            }
+        }
+    }
+
+    /// Adds `address`, transacted with, to the recent contacts, unless it's
+    /// already a contact. Returns whether it was added.
+    pub fn add_recent(&mut self, address: Address, contact: ContactValue) -> bool {
+        if self.category_of(&address).is_some() {
+            return false;
+        }
+        self.add(address, contact, Category::Recent);
+        true
+    }
+
     pub fn clear(&mut self, category: Category) {
         if let Some(book) = self.books.get_mut(&category) {
             book.clear();
@@ -138,3 +207,53 @@ impl Contacts {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contact(label: &str) -> ContactValue {
+        ContactValue {
+            label: label.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn warns_about_untrusted_and_blocked_contacts() {
+        let mut contacts = Contacts::new();
+        let (trusted, untrusted, blocked) = (
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+            Address::repeat_byte(3),
+        );
+        contacts.add(trusted, contact("Alice"), Category::Trusted);
+        contacts.add(untrusted, contact("Bob"), Category::Untrusted);
+        contacts.add(blocked, contact("Mallory"), Category::Blocked);
+        // Blocking a trusted contact moves it to the blocked contacts.
+        contacts.add(trusted, contact("Alice"), Category::Blocked);
+        assert!(contacts.get(&trusted, Category::Trusted).is_none());
+
+        assert!(contacts.warning(&trusted).unwrap().contains("blocked"));
+        assert!(contacts.warning(&untrusted).unwrap().contains("Bob"));
+        assert!(contacts.is_blocked(&blocked));
+        assert_eq!(contacts.warning(&Address::repeat_byte(4)), None);
+
+        // Trusting an untrusted contact stops the warning.
+        contacts.add(untrusted, contact("Bob"), Category::Trusted);
+        assert_eq!(contacts.category_of(&untrusted), Some(Category::Trusted));
+        assert_eq!(contacts.warning(&untrusted), None);
+    }
+
+    #[test]
+    fn adds_only_new_addresses_to_recent() {
+        let mut contacts = Contacts::new();
+        let known = Address::repeat_byte(1);
+        contacts.add(known, contact("Alice"), Category::Trusted);
+
+        assert!(!contacts.add_recent(known, contact("DFMM")));
+        assert!(contacts.add_recent(Address::repeat_byte(2), contact("DFMM")));
+        assert_eq!(contacts.list(Category::Recent).len(), 1);
+        assert_eq!(contacts.category_of(&known), Some(Category::Trusted));
+    }
+}
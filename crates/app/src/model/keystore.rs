@@ -0,0 +1,302 @@
+//! Local signers, kept encrypted at rest.
+//!
+//! Every imported key is encrypted with a password of the user's choosing into
+//! a JSON V3 keystore file in the keystore directory, named by its id. The
+//! profile only holds the [`Keystore`] index of names and addresses, so keys
+//! are never written in the clear, and are decrypted only to sign.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use ethers::{
+    core::rand::thread_rng,
+    signers::{coins_bip39::English, LocalWallet, MnemonicBuilder, Signer},
+    types::Address,
+};
+use serde::{Deserialize, Serialize};
+
+use super::user::{Saveable, UserProfile};
+
+/// Directory in the config directory holding the keystore files.
+pub const KEYSTORE_DIR: &str = "keystore";
+
+/// Path used for mnemonics if none is given: the first account of the standard
+/// Ethereum path.
+pub const DEFAULT_DERIVATION_PATH: &str = "m/44'/60'/0'/0/0";
+
+/// Where the key of a signer was imported from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SignerSource {
+    PrivateKey,
+    Mnemonic { derivation_path: String },
+    Keystore,
+}
+
+/// Key material to import a signer from.
+#[derive(Clone, Debug)]
+pub enum SignerImport {
+    /// Hex encoded private key.
+    PrivateKey(String),
+    /// BIP-39 phrase, with the path of the account to derive.
+    Mnemonic {
+        phrase: String,
+        derivation_path: Option<String>,
+    },
+    /// Existing JSON V3 keystore file, encrypted with the same password.
+    Keystore(PathBuf),
+}
+
+/// A signer in the keystore.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignerEntry {
+    /// Name of the keystore file of the signer.
+    pub id: String,
+    pub name: String,
+    pub address: Address,
+    pub source: SignerSource,
+}
+
+/// Index of the signers in the keystore directory.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Keystore {
+    pub signers: Vec<SignerEntry>,
+    /// Id of the signer the app sends transactions with. Keys are locked
+    /// again when the app restarts, so it isn't saved.
+    #[serde(skip)]
+    pub active: Option<String>,
+}
+
+impl Keystore {
+    /// Directory of the keystore files of the app.
+    pub fn dir() -> PathBuf {
+        UserProfile::config_dir().join(KEYSTORE_DIR)
+    }
+
+    pub fn list(&self) -> &[SignerEntry] {
+        &self.signers
+    }
+
+    pub fn get(&self, id: &str) -> Option<&SignerEntry> {
+        self.signers.iter().find(|signer| signer.id == id)
+    }
+
+    pub fn active(&self) -> Option<&SignerEntry> {
+        self.active.as_deref().and_then(|id| self.get(id))
+    }
+
+    /// Encrypts the key of `import` with `password` into a new file in `dir`,
+    /// returning the signer to [`Self::add`]. Decrypting and encrypting keys is
+    /// slow, so this should run off the UI thread.
+    pub fn import(
+        &self,
+        dir: &Path,
+        name: &str,
+        import: SignerImport,
+        password: &str,
+    ) -> anyhow::Result<SignerEntry> {
+        self.check_name(name, None)?;
+        if password.is_empty() {
+            anyhow::bail!("A password is required to encrypt the key.");
+        }
+
+        let (wallet, source) = match import {
+            SignerImport::PrivateKey(key) => {
+                let wallet = key
+                    .trim()
+                    .parse::<LocalWallet>()
+                    .map_err(|e| anyhow::anyhow!("Invalid private key: {}", e))?;
+                (wallet, SignerSource::PrivateKey)
+            }
+            SignerImport::Mnemonic {
+                phrase,
+                derivation_path,
+            } => {
+                let derivation_path =
+                    derivation_path.unwrap_or_else(|| DEFAULT_DERIVATION_PATH.to_string());
+                let wallet = MnemonicBuilder::<English>::default()
+                    .phrase(phrase.trim())
+                    .derivation_path(&derivation_path)
+                    .and_then(|builder| builder.build())
+                    .map_err(|e| anyhow::anyhow!("Invalid mnemonic: {}", e))?;
+                (wallet, SignerSource::Mnemonic { derivation_path })
+            }
+            SignerImport::Keystore(path) => {
+                let wallet = LocalWallet::decrypt_keystore(&path, password)
+                    .map_err(|e| anyhow::anyhow!("Could not decrypt {:?}: {}", path, e))?;
+                (wallet, SignerSource::Keystore)
+            }
+        };
+
+        let address = wallet.address();
+        if let Some(existing) = self.signers.iter().find(|s| s.address == address) {
+            anyhow::bail!("{:?} is already imported as {}.", address, existing.name);
+        }
+
+        fs::create_dir_all(dir)?;
+        let (_, id) = LocalWallet::encrypt_keystore(
+            dir,
+            &mut thread_rng(),
+            wallet.signer().to_bytes(),
+            password,
+            None,
+        )
+        .map_err(|e| anyhow::anyhow!("Could not encrypt key: {}", e))?;
+
+        Ok(SignerEntry {
+            id,
+            name: name.trim().to_string(),
+            address,
+            source,
+        })
+    }
+
+    /// Adds a signer returned by [`Self::import`].
+    pub fn add(&mut self, entry: SignerEntry) -> anyhow::Result<()> {
+        self.check_name(&entry.name, None)?;
+        if self.signers.iter().any(|s| s.address == entry.address) {
+            anyhow::bail!("{:?} is already imported.", entry.address);
+        }
+        self.signers.push(entry);
+        Ok(())
+    }
+
+    /// Decrypts the key of signer `id` in `dir` with `password`.
+    pub fn unlock(&self, dir: &Path, id: &str, password: &str) -> anyhow::Result<LocalWallet> {
+        let entry = self
+            .get(id)
+            .ok_or_else(|| anyhow::anyhow!("No signer with id {}", id))?;
+        LocalWallet::decrypt_keystore(dir.join(&entry.id), password)
+            .map_err(|_| anyhow::anyhow!("Wrong password for {}.", entry.name))
+    }
+
+    pub fn rename(&mut self, id: &str, name: &str) -> anyhow::Result<()> {
+        self.check_name(name, Some(id))?;
+        let entry = self
+            .signers
+            .iter_mut()
+            .find(|signer| signer.id == id)
+            .ok_or_else(|| anyhow::anyhow!("No signer with id {}", id))?;
+        entry.name = name.trim().to_string();
+        Ok(())
+    }
+
+    /// Removes signer `id` and deletes its keystore file from `dir`.
+    pub fn delete(&mut self, dir: &Path, id: &str) -> anyhow::Result<SignerEntry> {
+        let index = self
+            .signers
+            .iter()
+            .position(|signer| signer.id == id)
+            .ok_or_else(|| anyhow::anyhow!("No signer with id {}", id))?;
+        let path = dir.join(id);
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        if self.active.as_deref() == Some(id) {
+            self.active = None;
+        }
+        Ok(self.signers.remove(index))
+    }
+
+    pub fn set_active(&mut self, id: &str) -> anyhow::Result<()> {
+        if self.get(id).is_none() {
+            anyhow::bail!("No signer with id {}", id);
+        }
+        self.active = Some(id.to_string());
+        Ok(())
+    }
+
+    /// Checks `name` is usable for a signer, other than `id` if renaming.
+    fn check_name(&self, name: &str, id: Option<&str>) -> anyhow::Result<()> {
+        let name = name.trim();
+        if name.is_empty() {
+            anyhow::bail!("Signers need a name.");
+        }
+        if self
+            .signers
+            .iter()
+            .any(|signer| signer.name == name && Some(signer.id.as_str()) != id)
+        {
+            anyhow::bail!("A signer is already named {}.", name);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // First account of the Anvil and Hardhat test mnemonic.
+    const PHRASE: &str = "test test test test test test test test test test test junk";
+    const KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    #[test]
+    fn test_keystore() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join("excalibur_keystore_test");
+        let _ = fs::remove_dir_all(&dir);
+        let mut keystore = Keystore::default();
+
+        let entry = keystore.import(
+            &dir,
+            "dev",
+            SignerImport::PrivateKey(KEY.to_string()),
+            "password",
+        )?;
+        keystore.add(entry.clone())?;
+
+        // The same key from a mnemonic is a duplicate.
+        let duplicate = keystore.import(
+            &dir,
+            "from phrase",
+            SignerImport::Mnemonic {
+                phrase: PHRASE.to_string(),
+                derivation_path: None,
+            },
+            "password",
+        );
+        assert!(duplicate.is_err());
+
+        let second = keystore.import(
+            &dir,
+            "second",
+            SignerImport::Mnemonic {
+                phrase: PHRASE.to_string(),
+                derivation_path: Some("m/44'/60'/0'/0/1".to_string()),
+            },
+            "password",
+        )?;
+        keystore.add(second.clone())?;
+        assert_ne!(second.address, entry.address);
+
+        // Keys are only readable with the password.
+        assert!(keystore.unlock(&dir, &entry.id, "wrong").is_err());
+        let wallet = keystore.unlock(&dir, &entry.id, "password")?;
+        assert_eq!(wallet.address(), entry.address);
+
+        // Existing keystore files import with their password.
+        let mut other = Keystore::default();
+        let copied = other.import(
+            &dir.join("copy"),
+            "copy",
+            SignerImport::Keystore(dir.join(&entry.id)),
+            "password",
+        )?;
+        assert_eq!(copied.address, entry.address);
+        assert_eq!(copied.source, SignerSource::Keystore);
+
+        assert!(keystore.rename(&second.id, "dev").is_err());
+        keystore.rename(&second.id, "renamed")?;
+        keystore.set_active(&second.id)?;
+        assert_eq!(keystore.active().unwrap().name, "renamed");
+
+        keystore.delete(&dir, &second.id)?;
+        assert!(keystore.active().is_none());
+        assert!(!dir.join(&second.id).exists());
+        assert_eq!(keystore.list(), &[entry]);
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -1,13 +1,18 @@
 //! Aggregated model for the application's entire data system.
 
 pub mod contacts;
+pub mod keystore;
 pub mod portfolio;
+pub mod profiles;
 pub mod rpcs;
+pub mod sessions;
+pub mod storage;
 pub mod user;
 
-use std::{collections::HashMap, fs::File};
+use std::{collections::HashMap, path::PathBuf};
 
 use alloy_primitives::ChainId;
+use clients::transactions::{load_transactions, TrackedTransaction, TxKind};
 use datatypes::portfolio::{
     coin::Coin,
     position::{Position, PositionLayer, Positions},
@@ -16,7 +21,10 @@ use datatypes::portfolio::{
 use uuid::Uuid;
 
 use self::{
+    contacts::{classify::classify_all, ContactValue},
     portfolio::{AlloyAddress, AlloyU256, RawDataModel},
+    profiles::DEFAULT_PROFILE,
+    storage::Migration,
     user::{Saveable, UserProfile},
 };
 use super::*;
@@ -58,23 +66,77 @@ impl Model {
         client: Arc<M>,
     ) -> anyhow::Result<()> {
         let chain_id = client.get_chainid().await?;
-        self.networks
-            .entry(chain_id.as_u64())
-            .or_insert_with(|| RawDataModel::new(chain_id.as_u64()));
-        self.current = Some(chain_id.as_u64());
+        self.switch_network(chain_id.as_u64());
         Ok(())
     }
 
+    /// Sets the current network to `chain_id`, keeping the data model of the
+    /// network if it was connected before.
+    pub fn switch_network(&mut self, chain_id: ChainId) {
+        self.networks
+            .entry(chain_id)
+            .or_insert_with(|| RawDataModel::new(chain_id));
+        self.current = Some(chain_id);
+    }
+
     pub async fn update<M: Middleware + 'static>(&mut self, client: Arc<M>) -> anyhow::Result<()> {
         // 1. Fetches and updates the data model stored in `self.portfolio`.
         // 2. Fetches the now updated position info from the data model.
         // 3. Using the position info, derives the weights of the positions.
         // 4. Propagates updated position info to the user's saved portfolio data.
-        self.update_data_model(client).await.map(|_| {
-            if let Err(error) = self.update_portfolio_positions() {
-                tracing::warn!("Failed to update portfolio positions: {:?}", error);
-            }
-        })
+        // 5. Adds the recipients of the transactions sent from the app to the
+        //    recent contacts.
+        self.update_data_model(client.clone()).await?;
+        if let Err(error) = self.update_portfolio_positions() {
+            tracing::warn!("Failed to update portfolio positions: {:?}", error);
+        }
+        self.update_recent_contacts(client).await;
+        Ok(())
+    }
+
+    /// Adds the addresses the app sent the user's transactions to on the
+    /// current network to the recent contacts, classified from their code.
+    /// Addresses that are already contacts are left where they are.
+    pub async fn update_recent_contacts<M: Middleware + 'static>(&mut self, client: Arc<M>) {
+        let mut recipients: Vec<Address> = self
+            .sent_transactions()
+            .iter()
+            // Cancellations are sent to the sender.
+            .filter(|tx| tx.kind != TxKind::Cancel)
+            .filter_map(|tx| tx.tx.to_addr().copied())
+            .filter(|address| self.user.contacts.category_of(address).is_none())
+            .collect();
+        recipients.sort();
+        recipients.dedup();
+        if recipients.is_empty() {
+            return;
+        }
+
+        // Known contracts are named after their entry in the contract book.
+        let names: HashMap<Address, String> = self
+            .current
+            .and_then(|chain_id| self.user.contracts.get(&chain_id))
+            .map(|book| {
+                book.iter()
+                    .map(|(name, address)| (*address, name.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        for (address, class) in classify_all(client, recipients).await {
+            let label = names
+                .get(&address)
+                .cloned()
+                .unwrap_or_else(|| format!("{:?}", address));
+            self.user.contacts.add_recent(
+                address,
+                ContactValue {
+                    label,
+                    class,
+                    artifact: None,
+                },
+            );
+        }
     }
 
     /// Fetches the balances and values of the user's tokens.
@@ -282,14 +344,78 @@ impl Model {
 
         Ok(())
     }
+
+    /// USD price of the native token of the current network, from the latest
+    /// external price of its wrapped coin in the user's coin list.
+    pub fn native_price(&self) -> Option<f64> {
+        let chain_id = self.current?;
+        let coin = self.user.coins.tokens.iter().find(|coin| {
+            coin.chain_id == chain_id && matches!(coin.symbol.as_str(), "ETH" | "WETH")
+        })?;
+        let price = self.get_current()?.price_of_token(coin.address).ok()?;
+        alloy_primitives::utils::format_ether(price).parse().ok()
+    }
+
+    /// Where the transactions the profile sends on the current network are
+    /// saved, to track them until they are final.
+    pub fn transactions_path(&self) -> Option<PathBuf> {
+        let chain_id = self.current?;
+        let profile = self.user.name.as_deref().unwrap_or(DEFAULT_PROFILE);
+        Some(
+            Self::dir()
+                .join(TRANSACTIONS_DIR)
+                .join(profile)
+                .join(format!("{}.{}", chain_id, MODEL_EXTENSION)),
+        )
+    }
+
+    /// Transactions sent from the app on the current network, as saved by the
+    /// tracker at [`Self::transactions_path`].
+    pub fn sent_transactions(&self) -> Vec<TrackedTransaction> {
+        let Some(path) = self.transactions_path() else {
+            return vec![];
+        };
+        load_transactions(&path).unwrap_or_else(|e| {
+            tracing::warn!("Failed to read the transactions at {:?}: {:?}", path, e);
+            vec![]
+        })
+    }
+
+    /// Describes `cost` in ETH, and in USD when the model has a price for it.
+    pub fn describe_gas_cost(&self, cost: &clients::gas::GasCost) -> String {
+        cost.describe(self.native_price())
+    }
 }
 
 pub const MODEL_EXTENSION: &str = "json";
 pub const MODEL_SUFFIX: &str = "user_data";
+/// Directory the transactions sent from the app are saved in, by profile.
+pub const TRANSACTIONS_DIR: &str = "transactions";
+
+/// Upgrades the profile saved inside the model.
+fn upgrade_user(model: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+    storage::migrate_field(model, "user", user::add_signers_and_networks)
+}
+
+/// Sets aside the sandbox snapshot of the profile saved inside the model, to
+/// be imported as a session.
+fn move_user_snapshot(model: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+    storage::migrate_field(model, "user", user::move_snapshot_to_session)
+}
+
+/// Trusts the contracts in the address books of the profile saved inside the
+/// model.
+fn trust_user_contracts(model: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+    storage::migrate_field(model, "user", user::trust_recorded_contracts)
+}
+
+pub const MODEL_MIGRATIONS: &[Migration] =
+    &[upgrade_user, move_user_snapshot, trust_user_contracts];
 
 impl Saveable for Model {
     const EXTENSION: &'static str = MODEL_EXTENSION;
     const SUFFIX: &'static str = MODEL_SUFFIX;
+    const MIGRATIONS: &'static [Migration] = MODEL_MIGRATIONS;
 
     fn prefix(&self) -> Option<String> {
         self.user.name.clone()
@@ -324,15 +450,17 @@ impl Saveable for Model {
         }
 
         let profile_path = Self::dir().join(formatted_path);
-        let file = File::create(profile_path)?;
 
         let value = Model {
-            user: UserProfile::default(),
+            user: UserProfile {
+                name,
+                ..Default::default()
+            },
             networks: HashMap::new(),
             current: None,
         };
 
-        serde_json::to_writer_pretty(file, &value)?;
+        value.save_to(&profile_path)?;
 
         Ok(value)
     }
@@ -20,21 +20,30 @@
 //! - "compute" - Computes a result based on inputs. Can be expensive.
 //! - "derive" - Computes a result derived from model data input. Expensive.
 
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, sync::OnceLock};
 
 // use alloy_rpc_types::raw_log;
 use alloy_sol_types::{sol, SolCall};
 use anyhow::{anyhow, Error, Result};
 use bindings::{
-    dfmm::{InitFilter, DFMM},
+    dfmm::{InitFilter, PoolsReturn, DFMM},
+    erc20::ERC20,
+    g3m::G3M,
+    g3m_solver::G3MSolver,
     log_normal::LogNormal,
     log_normal_solver::LogNormalSolver,
+    shared_types::DynamicParam,
 };
 use cfmm_math::trading_functions::rmm::{
     compute_l_given_x_rust, compute_price_given_x_rust, compute_x_given_l_rust,
     compute_x_given_price, compute_y_given_l_rust, compute_y_given_x_rust, liq_distribution,
 };
 use chrono::{DateTime, Utc};
+use clients::protocol::{
+    batch::{decode, BatchCall, Batcher},
+    indexer::PoolIndex,
+    pool::PoolKind,
+};
 use datatypes::portfolio::coin_list::CoinList;
 use ethers::types::transaction::eip2718::TypedTransaction;
 use serde::{Deserialize, Serialize};
@@ -124,8 +133,7 @@ pub struct LogNormalStrategyState<V> {
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct G3MStrategyState<V> {
-    // todo!
-    pub todo: V,
+    pub weight_x: V,
 }
 
 /// The model!
@@ -169,6 +177,12 @@ pub struct RawDataModel<A: Ord, V> {
     pub liquidity_token_addresses: Option<Vec<A>>,
     // Stores the metadata of the tokens being fetched once.
     pub token_metadata: Option<BTreeMap<A, TokenInfo>>,
+    // Catalog of the pools of the DFMM deployment, synced from its events.
+    #[serde(default)]
+    pub pool_index: Option<PoolIndex>,
+    // Whether the chain has Multicall3, checked by the first batched read.
+    #[serde(skip)]
+    pub has_multicall: OnceLock<bool>,
 }
 
 sol! {
@@ -299,6 +313,21 @@ impl RawDataModel<AlloyAddress, AlloyU256> {
         Ok(())
     }
 
+    /// Tracks every pool found by `index`, so their state is filled in by the
+    /// next pool update. Their tokens are not the user's, so they are left out
+    /// of the balances. Pools are tracked by a `u64` id, so larger ids are
+    /// skipped.
+    pub fn add_indexed_pools(&mut self, index: &PoolIndex) -> Result<()> {
+        for pool in &index.pools {
+            let Ok(pool_id) = u64::try_from(pool.pool_id) else {
+                tracing::warn!("Skipping pool {} with an id above u64", pool.pool_id);
+                continue;
+            };
+            self.add_pool(pool_id)?;
+        }
+        Ok(())
+    }
+
     // ----- Model updates ----- //
 
     /// Updates the ENTIRE model! Wow!
@@ -322,6 +351,10 @@ impl RawDataModel<AlloyAddress, AlloyU256> {
             tracing::warn!("User history update failed: {:?}", err);
         }
 
+        if let Err(err) = self.update_pool_index(client.clone()).await {
+            tracing::warn!("Pool index update failed: {:?}", err);
+        }
+
         if let Err(err) = self.update_all_pools(client.clone()).await {
             tracing::warn!("Pool update failed: {:?}", err);
         }
@@ -354,16 +387,34 @@ impl RawDataModel<AlloyAddress, AlloyU256> {
         // update the model.
         let current_block = self.fetch_block_number(client.clone()).await?;
         let token_addresses: Vec<_> = self.user_token_balances.keys().cloned().collect();
-        for token_address in token_addresses {
-            let new_balance = self
-                .fetch_balance_of(client.clone(), token_address, user_address)
-                .await?;
+        let tokens: Vec<_> = token_addresses
+            .iter()
+            .map(|token_address| to_ethers_address(*token_address))
+            .collect();
+        let balances = self
+            .batcher(client)
+            .await?
+            .balances(&tokens, to_ethers_address(user_address))
+            .await?;
+        let mut failed = Vec::new();
+        for (token_address, new_balance) in token_addresses.into_iter().zip(balances) {
+            let Some(new_balance) = new_balance else {
+                failed.push(token_address);
+                continue;
+            };
             self.user_token_balances
                 .get_mut(&token_address)
                 .unwrap()
-                .push((current_block, new_balance));
+                .push((current_block, from_ethers_u256(new_balance)));
         }
 
+        // The other balances are kept, but the failed ones are reported.
+        if !failed.is_empty() {
+            return Err(anyhow!(
+                "Failed to fetch the balances of tokens {:?}",
+                failed
+            ));
+        }
         Ok(())
     }
 
@@ -412,11 +463,66 @@ impl RawDataModel<AlloyAddress, AlloyU256> {
         <M as ethers::providers::Middleware>::Error: 'static,
     {
         let new_pool_state = self.fetch_pool_state(client.clone(), pool_id).await?;
-        let liquidity_token_address = new_pool_state.liquidity_token.unwrap();
+        let liquidity_token_address = self.add_pool_state(pool_id, new_pool_state)?;
+
+        // Update the liquidity token's metadata if it is missing from the
+        // token_metadata mapping.
+        if self
+            .token_metadata
+            .get_or_insert_with(BTreeMap::new)
+            .get(&liquidity_token_address)
+            .is_none()
+        {
+            let token_info = self
+                .fetch_token_info(client.clone(), liquidity_token_address)
+                .await?;
+            self.token_metadata
+                .as_mut()
+                .unwrap()
+                .insert(liquidity_token_address, token_info);
+        }
+
+        Ok(())
+    }
+
+    /// Appends the series of a freshly fetched pool state to the tracked state
+    /// of the pool, and tracks its liquidity token. Returns the address of the
+    /// liquidity token.
+    fn add_pool_state(
+        &mut self,
+        pool_id: u64,
+        new_pool_state: PoolState<AlloyAddress, AlloyU256>,
+    ) -> Result<AlloyAddress> {
+        let liquidity_token_address = self.merge_pool_state(pool_id, new_pool_state)?;
+
+        // Update the liquidity_token_addresses mapping if it missing the new pool's
+        // liquidity token address.
+        let liquidity_token_addresses = self.liquidity_token_addresses.get_or_insert_with(Vec::new);
+        if !liquidity_token_addresses.contains(&liquidity_token_address) {
+            liquidity_token_addresses.push(liquidity_token_address);
+        }
+
+        Ok(liquidity_token_address)
+    }
+
+    /// Appends the series of a freshly fetched pool state to the tracked state
+    /// of the pool. A pool tracked before its state was ever fetched takes the
+    /// new state as is. Returns the address of the liquidity token.
+    fn merge_pool_state(
+        &mut self,
+        pool_id: u64,
+        new_pool_state: PoolState<AlloyAddress, AlloyU256>,
+    ) -> Result<AlloyAddress> {
+        let liquidity_token_address = new_pool_state
+            .liquidity_token
+            .ok_or(Error::msg("Liquidity token address not set for pool state"))?;
 
         let pool_state_map = self.pool_state.get_or_insert_with(BTreeMap::new);
 
-        if let Some(existing_pool_state) = pool_state_map.get_mut(&pool_id) {
+        if let Some(existing_pool_state) = pool_state_map
+            .get_mut(&pool_id)
+            .filter(|pool_state| pool_state.liquidity_token.is_some())
+        {
             // Append new series data to existing pool state
             if let Some(new_internal_price) = new_pool_state.internal_price {
                 existing_pool_state
@@ -456,34 +562,37 @@ impl RawDataModel<AlloyAddress, AlloyU256> {
             pool_state_map.insert(pool_id, new_pool_state);
         }
 
-        // Update the liquidity_token_addresses mapping if it missing the new pool's
-        // liquidity token address.
-        let liquidity_token_addresses = self.liquidity_token_addresses.get_or_insert_with(Vec::new);
-        if !liquidity_token_addresses.contains(&liquidity_token_address) {
-            liquidity_token_addresses.push(liquidity_token_address);
-        }
+        Ok(liquidity_token_address)
+    }
 
-        // Update the liquidity token's metadata if it is missing from the
-        // token_metadata mapping.
-        if self
-            .token_metadata
-            .get_or_insert_with(BTreeMap::new)
-            .get(&liquidity_token_address)
-            .is_none()
-        {
-            let token_info = self
-                .fetch_token_info(client.clone(), liquidity_token_address)
-                .await?;
-            self.token_metadata
-                .as_mut()
-                .unwrap()
-                .insert(liquidity_token_address, token_info);
-        }
+    /// Syncs the index of the pools of the DFMM deployment, and tracks the
+    /// pools it found. The index is kept even if the sync fails midway, so the
+    /// next update resumes from the last scanned block.
+    pub async fn update_pool_index<M: Middleware + 'static>(
+        &mut self,
+        client: Arc<M>,
+    ) -> Result<()> {
+        let Some(dfmm_address) = self.dfmm_address else {
+            return Ok(());
+        };
+        let dfmm_address = to_ethers_address(dfmm_address);
+
+        let protocol = DFMM::new(dfmm_address, client);
+        let mut index = match self.pool_index.take() {
+            Some(index) if index.dfmm == dfmm_address => index,
+            _ => PoolIndex::for_deployment(&protocol).await?,
+        };
+        let synced = index.sync(&protocol).await;
+        self.add_indexed_pools(&index)?;
+        self.pool_index = Some(index);
+        synced?;
 
         Ok(())
     }
 
-    /// Updates the state of all the pools.
+    /// Updates the state of all the pools: the ones the user created, and the
+    /// ones found by the pool index. Only the liquidity tokens of the user's
+    /// pools are added to their balances.
     pub async fn update_all_pools<M: Middleware + 'static>(&mut self, client: Arc<M>) -> Result<()>
     where
         <M as ethers::providers::Middleware>::Error: 'static,
@@ -498,11 +607,36 @@ impl RawDataModel<AlloyAddress, AlloyU256> {
             .filter(|tx| tx.action == ProtocolActions::CreatePosition)
             .collect();
 
-        let historical_create_pool_ids: Vec<_> =
+        let mut historical_create_pool_ids: Vec<_> =
             historical_creates.iter().map(|tx| tx.pool_id).collect();
+        historical_create_pool_ids.sort_unstable();
+        historical_create_pool_ids.dedup();
 
-        for pool_id in historical_create_pool_ids {
-            self.update_pool(client.clone(), pool_id).await?;
+        let mut pool_ids = historical_create_pool_ids.clone();
+        if let Some(pool_state) = &self.pool_state {
+            pool_ids.extend(pool_state.keys());
+        }
+        pool_ids.sort_unstable();
+        pool_ids.dedup();
+
+        // The metadata of the liquidity tokens is filled in by the token info
+        // update, along with the other tracked tokens.
+        let batcher = self.batcher(client).await?;
+        let pool_states = self.fetch_pool_states(&batcher, &pool_ids).await?;
+        for (pool_id, pool_state) in pool_ids.into_iter().zip(pool_states) {
+            // A pool that failed to read keeps its previous state.
+            let pool_state = match pool_state {
+                Ok(pool_state) => pool_state,
+                Err(err) => {
+                    tracing::warn!("Failed to fetch the state of pool {}: {:?}", pool_id, err);
+                    continue;
+                }
+            };
+            if historical_create_pool_ids.binary_search(&pool_id).is_ok() {
+                self.add_pool_state(pool_id, pool_state)?;
+            } else {
+                self.merge_pool_state(pool_id, pool_state)?;
+            }
         }
 
         Ok(())
@@ -517,20 +651,33 @@ impl RawDataModel<AlloyAddress, AlloyU256> {
     where
         <M as ethers::providers::Middleware>::Error: 'static,
     {
-        let token_addresses: Vec<_> = self.user_token_balances.keys().cloned().collect();
-        for token_address in token_addresses {
-            if self
-                .token_metadata
-                .get_or_insert_with(BTreeMap::new)
-                .get(&token_address)
-                .is_none()
-            {
-                let token_info = self.fetch_token_info(client.clone(), token_address).await?;
-                self.token_metadata
-                    .as_mut()
-                    .unwrap()
-                    .insert(token_address, token_info);
-            }
+        let token_metadata = self.token_metadata.get_or_insert_with(BTreeMap::new);
+        let missing: Vec<_> = self
+            .user_token_balances
+            .keys()
+            .filter(|token_address| !token_metadata.contains_key(*token_address))
+            .map(|token_address| to_ethers_address(*token_address))
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let fetched = self.batcher(client).await?.token_metadata(&missing).await?;
+        for metadata in fetched {
+            let (Some(name), Some(symbol), Some(decimals)) =
+                (metadata.name, metadata.symbol, metadata.decimals)
+            else {
+                tracing::warn!("Token {:?} is missing metadata", metadata.address);
+                continue;
+            };
+            token_metadata.insert(
+                from_ethers_address(metadata.address),
+                TokenInfo {
+                    name,
+                    symbol,
+                    decimals,
+                },
+            );
         }
         Ok(())
     }
@@ -821,107 +968,119 @@ impl RawDataModel<AlloyAddress, AlloyU256> {
     where
         <M as ethers::providers::Middleware>::Error: 'static,
     {
-        let parsed_pool_id = EthersU256::from(pool_id);
-
-        let dfmm = self.protocol(client.clone()).await?;
-        let current_block = self.fetch_block_number(client.clone()).await?;
-
-        let (
-            controller,
-            strategy,
-            asset_token,
-            quote_token,
-            asset_reserve,
-            quote_reserve,
-            total_liquidity,
-            liquidity_token,
-        ) = dfmm.pools(parsed_pool_id).call().await?;
-
-        let asset_reserve: Option<Vec<(u64, AlloyU256)>> = if asset_reserve.is_zero() {
-            None
-        } else {
-            Some(vec![(current_block, from_ethers_u256(asset_reserve))])
-        };
-
-        let quote_reserve: Option<Vec<(u64, AlloyU256)>> = if quote_reserve.is_zero() {
-            None
-        } else {
-            Some(vec![(current_block, from_ethers_u256(quote_reserve))])
-        };
-
-        let total_liquidity: Option<Vec<(u64, AlloyU256)>> = if total_liquidity.is_zero() {
-            None
-        } else {
-            Some(vec![(current_block, from_ethers_u256(total_liquidity))])
-        };
-
-        let payload = IERC20::totalSupplyCall {};
-        let payload = ethers::types::Bytes::from(payload.abi_encode());
-
-        let mut tx = TypedTransaction::default();
-        tx.set_to(liquidity_token).set_data(payload);
-
-        // Send the call to the token contract.
-        let total_supply = client.call(&tx, None).await?;
-        let decoded: <IERC20::totalSupplyCall as SolCall>::Return =
-            IERC20::totalSupplyCall::abi_decode_returns(&total_supply, false)?;
-        let total_supply = decoded._0;
-
-        let liquidity_token_total_supply: Option<Vec<(u64, AlloyU256)>> = if total_supply.is_zero()
-        {
-            None
-        } else {
-            Some(vec![(current_block, total_supply)])
-        };
-
-        // todo: add g3m strategy check/integration
-        let strategy_instance =
-            self.log_normal_strategy(client.clone(), from_ethers_address(strategy))?;
-
-        let (strike_price, volatility, time_remaining, swap_fee_wad) = strategy_instance
-            .internal_params(parsed_pool_id)
-            .call()
-            .await
-            .map_err(|error| anyhow!(error))?;
-
-        let strike_price = strike_price.last_computed_value;
-        let volatility = volatility.last_computed_value;
-        let time_remaining = time_remaining.last_computed_value;
+        let batcher = self.batcher(client).await?;
+        self.fetch_pool_states(&batcher, &[pool_id])
+            .await?
+            .pop()
+            .unwrap_or_else(|| Err(Error::msg("Missing pool state")))
+    }
 
-        let log_normal_strategy = Some(LogNormalStrategyState {
-            strike_price: from_ethers_u256(strike_price),
-            volatility: from_ethers_u256(volatility),
-            time_remaining: from_ethers_u256(time_remaining),
-        });
+    /// Fetches the raw state of each pool in `pool_ids` in two batches, one for
+    /// the pools and one for their tokens, strategies and prices. A pool that
+    /// fails to read only fails its own entry.
+    pub async fn fetch_pool_states<M: Middleware + 'static>(
+        &self,
+        batcher: &Batcher<M>,
+        pool_ids: &[u64],
+    ) -> Result<Vec<Result<PoolState<AlloyAddress, AlloyU256>>>>
+    where
+        <M as ethers::providers::Middleware>::Error: 'static,
+    {
+        let client = batcher.client.clone();
+        let parsed_pool_ids: Vec<_> = pool_ids.iter().map(|id| EthersU256::from(*id)).collect();
 
+        let dfmm = self.protocol(client.clone()).await?;
         let solver_address = self
             .log_normal_solver_address
             .ok_or(Error::msg("Solver address not set"))?;
-        let solver = self.get_solver(client.clone(), solver_address).await?;
-        let internal_price = solver.internal_price(parsed_pool_id).call().await?;
+        let ln_solver = self.get_solver(client.clone(), solver_address).await?;
+        let g3m_solver = self
+            .g3m_solver_address
+            .map(|address| G3MSolver::new(to_ethers_address(address), client.clone()));
+        let current_block = self.fetch_block_number(client.clone()).await?;
 
-        let internal_price = if internal_price.is_zero() {
-            None
-        } else {
-            Some(vec![(current_block, from_ethers_u256(internal_price))])
-        };
+        // The strategy and liquidity token of each pool are needed to know
+        // what else to read, so the pools are read first, along with the
+        // strategies of the solvers to tell the pools apart.
+        let mut calls = parsed_pool_ids
+            .iter()
+            .map(|pool_id| BatchCall::new(&dfmm.pools(*pool_id)))
+            .collect::<Result<Vec<_>>>()?;
+        calls.push(BatchCall::new(&ln_solver.strategy())?);
+        if let Some(g3m_solver) = &g3m_solver {
+            calls.push(BatchCall::new(&g3m_solver.strategy())?);
+        }
+        let mut results = batcher
+            .aggregate(Some(current_block), calls)
+            .await?
+            .into_iter();
+        let pools: Vec<_> = pool_ids
+            .iter()
+            .map(|pool_id| {
+                decode::<PoolsReturn>(results.next().flatten())
+                    .ok_or(anyhow!("Failed to fetch pool {}", pool_id))
+            })
+            .collect();
+        let ln_strategy = decode::<EthersAddress>(results.next().flatten());
+        let g3m_strategy = decode::<EthersAddress>(results.next().flatten());
+
+        let mut calls = Vec::with_capacity(pools.len() * 3);
+        let mut kinds = Vec::with_capacity(pools.len());
+        for (pool_id, pool) in parsed_pool_ids.iter().zip(&pools) {
+            let Ok(pool) = pool else {
+                kinds.push(None);
+                continue;
+            };
+            let liquidity_token = ERC20::new(pool.liquidity_token, client.clone());
+            let kind = match &g3m_solver {
+                _ if Some(pool.strategy) == ln_strategy => {
+                    let strategy = self
+                        .log_normal_strategy(client.clone(), from_ethers_address(pool.strategy))?;
+                    calls.push(BatchCall::new(&liquidity_token.total_supply())?);
+                    calls.push(BatchCall::new(&strategy.internal_params(*pool_id))?);
+                    calls.push(BatchCall::new(&ln_solver.internal_price(*pool_id))?);
+                    Some(PoolKind::LogNormal)
+                }
+                Some(g3m_solver) if Some(pool.strategy) == g3m_strategy => {
+                    let strategy = G3M::new(pool.strategy, client.clone());
+                    calls.push(BatchCall::new(&liquidity_token.total_supply())?);
+                    calls.push(BatchCall::new(&strategy.internal_params(*pool_id))?);
+                    calls.push(BatchCall::new(&g3m_solver.internal_price(*pool_id))?);
+                    Some(PoolKind::G3M)
+                }
+                _ => None,
+            };
+            kinds.push(kind);
+        }
+        let mut results = batcher
+            .aggregate(Some(current_block), calls)
+            .await?
+            .into_iter();
+
+        let mut pool_states = Vec::with_capacity(pools.len());
+        for ((pool_id, pool), kind) in parsed_pool_ids.into_iter().zip(pools).zip(kinds) {
+            let pool_state = match (pool, kind) {
+                (Ok(pool), Some(kind)) => {
+                    // Every pool of a known strategy has three results, which
+                    // are all taken before decoding to keep the rest aligned.
+                    let outputs = [
+                        results.next().flatten(),
+                        results.next().flatten(),
+                        results.next().flatten(),
+                    ];
+                    decode_pool_state(pool_id, pool, kind, outputs, current_block)
+                }
+                (Ok(pool), None) => Err(anyhow!(
+                    "Pool {} uses an unknown strategy {:?}",
+                    pool_id,
+                    pool.strategy
+                )),
+                (Err(err), _) => Err(err),
+            };
+            pool_states.push(pool_state);
+        }
 
-        Ok(PoolState {
-            id: Some(from_ethers_u256(parsed_pool_id)),
-            controller: Some(from_ethers_address(controller)),
-            strategy: Some(from_ethers_address(strategy)),
-            asset_token: Some(from_ethers_address(asset_token)),
-            quote_token: Some(from_ethers_address(quote_token)),
-            liquidity_token: Some(from_ethers_address(liquidity_token)),
-            internal_price,
-            total_liquidity,
-            asset_reserve,
-            quote_reserve,
-            liquidity_token_total_supply,
-            log_normal_strategy,
-            g3m_strategy: None,
-            swap_fee_wad: Some(from_ethers_u256(swap_fee_wad)),
-        })
+        Ok(pool_states)
     }
 
     #[allow(dead_code)]
@@ -1239,6 +1398,12 @@ impl RawDataModel<AlloyAddress, AlloyU256> {
         Ok(protocol)
     }
 
+    /// Batcher over `client`. Whether the chain has Multicall3 is only checked
+    /// once per model.
+    pub async fn batcher<M: Middleware + 'static>(&self, client: Arc<M>) -> Result<Batcher<M>> {
+        Batcher::cached(client, &self.has_multicall).await
+    }
+
     /// Gets the strategy contract instance given a pool's strategy address.
     pub fn log_normal_strategy<M: Middleware + 'static>(
         &self,
@@ -2844,9 +3009,79 @@ pub fn format_and_parse(value: AlloyU256) -> Result<f64> {
     formatted.parse::<f64>().map_err(|e| e.into())
 }
 
+/// Decodes the state of a pool from its `(liquidity token supply, strategy
+/// params, internal price)` outputs, read at `block`.
+fn decode_pool_state(
+    pool_id: EthersU256,
+    pool: PoolsReturn,
+    kind: PoolKind,
+    [total_supply, params, internal_price]: [Option<Vec<ethers::abi::Token>>; 3],
+    block: u64,
+) -> Result<PoolState<AlloyAddress, AlloyU256>> {
+    let series = |value: EthersU256| -> Option<Vec<(u64, AlloyU256)>> {
+        if value.is_zero() {
+            None
+        } else {
+            Some(vec![(block, from_ethers_u256(value))])
+        }
+    };
+
+    let total_supply = decode::<EthersU256>(total_supply).ok_or(anyhow!(
+        "Failed to fetch the liquidity token supply of pool {}",
+        pool_id
+    ))?;
+    let internal_price = decode::<EthersU256>(internal_price).ok_or(anyhow!(
+        "Failed to fetch the internal price of pool {}",
+        pool_id
+    ))?;
+    let params_error = || anyhow!("Failed to fetch the parameters of pool {}", pool_id);
+    let (log_normal_strategy, g3m_strategy, swap_fee_wad) = match kind {
+        PoolKind::LogNormal => {
+            let (strike_price, volatility, time_remaining, swap_fee_wad) =
+                decode::<(DynamicParam, DynamicParam, DynamicParam, EthersU256)>(params)
+                    .ok_or_else(params_error)?;
+            let strategy = LogNormalStrategyState {
+                strike_price: from_ethers_u256(strike_price.last_computed_value),
+                volatility: from_ethers_u256(volatility.last_computed_value),
+                time_remaining: from_ethers_u256(time_remaining.last_computed_value),
+            };
+            (Some(strategy), None, swap_fee_wad)
+        }
+        PoolKind::G3M => {
+            let (weight_x, swap_fee_wad) =
+                decode::<(DynamicParam, EthersU256)>(params).ok_or_else(params_error)?;
+            let strategy = G3MStrategyState {
+                weight_x: from_ethers_u256(weight_x.last_computed_value),
+            };
+            (None, Some(strategy), swap_fee_wad)
+        }
+        PoolKind::StableSwap => {
+            return Err(anyhow!("Pool {} uses an unsupported strategy", pool_id))
+        }
+    };
+
+    Ok(PoolState {
+        id: Some(from_ethers_u256(pool_id)),
+        controller: Some(from_ethers_address(pool.controller)),
+        strategy: Some(from_ethers_address(pool.strategy)),
+        asset_token: Some(from_ethers_address(pool.token_x)),
+        quote_token: Some(from_ethers_address(pool.token_y)),
+        liquidity_token: Some(from_ethers_address(pool.liquidity_token)),
+        internal_price: series(internal_price),
+        total_liquidity: series(pool.total_liquidity),
+        asset_reserve: series(pool.reserve_x),
+        quote_reserve: series(pool.reserve_y),
+        liquidity_token_total_supply: series(total_supply),
+        log_normal_strategy,
+        g3m_strategy,
+        swap_fee_wad: Some(from_ethers_u256(swap_fee_wad)),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use arbiter_bindings::bindings::arbiter_token::ArbiterToken;
+    use clients::protocol::indexer::PoolRecord;
     use ethers::{
         prelude::*,
         utils::{Anvil, AnvilInstance},
@@ -2896,6 +3131,46 @@ mod tests {
         println!("Decoded: {:?}", decoded);
     }
 
+    #[test]
+    fn test_add_indexed_pools() {
+        let record = |pool_id: EthersU256| PoolRecord {
+            pool_id,
+            strategy: EthersAddress::zero(),
+            token_x: EthersAddress::repeat_byte(1),
+            token_y: EthersAddress::repeat_byte(2),
+            controller: EthersAddress::zero(),
+            liquidity_token: EthersAddress::zero(),
+            creator: EthersAddress::zero(),
+            creation_block: 0,
+        };
+        let mut index = PoolIndex::new(EthersAddress::repeat_byte(0xdf), 0);
+        index.pools = vec![record(1.into()), record(EthersU256::MAX)];
+
+        let mut model = RawDataModel::<AlloyAddress, AlloyU256>::new(1);
+        model.add_indexed_pools(&index).unwrap();
+
+        let pool_ids: Vec<u64> = model.pool_state.clone().unwrap().into_keys().collect();
+        assert_eq!(pool_ids, vec![1]);
+        assert!(model.user_token_balances.is_empty());
+
+        // The empty state tracked for the pool is replaced by the first state
+        // fetched for it, without tracking its liquidity token.
+        let liquidity_token = from_ethers_address(EthersAddress::repeat_byte(3));
+        let state = PoolState {
+            liquidity_token: Some(liquidity_token),
+            asset_reserve: Some(vec![(10, AlloyU256::from(5_u64))]),
+            ..Default::default()
+        };
+        model.merge_pool_state(1, state).unwrap();
+        let pool_state = &model.pool_state.as_ref().unwrap()[&1];
+        assert_eq!(pool_state.liquidity_token, Some(liquidity_token));
+        assert_eq!(
+            pool_state.asset_reserve,
+            Some(vec![(10, AlloyU256::from(5_u64))])
+        );
+        assert!(model.liquidity_token_addresses.is_none());
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
     async fn test_fetch_balance_of() -> anyhow::Result<()> {
         // TODO: unused anvil instance
@@ -0,0 +1,172 @@
+//! Named profiles, each saved as its own [`Model`] file.
+//!
+//! The default profile is saved without a prefix, as `user_data.json`, and
+//! named profiles as `<name>.user_data.json`. Every profile carries its own
+//! contacts, RPCs, coin list, portfolio and sandbox snapshot.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Result};
+
+use super::{keystore::Keystore, sessions::SessionStore, user::Saveable, Model};
+
+/// Name of the profile saved without a prefix.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Path of the file of the profile `name`.
+pub fn path(name: &str) -> PathBuf {
+    match name {
+        DEFAULT_PROFILE => Model::path(),
+        _ => Model::file_path_with_name(name.to_string()),
+    }
+}
+
+/// Profile names are part of file names, so they're limited to letters,
+/// digits, `-` and `_`.
+pub fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty() {
+        bail!("Profile name can't be empty.");
+    }
+    if name == DEFAULT_PROFILE {
+        bail!(
+            "\"{}\" is the name of the default profile.",
+            DEFAULT_PROFILE
+        );
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!("Profile names can only have letters, digits, '-' and '_'.");
+    }
+    Ok(())
+}
+
+/// Names of the saved profiles, the default first and the rest sorted.
+pub fn list() -> Result<Vec<String>> {
+    list_in(&Model::dir())
+}
+
+fn list_in(dir: &Path) -> Result<Vec<String>> {
+    let default = Model::file_name_ending();
+    let ending = format!(".{}", default);
+
+    let mut has_default = false;
+    let mut names = vec![];
+    for entry in fs::read_dir(dir)? {
+        let file_name = entry?.file_name().to_string_lossy().to_string();
+        if file_name == default {
+            has_default = true;
+        } else if let Some(name) = file_name.strip_suffix(&ending) {
+            if validate_name(name).is_ok() {
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    names.sort();
+    if has_default {
+        names.insert(0, DEFAULT_PROFILE.to_string());
+    }
+    Ok(names)
+}
+
+/// Loads the profile `name`, creating it if it doesn't exist.
+pub fn load_or_create(name: &str) -> Result<Model> {
+    match name {
+        DEFAULT_PROFILE => Model::load(None),
+        _ => {
+            validate_name(name)?;
+            Model::create_new(Some(name.to_string()))
+        }
+    }
+}
+
+/// Creates the empty profile `name`.
+pub fn create(name: &str) -> Result<Model> {
+    validate_name(name)?;
+    if path(name).exists() {
+        bail!("Profile \"{}\" already exists.", name);
+    }
+    Model::create_new(Some(name.to_string()))
+}
+
+/// Copies the profile `from`, with its sandbox sessions, to the new profile
+/// `to`. Signers are left out, since their keys are shared by every profile
+/// that lists them.
+pub fn duplicate(from: &str, to: &str) -> Result<Model> {
+    validate_name(to)?;
+    if path(to).exists() {
+        bail!("Profile \"{}\" already exists.", to);
+    }
+
+    let from_path = path(from);
+    if !from_path.exists() {
+        bail!("Profile \"{}\" doesn't exist.", from);
+    }
+    let mut model = Model::load(Some(from_path))?;
+    model.user.name = Some(to.to_string());
+    model.user.signers = Keystore::default();
+    SessionStore::for_name(from).copy_to(&SessionStore::for_name(to).dir)?;
+    model.save()?;
+
+    Ok(model)
+}
+
+/// Deletes the profile `name` and its sandbox sessions. The profile can't be
+/// the default or the `active` profile. Its backups are kept.
+pub fn delete(name: &str, active: &str) -> Result<()> {
+    if name == DEFAULT_PROFILE {
+        bail!("The default profile can't be deleted.");
+    }
+    if name == active {
+        bail!("Switch to another profile before deleting \"{}\".", name);
+    }
+
+    let path = path(name);
+    fs::remove_file(&path).map_err(|error| anyhow!("Failed to delete {:?}: {}", path, error))?;
+
+    let sessions = SessionStore::for_name(name).dir;
+    if sessions.exists() {
+        fs::remove_dir_all(sessions)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_profiles_default_first() -> Result<()> {
+        let dir = std::env::temp_dir().join("excalibur_profiles_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir)?;
+
+        for file in [
+            "research.user_data.json",
+            "user_data.json",
+            "production.user_data.json",
+            ".research.user_data.json.tmp",
+            "research.user_data.json.corrupted.20240101000000",
+            "research.profile.json",
+        ] {
+            fs::write(dir.join(file), "{}")?;
+        }
+
+        assert_eq!(list_in(&dir)?, vec!["default", "production", "research"]);
+        Ok(())
+    }
+
+    #[test]
+    fn validates_names() {
+        assert!(validate_name("research_2").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name(DEFAULT_PROFILE).is_err());
+        assert!(validate_name("../production").is_err());
+        assert!(validate_name("my profile").is_err());
+    }
+}
@@ -2,16 +2,63 @@
 
 use std::{
     collections::HashMap,
+    fmt,
     io::{Read, Write},
 };
 
+use ethers::types::Address;
 use serde::{Deserialize, Serialize};
 
+/// Human readable contract identifiers to their addresses on one network.
+pub type ContractBook = HashMap<String, Address>;
+
+/// A network the app can connect to.
+#[derive(Clone, Debug)]
+pub enum NetworkTarget {
+    /// The local anvil instance started with the app.
+    Sandbox,
+    /// Saved RPC endpoints of one chain, the preferred one first, failing over
+    /// to the rest.
+    Rpc(Vec<RPCValue>),
+}
+
+impl NetworkTarget {
+    /// Name of the preferred RPC, or `None` for the sandbox.
+    pub fn rpc_name(&self) -> Option<String> {
+        match self {
+            NetworkTarget::Sandbox => None,
+            NetworkTarget::Rpc(rpcs) => rpcs.first().map(|rpc| rpc.name.clone()),
+        }
+    }
+}
+
+impl fmt::Display for NetworkTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkTarget::Sandbox => write!(f, "Sandbox"),
+            NetworkTarget::Rpc(rpcs) => match rpcs.split_first() {
+                Some((rpc, [])) => write!(f, "{} ({})", rpc.name, rpc.chain_id),
+                Some((rpc, fallbacks)) => write!(
+                    f,
+                    "{} ({}) with {} fallbacks",
+                    rpc.name,
+                    rpc.chain_id,
+                    fallbacks.len()
+                ),
+                None => write!(f, "No RPCs"),
+            },
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RPCValue {
     pub chain_id: u64,
     pub name: String,
     pub url: String,
+    /// Requests per second the endpoint allows, if it's limited.
+    #[serde(default)]
+    pub rate_limit: Option<u32>,
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -42,6 +89,23 @@ impl RPCList {
         self.chains.values().collect()
     }
 
+    /// Targets the RPC `name`, failing over to the other RPCs saved for its
+    /// chain.
+    pub fn target(&self, name: &str) -> Option<NetworkTarget> {
+        let preferred = self.get(name)?;
+        let mut fallbacks = self
+            .list()
+            .into_iter()
+            .filter(|rpc| rpc.chain_id == preferred.chain_id && rpc.name != preferred.name)
+            .cloned()
+            .collect::<Vec<_>>();
+        fallbacks.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut rpcs = vec![preferred.clone()];
+        rpcs.extend(fallbacks);
+        Some(NetworkTarget::Rpc(rpcs))
+    }
+
     pub fn clear(&mut self) {
         self.chains.clear();
     }
@@ -62,3 +126,32 @@ impl RPCList {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rpc(name: &str, chain_id: u64) -> RPCValue {
+        RPCValue {
+            chain_id,
+            name: name.to_string(),
+            url: format!("https://{}.example", name),
+            rate_limit: None,
+        }
+    }
+
+    #[test]
+    fn targets_rpcs_of_one_chain() {
+        let mut list = RPCList::new();
+        for (name, chain_id) in [("c", 1), ("a", 1), ("b", 1), ("other", 5)] {
+            list.add(rpc(name, chain_id));
+        }
+
+        let Some(NetworkTarget::Rpc(rpcs)) = list.target("b") else {
+            panic!("Expected the saved RPCs");
+        };
+        let names = rpcs.iter().map(|rpc| rpc.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["b", "a", "c"]);
+        assert!(list.target("missing").is_none());
+    }
+}
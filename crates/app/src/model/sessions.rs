@@ -0,0 +1,580 @@
+//! Named sandbox sessions, saved as their own files.
+//!
+//! A session is the state of the sandbox at a block, from `anvil_dumpState`,
+//! or a [`SnapshotDB`](sim::engine::SnapshotDB) of the arbiter environment
+//! when the sandbox runs in process, with the address book of the contracts
+//! deployed in it and a summary of the accounts in the book. Every profile has its own sessions directory, holding
+//! a `<name>.session.json` file of the session and a `<name>.state` file of its
+//! state, so sessions can be listed and compared without reading their states.
+//!
+//! The profile only holds the name of its active session, which the sandbox is
+//! saved to when the app exits and restored from when it loads.
+
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use ethers::types::{Address, H256, U256};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{
+    profiles::DEFAULT_PROFILE,
+    rpcs::ContractBook,
+    storage::{self, Migration},
+    user::{Saveable, UserProfile},
+    Model,
+};
+
+/// Directory in the config directory holding the sessions of every profile.
+pub const SESSIONS_DIR: &str = "sessions";
+/// Session the sandbox is saved to if the profile has no active session.
+pub const DEFAULT_SESSION: &str = "default";
+/// Session the in-process sandbox is saved to if the active session is from
+/// anvil.
+pub const ARBITER_SESSION: &str = "arbiter";
+/// Prefix of the names of sessions saved before risky actions.
+pub const AUTO_PREFIX: &str = "auto-";
+/// Number of automatic sessions kept, the oldest are deleted past this.
+pub const MAX_AUTO_SESSIONS: usize = 10;
+
+const SESSION_EXTENSION: &str = "session.json";
+const STATE_EXTENSION: &str = "state";
+const SESSION_MIGRATIONS: &[Migration] = &[];
+
+/// What runs the sandbox. Sessions can only be restored by the backend that
+/// saved them, since their states have different formats.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SandboxBackend {
+    /// An anvil process, which must be installed.
+    #[default]
+    Anvil,
+    /// An arbiter environment in the app's process.
+    Arbiter,
+}
+
+impl SandboxBackend {
+    /// Session the sandbox is saved to if the profile has no active session
+    /// from this backend.
+    pub fn default_session(&self) -> &'static str {
+        match self {
+            SandboxBackend::Anvil => DEFAULT_SESSION,
+            SandboxBackend::Arbiter => ARBITER_SESSION,
+        }
+    }
+}
+
+impl fmt::Display for SandboxBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SandboxBackend::Anvil => write!(f, "anvil"),
+            SandboxBackend::Arbiter => write!(f, "arbiter"),
+        }
+    }
+}
+
+impl FromStr for SandboxBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "anvil" => Ok(SandboxBackend::Anvil),
+            "arbiter" => Ok(SandboxBackend::Arbiter),
+            _ => bail!("Unknown sandbox \"{}\", expected anvil or arbiter.", s),
+        }
+    }
+}
+
+/// How a session was made.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SessionOrigin {
+    /// Saved by the user.
+    Manual,
+    /// Copied from the session with the name.
+    Fork(String),
+    /// Saved before the action.
+    Auto(String),
+}
+
+impl fmt::Display for SessionOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionOrigin::Manual => write!(f, "Saved"),
+            SessionOrigin::Fork(from) => write!(f, "Forked from {}", from),
+            SessionOrigin::Auto(action) => write!(f, "Before {}", action),
+        }
+    }
+}
+
+/// Balance, nonce and code of an account when a session was saved.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountSummary {
+    pub address: Address,
+    pub balance: U256,
+    pub nonce: U256,
+    /// Hash of the code of the account, zero if it has none.
+    pub code_hash: H256,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SandboxSession {
+    pub name: String,
+    pub origin: SessionOrigin,
+    pub created_at: DateTime<Utc>,
+    pub chain_id: u64,
+    pub block_number: u64,
+    /// Sessions saved before the in-process sandbox are from anvil.
+    #[serde(default)]
+    pub backend: SandboxBackend,
+    /// Contracts deployed in the sandbox.
+    pub contracts: ContractBook,
+    /// Accounts of the contracts and the signer, by name.
+    pub accounts: BTreeMap<String, AccountSummary>,
+}
+
+impl SandboxSession {
+    pub fn is_auto(&self) -> bool {
+        matches!(self.origin, SessionOrigin::Auto(_))
+    }
+}
+
+/// Session names are part of file names, so they're limited to letters,
+/// digits, `-` and `_`.
+pub fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty() {
+        bail!("Session name can't be empty.");
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!("Session names can only have letters, digits, '-' and '_'.");
+    }
+    Ok(())
+}
+
+/// Name of a session saved automatically now.
+pub fn auto_name() -> String {
+    format!("{}{}", AUTO_PREFIX, Utc::now().format("%Y%m%d-%H%M%S-%3f"))
+}
+
+/// A difference between two sessions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionChange {
+    Chain(u64, u64),
+    Block(u64, u64),
+    ContractAdded(String, Address),
+    ContractRemoved(String, Address),
+    ContractMoved(String, Address, Address),
+    Balance(String, U256, U256),
+    Nonce(String, U256, U256),
+    Code(String),
+}
+
+impl fmt::Display for SessionChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionChange::Chain(from, to) => write!(f, "Chain id {} -> {}", from, to),
+            SessionChange::Block(from, to) => write!(f, "Block {} -> {}", from, to),
+            SessionChange::ContractAdded(name, address) => {
+                write!(f, "Added {} at {:?}", name, address)
+            }
+            SessionChange::ContractRemoved(name, address) => {
+                write!(f, "Removed {} at {:?}", name, address)
+            }
+            SessionChange::ContractMoved(name, from, to) => {
+                write!(f, "Moved {} from {:?} to {:?}", name, from, to)
+            }
+            SessionChange::Balance(name, from, to) => write!(
+                f,
+                "Balance of {}: {} -> {} ETH",
+                name,
+                ethers::utils::format_ether(*from),
+                ethers::utils::format_ether(*to)
+            ),
+            SessionChange::Nonce(name, from, to) => {
+                write!(f, "Nonce of {}: {} -> {}", name, from, to)
+            }
+            SessionChange::Code(name) => write!(f, "Code of {} changed", name),
+        }
+    }
+}
+
+/// Changes from session `a` to session `b`.
+pub fn diff(a: &SandboxSession, b: &SandboxSession) -> Vec<SessionChange> {
+    let mut changes = vec![];
+    if a.chain_id != b.chain_id {
+        changes.push(SessionChange::Chain(a.chain_id, b.chain_id));
+    }
+    if a.block_number != b.block_number {
+        changes.push(SessionChange::Block(a.block_number, b.block_number));
+    }
+
+    let a_contracts: BTreeMap<_, _> = a.contracts.iter().collect();
+    let b_contracts: BTreeMap<_, _> = b.contracts.iter().collect();
+    for (name, address) in &a_contracts {
+        match b_contracts.get(name) {
+            None => changes.push(SessionChange::ContractRemoved(name.to_string(), **address)),
+            Some(moved) if moved != address => changes.push(SessionChange::ContractMoved(
+                name.to_string(),
+                **address,
+                **moved,
+            )),
+            _ => {}
+        }
+    }
+    for (name, address) in &b_contracts {
+        if !a_contracts.contains_key(name) {
+            changes.push(SessionChange::ContractAdded(name.to_string(), **address));
+        }
+    }
+
+    // Only accounts at the same address in both sessions are comparable.
+    for (name, before) in &a.accounts {
+        let Some(after) = b.accounts.get(name) else {
+            continue;
+        };
+        if before.address != after.address {
+            continue;
+        }
+        if before.balance != after.balance {
+            changes.push(SessionChange::Balance(
+                name.clone(),
+                before.balance,
+                after.balance,
+            ));
+        }
+        if before.nonce != after.nonce {
+            changes.push(SessionChange::Nonce(
+                name.clone(),
+                before.nonce,
+                after.nonce,
+            ));
+        }
+        if before.code_hash != after.code_hash {
+            changes.push(SessionChange::Code(name.clone()));
+        }
+    }
+
+    changes
+}
+
+/// The sessions directory of a profile.
+#[derive(Debug, Clone)]
+pub struct SessionStore {
+    pub dir: PathBuf,
+}
+
+impl SessionStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Sessions of the profile with the name.
+    pub fn for_name(profile: &str) -> Self {
+        Self::new(Model::dir().join(SESSIONS_DIR).join(profile))
+    }
+
+    pub fn for_profile(user: &UserProfile) -> Self {
+        Self::for_name(user.name.as_deref().unwrap_or(DEFAULT_PROFILE))
+    }
+
+    fn session_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", name, SESSION_EXTENSION))
+    }
+
+    fn state_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", name, STATE_EXTENSION))
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        self.session_path(name).exists()
+    }
+
+    /// Name of the session a sandbox on `backend` is saved to and loaded from:
+    /// the `active` session if it's from the same backend, or else the first
+    /// free or matching session named after the default of the backend.
+    pub fn session_for(&self, active: Option<&str>, backend: SandboxBackend) -> String {
+        let fits = |name: &str| {
+            !self.exists(name)
+                || self
+                    .get(name)
+                    .map_or(false, |session| session.backend == backend)
+        };
+        if let Some(active) = active.filter(|name| fits(name)) {
+            return active.to_string();
+        }
+
+        let default = backend.default_session();
+        (1..)
+            .map(|n| match n {
+                1 => default.to_string(),
+                _ => format!("{}-{}", default, n),
+            })
+            .find(|name| fits(name))
+            .expect("There are always free session names.")
+    }
+
+    /// Writes the session and its state, replacing a session with the same
+    /// name.
+    pub fn save(&self, session: &SandboxSession, state: &str) -> Result<()> {
+        validate_name(&session.name)?;
+        fs::create_dir_all(&self.dir)?;
+
+        // The state goes first, so a session file always has its state.
+        storage::write_atomic(&self.state_path(&session.name), state.as_bytes())?;
+        let contents = storage::encode(session, SESSION_MIGRATIONS)?;
+        storage::write_atomic(&self.session_path(&session.name), contents.as_bytes())
+    }
+
+    /// Reads the session with the name, without its state.
+    pub fn get(&self, name: &str) -> Result<SandboxSession> {
+        let path = self.session_path(name);
+        let contents = fs::read_to_string(&path)
+            .map_err(|error| anyhow!("Failed to read session {}: {}", name, error))?;
+        storage::decode(&contents, SESSION_MIGRATIONS)
+    }
+
+    /// Reads the session with the name and its state.
+    pub fn load(&self, name: &str) -> Result<(SandboxSession, String)> {
+        let session = self.get(name)?;
+        let state = fs::read_to_string(self.state_path(name))?;
+        Ok((session, state))
+    }
+
+    /// Sessions in the directory, oldest first.
+    pub fn list(&self) -> Result<Vec<SandboxSession>> {
+        if !self.dir.exists() {
+            return Ok(vec![]);
+        }
+
+        let ending = format!(".{}", SESSION_EXTENSION);
+        let mut sessions = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let file_name = entry?.file_name().to_string_lossy().to_string();
+            let Some(name) = file_name.strip_suffix(&ending) else {
+                continue;
+            };
+            match self.get(name) {
+                Ok(session) => sessions.push(session),
+                Err(error) => tracing::warn!("Skipping session {}: {:?}", name, error),
+            }
+        }
+
+        sessions.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+        Ok(sessions)
+    }
+
+    /// Copies the session `from` to the new session `to`.
+    pub fn fork(&self, from: &str, to: &str) -> Result<SandboxSession> {
+        validate_name(to)?;
+        if self.exists(to) {
+            bail!("Session {} already exists.", to);
+        }
+
+        let (mut session, state) = self.load(from)?;
+        session.name = to.to_string();
+        session.origin = SessionOrigin::Fork(from.to_string());
+        session.created_at = Utc::now();
+        self.save(&session, &state)?;
+        Ok(session)
+    }
+
+    pub fn delete(&self, name: &str) -> Result<()> {
+        fs::remove_file(self.session_path(name))?;
+        let state = self.state_path(name);
+        if state.exists() {
+            fs::remove_file(state)?;
+        }
+        Ok(())
+    }
+
+    /// Copies the session `name` to an automatic session before the risky
+    /// `action`, and deletes the oldest automatic sessions past
+    /// [`MAX_AUTO_SESSIONS`].
+    pub fn auto_snapshot(&self, name: &str, action: &str) -> Result<SandboxSession> {
+        let (mut session, state) = self.load(name)?;
+        session.name = auto_name();
+        session.origin = SessionOrigin::Auto(action.to_string());
+        session.created_at = Utc::now();
+        self.save(&session, &state)?;
+        self.prune_auto(MAX_AUTO_SESSIONS)?;
+        Ok(session)
+    }
+
+    /// Deletes the oldest automatic sessions past `keep`.
+    pub fn prune_auto(&self, keep: usize) -> Result<()> {
+        let auto: Vec<_> = self
+            .list()?
+            .into_iter()
+            .filter(SandboxSession::is_auto)
+            .collect();
+        for session in auto.iter().take(auto.len().saturating_sub(keep)) {
+            self.delete(&session.name)?;
+        }
+        Ok(())
+    }
+
+    /// Copies every session to `dir`, for a copy of the profile.
+    pub fn copy_to(&self, dir: &Path) -> Result<()> {
+        if !self.dir.exists() {
+            return Ok(());
+        }
+
+        fs::create_dir_all(dir)?;
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                fs::copy(entry.path(), dir.join(entry.file_name()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Saves a snapshot of a profile saved before sessions, as
+    /// `{"snapshot": .., "block_number": ..}`, as the default session.
+    pub fn import_legacy(&self, snapshot: Value, contracts: ContractBook) -> Result<()> {
+        let state = snapshot
+            .get("snapshot")
+            .and_then(Value::as_str)
+            .ok_or(anyhow!("Snapshot is missing its state."))?;
+        let block_number = snapshot
+            .get("block_number")
+            .and_then(Value::as_u64)
+            .ok_or(anyhow!("Snapshot is missing its block number."))?;
+
+        let session = SandboxSession {
+            name: DEFAULT_SESSION.to_string(),
+            origin: SessionOrigin::Manual,
+            created_at: Utc::now(),
+            // Snapshots were only taken of the default sandbox.
+            chain_id: 31337,
+            block_number,
+            backend: SandboxBackend::Anvil,
+            contracts,
+            accounts: BTreeMap::new(),
+        };
+        self.save(&session, state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(name: &str, origin: SessionOrigin, balance: u64) -> SandboxSession {
+        let strategy = Address::repeat_byte(2);
+        SandboxSession {
+            name: name.to_string(),
+            origin,
+            created_at: Utc::now(),
+            chain_id: 31337,
+            block_number: 10 + balance,
+            backend: SandboxBackend::Anvil,
+            contracts: ContractBook::from([("strategy".to_string(), strategy)]),
+            accounts: BTreeMap::from([(
+                "strategy".to_string(),
+                AccountSummary {
+                    address: strategy,
+                    balance: U256::from(balance),
+                    nonce: U256::one(),
+                    code_hash: H256::repeat_byte(1),
+                },
+            )]),
+        }
+    }
+
+    #[test]
+    fn saves_forks_and_prunes_sessions() -> Result<()> {
+        let dir = std::env::temp_dir().join("excalibur_sessions_test");
+        let _ = fs::remove_dir_all(&dir);
+        let store = SessionStore::new(dir);
+
+        let base = session("base", SessionOrigin::Manual, 1);
+        store.save(&base, "0x01")?;
+        assert_eq!(store.load("base")?, (base.clone(), "0x01".to_string()));
+
+        let fork = store.fork("base", "experiment")?;
+        assert_eq!(fork.origin, SessionOrigin::Fork("base".to_string()));
+        assert_eq!(store.load("experiment")?.1, "0x01");
+        assert!(store.fork("base", "experiment").is_err());
+
+        for _ in 0..MAX_AUTO_SESSIONS + 2 {
+            let auto = session(&auto_name(), SessionOrigin::Auto("allocate".into()), 2);
+            store.save(&auto, "0x02")?;
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        store.prune_auto(MAX_AUTO_SESSIONS)?;
+
+        let sessions = store.list()?;
+        assert_eq!(sessions.len(), MAX_AUTO_SESSIONS + 2);
+        assert_eq!(sessions[0].name, "base");
+        assert_eq!(sessions[1].name, "experiment");
+
+        // A session copied before it's deleted can still be restored.
+        let snapshot = store.auto_snapshot("experiment", "delete session experiment")?;
+        store.delete("experiment")?;
+        assert_eq!(store.load(&snapshot.name)?.1, "0x01");
+        assert_eq!(
+            snapshot.origin,
+            SessionOrigin::Auto("delete session experiment".into())
+        );
+        assert_eq!(store.list()?.len(), MAX_AUTO_SESSIONS + 1);
+        Ok(())
+    }
+
+    #[test]
+    fn picks_sessions_of_the_backend() -> Result<()> {
+        let dir = std::env::temp_dir().join("excalibur_session_backends_test");
+        let _ = fs::remove_dir_all(&dir);
+        let store = SessionStore::new(dir);
+
+        // Both sessions are from anvil, one named like the default of arbiter.
+        store.save(&session("default", SessionOrigin::Manual, 1), "0x01")?;
+        store.save(&session(ARBITER_SESSION, SessionOrigin::Manual, 1), "0x01")?;
+
+        // Anvil keeps its active session, arbiter can't load it.
+        assert_eq!(
+            store.session_for(Some("default"), SandboxBackend::Anvil),
+            "default"
+        );
+        assert_eq!(
+            store.session_for(Some("default"), SandboxBackend::Arbiter),
+            "arbiter-2"
+        );
+        assert_eq!(store.session_for(None, SandboxBackend::Anvil), "default");
+        assert_eq!(
+            store.session_for(Some("new"), SandboxBackend::Arbiter),
+            "new"
+        );
+        assert_eq!(
+            "arbiter".parse::<SandboxBackend>()?,
+            SandboxBackend::Arbiter
+        );
+        assert!("hardhat".parse::<SandboxBackend>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn diffs_sessions() {
+        let a = session("a", SessionOrigin::Manual, 1);
+        let mut b = session("b", SessionOrigin::Manual, 2);
+        let token = Address::repeat_byte(3);
+        b.contracts.insert("token_x".to_string(), token);
+
+        assert_eq!(
+            diff(&a, &b),
+            vec![
+                SessionChange::Block(11, 12),
+                SessionChange::ContractAdded("token_x".to_string(), token),
+                SessionChange::Balance("strategy".to_string(), U256::from(1), U256::from(2)),
+            ]
+        );
+        assert!(diff(&a, &a).is_empty());
+    }
+}
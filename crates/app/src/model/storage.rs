@@ -0,0 +1,412 @@
+//! Versioned files for [`Saveable`](super::user::Saveable) types.
+//!
+//! Files are saved as `{"version": n, "data": ..}`, where `n` is the number of
+//! migrations the type has. Files saved before versioning are version 0. On
+//! load, the data is upgraded by every migration from its version on, so old
+//! files keep loading as the types change.
+//!
+//! Writes go to a temporary file that replaces the saved file once it's
+//! complete, so an interrupted save can't leave half a file behind. The
+//! previous saves are kept in the backups directory, at most one every
+//! `BACKUP_INTERVAL`, and a file that can't be read is moved aside and replaced
+//! with its newest readable backup. Files saved by a newer version of the app,
+//! and files a migration fails on, are left alone, since they aren't broken.
+
+use std::{
+    fmt, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+/// Number of previous saves kept of every file.
+pub const MAX_BACKUPS: usize = 5;
+/// Directory next to the saved files that holds their backups.
+pub const BACKUP_DIR: &str = "backups";
+/// Time between backups of a file, so saves in quick succession don't push
+/// the older backups out.
+pub const BACKUP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Upgrades saved data from one version to the next. The migration at index
+/// `i` upgrades version `i` to `i + 1`.
+pub type Migration = fn(Value) -> Result<Value>;
+
+/// Error decoding a file saved by a newer version of the app, which has
+/// migrations this one doesn't know.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NewerVersion {
+    /// Version the file was saved at.
+    pub version: usize,
+    /// Latest version this app reads.
+    pub supported: usize,
+}
+
+impl fmt::Display for NewerVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Saved by a newer version of the app, at version {}, and this one reads up to version {}.",
+            self.version, self.supported
+        )
+    }
+}
+
+impl std::error::Error for NewerVersion {}
+
+/// Error upgrading a file with one of the migrations, which is a bug in the
+/// migration rather than a broken file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationFailed {
+    /// Version the failing migration upgrades from.
+    pub version: usize,
+    pub error: String,
+}
+
+impl fmt::Display for MigrationFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Failed to upgrade the file from version {}: {}",
+            self.version, self.error
+        )
+    }
+}
+
+impl std::error::Error for MigrationFailed {}
+
+/// Whether a file that failed to load is intact, and has to be kept as it is
+/// rather than recovered from a backup.
+pub fn is_intact(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<NewerVersion>().is_some()
+        || error.downcast_ref::<MigrationFailed>().is_some()
+}
+
+#[derive(Serialize, Deserialize)]
+struct Versioned<T> {
+    version: usize,
+    data: T,
+}
+
+/// Serializes `data` as the latest version, the number of `migrations`.
+pub fn encode<T: Serialize>(data: &T, migrations: &[Migration]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&Versioned {
+        version: migrations.len(),
+        data,
+    })?)
+}
+
+/// Deserializes data saved at any version, upgrading it with `migrations`.
+/// Fails with [`NewerVersion`] if the data is newer than the migrations, and
+/// with [`MigrationFailed`] if a migration fails.
+pub fn decode<T: DeserializeOwned>(contents: &str, migrations: &[Migration]) -> Result<T> {
+    let value: Value = serde_json::from_str(contents)?;
+    let (version, mut data) = match value {
+        Value::Object(mut object)
+            if object.len() == 2
+                && object.contains_key("version")
+                && object.contains_key("data") =>
+        {
+            let version = object["version"]
+                .as_u64()
+                .ok_or(anyhow!("Version must be a number."))?;
+            (version as usize, object.remove("data").unwrap_or_default())
+        }
+        // Saved before files were versioned.
+        data => (0, data),
+    };
+
+    if version > migrations.len() {
+        bail!(NewerVersion {
+            version,
+            supported: migrations.len(),
+        });
+    }
+
+    for (version, migration) in migrations.iter().enumerate().skip(version) {
+        data = migration(data).map_err(|error| MigrationFailed {
+            version,
+            error: format!("{:#}", error),
+        })?;
+    }
+
+    Ok(serde_json::from_value(data)?)
+}
+
+fn file_name(path: &Path) -> Result<String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or(anyhow!("No file name in path: {:?}", path))
+}
+
+/// Path of the `n`th newest backup of `path`, starting at 1.
+pub fn backup_path(path: &Path, n: usize) -> Result<PathBuf> {
+    let dir = path.parent().unwrap_or(Path::new("")).join(BACKUP_DIR);
+    Ok(dir.join(format!("{}.{}", file_name(path)?, n)))
+}
+
+/// Backups of `path` that exist, newest first.
+pub fn backups(path: &Path) -> Vec<PathBuf> {
+    (1..=MAX_BACKUPS)
+        .filter_map(|n| backup_path(path, n).ok())
+        .filter(|backup| backup.exists())
+        .collect()
+}
+
+/// Backs up the file at `path`, unless its newest backup is less than
+/// `BACKUP_INTERVAL` old.
+pub fn backup(path: &Path) -> Result<()> {
+    let recent = fs::metadata(backup_path(path, 1)?)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .map_or(false, |age| age < BACKUP_INTERVAL);
+    if recent {
+        return Ok(());
+    }
+    rotate(path)
+}
+
+/// Copies the file at `path` to the newest backup, shifting the older backups
+/// back and dropping the oldest past `MAX_BACKUPS`.
+pub fn rotate(path: &Path) -> Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+
+    fs::create_dir_all(backup_path(path, 1)?.parent().unwrap())?;
+    for n in (1..MAX_BACKUPS).rev() {
+        let older = backup_path(path, n)?;
+        if older.exists() {
+            fs::rename(&older, backup_path(path, n + 1)?)?;
+        }
+    }
+    fs::copy(path, backup_path(path, 1)?)?;
+
+    Ok(())
+}
+
+/// Writes `contents` to a temporary file next to `path`, then renames it over
+/// `path`, so `path` holds either the old or the new contents.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let temp = path.with_file_name(format!(".{}.tmp", file_name(path)?));
+
+    let mut file = fs::File::create(&temp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&temp, path)?;
+    Ok(())
+}
+
+/// Moves a file that can't be read aside, next to where it was, so it can be
+/// inspected. Returns where it was moved.
+pub fn quarantine(path: &Path) -> Result<PathBuf> {
+    let timestamp = chrono::Utc::now().format("%Y%m%d%H%M%S");
+    let moved = path.with_file_name(format!("{}.corrupted.{}", file_name(path)?, timestamp));
+    fs::rename(path, &moved)?;
+    Ok(moved)
+}
+
+/// Applies `migration` to the object at `key` of `value`, for types saved
+/// inside other types.
+pub fn migrate_field(mut value: Value, key: &str, migration: Migration) -> Result<Value> {
+    let field = value
+        .get_mut(key)
+        .ok_or(anyhow!("Missing field to migrate: {}", key))?;
+    *field = migration(field.take())?;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::model::user::Saveable;
+
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    struct Settings {
+        name: String,
+        retries: u32,
+    }
+
+    fn rename_label(mut value: Value) -> Result<Value> {
+        let object = value
+            .as_object_mut()
+            .ok_or(anyhow!("Expected an object."))?;
+        let label = object.remove("label").unwrap_or(json!(""));
+        object.insert("name".to_string(), label);
+        Ok(value)
+    }
+
+    fn add_retries(mut value: Value) -> Result<Value> {
+        value["retries"] = json!(3);
+        Ok(value)
+    }
+
+    const MIGRATIONS: &[Migration] = &[rename_label, add_retries];
+
+    impl Saveable for Settings {
+        const SUFFIX: &'static str = "settings";
+        const MIGRATIONS: &'static [Migration] = MIGRATIONS;
+
+        fn dir() -> PathBuf {
+            std::env::temp_dir().join("excalibur_storage_test")
+        }
+
+        fn create_new(_name: Option<String>) -> Result<Self> {
+            Ok(Self::default())
+        }
+    }
+
+    #[test]
+    fn migrates_old_versions() -> Result<()> {
+        let expected = Settings {
+            name: "dev".to_string(),
+            retries: 3,
+        };
+
+        let unversioned = json!({ "label": "dev" }).to_string();
+        assert_eq!(decode::<Settings>(&unversioned, MIGRATIONS)?, expected);
+
+        let version_1 = json!({ "version": 1, "data": { "name": "dev" } }).to_string();
+        assert_eq!(decode::<Settings>(&version_1, MIGRATIONS)?, expected);
+
+        let latest = encode(&expected, MIGRATIONS)?;
+        assert_eq!(decode::<Settings>(&latest, MIGRATIONS)?, expected);
+
+        let newer = json!({ "version": 3, "data": { "name": "dev" } }).to_string();
+        let error = decode::<Settings>(&newer, MIGRATIONS).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<NewerVersion>(),
+            Some(&NewerVersion {
+                version: 3,
+                supported: 2
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_newer_versions() -> Result<()> {
+        #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+        struct Newer {
+            name: String,
+        }
+
+        impl Saveable for Newer {
+            const SUFFIX: &'static str = "newer";
+            const MIGRATIONS: &'static [Migration] = &[];
+
+            fn dir() -> PathBuf {
+                std::env::temp_dir().join("excalibur_storage_newer_test")
+            }
+
+            fn create_new(_name: Option<String>) -> Result<Self> {
+                Ok(Self::default())
+            }
+        }
+
+        let _ = fs::remove_dir_all(Newer::dir());
+        fs::create_dir_all(Newer::dir())?;
+
+        // A file from a newer app fails to load, and is left where it is.
+        let path = Newer::path();
+        let contents = json!({ "version": 1, "data": { "name": "dev" } }).to_string();
+        fs::write(&path, &contents)?;
+        let error = Newer::load(None).unwrap_err();
+        assert!(error.downcast_ref::<NewerVersion>().is_some());
+        assert_eq!(fs::read_to_string(&path)?, contents);
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_files_a_migration_fails_on() -> Result<()> {
+        #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+        struct Failing {
+            name: String,
+        }
+
+        fn fail(_value: Value) -> Result<Value> {
+            Err(anyhow!("Can't upgrade."))
+        }
+
+        impl Saveable for Failing {
+            const SUFFIX: &'static str = "failing";
+            const MIGRATIONS: &'static [Migration] = &[add_retries, fail];
+
+            fn dir() -> PathBuf {
+                std::env::temp_dir().join("excalibur_storage_failing_test")
+            }
+
+            fn create_new(_name: Option<String>) -> Result<Self> {
+                Ok(Self::default())
+            }
+        }
+
+        let _ = fs::remove_dir_all(Failing::dir());
+        fs::create_dir_all(Failing::dir())?;
+
+        // The file fails to load, and is neither quarantined nor recovered.
+        let path = Failing::path();
+        let contents = json!({ "name": "dev" }).to_string();
+        fs::write(&path, &contents)?;
+        let error = Failing::load(None).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<MigrationFailed>(),
+            Some(&MigrationFailed {
+                version: 1,
+                error: "Can't upgrade.".to_string()
+            })
+        );
+        assert!(is_intact(&error));
+        assert_eq!(fs::read_to_string(&path)?, contents);
+        assert_eq!(fs::read_dir(Failing::dir())?.count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_backups_and_recovers() -> Result<()> {
+        let _ = fs::remove_dir_all(Settings::dir());
+        fs::create_dir_all(Settings::dir())?;
+
+        for retries in 0..MAX_BACKUPS as u32 + 2 {
+            Settings {
+                name: "dev".to_string(),
+                retries,
+            }
+            .save()?;
+        }
+
+        // Saves in quick succession are backed up once, from the first save.
+        let path = Settings::path();
+        assert_eq!(backups(&path).len(), 1);
+        let newest = fs::read_to_string(backup_path(&path, 1)?)?;
+        assert_eq!(decode::<Settings>(&newest, MIGRATIONS)?.retries, 0);
+
+        // Rotating shifts the older backups back, keeping `MAX_BACKUPS`.
+        for _ in 0..MAX_BACKUPS {
+            rotate(&path)?;
+        }
+        assert_eq!(backups(&path).len(), MAX_BACKUPS);
+        let newest = fs::read_to_string(backup_path(&path, 1)?)?;
+        assert_eq!(decode::<Settings>(&newest, MIGRATIONS)?.retries, 6);
+
+        // A corrupted file is replaced by the newest backup, and kept aside.
+        fs::write(&path, "{ \"version\": 2, \"da")?;
+        let recovered = Settings::load(None)?;
+        assert_eq!(recovered.retries, 6);
+        assert_eq!(Settings::load(None)?, recovered);
+        let quarantined = fs::read_dir(Settings::dir())?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().contains("corrupted"))
+            .count();
+        assert_eq!(quarantined, 1);
+        Ok(())
+    }
+}
@@ -1,16 +1,25 @@
 //! Save and load profiles from disk. This contains all the information a user
 //! needs to save and load their Excalibur workspace.
 
-use std::{fs::File, path::PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use datatypes::portfolio::{coin_list::CoinList, Portfolio};
 use directories_next::{self, ProjectDirs};
 use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
 use tracing;
 
-use super::{contacts::Contacts, rpcs::RPCList};
-use crate::app::AnvilSave;
+use super::{
+    contacts::Contacts,
+    keystore::Keystore,
+    rpcs::{ContractBook, RPCList},
+    sessions::DEFAULT_SESSION,
+    storage::{self, Migration},
+};
 
 pub const PROFILE_FILE_EXTENSION: &str = "json";
 pub const PROFILE_FILE_NAME: &str = "profile";
@@ -55,9 +64,12 @@ pub trait Saveable: Serialize + DeserializeOwned + Sized {
     const EXTENSION: &'static str = "json";
     /// File suffix of the file. e..g `config` for `config.json`.
     const SUFFIX: &'static str;
+    /// Upgrades files saved by older versions, oldest first. The number of
+    /// migrations is the version files are saved at, see [`storage`].
+    const MIGRATIONS: &'static [Migration] = &[];
 
-    /// Creates a new instance of this type and writes it to disk with `name`.
-    /// todo: be careful about overwriting.
+    /// Creates a new instance of this type and writes it to disk with `name`,
+    /// or loads the existing file with `name`.
     fn create_new(name: Option<String>) -> Result<Self>;
 
     /// Gets the directory which stores the project's application and config
@@ -131,10 +143,15 @@ pub trait Saveable: Serialize + DeserializeOwned + Sized {
 
     /// Writes the file to disk.
     fn save(&self) -> Result<()> {
-        let path = self.file_path();
-        let file = File::create(path)?;
-        serde_json::to_writer_pretty(file, self)?;
-        Ok(())
+        self.save_to(&self.file_path())
+    }
+
+    /// Writes the file to `path` at the latest version, keeping a backup of
+    /// a previous save.
+    fn save_to(&self, path: &Path) -> Result<()> {
+        let contents = storage::encode(self, Self::MIGRATIONS)?;
+        storage::backup(path)?;
+        storage::write_atomic(path, contents.as_bytes())
     }
 
     /// Loads the file from disk into an instance of this type.
@@ -146,7 +163,6 @@ pub trait Saveable: Serialize + DeserializeOwned + Sized {
 
         // If the file doesn't exist, create a new instance.
         if !path.exists() {
-            // return Err(anyhow::anyhow!("Could not find file at path: {:?}", path));
             tracing::trace!("Creating new instance of {}.", Self::SUFFIX);
             let instance = Self::create_new(None)?;
             instance.save()?;
@@ -154,14 +170,135 @@ pub trait Saveable: Serialize + DeserializeOwned + Sized {
         }
 
         tracing::trace!("Loading {} at path: {:?}", Self::SUFFIX, path);
-        let file = File::open(path)?;
+        match Self::read(&path) {
+            Ok(instance) => Ok(instance),
+            // The file isn't broken, so it's kept as it is for the app that
+            // saved it or a fixed migration.
+            Err(error) if storage::is_intact(&error) => Err(error),
+            Err(error) => {
+                tracing::error!("Failed to read {:?}: {:?}", path, error);
+                Self::recover(&path)
+            }
+        }
+    }
+
+    /// Reads the file at `path`, upgrading it from the version it was saved
+    /// at.
+    fn read(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        storage::decode(&contents, Self::MIGRATIONS)
+    }
+
+    /// Replaces a file that can't be read with its newest readable backup,
+    /// keeping the unreadable file aside.
+    fn recover(path: &Path) -> Result<Self> {
+        let quarantined = storage::quarantine(path)?;
+        tracing::warn!("Moved unreadable {:?} to {:?}", path, quarantined);
+
+        for backup in storage::backups(path) {
+            match Self::read(&backup) {
+                Ok(instance) => {
+                    tracing::info!("Recovered {:?} from {:?}", path, backup);
+                    let contents = storage::encode(&instance, Self::MIGRATIONS)?;
+                    storage::write_atomic(path, contents.as_bytes())?;
+                    return Ok(instance);
+                }
+                Err(error) => tracing::warn!("Failed to read backup {:?}: {:?}", backup, error),
+            }
+        }
+
+        Err(anyhow!("No readable backups of {:?}", path))
+    }
+}
+
+/// Profiles saved before versioning can be missing the fields added for
+/// signers and networks.
+pub fn add_signers_and_networks(mut profile: Value) -> Result<Value> {
+    let signers = serde_json::to_value(Keystore::default())?;
+    let object = profile
+        .as_object_mut()
+        .ok_or(anyhow!("Profile must be an object."))?;
+    object.entry("signers").or_insert(signers);
+    object
+        .entry("contracts")
+        .or_insert(Value::Object(Default::default()));
+    object.entry("network").or_insert(Value::Null);
+    Ok(profile)
+}
 
-        let instance = serde_json::from_reader(file)?;
+/// Profiles used to hold the whole state of the sandbox, which is kept as a
+/// legacy snapshot for the loader to import as the default session.
+pub fn move_snapshot_to_session(mut profile: Value) -> Result<Value> {
+    let object = profile
+        .as_object_mut()
+        .ok_or(anyhow!("Profile must be an object."))?;
+
+    let session = match object.remove("anvil_snapshot") {
+        None | Some(Value::Null) => Value::Null,
+        Some(snapshot) => {
+            object.insert("legacy_snapshot".to_string(), snapshot);
+            Value::String(DEFAULT_SESSION.to_string())
+        }
+    };
+    object.insert("session".to_string(), session);
+    Ok(profile)
+}
 
-        Ok(instance)
+/// The contracts deployed to the sandbox used to be added to the untrusted
+/// contacts, so the contacts of the contracts in the address books are moved
+/// to the trusted ones.
+pub fn trust_recorded_contracts(mut profile: Value) -> Result<Value> {
+    let contracts: Vec<String> = profile
+        .get("contracts")
+        .and_then(Value::as_object)
+        .map(|books| {
+            books
+                .values()
+                .filter_map(Value::as_object)
+                .flat_map(|book| book.values().filter_map(Value::as_str))
+                .map(str::to_lowercase)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let Some(books) = profile
+        .pointer_mut("/contacts/books")
+        .and_then(Value::as_object_mut)
+    else {
+        return Ok(profile);
+    };
+    let mut moved = serde_json::Map::new();
+    if let Some(untrusted) = books
+        .get_mut("Untrusted")
+        .and_then(|list| list.get_mut("addresses"))
+        .and_then(Value::as_object_mut)
+    {
+        for address in contracts {
+            if let Some(contact) = untrusted.remove(&address) {
+                moved.insert(address, contact);
+            }
+        }
+    }
+    if moved.is_empty() {
+        return Ok(profile);
     }
+
+    books
+        .entry("Trusted")
+        .or_insert(serde_json::json!({ "addresses": {} }))
+        .get_mut("addresses")
+        .and_then(Value::as_object_mut)
+        .ok_or(anyhow!("Trusted contacts must be an object."))?
+        .extend(moved);
+    Ok(profile)
 }
 
+pub const PROFILE_MIGRATIONS: &[Migration] = &[
+    add_signers_and_networks,
+    move_snapshot_to_session,
+    trust_recorded_contracts,
+];
+
 #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct UserProfile {
     pub name: Option<String>,
@@ -169,7 +306,22 @@ pub struct UserProfile {
     pub rpcs: RPCList,
     pub coins: CoinList,
     pub portfolio: Portfolio,
-    pub anvil_snapshot: Option<AnvilSave>,
+    /// Sandbox session the sandbox is saved to and restored from.
+    #[serde(default)]
+    pub session: Option<String>,
+    /// Local signers, whose keys are encrypted in the keystore directory.
+    #[serde(default)]
+    pub signers: Keystore,
+    /// Contract addresses by chain id.
+    #[serde(default)]
+    pub contracts: HashMap<u64, ContractBook>,
+    /// Saved RPC the app last connected to, or `None` for the sandbox.
+    #[serde(default)]
+    pub network: Option<String>,
+    /// Sandbox snapshot of a profile saved before sessions, waiting to be
+    /// imported as the default session.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub legacy_snapshot: Option<Value>,
 }
 
 impl UserProfile {
@@ -183,6 +335,7 @@ impl UserProfile {
 impl Saveable for UserProfile {
     const EXTENSION: &'static str = PROFILE_FILE_EXTENSION;
     const SUFFIX: &'static str = PROFILE_FILE_NAME;
+    const MIGRATIONS: &'static [Migration] = PROFILE_MIGRATIONS;
 
     fn prefix(&self) -> Option<String> {
         self.name.clone()
@@ -217,7 +370,6 @@ impl Saveable for UserProfile {
         }
 
         let profile_path = Self::dir().join(formatted_path);
-        let file = File::create(profile_path)?;
 
         let value = UserProfile {
             contacts: Contacts::new(),
@@ -225,10 +377,14 @@ impl Saveable for UserProfile {
             name,
             coins: CoinList::default(),
             portfolio: Portfolio::default(),
-            anvil_snapshot: None,
+            session: None,
+            signers: Keystore::default(),
+            contracts: HashMap::new(),
+            network: None,
+            legacy_snapshot: None,
         };
 
-        serde_json::to_writer_pretty(file, &value)?;
+        value.save_to(&profile_path)?;
 
         Ok(value)
     }
@@ -255,6 +411,44 @@ mod tests {
         assert!(loaded_profile.is_ok());
     }
 
+    #[test]
+    fn test_move_snapshot_to_session() -> Result<()> {
+        let snapshot = serde_json::json!({ "snapshot": "0x00", "block_number": 1 });
+        let profile = move_snapshot_to_session(serde_json::json!({
+            "name": "legacy",
+            "anvil_snapshot": snapshot,
+        }))?;
+        assert_eq!(profile["legacy_snapshot"], snapshot);
+        assert_eq!(profile["session"], DEFAULT_SESSION);
+        assert!(profile.get("anvil_snapshot").is_none());
+
+        let profile = move_snapshot_to_session(serde_json::json!({ "name": "new" }))?;
+        assert!(profile.get("legacy_snapshot").is_none());
+        assert!(profile["session"].is_null());
+        Ok(())
+    }
+
+    #[test]
+    fn test_trust_recorded_contracts() -> Result<()> {
+        let protocol = "0x0101010101010101010101010101010101010101";
+        let stranger = "0x0202020202020202020202020202020202020202";
+        let profile = trust_recorded_contracts(serde_json::json!({
+            "contracts": { "31337": { "protocol": protocol } },
+            "contacts": { "books": {
+                "Trusted": { "addresses": {} },
+                "Untrusted": { "addresses": {
+                    protocol: { "label": "protocol" },
+                    stranger: { "label": "stranger" },
+                } },
+            } },
+        }))?;
+        let books = &profile["contacts"]["books"];
+        assert_eq!(books["Trusted"]["addresses"][protocol]["label"], "protocol");
+        assert!(books["Untrusted"]["addresses"].get(protocol).is_none());
+        assert!(books["Untrusted"]["addresses"].get(stranger).is_some());
+        Ok(())
+    }
+
     #[test]
     fn test_profile_save() {
         let profile = UserProfile::create_new(Some("test".to_string())).unwrap();
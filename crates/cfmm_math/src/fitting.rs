@@ -0,0 +1,395 @@
+//! Fits trading function parameters to a desired outcome.
+//!
+//! Rather than picking a strike, volatility and time to expiry by hand, a
+//! position can be described by intent: the price range it should cover, the
+//! payoff it should replicate, or the volatility it should be exposed to. The
+//! functions here solve for the Log Normal or G3M parameters that best match
+//! that intent, and report how far off the fit is.
+
+use statrs::distribution::{ContinuousCDF, Normal};
+
+use crate::trading_functions::rmm::{compute_d1, compute_value_function};
+
+/// Maximum number of iterations of the numerical optimizers.
+const MAX_ITERATIONS: usize = 500;
+/// Tolerance at which the numerical optimizers are considered converged.
+const TOLERANCE: f64 = 1e-10;
+
+/// Log Normal parameters found by a fit. `error` is the root mean squared
+/// error of the fit, or zero if the solution is exact.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogNormalFit {
+    pub strike: f64,
+    pub sigma: f64,
+    pub tau: f64,
+    pub error: f64,
+}
+
+/// Inputs a fit cannot be computed from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FitError {
+    /// The price range is not `0 < lower < upper`.
+    InvalidRange { lower: f64, upper: f64 },
+    /// The share of liquidity to cover is not within (0, 1).
+    InvalidCoverage(f64),
+    /// The time to expiry is not positive.
+    InvalidTau(f64),
+    /// There are no points to fit.
+    EmptyTarget,
+}
+
+impl std::fmt::Display for FitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FitError::InvalidRange { lower, upper } => {
+                write!(f, "Invalid price range [{}, {}]", lower, upper)
+            }
+            FitError::InvalidCoverage(coverage) => {
+                write!(f, "Coverage {} is not between 0 and 1", coverage)
+            }
+            FitError::InvalidTau(tau) => write!(f, "Time to expiry {} is not positive", tau),
+            FitError::EmptyTarget => write!(f, "No target points to fit"),
+        }
+    }
+}
+
+impl std::error::Error for FitError {}
+
+/// G3M weight found by a fit. `error` is the root mean squared error of the
+/// fit, or zero if the solution is exact.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct G3mFit {
+    pub weight_x: f64,
+    pub error: f64,
+}
+
+/// Finds the Log Normal curve that trades `coverage` of its liquidity while
+/// the price stays within `[lower_price, upper_price]`.
+///
+/// The liquidity of the curve is normally distributed in log price, so the
+/// range maps to d1 = -z at the lower price and d1 = z at the upper price,
+/// where z is the `(1 + coverage) / 2` quantile. This has a closed form
+/// solution for sigma * sqrt(tau) and the strike.
+pub fn fit_price_range(
+    lower_price: f64,
+    upper_price: f64,
+    tau: f64,
+    coverage: f64,
+) -> Result<LogNormalFit, FitError> {
+    if !(lower_price > 0.0 && lower_price < upper_price && upper_price.is_finite()) {
+        return Err(FitError::InvalidRange {
+            lower: lower_price,
+            upper: upper_price,
+        });
+    }
+    if !(coverage > 0.0 && coverage < 1.0) {
+        return Err(FitError::InvalidCoverage(coverage));
+    }
+    check_tau(tau)?;
+
+    let normal = Normal::new(0.0, 1.0).unwrap();
+    let z = normal.inverse_cdf((1.0 + coverage) / 2.0);
+
+    let sigma_sqrt_tau = (upper_price / lower_price).ln() / (2.0 * z);
+    let strike = (upper_price * lower_price).sqrt() * (sigma_sqrt_tau.powi(2) / 2.0).exp();
+
+    Ok(LogNormalFit {
+        strike,
+        sigma: sigma_sqrt_tau / tau.sqrt(),
+        tau,
+        error: 0.0,
+    })
+}
+
+/// Finds the Log Normal curve whose value for one unit of liquidity best
+/// matches `target`, a list of `(price, value)` points.
+///
+/// Sigma and tau only enter the curve through sigma * sqrt(tau), so tau is
+/// kept fixed and the strike and sigma are fitted.
+pub fn fit_payoff(target: &[(f64, f64)], tau: f64) -> Result<LogNormalFit, FitError> {
+    check_target(target)?;
+    check_tau(tau)?;
+    let error = |strike: f64, sigma: f64| {
+        root_mean_squared_error(target, |price| {
+            compute_value_function(price, strike, sigma, tau)
+        })
+    };
+
+    // Start from a strike at the geometric mean of the sampled prices.
+    let mean_log_price =
+        target.iter().map(|(price, _)| price.ln()).sum::<f64>() / target.len() as f64;
+    let (log_strike, log_sigma) = minimize_2d(
+        |log_strike, log_sigma| error(log_strike.exp(), log_sigma.exp()),
+        (mean_log_price, 0.5_f64.ln()),
+    );
+
+    let (strike, sigma) = (log_strike.exp(), log_sigma.exp());
+    Ok(LogNormalFit {
+        strike,
+        sigma,
+        tau,
+        error: error(strike, sigma),
+    })
+}
+
+/// Finds the strike of a Log Normal curve with the given sigma and tau, such
+/// that the position is exposed to `target_volatility` when the asset has
+/// `asset_volatility`. The exposure is the share of the position's value held
+/// in the risky asset, times the volatility of the asset.
+pub fn fit_log_normal_volatility(
+    target_volatility: f64,
+    asset_volatility: f64,
+    price: f64,
+    sigma: f64,
+    tau: f64,
+) -> LogNormalFit {
+    let target_weight = target_volatility / asset_volatility;
+    let normal = Normal::new(0.0, 1.0).unwrap();
+    let weight_x = |strike: f64| {
+        let x = 1.0 - normal.cdf(compute_d1(price, strike, sigma, tau));
+        price * x / compute_value_function(price, strike, sigma, tau)
+    };
+
+    // The share held in the risky asset increases with the strike.
+    let spread = 10.0 * sigma * tau.sqrt();
+    let (mut low, mut high) = (price.ln() - spread, price.ln() + spread);
+    for _ in 0..MAX_ITERATIONS {
+        let mid = (low + high) / 2.0;
+        if weight_x(mid.exp()) < target_weight {
+            low = mid;
+        } else {
+            high = mid;
+        }
+        if high - low <= TOLERANCE {
+            break;
+        }
+    }
+
+    let strike = ((low + high) / 2.0).exp();
+    LogNormalFit {
+        strike,
+        sigma,
+        tau,
+        error: (weight_x(strike) - target_weight).abs() * asset_volatility,
+    }
+}
+
+/// Value of a G3M position of one unit of liquidity at `price`:
+/// P^w_x / (w_x^w_x * w_y^w_y)
+pub fn compute_g3m_value(price: f64, weight_x: f64) -> f64 {
+    let weight_y = 1.0 - weight_x;
+    price.powf(weight_x) / (weight_x.powf(weight_x) * weight_y.powf(weight_y))
+}
+
+/// Finds the G3M weight whose value for one unit of liquidity best matches
+/// `target`, a list of `(price, value)` points.
+pub fn fit_g3m_payoff(target: &[(f64, f64)]) -> Result<G3mFit, FitError> {
+    check_target(target)?;
+    let error =
+        |weight_x: f64| root_mean_squared_error(target, |price| compute_g3m_value(price, weight_x));
+    let weight_x = minimize_1d(error, 1e-6, 1.0 - 1e-6);
+    Ok(G3mFit {
+        weight_x,
+        error: error(weight_x),
+    })
+}
+
+/// A G3M position holds a constant share `w_x` of its value in the risky
+/// asset, so its volatility is `w_x` times the volatility of the asset.
+pub fn fit_g3m_volatility(target_volatility: f64, asset_volatility: f64) -> G3mFit {
+    let exact = target_volatility / asset_volatility;
+    let weight_x = exact.clamp(0.01, 0.99);
+    G3mFit {
+        weight_x,
+        error: (weight_x - exact).abs() * asset_volatility,
+    }
+}
+
+fn check_tau(tau: f64) -> Result<(), FitError> {
+    if tau > 0.0 && tau.is_finite() {
+        Ok(())
+    } else {
+        Err(FitError::InvalidTau(tau))
+    }
+}
+
+fn check_target(target: &[(f64, f64)]) -> Result<(), FitError> {
+    if target.is_empty() {
+        Err(FitError::EmptyTarget)
+    } else {
+        Ok(())
+    }
+}
+
+fn root_mean_squared_error(target: &[(f64, f64)], model: impl Fn(f64) -> f64) -> f64 {
+    let sum = target
+        .iter()
+        .map(|(price, value)| (model(*price) - value).powi(2))
+        .sum::<f64>();
+    (sum / target.len() as f64).sqrt()
+}
+
+/// Golden section search for the minimum of `f` in `[low, high]`.
+fn minimize_1d(f: impl Fn(f64) -> f64, mut low: f64, mut high: f64) -> f64 {
+    let ratio = (5.0_f64.sqrt() - 1.0) / 2.0;
+    let mut a = high - ratio * (high - low);
+    let mut b = low + ratio * (high - low);
+    let (mut f_a, mut f_b) = (f(a), f(b));
+    for _ in 0..MAX_ITERATIONS {
+        if high - low <= TOLERANCE {
+            break;
+        }
+        if f_a < f_b {
+            high = b;
+            b = a;
+            f_b = f_a;
+            a = high - ratio * (high - low);
+            f_a = f(a);
+        } else {
+            low = a;
+            a = b;
+            f_a = f_b;
+            b = low + ratio * (high - low);
+            f_b = f(b);
+        }
+    }
+    (low + high) / 2.0
+}
+
+/// Nelder-Mead search for the minimum of `f`, starting at `start`.
+fn minimize_2d(f: impl Fn(f64, f64) -> f64, start: (f64, f64)) -> (f64, f64) {
+    let eval = |p: (f64, f64)| f(p.0, p.1);
+    let mut simplex = [start, (start.0 + 0.5, start.1), (start.0, start.1 + 0.5)]
+        .map(|point| (point, eval(point)));
+
+    for _ in 0..MAX_ITERATIONS {
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let [(best, f_best), (good, f_good), (worst, f_worst)] = simplex;
+        if (f_worst - f_best).abs() <= TOLERANCE {
+            break;
+        }
+
+        let centroid = ((best.0 + good.0) / 2.0, (best.1 + good.1) / 2.0);
+        let towards = |scale: f64| {
+            (
+                centroid.0 + scale * (worst.0 - centroid.0),
+                centroid.1 + scale * (worst.1 - centroid.1),
+            )
+        };
+
+        let reflected = towards(-1.0);
+        let f_reflected = eval(reflected);
+        if f_reflected < f_best {
+            let expanded = towards(-2.0);
+            let f_expanded = eval(expanded);
+            simplex[2] = if f_expanded < f_reflected {
+                (expanded, f_expanded)
+            } else {
+                (reflected, f_reflected)
+            };
+        } else if f_reflected < f_good {
+            simplex[2] = (reflected, f_reflected);
+        } else {
+            let contracted = towards(0.5);
+            let f_contracted = eval(contracted);
+            if f_contracted < f_worst {
+                simplex[2] = (contracted, f_contracted);
+            } else {
+                // Shrink everything towards the best point.
+                for vertex in simplex.iter_mut().skip(1) {
+                    let point = (
+                        best.0 + (vertex.0 .0 - best.0) / 2.0,
+                        best.1 + (vertex.0 .1 - best.1) / 2.0,
+                    );
+                    *vertex = (point, eval(point));
+                }
+            }
+        }
+    }
+
+    simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+    simplex[0].0
+}
+
+#[cfg(test)]
+mod test {
+    use statrs::assert_almost_eq;
+
+    use super::*;
+
+    #[test]
+    fn price_range_covers_liquidity() {
+        let fit = fit_price_range(0.5, 2.0, 1.0, 0.9).unwrap();
+        let normal = Normal::new(0.0, 1.0).unwrap();
+        let d1_lower = compute_d1(0.5, fit.strike, fit.sigma, fit.tau);
+        let d1_upper = compute_d1(2.0, fit.strike, fit.sigma, fit.tau);
+
+        assert_almost_eq!(normal.cdf(d1_upper) - normal.cdf(d1_lower), 0.9, 1e-9);
+        assert_almost_eq!(d1_lower, -d1_upper, 1e-9);
+    }
+
+    #[test]
+    fn invalid_inputs_are_rejected() {
+        assert_eq!(
+            fit_price_range(2.0, 0.5, 1.0, 0.9),
+            Err(FitError::InvalidRange {
+                lower: 2.0,
+                upper: 0.5
+            })
+        );
+        assert!(fit_price_range(0.0, 2.0, 1.0, 0.9).is_err());
+        assert_eq!(
+            fit_price_range(0.5, 2.0, 1.0, 1.0),
+            Err(FitError::InvalidCoverage(1.0))
+        );
+        assert_eq!(
+            fit_price_range(0.5, 2.0, 0.0, 0.9),
+            Err(FitError::InvalidTau(0.0))
+        );
+        assert_eq!(fit_payoff(&[], 1.0), Err(FitError::EmptyTarget));
+        assert_eq!(fit_g3m_payoff(&[]), Err(FitError::EmptyTarget));
+    }
+
+    #[test]
+    fn payoff_recovers_log_normal_parameters() {
+        let target = (1..=40)
+            .map(|i| {
+                let price = 0.1 * i as f64;
+                (price, compute_value_function(price, 1.5, 0.6, 1.0))
+            })
+            .collect::<Vec<_>>();
+        let fit = fit_payoff(&target, 1.0).unwrap();
+
+        assert_almost_eq!(fit.strike, 1.5, 1e-3);
+        assert_almost_eq!(fit.sigma, 0.6, 1e-3);
+        assert!(fit.error < 1e-4);
+    }
+
+    #[test]
+    fn payoff_recovers_g3m_weight() {
+        let target = (1..=40)
+            .map(|i| {
+                let price = 0.1 * i as f64;
+                (price, compute_g3m_value(price, 0.3))
+            })
+            .collect::<Vec<_>>();
+        let fit = fit_g3m_payoff(&target).unwrap();
+
+        assert_almost_eq!(fit.weight_x, 0.3, 1e-6);
+        assert!(fit.error < 1e-6);
+    }
+
+    #[test]
+    fn volatility_exposure() {
+        let fit = fit_g3m_volatility(0.2, 0.8);
+        assert_almost_eq!(fit.weight_x, 0.25, 1e-12);
+        assert_eq!(fit.error, 0.0);
+
+        let fit = fit_log_normal_volatility(0.4, 0.8, 1.0, 0.5, 1.0);
+        let normal = Normal::new(0.0, 1.0).unwrap();
+        let x = 1.0 - normal.cdf(compute_d1(1.0, fit.strike, fit.sigma, fit.tau));
+        let weight = x / compute_value_function(1.0, fit.strike, fit.sigma, fit.tau);
+        assert_almost_eq!(weight, 0.5, 1e-6);
+        assert!(fit.error < 1e-6);
+    }
+}
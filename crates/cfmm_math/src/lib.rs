@@ -3,4 +3,5 @@ use statrs::distribution::{ContinuousCDF, Normal};
 use tracing::{debug, error, info, trace, warn};
 use RustQuant::stochastics::*;
 
+pub mod fitting;
 pub mod trading_functions;
@@ -0,0 +1,271 @@
+//! Concentrated liquidity range positions, as in Uniswap v3.
+//!
+//! A position provides liquidity L between a lower and an upper price. Inside
+//! the range it behaves like the constant product curve
+//! (x + L / sqrt(P_u)) * (y + L sqrt(P_l)) = L^2, below the range it holds only
+//! `x` and above it only `y`. Prices are prices of `x` in terms of `y`.
+
+use super::*;
+
+/// Liquidity provided by `amount_x` of `x` over the range, for a position at
+/// `price`. At or above the range the position holds no `x`, so `x` provides
+/// no liquidity.
+pub fn compute_liquidity_given_x(amount_x: f64, price: f64, lower: f64, upper: f64) -> f64 {
+    if price >= upper {
+        return 0.0;
+    }
+    let sqrt_price = price.clamp(lower, upper).sqrt();
+    let sqrt_upper = upper.sqrt();
+    amount_x * sqrt_price * sqrt_upper / (sqrt_upper - sqrt_price)
+}
+
+/// Liquidity provided by `amount_y` of `y` over the range, for a position at
+/// `price`. At or below the range the position holds no `y`, so `y` provides
+/// no liquidity.
+pub fn compute_liquidity_given_y(amount_y: f64, price: f64, lower: f64, upper: f64) -> f64 {
+    if price <= lower {
+        return 0.0;
+    }
+    let sqrt_price = price.clamp(lower, upper).sqrt();
+    amount_y / (sqrt_price - lower.sqrt())
+}
+
+/// Largest liquidity that can be provided with at most `amount_x` and
+/// `amount_y`, for a position at `price`.
+pub fn compute_liquidity_given_amounts(
+    amount_x: f64,
+    amount_y: f64,
+    price: f64,
+    lower: f64,
+    upper: f64,
+) -> f64 {
+    if price <= lower {
+        compute_liquidity_given_x(amount_x, price, lower, upper)
+    } else if price >= upper {
+        compute_liquidity_given_y(amount_y, price, lower, upper)
+    } else {
+        compute_liquidity_given_x(amount_x, price, lower, upper)
+            .min(compute_liquidity_given_y(amount_y, price, lower, upper))
+    }
+}
+
+/// Reserves `(x, y)` held by `liquidity` over the range at `price`.
+pub fn compute_amounts_given_price(
+    liquidity: f64,
+    price: f64,
+    lower: f64,
+    upper: f64,
+) -> (f64, f64) {
+    let sqrt_price = price.clamp(lower, upper).sqrt();
+    let amount_x = liquidity * (1.0 / sqrt_price - 1.0 / upper.sqrt());
+    let amount_y = liquidity * (sqrt_price - lower.sqrt());
+    (amount_x, amount_y)
+}
+
+/// Value in `y` of the reserves held by `liquidity` over the range at `price`.
+pub fn compute_value_given_price(liquidity: f64, price: f64, lower: f64, upper: f64) -> f64 {
+    let (amount_x, amount_y) = compute_amounts_given_price(liquidity, price, lower, upper);
+    amount_x * price + amount_y
+}
+
+/// Outcome of a swap against a range position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RangeSwap {
+    /// Amount of the input token used, including the fee. Smaller than the
+    /// requested amount if the swap reached the edge of the range.
+    pub amount_in: f64,
+    pub amount_out: f64,
+    /// Fee paid on the input, in the input token.
+    pub fee: f64,
+    pub next_price: f64,
+}
+
+/// Swaps `delta_x` of `x` into a position at `price`. The price moves down and
+/// stops at the lower edge of the range.
+pub fn compute_output_y_given_x(
+    liquidity: f64,
+    price: f64,
+    lower: f64,
+    upper: f64,
+    delta_x: f64,
+    swap_fee: f64,
+) -> RangeSwap {
+    let sqrt_price = price.clamp(lower, upper).sqrt();
+    let max_x = liquidity * (1.0 / lower.sqrt() - 1.0 / sqrt_price);
+    let net_x = (delta_x * (1.0 - swap_fee)).min(max_x);
+    let next_sqrt_price = 1.0 / (1.0 / sqrt_price + net_x / liquidity);
+    let amount_in = net_x / (1.0 - swap_fee);
+
+    RangeSwap {
+        amount_in,
+        amount_out: liquidity * (sqrt_price - next_sqrt_price),
+        fee: amount_in - net_x,
+        next_price: next_sqrt_price.powi(2),
+    }
+}
+
+/// Swaps `delta_y` of `y` into a position at `price`. The price moves up and
+/// stops at the upper edge of the range.
+pub fn compute_output_x_given_y(
+    liquidity: f64,
+    price: f64,
+    lower: f64,
+    upper: f64,
+    delta_y: f64,
+    swap_fee: f64,
+) -> RangeSwap {
+    let sqrt_price = price.clamp(lower, upper).sqrt();
+    let max_y = liquidity * (upper.sqrt() - sqrt_price);
+    let net_y = (delta_y * (1.0 - swap_fee)).min(max_y);
+    let next_sqrt_price = sqrt_price + net_y / liquidity;
+    let amount_in = net_y / (1.0 - swap_fee);
+
+    RangeSwap {
+        amount_in,
+        amount_out: liquidity * (1.0 / sqrt_price - 1.0 / next_sqrt_price),
+        fee: amount_in - net_y,
+        next_price: next_sqrt_price.powi(2),
+    }
+}
+
+/// A range position with the fees it has collected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RangePosition {
+    pub lower: f64,
+    pub upper: f64,
+    pub liquidity: f64,
+    pub swap_fee: f64,
+    pub price: f64,
+    pub fees_x: f64,
+    pub fees_y: f64,
+}
+
+impl RangePosition {
+    /// Opens a position at `price` with the largest liquidity that
+    /// `amount_x` and `amount_y` can provide.
+    pub fn new(
+        amount_x: f64,
+        amount_y: f64,
+        price: f64,
+        lower: f64,
+        upper: f64,
+        swap_fee: f64,
+    ) -> Self {
+        Self {
+            lower,
+            upper,
+            liquidity: compute_liquidity_given_amounts(amount_x, amount_y, price, lower, upper),
+            swap_fee,
+            price,
+            fees_x: 0.0,
+            fees_y: 0.0,
+        }
+    }
+
+    /// Reserves `(x, y)` of the position at its current price, excluding fees.
+    pub fn amounts(&self) -> (f64, f64) {
+        compute_amounts_given_price(self.liquidity, self.price, self.lower, self.upper)
+    }
+
+    /// Value in `y` of the reserves and collected fees at `price`.
+    pub fn value_at(&self, price: f64) -> f64 {
+        let (amount_x, amount_y) = self.amounts();
+        (amount_x + self.fees_x) * price + amount_y + self.fees_y
+    }
+
+    /// Moves the position to `next_price`, as an arbitrageur trading against
+    /// it would, and collects the fees on the amount swapped within the range.
+    pub fn move_to_price(&mut self, next_price: f64) {
+        let (amount_x, amount_y) = self.amounts();
+        let (next_x, next_y) =
+            compute_amounts_given_price(self.liquidity, next_price, self.lower, self.upper);
+        let fee_per_unit = self.swap_fee / (1.0 - self.swap_fee);
+        if next_x > amount_x {
+            self.fees_x += (next_x - amount_x) * fee_per_unit;
+        }
+        if next_y > amount_y {
+            self.fees_y += (next_y - amount_y) * fee_per_unit;
+        }
+        self.price = next_price;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use statrs::assert_almost_eq;
+
+    use super::*;
+
+    const LOWER: f64 = 0.5;
+    const UPPER: f64 = 2.0;
+
+    #[test]
+    fn amounts_round_trip_liquidity() {
+        let (x, y) = compute_amounts_given_price(100.0, 1.2, LOWER, UPPER);
+        assert_almost_eq!(compute_liquidity_given_x(x, 1.2, LOWER, UPPER), 100.0, 1e-9);
+        assert_almost_eq!(compute_liquidity_given_y(y, 1.2, LOWER, UPPER), 100.0, 1e-9);
+        assert_almost_eq!(
+            compute_liquidity_given_amounts(x, y * 2.0, 1.2, LOWER, UPPER),
+            100.0,
+            1e-9
+        );
+    }
+
+    #[test]
+    fn single_sided_outside_range() {
+        let (x, y) = compute_amounts_given_price(100.0, 0.1, LOWER, UPPER);
+        assert_eq!(y, 0.0);
+        assert!(x > 0.0);
+
+        let (x, y) = compute_amounts_given_price(100.0, 10.0, LOWER, UPPER);
+        assert_almost_eq!(x, 0.0, 1e-12);
+        assert!(y > 0.0);
+    }
+
+    #[test]
+    fn missing_token_provides_no_liquidity() {
+        assert_eq!(compute_liquidity_given_x(10.0, UPPER, LOWER, UPPER), 0.0);
+        assert_eq!(compute_liquidity_given_x(10.0, 10.0, LOWER, UPPER), 0.0);
+        assert_eq!(compute_liquidity_given_y(10.0, LOWER, LOWER, UPPER), 0.0);
+        assert_eq!(compute_liquidity_given_y(10.0, 0.1, LOWER, UPPER), 0.0);
+    }
+
+    #[test]
+    fn swap_stays_on_curve() {
+        let (x, y) = compute_amounts_given_price(100.0, 1.0, LOWER, UPPER);
+        let swap = compute_output_y_given_x(100.0, 1.0, LOWER, UPPER, 5.0, 0.003);
+        let (next_x, next_y) = compute_amounts_given_price(100.0, swap.next_price, LOWER, UPPER);
+        assert_almost_eq!(next_x - x, swap.amount_in - swap.fee, 1e-9);
+        assert_almost_eq!(y - next_y, swap.amount_out, 1e-9);
+        assert_almost_eq!(swap.fee, 5.0 * 0.003, 1e-9);
+
+        let swap = compute_output_x_given_y(100.0, 1.0, LOWER, UPPER, 5.0, 0.003);
+        let (next_x, next_y) = compute_amounts_given_price(100.0, swap.next_price, LOWER, UPPER);
+        assert_almost_eq!(next_y - y, swap.amount_in - swap.fee, 1e-9);
+        assert_almost_eq!(x - next_x, swap.amount_out, 1e-9);
+    }
+
+    #[test]
+    fn swap_stops_at_range_edge() {
+        let swap = compute_output_y_given_x(100.0, 1.0, LOWER, UPPER, 1e6, 0.003);
+        assert_almost_eq!(swap.next_price, LOWER, 1e-9);
+        assert!(swap.amount_in < 1e6);
+
+        let swap = compute_output_x_given_y(100.0, 1.0, LOWER, UPPER, 1e6, 0.003);
+        assert_almost_eq!(swap.next_price, UPPER, 1e-9);
+    }
+
+    #[test]
+    fn position_collects_fees() {
+        let mut position = RangePosition::new(10.0, 10.0, 1.0, LOWER, UPPER, 0.003);
+        let (x, y) = position.amounts();
+        position.move_to_price(0.8);
+        position.move_to_price(1.0);
+        let (next_x, next_y) = position.amounts();
+        assert_almost_eq!(next_x, x, 1e-9);
+        assert_almost_eq!(next_y, y, 1e-9);
+        assert!(position.fees_x > 0.0);
+        assert!(position.fees_y > 0.0);
+        assert!(position.value_at(1.0) > x + y);
+    }
+}
@@ -0,0 +1,61 @@
+//! Geometric Mean Market Maker (G3M) trading function.
+//!
+//! x^w_x * y^w_y = L, with w_x + w_y = 1.
+
+use super::*;
+
+#[tracing::instrument(ret, level = "trace")]
+pub fn compute_l_given_reserves(reserve_x_float: f64, reserve_y_float: f64, weight_x: f64) -> f64 {
+    reserve_x_float.powf(weight_x) * reserve_y_float.powf(1.0 - weight_x)
+}
+
+#[tracing::instrument(ret, level = "trace")]
+pub fn compute_y_given_x(reserve_x_float: f64, liquidity_float: f64, weight_x: f64) -> f64 {
+    (liquidity_float / reserve_x_float.powf(weight_x)).powf(1.0 / (1.0 - weight_x))
+}
+
+#[tracing::instrument(ret, level = "trace")]
+pub fn compute_x_given_y(reserve_y_float: f64, liquidity_float: f64, weight_x: f64) -> f64 {
+    (liquidity_float / reserve_y_float.powf(1.0 - weight_x)).powf(1.0 / weight_x)
+}
+
+/// Price of `x` in terms of `y`: (w_x / w_y) * (y / x)
+#[tracing::instrument(ret, level = "trace")]
+pub fn compute_spot_price(reserve_x_float: f64, reserve_y_float: f64, weight_x: f64) -> f64 {
+    weight_x / (1.0 - weight_x) * reserve_y_float / reserve_x_float
+}
+
+/// Reserve of `y` such that a pool with `reserve_x_float` of `x` trades at
+/// `price_float`.
+#[tracing::instrument(ret, level = "trace")]
+pub fn compute_y_given_price(reserve_x_float: f64, price_float: f64, weight_x: f64) -> f64 {
+    price_float * reserve_x_float * (1.0 - weight_x) / weight_x
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct G3M {
+    pub weight_x: f64,
+    pub swap_fee: f64,
+}
+
+impl TradingFunction for G3M {
+    fn trading_function(&self, reserve_x: f64, reserve_y: f64, liquidity: f64) -> f64 {
+        compute_l_given_reserves(reserve_x, reserve_y, self.weight_x) / liquidity - 1.0
+    }
+
+    fn y_given_x(&self, reserve_x: f64, liquidity: f64) -> f64 {
+        compute_y_given_x(reserve_x, liquidity, self.weight_x)
+    }
+
+    fn x_given_y(&self, reserve_y: f64, liquidity: f64) -> f64 {
+        compute_x_given_y(reserve_y, liquidity, self.weight_x)
+    }
+
+    fn spot_price(&self, reserve_x: f64, reserve_y: f64, _liquidity: f64) -> f64 {
+        compute_spot_price(reserve_x, reserve_y, self.weight_x)
+    }
+
+    fn swap_fee(&self) -> f64 {
+        self.swap_fee
+    }
+}
@@ -1,2 +1,84 @@
 use super::*;
+pub mod concentrated_liquidity;
+pub mod g3m;
+#[cfg(test)]
+mod properties;
 pub mod rmm;
+pub mod stable_swap;
+pub mod tau;
+
+/// Common interface over the trading functions of the DFMM strategies.
+///
+/// Every curve is homogeneous of degree one in `(x, y, L)`, so allocations and
+/// deallocations are proportional to the reserves. Swaps follow the DFMM
+/// convention of charging the fee by growing the liquidity by
+/// `fee * amount_in * L / reserve_in`.
+pub trait TradingFunction: std::fmt::Debug {
+    /// Residual of the invariant, zero if the reserves lie on the curve.
+    fn trading_function(&self, reserve_x: f64, reserve_y: f64, liquidity: f64) -> f64;
+
+    /// Reserve of `y` on the curve for a given reserve of `x`.
+    fn y_given_x(&self, reserve_x: f64, liquidity: f64) -> f64;
+
+    /// Reserve of `x` on the curve for a given reserve of `y`.
+    fn x_given_y(&self, reserve_y: f64, liquidity: f64) -> f64;
+
+    /// Price of `x` in terms of `y`, i.e. -dy/dx along the curve.
+    fn spot_price(&self, reserve_x: f64, reserve_y: f64, liquidity: f64) -> f64;
+
+    fn swap_fee(&self) -> f64;
+
+    /// Swaps `delta_x` of `x` into the pool.
+    /// Returns the amount of `y` out and the next liquidity.
+    fn swap_x_in(
+        &self,
+        reserve_x: f64,
+        reserve_y: f64,
+        liquidity: f64,
+        delta_x: f64,
+    ) -> (f64, f64) {
+        let next_l = liquidity + self.swap_fee() * delta_x * liquidity / reserve_x;
+        let next_y = self.y_given_x(reserve_x + delta_x, next_l);
+        (reserve_y - next_y, next_l)
+    }
+
+    /// Swaps `delta_y` of `y` into the pool.
+    /// Returns the amount of `x` out and the next liquidity.
+    fn swap_y_in(
+        &self,
+        reserve_x: f64,
+        reserve_y: f64,
+        liquidity: f64,
+        delta_y: f64,
+    ) -> (f64, f64) {
+        let next_l = liquidity + self.swap_fee() * delta_y * liquidity / reserve_y;
+        let next_x = self.x_given_y(reserve_y + delta_y, next_l);
+        (reserve_x - next_x, next_l)
+    }
+
+    /// Adds `delta_x` of `x` and the proportional amount of `y`.
+    /// Returns the next `(x, y, L)`.
+    fn allocate_given_x(
+        &self,
+        reserve_x: f64,
+        reserve_y: f64,
+        liquidity: f64,
+        delta_x: f64,
+    ) -> (f64, f64, f64) {
+        let ratio = (reserve_x + delta_x) / reserve_x;
+        (reserve_x + delta_x, reserve_y * ratio, liquidity * ratio)
+    }
+
+    /// Removes `delta_l` of liquidity and the proportional reserves.
+    /// Returns the next `(x, y, L)`.
+    fn deallocate_given_l(
+        &self,
+        reserve_x: f64,
+        reserve_y: f64,
+        liquidity: f64,
+        delta_l: f64,
+    ) -> (f64, f64, f64) {
+        let ratio = (liquidity - delta_l) / liquidity;
+        (reserve_x * ratio, reserve_y * ratio, liquidity - delta_l)
+    }
+}
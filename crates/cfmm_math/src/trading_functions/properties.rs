@@ -0,0 +1,224 @@
+//! Property tests shared by every [`TradingFunction`].
+//!
+//! Each curve provides a generator of random pools, and the same set of checks
+//! runs against all of them. Adding a new trading function only requires a
+//! generator and a call to [`check_all`].
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::{g3m::G3M, rmm::LogNormal, stable_swap::StableSwap, TradingFunction};
+
+/// Number of random pools sampled per trading function.
+const CASES: usize = 256;
+/// Relative tolerance of the numerical checks.
+const TOLERANCE: f64 = 1e-6;
+/// Largest swap sampled, as a fraction of the reserve swapped into.
+const MAX_SWAP_FRACTION: f64 = 0.25;
+
+/// A pool sitting on its curve.
+#[derive(Debug, Clone, Copy)]
+struct Pool<T> {
+    curve: T,
+    reserve_x: f64,
+    reserve_y: f64,
+    liquidity: f64,
+}
+
+fn assert_relative_eq(actual: f64, expected: f64, tolerance: f64, context: &str) {
+    let scale = expected.abs().max(1.0);
+    assert!(
+        (actual - expected).abs() <= tolerance * scale,
+        "{context}: expected {expected}, got {actual}"
+    );
+}
+
+/// Swapping in either direction keeps the pool on its curve, and fees only
+/// ever grow the liquidity.
+fn check_invariant_preserved<T: TradingFunction>(pool: &Pool<T>, rng: &mut StdRng) {
+    let Pool {
+        curve,
+        reserve_x,
+        reserve_y,
+        liquidity,
+    } = pool;
+
+    let delta_x = reserve_x * rng.gen_range(0.001..MAX_SWAP_FRACTION);
+    let (out_y, next_l) = curve.swap_x_in(*reserve_x, *reserve_y, *liquidity, delta_x);
+    assert!(out_y >= 0.0, "{pool:?}: negative output {out_y}");
+    assert!(next_l >= *liquidity, "{pool:?}: liquidity decreased");
+    let residual = curve.trading_function(reserve_x + delta_x, reserve_y - out_y, next_l);
+    assert_relative_eq(residual, 0.0, TOLERANCE, &format!("{pool:?} x in"));
+
+    let delta_y = reserve_y * rng.gen_range(0.001..MAX_SWAP_FRACTION);
+    let (out_x, next_l) = curve.swap_y_in(*reserve_x, *reserve_y, *liquidity, delta_y);
+    assert!(out_x >= 0.0, "{pool:?}: negative output {out_x}");
+    assert!(next_l >= *liquidity, "{pool:?}: liquidity decreased");
+    let residual = curve.trading_function(reserve_x - out_x, reserve_y + delta_y, next_l);
+    assert_relative_eq(residual, 0.0, TOLERANCE, &format!("{pool:?} y in"));
+}
+
+/// A larger input never yields a smaller output.
+fn check_output_monotonic<T: TradingFunction>(pool: &Pool<T>, rng: &mut StdRng) {
+    let mut inputs = (0..8)
+        .map(|_| pool.reserve_x * rng.gen_range(0.001..MAX_SWAP_FRACTION))
+        .collect::<Vec<f64>>();
+    inputs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let outputs = inputs
+        .iter()
+        .map(|delta_x| {
+            pool.curve
+                .swap_x_in(pool.reserve_x, pool.reserve_y, pool.liquidity, *delta_x)
+                .0
+        })
+        .collect::<Vec<f64>>();
+
+    for window in outputs.windows(2) {
+        assert!(
+            window[1] >= window[0] * (1.0 - TOLERANCE),
+            "{pool:?}: output not monotonic {outputs:?} for inputs {inputs:?}"
+        );
+    }
+}
+
+/// The spot price matches the slope of `y_given_x` at constant liquidity.
+fn check_spot_price_is_derivative<T: TradingFunction>(pool: &Pool<T>) {
+    let h = pool.reserve_x * 1e-6;
+    let y_up = pool.curve.y_given_x(pool.reserve_x + h, pool.liquidity);
+    let y_down = pool.curve.y_given_x(pool.reserve_x - h, pool.liquidity);
+    let numeric = -(y_up - y_down) / (2.0 * h);
+
+    let price = pool
+        .curve
+        .spot_price(pool.reserve_x, pool.reserve_y, pool.liquidity);
+    assert_relative_eq(price, numeric, 1e-4, &format!("{pool:?} spot price"));
+}
+
+/// Allocating and deallocating scales every reserve and the liquidity by the
+/// same ratio, and leaves the pool on its curve.
+fn check_allocate_deallocate_proportional<T: TradingFunction>(pool: &Pool<T>, rng: &mut StdRng) {
+    let Pool {
+        curve,
+        reserve_x,
+        reserve_y,
+        liquidity,
+    } = pool;
+
+    let delta_x = reserve_x * rng.gen_range(0.001..2.0);
+    let (next_x, next_y, next_l) =
+        curve.allocate_given_x(*reserve_x, *reserve_y, *liquidity, delta_x);
+    let ratio = next_x / reserve_x;
+    assert_relative_eq(next_y / reserve_y, ratio, TOLERANCE, "allocate y ratio");
+    assert_relative_eq(next_l / liquidity, ratio, TOLERANCE, "allocate L ratio");
+    let residual = curve.trading_function(next_x, next_y, next_l);
+    assert_relative_eq(residual, 0.0, TOLERANCE, &format!("{pool:?} allocate"));
+
+    let delta_l = next_l * rng.gen_range(0.001..0.999);
+    let (last_x, last_y, last_l) = curve.deallocate_given_l(next_x, next_y, next_l, delta_l);
+    let ratio = last_l / next_l;
+    assert_relative_eq(last_x / next_x, ratio, TOLERANCE, "deallocate x ratio");
+    assert_relative_eq(last_y / next_y, ratio, TOLERANCE, "deallocate y ratio");
+    let residual = curve.trading_function(last_x, last_y, last_l);
+    assert_relative_eq(residual, 0.0, TOLERANCE, &format!("{pool:?} deallocate"));
+}
+
+/// Swapping in and immediately swapping the output back never returns more
+/// than was put in.
+fn check_no_round_trip_profit<T: TradingFunction>(pool: &Pool<T>, rng: &mut StdRng) {
+    let Pool {
+        curve,
+        reserve_x,
+        reserve_y,
+        liquidity,
+    } = pool;
+
+    let delta_x = reserve_x * rng.gen_range(0.001..MAX_SWAP_FRACTION);
+    let (out_y, next_l) = curve.swap_x_in(*reserve_x, *reserve_y, *liquidity, delta_x);
+    let (next_x, next_y) = (reserve_x + delta_x, reserve_y - out_y);
+    let (back_x, _) = curve.swap_y_in(next_x, next_y, next_l, out_y);
+
+    assert!(
+        back_x <= delta_x * (1.0 + TOLERANCE),
+        "{pool:?}: round trip of {delta_x} returned {back_x}"
+    );
+}
+
+fn check_all<T: TradingFunction>(seed: u64, generate: impl Fn(&mut StdRng) -> Pool<T>) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    for _ in 0..CASES {
+        let pool = generate(&mut rng);
+        check_invariant_preserved(&pool, &mut rng);
+        check_output_monotonic(&pool, &mut rng);
+        check_spot_price_is_derivative(&pool);
+        check_allocate_deallocate_proportional(&pool, &mut rng);
+        check_no_round_trip_profit(&pool, &mut rng);
+    }
+}
+
+fn random_log_normal(rng: &mut StdRng) -> Pool<LogNormal> {
+    let curve = LogNormal {
+        strike: rng.gen_range(0.5..2.0),
+        sigma: rng.gen_range(0.1..1.5),
+        tau: rng.gen_range(0.1..2.0),
+        swap_fee: rng.gen_range(0.0..0.01),
+    };
+    let liquidity = rng.gen_range(1.0..1000.0);
+    // Keep x / L away from the edges, where the sampled swaps would push the
+    // reserves outside of the curve's domain.
+    let reserve_x = liquidity * rng.gen_range(0.2..0.6);
+    let reserve_y = curve.y_given_x(reserve_x, liquidity);
+    Pool {
+        curve,
+        reserve_x,
+        reserve_y,
+        liquidity,
+    }
+}
+
+fn random_g3m(rng: &mut StdRng) -> Pool<G3M> {
+    let curve = G3M {
+        weight_x: rng.gen_range(0.05..0.95),
+        swap_fee: rng.gen_range(0.0..0.01),
+    };
+    let reserve_x = rng.gen_range(1.0..1000.0);
+    let reserve_y = reserve_x * rng.gen_range(0.1..10.0);
+    let liquidity = super::g3m::compute_l_given_reserves(reserve_x, reserve_y, curve.weight_x);
+    Pool {
+        curve,
+        reserve_x,
+        reserve_y,
+        liquidity,
+    }
+}
+
+fn random_stable_swap(rng: &mut StdRng) -> Pool<StableSwap> {
+    let curve = StableSwap {
+        amplification: rng.gen_range(1.0..2000.0),
+        swap_fee: rng.gen_range(0.0..0.01),
+    };
+    let reserve_x = rng.gen_range(1.0..1000.0);
+    let reserve_y = reserve_x * rng.gen_range(0.5..2.0);
+    let liquidity =
+        super::stable_swap::compute_l_given_reserves(reserve_x, reserve_y, curve.amplification);
+    Pool {
+        curve,
+        reserve_x,
+        reserve_y,
+        liquidity,
+    }
+}
+
+#[test]
+fn log_normal_properties() {
+    check_all(0, random_log_normal);
+}
+
+#[test]
+fn g3m_properties() {
+    check_all(1, random_g3m);
+}
+
+#[test]
+fn stable_swap_properties() {
+    check_all(2, random_stable_swap);
+}
@@ -194,6 +194,41 @@ pub fn compute_y_given_x_rust(
     kl * cdf
 }
 
+#[tracing::instrument(ret, level = "trace")]
+pub fn compute_x_given_y_rust(
+    reserve_y_float: f64,
+    liquidity_float: f64,
+    strike_price_float: f64,
+    sigma_float: f64,
+    tau_float: f64,
+) -> f64 {
+    let normal = Normal::new(0.0, 1.0).unwrap();
+    let sigma_sqrt_tau = compute_sigma_sqrt_tau(sigma_float, tau_float);
+    let kl = strike_price_float * liquidity_float;
+
+    let cdf = normal.cdf(-normal.inverse_cdf(reserve_y_float / kl) - sigma_sqrt_tau);
+
+    liquidity_float * cdf
+}
+
+/// Gaussian.ppf(x / L) + Gaussian.ppf(y / KL) + sigma * sqrt(tau)
+#[tracing::instrument(ret, level = "trace")]
+pub fn compute_trading_function_rust(
+    reserve_x_float: f64,
+    reserve_y_float: f64,
+    liquidity_float: f64,
+    strike_price_float: f64,
+    sigma_float: f64,
+    tau_float: f64,
+) -> f64 {
+    let normal = Normal::new(0.0, 1.0).unwrap();
+    let sigma_sqrt_tau = compute_sigma_sqrt_tau(sigma_float, tau_float);
+
+    normal.inverse_cdf(reserve_x_float / liquidity_float)
+        + normal.inverse_cdf(reserve_y_float / (strike_price_float * liquidity_float))
+        + sigma_sqrt_tau
+}
+
 /// K e^(-1/2 v^2 t) [v sqrt(t) e^(ln(x / K) + 1/2 v^2 t) / Gaussian.pdf{
 /// ln(x/K) / v sqrt(t) + 1/2 v sqrt(t) }]
 /// Computes a necessary component of the liquidity distribution, but I am not
@@ -347,6 +382,99 @@ pub fn compute_price_given_x_rust(
     strike_price_wad_float * power.exp()
 }
 
+/// Theta of the value function for one unit of liquidity, expressed per year.
+/// As time passes `tau` decays, so the LP gains value at the rate
+/// dV/dt = -dV/dtau = K n(d2) sigma / (2 sqrt(tau))
+pub fn compute_theta(
+    spot_price_float: f64,
+    strike_price_wad_float: f64,
+    sigma_percent_wad_float: f64,
+    time_to_expiry_years_wad_float: f64,
+) -> f64 {
+    let normal = statrs::distribution::Normal::new(0.0, 1.0).expect("Normal distribution failed");
+    let d2 = compute_d2(
+        spot_price_float,
+        strike_price_wad_float,
+        sigma_percent_wad_float,
+        time_to_expiry_years_wad_float,
+    );
+
+    strike_price_wad_float * normal.pdf(d2) * sigma_percent_wad_float
+        / (2.0 * time_to_expiry_years_wad_float.sqrt())
+}
+
+/// Splits the change in value of one unit of liquidity between two
+/// observations into the part driven by time decay (theta) and the part driven
+/// by the price move. Returns `(theta_component, price_component)`, which sum
+/// to the total change in value.
+pub fn decompose_value_change(
+    spot_price_0: f64,
+    spot_price_1: f64,
+    strike_price_wad_float: f64,
+    sigma_percent_wad_float: f64,
+    tau_0: f64,
+    tau_1: f64,
+) -> (f64, f64) {
+    let value_0 = compute_value_function(
+        spot_price_0,
+        strike_price_wad_float,
+        sigma_percent_wad_float,
+        tau_0,
+    );
+    // Value at the old price once time has passed.
+    let value_decayed = compute_value_function(
+        spot_price_0,
+        strike_price_wad_float,
+        sigma_percent_wad_float,
+        tau_1,
+    );
+    let value_1 = compute_value_function(
+        spot_price_1,
+        strike_price_wad_float,
+        sigma_percent_wad_float,
+        tau_1,
+    );
+
+    (value_decayed - value_0, value_1 - value_decayed)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogNormal {
+    pub strike: f64,
+    pub sigma: f64,
+    pub tau: f64,
+    pub swap_fee: f64,
+}
+
+impl TradingFunction for LogNormal {
+    fn trading_function(&self, reserve_x: f64, reserve_y: f64, liquidity: f64) -> f64 {
+        compute_trading_function_rust(
+            reserve_x,
+            reserve_y,
+            liquidity,
+            self.strike,
+            self.sigma,
+            self.tau,
+        )
+    }
+
+    fn y_given_x(&self, reserve_x: f64, liquidity: f64) -> f64 {
+        compute_y_given_x_rust(reserve_x, liquidity, self.strike, self.sigma, self.tau)
+    }
+
+    fn x_given_y(&self, reserve_y: f64, liquidity: f64) -> f64 {
+        compute_x_given_y_rust(reserve_y, liquidity, self.strike, self.sigma, self.tau)
+    }
+
+    fn spot_price(&self, reserve_x: f64, _reserve_y: f64, liquidity: f64) -> f64 {
+        compute_spot_price_rust(reserve_x, liquidity, self.strike, self.sigma, self.tau)
+    }
+
+    fn swap_fee(&self) -> f64 {
+        self.swap_fee
+    }
+}
+
 #[cfg(test)]
 mod test {
     use statrs::assert_almost_eq;
@@ -390,4 +518,31 @@ mod test {
 
         assert_almost_eq!(depth, 0.354, 1e-3);
     }
+
+    #[test]
+    fn test_theta_matches_value_decay() {
+        let spot = 1.1;
+        let strike = 1.0;
+        let sigma = 0.8;
+        let tau = 0.5;
+        let dt = 1e-6;
+
+        let theta = compute_theta(spot, strike, sigma, tau);
+        let numeric = (compute_value_function(spot, strike, sigma, tau - dt)
+            - compute_value_function(spot, strike, sigma, tau))
+            / dt;
+
+        assert!(theta > 0.0);
+        assert_almost_eq!(theta, numeric, 1e-4);
+    }
+
+    #[test]
+    fn test_decompose_value_change() {
+        let (theta, price) = decompose_value_change(1.0, 1.2, 1.0, 1.0, 1.0, 0.9);
+        let total =
+            compute_value_function(1.2, 1.0, 1.0, 0.9) - compute_value_function(1.0, 1.0, 1.0, 1.0);
+
+        assert!(theta > 0.0);
+        assert_almost_eq!(theta + price, total, 1e-12);
+    }
 }
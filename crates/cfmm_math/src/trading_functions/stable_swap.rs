@@ -0,0 +1,299 @@
+//! StableSwap (Curve-style) trading function for two correlated assets.
+//!
+//! The invariant blends a constant sum and a constant product curve, weighted
+//! by the amplification coefficient `A`:
+//!
+//! 4A(x + y) + D = 4AD + D^3 / (4xy)
+//!
+//! `D` is the total amount of tokens when both reserves are balanced, and is
+//! used as the liquidity `L` of the pool. Large `A` keeps the curve flat around
+//! the peg, while `A` close to zero approaches the constant product curve.
+
+use super::*;
+
+/// Maximum number of Newton iterations before giving up on convergence.
+const MAX_ITERATIONS: usize = 255;
+/// Relative tolerance at which Newton's method is considered converged.
+const TOLERANCE: f64 = 1e-12;
+
+/// Residual of the trading function, normalized by `L`.
+/// Zero if the reserves lie on the curve of liquidity `L`.
+#[tracing::instrument(ret, level = "trace")]
+pub fn compute_trading_function(
+    reserve_x_float: f64,
+    reserve_y_float: f64,
+    liquidity_float: f64,
+    amplification_float: f64,
+) -> f64 {
+    let ann = 4.0 * amplification_float;
+    let sum = reserve_x_float + reserve_y_float;
+    let product = 4.0 * reserve_x_float * reserve_y_float;
+
+    (ann * sum + liquidity_float - ann * liquidity_float - liquidity_float.powi(3) / product)
+        / liquidity_float
+}
+
+/// Solves the invariant for `D` (the liquidity) given both reserves.
+/// The curve only meets the axes in the limit, so a pool missing either
+/// reserve holds no liquidity.
+#[tracing::instrument(ret, level = "trace")]
+pub fn compute_l_given_reserves(
+    reserve_x_float: f64,
+    reserve_y_float: f64,
+    amplification_float: f64,
+) -> f64 {
+    if reserve_x_float <= 0.0 || reserve_y_float <= 0.0 {
+        return 0.0;
+    }
+    let sum = reserve_x_float + reserve_y_float;
+
+    let ann = 4.0 * amplification_float;
+    let mut d = sum;
+    for _ in 0..MAX_ITERATIONS {
+        // D_P = D^3 / (4xy)
+        let d_p = d.powi(3) / (4.0 * reserve_x_float * reserve_y_float);
+        let previous = d;
+        d = (ann * sum + 2.0 * d_p) * d / ((ann - 1.0) * d + 3.0 * d_p);
+        if (d - previous).abs() <= TOLERANCE * d {
+            break;
+        }
+    }
+    d
+}
+
+/// Solves the invariant for the reserve of one token given the reserve of the
+/// other. The invariant is symmetric, so this works for either side.
+#[tracing::instrument(ret, level = "trace")]
+pub fn compute_reserve_given_other(
+    reserve_other_float: f64,
+    liquidity_float: f64,
+    amplification_float: f64,
+) -> f64 {
+    let ann = 4.0 * amplification_float;
+    // y^2 + (x + D / 4A - D) y = D^3 / (16 A x)
+    let b = reserve_other_float + liquidity_float / ann - liquidity_float;
+    let c = liquidity_float.powi(3) / (4.0 * ann * reserve_other_float);
+
+    let mut y = liquidity_float;
+    for _ in 0..MAX_ITERATIONS {
+        let previous = y;
+        y = (y * y + c) / (2.0 * y + b);
+        if (y - previous).abs() <= TOLERANCE * y {
+            break;
+        }
+    }
+    y
+}
+
+#[tracing::instrument(ret, level = "trace")]
+pub fn compute_y_given_x(
+    reserve_x_float: f64,
+    liquidity_float: f64,
+    amplification_float: f64,
+) -> f64 {
+    compute_reserve_given_other(reserve_x_float, liquidity_float, amplification_float)
+}
+
+#[tracing::instrument(ret, level = "trace")]
+pub fn compute_x_given_y(
+    reserve_y_float: f64,
+    liquidity_float: f64,
+    amplification_float: f64,
+) -> f64 {
+    compute_reserve_given_other(reserve_y_float, liquidity_float, amplification_float)
+}
+
+/// Price of `x` in terms of `y`, i.e. -dy/dx along the curve.
+/// A pool missing either reserve has no price, and quotes zero.
+#[tracing::instrument(ret, level = "trace")]
+pub fn compute_spot_price(
+    reserve_x_float: f64,
+    reserve_y_float: f64,
+    amplification_float: f64,
+) -> f64 {
+    if reserve_x_float <= 0.0 || reserve_y_float <= 0.0 {
+        return 0.0;
+    }
+    let ann = 4.0 * amplification_float;
+    let d = compute_l_given_reserves(reserve_x_float, reserve_y_float, amplification_float);
+    let d_cubed_div_4 = d.powi(3) / 4.0;
+
+    let partial_x = ann + d_cubed_div_4 / (reserve_x_float.powi(2) * reserve_y_float);
+    let partial_y = ann + d_cubed_div_4 / (reserve_x_float * reserve_y_float.powi(2));
+    partial_x / partial_y
+}
+
+/// Amount of `y` received for swapping `delta_x` of `x` into the pool.
+/// Follows the DFMM convention of `TradingFunction::swap_x_in`: the fee grows
+/// `D` by `fee * delta_x * D / x`, so it accrues to the liquidity providers.
+#[tracing::instrument(ret, level = "trace")]
+pub fn compute_output_y_given_x(
+    reserve_x_float: f64,
+    reserve_y_float: f64,
+    delta_x_float: f64,
+    amplification_float: f64,
+    swap_fee_float: f64,
+) -> f64 {
+    let d = compute_l_given_reserves(reserve_x_float, reserve_y_float, amplification_float);
+    let next_d = d + swap_fee_float * delta_x_float * d / reserve_x_float;
+    let next_x = reserve_x_float + delta_x_float;
+    reserve_y_float - compute_y_given_x(next_x, next_d, amplification_float)
+}
+
+/// Amount of `x` received for swapping `delta_y` of `y` into the pool.
+#[tracing::instrument(ret, level = "trace")]
+pub fn compute_output_x_given_y(
+    reserve_x_float: f64,
+    reserve_y_float: f64,
+    delta_y_float: f64,
+    amplification_float: f64,
+    swap_fee_float: f64,
+) -> f64 {
+    let d = compute_l_given_reserves(reserve_x_float, reserve_y_float, amplification_float);
+    let next_d = d + swap_fee_float * delta_y_float * d / reserve_y_float;
+    let next_y = reserve_y_float + delta_y_float;
+    reserve_x_float - compute_x_given_y(next_y, next_d, amplification_float)
+}
+
+/// Finds the reserve of `y` such that a pool with `reserve_x_float` of `x`
+/// trades at `price_float`. Returns `(reserve_y, liquidity)`.
+#[tracing::instrument(ret, level = "trace")]
+pub fn compute_initial_reserves(
+    reserve_x_float: f64,
+    price_float: f64,
+    amplification_float: f64,
+) -> (f64, f64) {
+    // The spot price decreases as the reserve of y shrinks, so bisect on the
+    // log of the ratio y / x.
+    let (mut low, mut high) = (-50.0_f64, 50.0_f64);
+    for _ in 0..MAX_ITERATIONS {
+        let mid = (low + high) / 2.0;
+        let reserve_y = reserve_x_float * mid.exp();
+        let price = compute_spot_price(reserve_x_float, reserve_y, amplification_float);
+        if price < price_float {
+            low = mid;
+        } else {
+            high = mid;
+        }
+        if high - low <= TOLERANCE {
+            break;
+        }
+    }
+    let reserve_y = reserve_x_float * ((low + high) / 2.0).exp();
+    let liquidity = compute_l_given_reserves(reserve_x_float, reserve_y, amplification_float);
+    (reserve_y, liquidity)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StableSwap {
+    pub amplification: f64,
+    pub swap_fee: f64,
+}
+
+impl TradingFunction for StableSwap {
+    fn trading_function(&self, reserve_x: f64, reserve_y: f64, liquidity: f64) -> f64 {
+        compute_trading_function(reserve_x, reserve_y, liquidity, self.amplification)
+    }
+
+    fn y_given_x(&self, reserve_x: f64, liquidity: f64) -> f64 {
+        compute_y_given_x(reserve_x, liquidity, self.amplification)
+    }
+
+    fn x_given_y(&self, reserve_y: f64, liquidity: f64) -> f64 {
+        compute_x_given_y(reserve_y, liquidity, self.amplification)
+    }
+
+    fn spot_price(&self, reserve_x: f64, reserve_y: f64, _liquidity: f64) -> f64 {
+        compute_spot_price(reserve_x, reserve_y, self.amplification)
+    }
+
+    fn swap_fee(&self) -> f64 {
+        self.swap_fee
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use statrs::assert_almost_eq;
+
+    use super::*;
+
+    #[test]
+    fn balanced_pool_is_at_peg() {
+        let d = compute_l_given_reserves(100.0, 100.0, 100.0);
+        assert_almost_eq!(d, 200.0, 1e-9);
+        assert_almost_eq!(compute_spot_price(100.0, 100.0, 100.0), 1.0, 1e-12);
+        assert_almost_eq!(compute_trading_function(100.0, 100.0, d, 100.0), 0.0, 1e-12);
+    }
+
+    #[test]
+    fn reserves_solve_the_invariant() {
+        let amplification = 50.0;
+        let d = compute_l_given_reserves(80.0, 130.0, amplification);
+        assert_almost_eq!(
+            compute_trading_function(80.0, 130.0, d, amplification),
+            0.0,
+            1e-9
+        );
+        assert_almost_eq!(compute_y_given_x(80.0, d, amplification), 130.0, 1e-9);
+        assert_almost_eq!(compute_x_given_y(130.0, d, amplification), 80.0, 1e-9);
+    }
+
+    #[test]
+    fn amplification_flattens_the_curve() {
+        let flat = compute_output_y_given_x(100.0, 100.0, 50.0, 1000.0, 0.0);
+        let curved = compute_output_y_given_x(100.0, 100.0, 50.0, 1.0, 0.0);
+        // Close to a constant sum curve, the swap executes near the peg.
+        assert!(flat > curved);
+        assert!(flat > 49.5 && flat < 50.0);
+    }
+
+    #[test]
+    fn fees_reduce_output() {
+        let without_fee = compute_output_x_given_y(100.0, 100.0, 10.0, 100.0, 0.0);
+        let with_fee = compute_output_x_given_y(100.0, 100.0, 10.0, 100.0, 0.003);
+        assert!(with_fee < without_fee);
+    }
+
+    #[test]
+    fn output_matches_trading_function_swap() {
+        let curve = StableSwap {
+            amplification: 100.0,
+            swap_fee: 0.003,
+        };
+        let (rx, ry) = (80.0, 130.0);
+        let d = compute_l_given_reserves(rx, ry, curve.amplification);
+
+        let (y_out, _) = curve.swap_x_in(rx, ry, d, 10.0);
+        assert_almost_eq!(
+            compute_output_y_given_x(rx, ry, 10.0, curve.amplification, curve.swap_fee),
+            y_out,
+            1e-9
+        );
+        let (x_out, _) = curve.swap_y_in(rx, ry, d, 10.0);
+        assert_almost_eq!(
+            compute_output_x_given_y(rx, ry, 10.0, curve.amplification, curve.swap_fee),
+            x_out,
+            1e-9
+        );
+    }
+
+    #[test]
+    fn empty_reserves_are_finite() {
+        assert_eq!(compute_l_given_reserves(0.0, 0.0, 100.0), 0.0);
+        assert_eq!(compute_l_given_reserves(100.0, 0.0, 100.0), 0.0);
+        assert_eq!(compute_spot_price(0.0, 100.0, 100.0), 0.0);
+        assert_eq!(compute_spot_price(100.0, 0.0, 100.0), 0.0);
+    }
+
+    #[test]
+    fn initial_reserves_match_price() {
+        let (reserve_y, liquidity) = compute_initial_reserves(100.0, 0.98, 100.0);
+        assert_almost_eq!(compute_spot_price(100.0, reserve_y, 100.0), 0.98, 1e-9);
+        assert_almost_eq!(
+            compute_trading_function(100.0, reserve_y, liquidity, 100.0),
+            0.0,
+            1e-9
+        );
+    }
+}
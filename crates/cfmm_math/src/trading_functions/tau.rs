@@ -0,0 +1,128 @@
+//! Time to expiry of the Log Normal (RMM) curve.
+//!
+//! The Log Normal trading function is parameterized by `tau`, the time
+//! remaining until the position expires. A pool can either keep `tau` constant
+//! (perpetual mode) or let it decay with the block timestamp towards expiry,
+//! after which the position is rolled into a new period and `tau` is reset.
+
+/// Number of seconds in a year, used to convert block timestamps into the
+/// annualized units `tau` is expressed in.
+pub const SECONDS_PER_YEAR: f64 = 31_536_000.0;
+
+/// How `tau` evolves over time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TauMode {
+    /// `tau` never changes, i.e. the curve never approaches expiry.
+    Fixed(f64),
+    /// `tau` decays linearly from `initial_tau` to zero over `duration`
+    /// seconds, starting at `start`. Once the expiry is reached, the position
+    /// rolls into a new period of the same length and `tau` is reset to
+    /// `initial_tau`.
+    Expiry {
+        initial_tau: f64,
+        start: u64,
+        duration: u64,
+    },
+}
+
+impl TauMode {
+    /// Expiry mode where `tau` is the actual time left in years, i.e. a
+    /// position of `initial_tau` years expires after `initial_tau *
+    /// SECONDS_PER_YEAR` seconds.
+    pub fn annualized(initial_tau: f64, start: u64) -> Self {
+        TauMode::Expiry {
+            initial_tau,
+            start,
+            duration: (initial_tau * SECONDS_PER_YEAR) as u64,
+        }
+    }
+
+    /// Returns the value of `tau` at `timestamp`.
+    /// Timestamps before `start` are treated as the start of the first period.
+    #[tracing::instrument(ret, level = "trace")]
+    pub fn tau_at(&self, timestamp: u64) -> f64 {
+        match *self {
+            TauMode::Fixed(tau) => tau,
+            TauMode::Expiry {
+                initial_tau,
+                start,
+                duration,
+            } => {
+                if duration == 0 {
+                    return initial_tau;
+                }
+                let elapsed = timestamp.saturating_sub(start) % duration;
+                initial_tau * (duration - elapsed) as f64 / duration as f64
+            }
+        }
+    }
+
+    /// Returns the timestamp at which the period containing `timestamp`
+    /// expires, or `None` in fixed mode.
+    pub fn expiry_at(&self, timestamp: u64) -> Option<u64> {
+        match *self {
+            TauMode::Fixed(_) => None,
+            TauMode::Expiry {
+                start, duration, ..
+            } => {
+                if duration == 0 {
+                    return None;
+                }
+                let periods = timestamp.saturating_sub(start) / duration;
+                Some(start + (periods + 1) * duration)
+            }
+        }
+    }
+
+    /// Returns true if an expiry happened in `(from, to]`, i.e. `tau` was reset
+    /// in between the two timestamps.
+    pub fn expired_between(&self, from: u64, to: u64) -> bool {
+        match self.expiry_at(from) {
+            Some(expiry) => to >= expiry,
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use statrs::assert_almost_eq;
+
+    use super::*;
+
+    #[test]
+    fn fixed_tau_never_decays() {
+        let mode = TauMode::Fixed(1.0);
+        assert_eq!(mode.tau_at(0), 1.0);
+        assert_eq!(mode.tau_at(u64::MAX), 1.0);
+        assert_eq!(mode.expiry_at(100), None);
+        assert!(!mode.expired_between(0, u64::MAX));
+    }
+
+    #[test]
+    fn expiry_tau_decays_and_resets() {
+        let mode = TauMode::Expiry {
+            initial_tau: 1.0,
+            start: 100,
+            duration: 1000,
+        };
+        assert_almost_eq!(mode.tau_at(0), 1.0, 1e-12);
+        assert_almost_eq!(mode.tau_at(100), 1.0, 1e-12);
+        assert_almost_eq!(mode.tau_at(600), 0.5, 1e-12);
+        assert_almost_eq!(mode.tau_at(1099), 0.001, 1e-12);
+        // rolls into the next period at expiry
+        assert_almost_eq!(mode.tau_at(1100), 1.0, 1e-12);
+        assert_eq!(mode.expiry_at(600), Some(1100));
+        assert_eq!(mode.expiry_at(1100), Some(2100));
+        assert!(mode.expired_between(1000, 1100));
+        assert!(!mode.expired_between(1100, 2000));
+    }
+
+    #[test]
+    fn annualized_tau_matches_years_left() {
+        let mode = TauMode::annualized(2.0, 0);
+        let one_year = SECONDS_PER_YEAR as u64;
+        assert_almost_eq!(mode.tau_at(one_year), 1.0, 1e-9);
+        assert_eq!(mode.expiry_at(0), Some(2 * one_year));
+    }
+}
@@ -0,0 +1,484 @@
+//! Parses the human-friendly arguments of a transaction into ABI tokens.
+//!
+//! Arguments are written the way a user would type them:
+//! - Addresses as hex, or as the name of a contact.
+//! - Numbers as decimal or `0x` hex, optionally followed by a unit, such as
+//!   `1.5 ether` or `30 gwei`. Plain numbers are in the smallest unit.
+//! - Bytes as `0x` hex.
+//! - Booleans as `true` or `false`, and strings as is, optionally quoted.
+//! - Arrays as `[a, b, c]` and tuples (structs) as `(a, b, c)`, nested as deep
+//!   as needed.
+//!
+//! Errors name the argument they were found in, down to the array element or
+//! tuple field, i.e. `orders[1].2`.
+
+use std::collections::HashMap;
+
+use ethers::{
+    abi::{Abi, Function, ParamType, Token},
+    types::{Address, Bytes, I256, U256},
+    utils::{hex, parse_units},
+};
+use thiserror::Error;
+
+#[derive(Clone, Error, Debug, PartialEq, Eq)]
+pub enum ArgumentError {
+    #[error("No function `{0}` in the ABI")]
+    UnknownFunction(String),
+    #[error("`{method}` takes {expected:?} arguments, but {found} were given")]
+    ArgumentCount {
+        method: String,
+        expected: Vec<usize>,
+        found: usize,
+    },
+    #[error("Call to overloaded function `{0}` is ambiguous, use its full signature")]
+    Ambiguous(String),
+    #[error("Invalid argument `{path}`: {message}")]
+    Invalid { path: String, message: String },
+}
+
+/// Parses arguments, resolving contact names to their address.
+#[derive(Debug, Clone, Copy)]
+pub struct ArgumentParser<'a> {
+    contacts: &'a HashMap<String, Address>,
+}
+
+impl<'a> ArgumentParser<'a> {
+    pub fn new(contacts: &'a HashMap<String, Address>) -> Self {
+        Self { contacts }
+    }
+
+    /// Finds the function of `abi` called by `method` and parses `arguments`
+    /// for it. `method` is either a name or a full signature, such as
+    /// `transfer(address,uint256)`. Overloads of a name are told apart by the
+    /// number of arguments, then by which ones the arguments parse for.
+    pub fn resolve(
+        &self,
+        abi: &Abi,
+        method: &str,
+        arguments: &[String],
+    ) -> Result<(Function, Vec<Token>), ArgumentError> {
+        let method: String = method.chars().filter(|c| !c.is_whitespace()).collect();
+        let name = method.split('(').next().unwrap_or_default();
+        let functions = abi
+            .functions_by_name(name)
+            .map_err(|_| ArgumentError::UnknownFunction(method.clone()))?;
+
+        let functions = functions
+            .iter()
+            .filter(|function| !method.contains('(') || signature(function) == method)
+            .collect::<Vec<_>>();
+        if functions.is_empty() {
+            return Err(ArgumentError::UnknownFunction(method));
+        }
+
+        let candidates = functions
+            .iter()
+            .copied()
+            .filter(|function| function.inputs.len() == arguments.len())
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            let mut expected = functions
+                .iter()
+                .map(|function| function.inputs.len())
+                .collect::<Vec<_>>();
+            expected.sort();
+            expected.dedup();
+            return Err(ArgumentError::ArgumentCount {
+                method,
+                expected,
+                found: arguments.len(),
+            });
+        }
+
+        let mut results = candidates
+            .into_iter()
+            .map(|function| {
+                self.parse_all(function, arguments)
+                    .map(|tokens| (function.clone(), tokens))
+            })
+            .collect::<Vec<_>>();
+        match results.iter().filter(|result| result.is_ok()).count() {
+            // Report the errors of the first overload if none of them parse.
+            0 => results.remove(0),
+            1 => results.into_iter().find(Result::is_ok).unwrap(),
+            _ => Err(ArgumentError::Ambiguous(method)),
+        }
+    }
+
+    /// Parses `arguments` as the inputs of `function`.
+    pub fn parse_all(
+        &self,
+        function: &Function,
+        arguments: &[String],
+    ) -> Result<Vec<Token>, ArgumentError> {
+        function
+            .inputs
+            .iter()
+            .zip(arguments)
+            .enumerate()
+            .map(|(index, (param, argument))| {
+                let path = match param.name.is_empty() {
+                    true => format!("#{}", index),
+                    false => param.name.clone(),
+                };
+                self.parse(argument, &param.kind, &path)
+            })
+            .collect()
+    }
+
+    /// Parses `input` as a value of type `kind`. `path` names the value in
+    /// errors.
+    pub fn parse(&self, input: &str, kind: &ParamType, path: &str) -> Result<Token, ArgumentError> {
+        let input = input.trim();
+        let invalid = |message: String| ArgumentError::Invalid {
+            path: path.to_string(),
+            message,
+        };
+
+        match kind {
+            ParamType::Address => self
+                .parse_address(input)
+                .map(Token::Address)
+                .map_err(invalid),
+            ParamType::Bool => match input {
+                "true" => Ok(Token::Bool(true)),
+                "false" => Ok(Token::Bool(false)),
+                _ => Err(invalid(format!(
+                    "expected `true` or `false`, got `{}`",
+                    input
+                ))),
+            },
+            ParamType::Uint(bits) => {
+                let value = parse_amount(input).map_err(invalid)?;
+                if value.bits() > *bits {
+                    return Err(invalid(format!("{} does not fit in uint{}", value, bits)));
+                }
+                Ok(Token::Uint(value))
+            }
+            ParamType::Int(bits) => {
+                let (negative, magnitude) = match input.strip_prefix('-') {
+                    Some(magnitude) => (true, magnitude),
+                    None => (false, input),
+                };
+                let magnitude = parse_amount(magnitude).map_err(invalid)?;
+                // The range of a signed integer is [-2^(bits-1), 2^(bits-1)).
+                let limit = U256::one() << (bits - 1);
+                if magnitude > limit || (!negative && magnitude == limit) {
+                    return Err(invalid(format!("{} does not fit in int{}", input, bits)));
+                }
+                let value = match negative && !magnitude.is_zero() {
+                    true => (!magnitude).overflowing_add(U256::one()).0,
+                    false => magnitude,
+                };
+                Ok(Token::Int(value))
+            }
+            ParamType::String => {
+                let value = input
+                    .strip_prefix('"')
+                    .and_then(|value| value.strip_suffix('"'))
+                    .unwrap_or(input);
+                Ok(Token::String(value.to_string()))
+            }
+            ParamType::Bytes => parse_hex(input).map(Token::Bytes).map_err(invalid),
+            ParamType::FixedBytes(size) => {
+                let value = parse_hex(input).map_err(invalid)?;
+                if value.len() != *size {
+                    return Err(invalid(format!(
+                        "expected {} bytes, got {}",
+                        size,
+                        value.len()
+                    )));
+                }
+                Ok(Token::FixedBytes(value))
+            }
+            ParamType::Array(inner) => {
+                let items = split_items(input, '[', ']').map_err(invalid)?;
+                let tokens = items
+                    .iter()
+                    .enumerate()
+                    .map(|(index, item)| self.parse(item, inner, &format!("{}[{}]", path, index)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Token::Array(tokens))
+            }
+            ParamType::FixedArray(inner, size) => {
+                let items = split_items(input, '[', ']').map_err(invalid)?;
+                if items.len() != *size {
+                    return Err(invalid(format!(
+                        "expected {} elements, got {}",
+                        size,
+                        items.len()
+                    )));
+                }
+                let tokens = items
+                    .iter()
+                    .enumerate()
+                    .map(|(index, item)| self.parse(item, inner, &format!("{}[{}]", path, index)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Token::FixedArray(tokens))
+            }
+            ParamType::Tuple(kinds) => {
+                let items = split_items(input, '(', ')').map_err(invalid)?;
+                if items.len() != kinds.len() {
+                    return Err(invalid(format!(
+                        "expected {} fields, got {}",
+                        kinds.len(),
+                        items.len()
+                    )));
+                }
+                let tokens = items
+                    .iter()
+                    .zip(kinds)
+                    .enumerate()
+                    .map(|(index, (item, kind))| {
+                        self.parse(item, kind, &format!("{}.{}", path, index))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Token::Tuple(tokens))
+            }
+        }
+    }
+
+    fn parse_address(&self, input: &str) -> Result<Address, String> {
+        if let Some(address) = self.contacts.get(input) {
+            return Ok(*address);
+        }
+        if input.starts_with("0x") {
+            return input
+                .parse::<Address>()
+                .map_err(|e| format!("`{}` is not an address: {}", input, e));
+        }
+        Err(format!("`{}` is not an address or a contact", input))
+    }
+}
+
+/// Formats `token` the way [`ArgumentParser`] reads it back.
+pub fn format_token(token: &Token) -> String {
+    let list = |tokens: &[Token]| {
+        tokens
+            .iter()
+            .map(format_token)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    match token {
+        Token::Address(address) => format!("{:?}", address),
+        Token::Uint(value) => value.to_string(),
+        Token::Int(value) => I256::from_raw(*value).to_string(),
+        Token::Bool(value) => value.to_string(),
+        Token::String(value) => format!("\"{}\"", value),
+        Token::Bytes(value) | Token::FixedBytes(value) => format!("0x{}", hex::encode(value)),
+        Token::Array(tokens) | Token::FixedArray(tokens) => format!("[{}]", list(tokens)),
+        Token::Tuple(tokens) => format!("({})", list(tokens)),
+    }
+}
+
+/// Canonical signature of `function`, i.e. `transfer(address,uint256)`.
+fn signature(function: &Function) -> String {
+    let inputs = function
+        .inputs
+        .iter()
+        .map(|param| param.kind.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("{}({})", function.name, inputs)
+}
+
+/// Parses a non-negative number, with an optional unit.
+fn parse_amount(input: &str) -> Result<U256, String> {
+    let input = input.replace('_', "");
+    if let Some(hex) = input.strip_prefix("0x") {
+        return U256::from_str_radix(hex, 16)
+            .map_err(|_| format!("`{}` is not a hex number", input));
+    }
+
+    let split = input
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(input.len());
+    let (amount, unit) = input.split_at(split);
+    let (amount, unit) = (amount.trim(), unit.trim());
+    if unit.is_empty() {
+        if amount.contains('.') {
+            return Err(format!(
+                "`{}` has decimals, so it needs a unit such as `ether`",
+                input
+            ));
+        }
+        return U256::from_dec_str(amount).map_err(|_| format!("`{}` is not a number", input));
+    }
+
+    parse_units(amount, unit)
+        .map(U256::from)
+        .map_err(|e| format!("`{}` is not an amount: {}", input, e))
+}
+
+fn parse_hex(input: &str) -> Result<Vec<u8>, String> {
+    let Some(hex) = input.strip_prefix("0x") else {
+        return Err(format!("expected `0x` prefixed hex, got `{}`", input));
+    };
+    hex::decode(hex).map_err(|e| format!("`{}` is not valid hex: {}", input, e))
+}
+
+/// Splits `open a, b, c close` into its items, leaving nested arrays, tuples
+/// and quoted strings whole.
+fn split_items(input: &str, open: char, close: char) -> Result<Vec<String>, String> {
+    let inner = input
+        .strip_prefix(open)
+        .and_then(|inner| inner.strip_suffix(close))
+        .ok_or_else(|| format!("expected `{}...{}`, got `{}`", open, close, input))?;
+    if inner.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut items = Vec::new();
+    let mut item = String::new();
+    let mut depth = 0usize;
+    let mut quoted = false;
+    for c in inner.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            '[' | '(' if !quoted => depth += 1,
+            ']' | ')' if !quoted => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| format!("unbalanced `{}` in `{}`", c, input))?;
+            }
+            ',' if !quoted && depth == 0 => {
+                items.push(item.trim().to_string());
+                item.clear();
+                continue;
+            }
+            _ => {}
+        }
+        item.push(c);
+    }
+    if depth != 0 || quoted {
+        return Err(format!("unbalanced brackets or quotes in `{}`", input));
+    }
+    items.push(item.trim().to_string());
+    Ok(items)
+}
+
+/// Encodes a call to `method` of `abi` with `arguments`.
+pub fn encode_call(
+    abi: &Abi,
+    method: &str,
+    arguments: &[String],
+    contacts: &HashMap<String, Address>,
+) -> anyhow::Result<Bytes> {
+    let (function, tokens) = ArgumentParser::new(contacts).resolve(abi, method, arguments)?;
+    Ok(function.encode_input(&tokens)?.into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ABI: &str = r#"[
+        { "type": "function", "name": "transfer", "stateMutability": "nonpayable",
+          "inputs": [{ "name": "to", "type": "address" }, { "name": "amount", "type": "uint256" }],
+          "outputs": [{ "name": "", "type": "bool" }] },
+        { "type": "function", "name": "transfer", "stateMutability": "nonpayable",
+          "inputs": [{ "name": "to", "type": "address" }, { "name": "data", "type": "bytes" }],
+          "outputs": [] },
+        { "type": "function", "name": "fill", "stateMutability": "nonpayable",
+          "inputs": [{ "name": "orders", "type": "tuple[]", "components": [
+              { "name": "maker", "type": "address" },
+              { "name": "delta", "type": "int128" },
+              { "name": "ids", "type": "uint8[2]" }
+          ] }],
+          "outputs": [] }
+    ]"#;
+
+    fn contacts() -> HashMap<String, Address> {
+        HashMap::from([("alice".to_string(), Address::repeat_byte(0xa1))])
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn resolves_overloads() {
+        let abi: Abi = serde_json::from_str(ABI).unwrap();
+        let contacts = contacts();
+        let parser = ArgumentParser::new(&contacts);
+
+        let (function, tokens) = parser
+            .resolve(&abi, "transfer", &args(&["alice", "1.5 ether"]))
+            .unwrap();
+        assert_eq!(signature(&function), "transfer(address,uint256)");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Address(Address::repeat_byte(0xa1)),
+                Token::Uint(U256::from(15) * U256::exp10(17))
+            ]
+        );
+
+        let (function, _) = parser
+            .resolve(&abi, "transfer(address, bytes)", &args(&["alice", "0x"]))
+            .unwrap();
+        assert_eq!(signature(&function), "transfer(address,bytes)");
+
+        assert_eq!(
+            parser.resolve(&abi, "transfer", &args(&["alice", "0x10"])),
+            Err(ArgumentError::Ambiguous("transfer".to_string()))
+        );
+        assert!(matches!(
+            parser.resolve(&abi, "transfer", &args(&["alice"])),
+            Err(ArgumentError::ArgumentCount { found: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn parses_nested_structs() {
+        let abi: Abi = serde_json::from_str(ABI).unwrap();
+        let contacts = contacts();
+        let parser = ArgumentParser::new(&contacts);
+
+        let (_, tokens) = parser
+            .resolve(&abi, "fill", &args(&["[(alice, -5, [1, 2]), (0x00000000000000000000000000000000000000b0, 3, [0, 255])]"]))
+            .unwrap();
+        let Token::Array(orders) = &tokens[0] else {
+            panic!("expected an array");
+        };
+        assert_eq!(
+            orders[0],
+            Token::Tuple(vec![
+                Token::Address(Address::repeat_byte(0xa1)),
+                Token::Int(U256::MAX - 4),
+                Token::FixedArray(vec![Token::Uint(1.into()), Token::Uint(2.into())]),
+            ])
+        );
+
+        // Formatted tokens parse back to themselves.
+        let (function, _) = parser.resolve(&abi, "fill", &args(&["[]"])).unwrap();
+        assert_eq!(
+            parser
+                .parse(
+                    &format_token(&tokens[0]),
+                    &function.inputs[0].kind,
+                    "orders"
+                )
+                .unwrap(),
+            tokens[0]
+        );
+
+        assert_eq!(
+            parser.resolve(
+                &abi,
+                "fill",
+                &args(&["[(alice, -5, [1, 2]), (bob, 3, [0, 256])]"])
+            ),
+            Err(ArgumentError::Invalid {
+                path: "orders[1].0".to_string(),
+                message: "`bob` is not an address or a contact".to_string(),
+            })
+        );
+        assert!(matches!(
+            parser.resolve(&abi, "fill", &args(&["[(alice, -5, [1, 256])]"])),
+            Err(ArgumentError::Invalid { path, .. }) if path == "orders[0].2[1]"
+        ));
+    }
+}
@@ -1,6 +1,6 @@
 //! Handles simulation transactions that will go over live networks
 
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
 
 use anyhow::anyhow;
 use arbiter_core::environment::{builder::EnvironmentBuilder, fork::ContractMetadata, Environment};
@@ -11,13 +11,18 @@ use revm::{
 };
 use revm_primitives::AccountInfo;
 
-use super::digest::{self, Artifacts, StorageLayout};
+use super::{
+    digest::{self, Artifacts, StorageLayout},
+    lazy_fork::LazyFork,
+};
 
 pub struct Forker {
     pub environment: Environment,
     pub client: Option<Arc<SignerMiddleware<Provider<Ws>, LocalWallet>>>,
     pub block_number: u64,
     pub last_db: Option<CacheDB<EmptyDB>>,
+    /// Directory lazy forks persist the state they fetch in.
+    pub fork_cache_dir: Option<PathBuf>,
 }
 
 impl Default for Forker {
@@ -27,10 +32,29 @@ impl Default for Forker {
             client: None,
             block_number: 2,
             last_db: None,
+            fork_cache_dir: None,
         }
     }
 }
 
+/// The network a [`Forker`] forks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ForkTarget {
+    pub chain_id: u64,
+    /// Whether the network is a local dev node, whose state can be rewritten.
+    pub mutable: bool,
+}
+
+/// Client version prefixes of local dev nodes.
+const DEV_NODES: [&str; 3] = ["anvil", "hardhat", "ganache"];
+
+fn is_dev_node(client_version: &str) -> bool {
+    let client_version = client_version.to_lowercase();
+    DEV_NODES
+        .iter()
+        .any(|node| client_version.starts_with(node))
+}
+
 const RPC_URL_WS: &str = "ws://localhost:8545";
 const CHAIN_ID: u64 = 31337;
 
@@ -66,6 +90,7 @@ impl Forker {
             client,
             block_number,
             last_db,
+            fork_cache_dir: None,
         }
     }
 
@@ -84,6 +109,12 @@ impl Forker {
         self
     }
 
+    /// Sets the directory lazy forks persist the state they fetch in.
+    pub fn with_fork_cache_dir(mut self, dir: PathBuf) -> Self {
+        self.fork_cache_dir = Some(dir);
+        self
+    }
+
     #[tracing::instrument(skip(wallet))]
     pub async fn connect(url: Option<String>, wallet: Option<LocalWallet>) -> anyhow::Result<Self> {
         // connect to the network
@@ -149,78 +180,48 @@ impl Forker {
         }
     }
 
-    /// Digests the config file and takes in an `EthersDB` so that the data can
-    /// be fetched from the blockchain.
-    /// Once all the `AccountInfo` for the contracts are fetched, we digest the
-    /// contract artifacts to get the storage layout.
-    /// todo: remove this
-    #[tracing::instrument(skip(self))]
-    pub fn digest_config(&self, addy: Address) -> anyhow::Result<CacheDB<EmptyDB>, anyhow::Error> {
-        // Spawn the `EthersDB` and the `CacheDB` we will write to.
-        let ethers_db = &mut self.spawn_ethers_db()?;
-        tracing::info!("Digesting starting now...");
-
-        // return Err(anyhow::anyhow!("Not implemented"));
-
-        let mut db = CacheDB::new(EmptyDB::new());
-        let mut contracts_meta = HashMap::new();
-
-        let current_dir = std::env::current_dir().unwrap();
-        let parent_dir = current_dir.parent().unwrap();
-        let path = std::path::Path::new(parent_dir)
-            .join("contracts")
-            .join("out")
-            .join("Counter.sol")
-            .join("counter.json")
-            .to_str()
-            .unwrap()
-            .to_string();
-
-        tracing::info!("Path: {}", path);
-
-        let counter_contract_meta: ContractMetadata = ContractMetadata {
-            address: addy,
-            mappings: HashMap::new(),
-            artifacts_path: path,
-        };
-
-        contracts_meta.insert("counter".to_string(), counter_contract_meta);
-
-        tracing::info!("Amount of values to digest: {}", contracts_meta.len());
-
-        for contract_data in contracts_meta.values() {
-            tracing::info!("fetching account info for {:?}", contract_data.address);
-            let address = contract_data.address;
-
-            // Load account information
-            let info = ethers_db
-                .basic(address.to_fixed_bytes().into())
-                .map_err(|_| {
-                    anyhow!("Failed to fetch account info with
-                EthersDB."
-                        .to_string(),)
-                })?
-                .ok_or(anyhow!(
-                    "Failed to fetch account info with EthersDB.".to_string(),
-                ))?;
-
-            tracing::info!(
-                "Account info: balance {:?} nonce {:?}",
-                info.clone().balance,
-                info.clone().nonce,
-            );
-
-            db.insert_account_info(address.to_fixed_bytes().into(), info);
-
-            // Load account storage
-            let artifacts = digest::digest_artifacts(contract_data.artifacts_path.as_str())?;
-            let storage_layout = artifacts.storage_layout;
+    /// Reads which network the forker is connected to. The chain id comes
+    /// from the provider rather than the signer, which may be configured for
+    /// another chain.
+    pub async fn fork_target(&self) -> anyhow::Result<ForkTarget> {
+        let client = self.client.clone().ok_or(anyhow!("No client"))?;
+        let provider = client.provider();
+        let chain_id = provider.get_chainid().await?.as_u64();
+        let client_version = provider.client_version().await.unwrap_or_default();
+        Ok(ForkTarget {
+            chain_id,
+            mutable: is_dev_node(&client_version),
+        })
+    }
 
-            // todo: also get label from storage for human readability.
-            digest::create_storage_layout(contract_data, storage_layout, &mut db, ethers_db)?;
+    /// Directory forks of `target` persist the state they fetch in. State of
+    /// a dev node is never cached, since its history can be reset or reverted
+    /// to a snapshot under the same chain id and block numbers.
+    pub fn fork_cache_dir_for(&self, target: &ForkTarget) -> Option<PathBuf> {
+        if target.mutable {
+            return None;
         }
+        self.fork_cache_dir.clone()
+    }
 
-        Ok(db)
+    /// Forks `target` lazily at `block`, or at the forker's block if not
+    /// given, persisting the fetched state in `fork_cache_dir` if set.
+    ///
+    /// Like the `EthersDB` it wraps, the fork fetches state by blocking on the
+    /// client, so it should be created and used from its own thread.
+    pub fn lazy_fork(
+        &self,
+        target: &ForkTarget,
+        block: Option<u64>,
+    ) -> anyhow::Result<LazyFork<Provider<Ws>>> {
+        let client = self.client.clone().ok_or(anyhow!("No client"))?;
+        let provider = Arc::new(client.provider().clone());
+        LazyFork::new(
+            provider,
+            target.chain_id,
+            block.unwrap_or(self.block_number),
+            self.fork_cache_dir_for(target).as_deref(),
+        )
     }
 
     #[tracing::instrument(skip(self))]
@@ -392,7 +393,7 @@ mod tests {
     use arbiter_core::middleware::RevmMiddleware;
     use ethers::{prelude::*, utils::Anvil};
 
-    use super::*;
+    use super::{super::lazy_fork::ForkCache, *};
 
     #[tokio::test]
     async fn test_spawn_ethers_db() -> anyhow::Result<(), anyhow::Error> {
@@ -472,39 +473,65 @@ mod tests {
 
     /// Is anvil in your user path?
     #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
-    async fn test_digest_into_db() -> anyhow::Result<(), anyhow::Error> {
+    async fn test_lazy_fork() -> anyhow::Result<(), anyhow::Error> {
         let anvil = Anvil::default()
             .arg("--gas-limit")
             .arg("20000000")
-            .chain_id(31337_u64)
+            .chain_id(CHAIN_ID)
             .spawn();
-        tracing::info!(
-            "Anvil spawned at endpoint {} with chain {}",
-            anvil.endpoint(),
-            anvil.chain_id()
-        );
         let wallet: LocalWallet = anvil.keys().get(0).unwrap().clone().into();
-        let battler = Forker::connect(Some(anvil.endpoint()), Some(wallet)).await?;
+        let cache_dir = std::env::temp_dir().join("excalibur_lazy_fork_test");
+        let battler = Forker::connect(Some(anvil.endpoint()), Some(wallet))
+            .await?
+            .with_fork_cache_dir(cache_dir.clone());
 
         let client = battler.client.clone().unwrap();
         let counter = Counter::deploy(client.clone(), ())?.send().await?;
         let counter_address = counter.address();
         tracing::info!("Counter address: {}", counter_address.clone());
 
-        // VERY IMPORTANT
-        let handle = std::thread::spawn(move || match battler.digest_config(counter_address) {
-            Ok(cached) => cached,
-            Err(e) => {
-                tracing::error!("Error: {:?}", e);
-                panic!("Error: {:?}", e);
-            }
+        // The counter was never declared to the fork, but its code is fetched
+        // as soon as it is read.
+        let block_number = client.get_block_number().await?.as_u64();
+        let target = battler.fork_target().await?;
+        assert_eq!(target.chain_id, anvil.chain_id());
+        assert!(target.mutable);
+        let handle = std::thread::spawn(move || {
+            let mut fork = battler.lazy_fork(&target, Some(block_number)).unwrap();
+            let info = fork
+                .basic(counter_address.to_fixed_bytes().into())
+                .unwrap()
+                .unwrap();
+            fork.save().unwrap();
+            (info, battler)
         });
-
-        let mut cached = handle.join().unwrap();
-        let loaded = cached
-            .load_account(counter_address.to_fixed_bytes().into())
-            .unwrap();
-        tracing::info!("Loaded: {:?}", loaded);
+        let (info, battler) = handle.join().unwrap();
+        assert!(info.code.is_some_and(|code| !code.bytes().is_empty()));
+
+        // Anvil can be reset under the same chain id, so nothing is cached.
+        let path = ForkCache::path(&cache_dir, target.chain_id, block_number);
+        assert!(!path.exists());
+
+        // A network whose history is final is cached, keyed by its own chain
+        // id, and loaded back by forks of the same block.
+        let target = ForkTarget {
+            mutable: false,
+            ..target
+        };
+        let handle = std::thread::spawn(move || {
+            let mut fork = battler.lazy_fork(&target, Some(block_number)).unwrap();
+            fork.basic(counter_address.to_fixed_bytes().into()).unwrap();
+            fork.save().unwrap();
+            battler
+        });
+        let battler = handle.join().unwrap();
+        let cache = ForkCache::load(&path)?;
+        assert_eq!(cache.chain_id, anvil.chain_id());
+        assert!(cache
+            .accounts
+            .contains_key(&counter_address.to_fixed_bytes().into()));
+        drop(battler);
+        std::fs::remove_dir_all(cache_dir)?;
 
         Ok(())
     }
@@ -532,20 +559,6 @@ mod tests {
         let tx = counter.increment().send().await?.await?;
         tracing::info!("Tx: {:?}", tx);
 
-        let battler = battler.with_block_number(2_u64);
-
-        // VERY IMPORTANT
-        let handle = std::thread::spawn(move || {
-            let cached = battler.digest_config(counter_address).unwrap();
-            (cached, battler)
-        });
-
-        let (mut cached, battler) = handle.join().unwrap();
-        let acc_before = cached
-            .load_account(counter_address.to_fixed_bytes().into())
-            .unwrap();
-        tracing::info!("Loaded before: {:?}", acc_before);
-
         // increment the counter
         let tx = counter.increment().send().await?.await?;
         tracing::info!("Tx: {:?}", tx);
@@ -553,30 +566,24 @@ mod tests {
         let count = counter.number().call().await?;
         tracing::info!("Count: {:?}", count);
 
-        let battler = battler.with_block_number(3_u64);
-
-        // re-digest
-        let handle = std::thread::spawn(move || match battler.digest_config(counter_address) {
-            Ok(cached) => cached,
-            Err(e) => {
-                tracing::error!("Error: {:?}", e);
-                panic!("Error: {:?}", e);
-            }
+        // Forks are pinned to their block, so each sees the count at that block.
+        let target = battler.fork_target().await?;
+        let handle = std::thread::spawn(move || {
+            [2_u64, 3_u64].map(|block| {
+                battler
+                    .lazy_fork(&target, Some(block))
+                    .unwrap()
+                    .storage(
+                        counter_address.to_fixed_bytes().into(),
+                        revm::primitives::U256::ZERO,
+                    )
+                    .unwrap()
+            })
         });
 
-        let mut cached = handle.join().unwrap();
-
-        let acc_after = cached
-            .load_account(counter_address.to_fixed_bytes().into())
-            .unwrap();
-
-        tracing::info!("Loaded after: {:?}", acc_after);
-
-        let count_from_storage = acc_after
-            .storage
-            .get(&revm::primitives::U256::ZERO)
-            .unwrap();
-        assert_eq!(*count_from_storage, revm::primitives::U256::from(2_u64));
+        let [count_before, count_after] = handle.join().unwrap();
+        assert_eq!(count_before, revm::primitives::U256::from(1_u64));
+        assert_eq!(count_after, revm::primitives::U256::from(2_u64));
 
         Ok(())
     }
@@ -29,13 +29,13 @@ pub(crate) struct StorageLayout {
 #[derive(Debug, Deserialize, Serialize)]
 pub(crate) struct StorageItem {
     #[serde(rename = "astId")]
-    ast_id: usize,
-    contract: String,
-    label: String,
-    offset: usize,
-    slot: String,
+    pub(crate) ast_id: usize,
+    pub(crate) contract: String,
+    pub(crate) label: String,
+    pub(crate) offset: usize,
+    pub(crate) slot: String,
     #[serde(rename = "type")]
-    type_: String,
+    pub(crate) type_: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
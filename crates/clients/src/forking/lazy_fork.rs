@@ -0,0 +1,240 @@
+//! A revm [`Database`] that forks a live network lazily.
+//!
+//! Accounts, code and storage are fetched from the RPC the first time they are
+//! read, at the block the fork is pinned to, and cached. Since nothing has to
+//! be declared up front, any transaction against any contract simulates with
+//! the state it would see on the network.
+//!
+//! State at a block never changes, so the cache can be saved to disk and
+//! reused by any later fork of the same chain and block.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use ethers::providers::Middleware;
+use revm::{
+    db::{CacheDB, EmptyDB, EthersDB},
+    primitives::{AccountInfo, Address, Bytecode, B256, U256},
+    Database,
+};
+use serde::{Deserialize, Serialize};
+
+/// State fetched by a [`LazyFork`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForkCache {
+    pub chain_id: u64,
+    pub block_number: u64,
+    /// Accounts, with their code.
+    pub accounts: HashMap<Address, AccountInfo>,
+    pub storage: HashMap<Address, HashMap<U256, U256>>,
+    pub block_hashes: HashMap<U256, B256>,
+}
+
+impl ForkCache {
+    /// Path of the cache of `chain_id` at `block_number` in `dir`.
+    pub fn path(dir: impl AsRef<Path>, chain_id: u64, block_number: u64) -> PathBuf {
+        dir.as_ref()
+            .join(chain_id.to_string())
+            .join(format!("{}.json", block_number))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let data = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Copies the cached state into a [`CacheDB`], to load into environments
+    /// that can't fetch state themselves.
+    pub fn to_cache_db(&self) -> CacheDB<EmptyDB> {
+        let mut db = CacheDB::new(EmptyDB::default());
+        for (address, info) in &self.accounts {
+            db.insert_account_info(*address, info.clone());
+        }
+        for (address, storage) in &self.storage {
+            for (slot, value) in storage {
+                // Inserting into a `CacheDB<EmptyDB>` can't fail.
+                let _ = db.insert_account_storage(*address, *slot, *value);
+            }
+        }
+        for (number, hash) in &self.block_hashes {
+            db.block_hashes.insert(*number, *hash);
+        }
+        db
+    }
+}
+
+/// Database fetching state from an RPC at a pinned block on demand.
+///
+/// Fetching blocks on the RPC, so the fork should be used from its own thread
+/// rather than from within an async task, like the other `EthersDB` users of
+/// the [`super::client_forking::Forker`].
+pub struct LazyFork<M: Middleware> {
+    remote: EthersDB<M>,
+    cache: ForkCache,
+    /// Where the cache is saved, if it is persisted.
+    cache_path: Option<PathBuf>,
+}
+
+impl<M: Middleware> LazyFork<M> {
+    /// Forks the network of `client` at `block_number`. If `cache_dir` is
+    /// given, the state cached by previous forks of the same block is loaded
+    /// from it.
+    pub fn new(
+        client: Arc<M>,
+        chain_id: u64,
+        block_number: u64,
+        cache_dir: Option<&Path>,
+    ) -> anyhow::Result<Self> {
+        let remote = EthersDB::new(client, Some(block_number.into()))
+            .ok_or_else(|| anyhow::anyhow!("Failed to connect the fork to the network"))?;
+
+        let cache_path = cache_dir.map(|dir| ForkCache::path(dir, chain_id, block_number));
+        let cache = match &cache_path {
+            Some(path) if path.exists() => ForkCache::load(path)?,
+            _ => ForkCache {
+                chain_id,
+                block_number,
+                ..Default::default()
+            },
+        };
+        if cache.chain_id != chain_id || cache.block_number != block_number {
+            anyhow::bail!(
+                "Fork cache is for block {} of chain {}",
+                cache.block_number,
+                cache.chain_id
+            );
+        }
+
+        Ok(Self {
+            remote,
+            cache,
+            cache_path,
+        })
+    }
+
+    pub fn cache(&self) -> &ForkCache {
+        &self.cache
+    }
+
+    /// Saves the cache, if it is persisted.
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(path) = &self.cache_path {
+            self.cache.save(path)?;
+        }
+        Ok(())
+    }
+}
+
+impl<M: Middleware> Database for LazyFork<M> {
+    type Error = anyhow::Error;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        if let Some(info) = self.cache.accounts.get(&address) {
+            return Ok(Some(info.clone()));
+        }
+        let info = self
+            .remote
+            .basic(address)
+            .map_err(|_| anyhow::anyhow!("Failed to fetch account {}", address))?
+            .unwrap_or_default();
+        self.cache.accounts.insert(address, info.clone());
+        Ok(Some(info))
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        // Code is fetched along with its account, so it is always cached by the
+        // time it is looked up by hash.
+        self.cache
+            .accounts
+            .values()
+            .find(|info| info.code_hash == code_hash)
+            .and_then(|info| info.code.clone())
+            .ok_or_else(|| anyhow::anyhow!("No code with hash {}", code_hash))
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        if let Some(value) = self
+            .cache
+            .storage
+            .get(&address)
+            .and_then(|storage| storage.get(&index))
+        {
+            return Ok(*value);
+        }
+        let value = self
+            .remote
+            .storage(address, index)
+            .map_err(|_| anyhow::anyhow!("Failed to fetch slot {} of {}", index, address))?;
+        self.cache
+            .storage
+            .entry(address)
+            .or_default()
+            .insert(index, value);
+        Ok(value)
+    }
+
+    fn block_hash(&mut self, number: U256) -> Result<B256, Self::Error> {
+        if let Some(hash) = self.cache.block_hashes.get(&number) {
+            return Ok(*hash);
+        }
+        let hash = self
+            .remote
+            .block_hash(number)
+            .map_err(|_| anyhow::anyhow!("Failed to fetch hash of block {}", number))?;
+        self.cache.block_hashes.insert(number, hash);
+        Ok(hash)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cache_persistence() {
+        let address = Address::repeat_byte(0xab);
+        let mut cache = ForkCache {
+            chain_id: 31337,
+            block_number: 12,
+            ..Default::default()
+        };
+        cache.accounts.insert(
+            address,
+            AccountInfo::new(
+                U256::from(100),
+                1,
+                Bytecode::new_raw(vec![0x60, 0x00].into()).hash_slow(),
+                Bytecode::new_raw(vec![0x60, 0x00].into()),
+            ),
+        );
+        cache
+            .storage
+            .entry(address)
+            .or_default()
+            .insert(U256::from(1), U256::from(42));
+
+        let dir = std::env::temp_dir().join("excalibur_fork_cache_test");
+        let path = ForkCache::path(&dir, 31337, 12);
+        assert!(path.ends_with("31337/12.json"));
+        cache.save(&path).unwrap();
+        assert_eq!(ForkCache::load(&path).unwrap(), cache);
+
+        let mut db = cache.to_cache_db();
+        assert_eq!(db.storage(address, U256::from(1)).unwrap(), U256::from(42));
+        assert_eq!(db.basic(address).unwrap().unwrap().nonce, 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,2 +1,6 @@
 pub mod client_forking;
-mod digest;
+pub(crate) mod digest;
+pub mod lazy_fork;
+pub mod overlay;
+pub mod state_diff;
+pub mod trace;
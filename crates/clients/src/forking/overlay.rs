@@ -0,0 +1,100 @@
+//! Keeps the changes of simulated transactions on top of a database, so a
+//! sequence of transactions can run each on the state left by the previous one
+//! without writing to the database underneath. For a [`super::lazy_fork`], that
+//! keeps its cache a faithful copy of the network.
+
+use std::collections::{HashMap, HashSet};
+
+use revm::{
+    primitives::{AccountInfo, Address, Bytecode, State, B256, U256},
+    Database,
+};
+
+pub struct Overlay<DB> {
+    base: DB,
+    accounts: HashMap<Address, AccountInfo>,
+    storage: HashMap<Address, HashMap<U256, U256>>,
+    /// Accounts created or destroyed, whose storage in `base` is gone.
+    cleared: HashSet<Address>,
+}
+
+impl<DB: Database> Overlay<DB> {
+    pub fn new(base: DB) -> Self {
+        Self {
+            base,
+            accounts: HashMap::new(),
+            storage: HashMap::new(),
+            cleared: HashSet::new(),
+        }
+    }
+
+    /// Applies the `state` left by a transaction.
+    pub fn commit(&mut self, state: &State) {
+        for (address, account) in state {
+            if !account.is_touched() {
+                continue;
+            }
+            if account.is_selfdestructed() || account.is_created() {
+                self.storage.remove(address);
+                self.cleared.insert(*address);
+            }
+            if account.is_selfdestructed() {
+                self.accounts.insert(*address, AccountInfo::default());
+                continue;
+            }
+
+            self.accounts.insert(*address, account.info.clone());
+            let storage = self.storage.entry(*address).or_default();
+            for (slot, value) in &account.storage {
+                if value.is_changed() {
+                    storage.insert(*slot, value.present_value());
+                }
+            }
+        }
+    }
+
+    pub fn into_inner(self) -> DB {
+        self.base
+    }
+}
+
+impl<DB: Database> Database for Overlay<DB> {
+    type Error = DB::Error;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        match self.accounts.get(&address) {
+            Some(info) => Ok(Some(info.clone())),
+            None => self.base.basic(address),
+        }
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        let code = self
+            .accounts
+            .values()
+            .find(|info| info.code_hash == code_hash)
+            .and_then(|info| info.code.clone());
+        match code {
+            Some(code) => Ok(code),
+            None => self.base.code_by_hash(code_hash),
+        }
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        if let Some(value) = self
+            .storage
+            .get(&address)
+            .and_then(|storage| storage.get(&index))
+        {
+            return Ok(*value);
+        }
+        if self.cleared.contains(&address) {
+            return Ok(U256::ZERO);
+        }
+        self.base.storage(address, index)
+    }
+
+    fn block_hash(&mut self, number: U256) -> Result<B256, Self::Error> {
+        self.base.block_hash(number)
+    }
+}
@@ -0,0 +1,561 @@
+//! Records every account and storage slot a transaction touches, and decodes
+//! the storage of the target contract against its `storageLayout`.
+//!
+//! The transaction is run on a plain revm instance over the forked database,
+//! see [`super::trace`]. The state revm returns holds every account and slot
+//! that was loaded during execution along with its original value, so it
+//! doubles as the access list of the transaction.
+//!
+//! Slots of mappings are hashes of their keys, so they can only be named by
+//! guessing the keys. The guesses are the words of the calldata and the
+//! addresses touched by the transaction, which covers balances and allowances
+//! keyed by the caller or by an argument, including nested mappings.
+
+use std::{collections::HashMap, fmt};
+
+use ethers::{
+    types::{Address, Bytes, H256, I256, U256},
+    utils::{hex, keccak256},
+};
+use revm::{
+    primitives::{ExecutionResult, State, U256 as StorageValue},
+    Database,
+};
+
+use super::digest::{StorageLayout, StorageType};
+
+/// Deepest nesting of mappings that is decoded.
+const MAX_MAPPING_DEPTH: usize = 3;
+
+/// A storage slot read or written by a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotDiff {
+    pub slot: U256,
+    /// Name of the variables in the slot, if the layout of the account is known
+    /// and the slot could be matched to it.
+    pub label: Option<String>,
+    pub before: U256,
+    pub after: U256,
+    pub decoded_before: Option<String>,
+    pub decoded_after: Option<String>,
+}
+
+impl SlotDiff {
+    pub fn is_changed(&self) -> bool {
+        self.before != self.after
+    }
+}
+
+/// An account touched by a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountDiff {
+    pub address: Address,
+    pub balance_before: U256,
+    pub balance_after: U256,
+    pub nonce_before: u64,
+    pub nonce_after: u64,
+    /// Slots read or written, ordered by slot.
+    pub slots: Vec<SlotDiff>,
+}
+
+impl AccountDiff {
+    pub fn is_changed(&self) -> bool {
+        self.balance_before != self.balance_after
+            || self.nonce_before != self.nonce_after
+            || self.slots.iter().any(SlotDiff::is_changed)
+    }
+}
+
+/// Every account and slot touched by a simulated transaction.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    pub success: bool,
+    /// Gas used, net of the refund.
+    pub gas_used: u64,
+    /// Gas refunded at the end of the transaction, which it needed until then.
+    pub gas_refunded: u64,
+    /// Accounts touched, ordered by address.
+    pub accounts: Vec<AccountDiff>,
+}
+
+impl StateDiff {
+    /// Builds the diff of a call to `target` with `data`, from the `state`
+    /// revm returned after running it on `db` without committing it. The
+    /// storage of `target` is decoded with `layout`, if given.
+    pub(crate) fn new<DB: Database>(
+        db: &mut DB,
+        target: Address,
+        data: &Bytes,
+        result: &ExecutionResult,
+        state: &State,
+        layout: Option<&StorageLayout>,
+    ) -> Self {
+        // Anything the transaction could have hashed into a mapping slot.
+        let mut keys = data
+            .get(4..)
+            .unwrap_or_default()
+            .chunks_exact(32)
+            .map(|word| <[u8; 32]>::try_from(word).unwrap())
+            .collect::<Vec<_>>();
+        keys.extend(
+            state
+                .keys()
+                .map(|address| H256::from(Address::from_slice(address.as_slice())).0),
+        );
+        keys.sort();
+        keys.dedup();
+
+        let decoder = layout.map(|layout| SlotDecoder::new(layout, &keys));
+
+        let mut accounts = Vec::new();
+        for (address, account) in state {
+            // The call wasn't committed, so the database still holds the
+            // account as it was before.
+            let before = db.basic(*address).ok().flatten().unwrap_or_default();
+            let address = Address::from_slice(address.as_slice());
+
+            let mut slots = account
+                .storage
+                .iter()
+                .map(|(slot, value)| {
+                    let slot = to_u256(*slot);
+                    let before = to_u256(value.original_value());
+                    let after = to_u256(value.present_value());
+                    let decoder = decoder.as_ref().filter(|_| address == target);
+                    SlotDiff {
+                        slot,
+                        label: decoder.and_then(|decoder| decoder.label(slot)),
+                        before,
+                        after,
+                        decoded_before: decoder.and_then(|decoder| decoder.decode(slot, before)),
+                        decoded_after: decoder.and_then(|decoder| decoder.decode(slot, after)),
+                    }
+                })
+                .collect::<Vec<_>>();
+            slots.sort_by_key(|slot| slot.slot);
+
+            let diff = AccountDiff {
+                address,
+                balance_before: to_u256(before.balance),
+                balance_after: to_u256(account.info.balance),
+                nonce_before: before.nonce,
+                nonce_after: account.info.nonce,
+                slots,
+            };
+            // Skip accounts that were only loaded, such as precompiles and the
+            // coinbase.
+            if address == target || !diff.slots.is_empty() || diff.is_changed() {
+                accounts.push(diff);
+            }
+        }
+        accounts.sort_by_key(|account| account.address);
+
+        let gas_refunded = match result {
+            ExecutionResult::Success { gas_refunded, .. } => *gas_refunded,
+            _ => 0,
+        };
+
+        Self {
+            success: result.is_success(),
+            gas_used: result.gas_used(),
+            gas_refunded,
+            accounts,
+        }
+    }
+
+    /// Combines the diffs of calls run one after the other into the diff of
+    /// the whole sequence, from the state before the first to the state after
+    /// the last.
+    pub fn combine<'a>(diffs: impl IntoIterator<Item = &'a StateDiff>) -> Self {
+        let mut success = true;
+        let mut gas_used = 0;
+        let mut gas_refunded = 0;
+        let mut accounts: Vec<AccountDiff> = Vec::new();
+        for diff in diffs {
+            success &= diff.success;
+            gas_used += diff.gas_used;
+            gas_refunded += diff.gas_refunded;
+            for account in &diff.accounts {
+                let Some(existing) = accounts.iter_mut().find(|a| a.address == account.address)
+                else {
+                    accounts.push(account.clone());
+                    continue;
+                };
+                existing.balance_after = account.balance_after;
+                existing.nonce_after = account.nonce_after;
+                for slot in &account.slots {
+                    match existing.slots.iter_mut().find(|s| s.slot == slot.slot) {
+                        Some(existing) => {
+                            existing.after = slot.after;
+                            existing.decoded_after = slot.decoded_after.clone();
+                            if existing.label.is_none() {
+                                existing.label = slot.label.clone();
+                            }
+                        }
+                        None => existing.slots.push(slot.clone()),
+                    }
+                }
+                existing.slots.sort_by_key(|slot| slot.slot);
+            }
+        }
+        accounts.sort_by_key(|account| account.address);
+
+        Self {
+            success,
+            gas_used,
+            gas_refunded,
+            accounts,
+        }
+    }
+
+    /// Gas the transaction needs to run, before the refund is paid back at
+    /// its end.
+    pub fn gas_needed(&self) -> u64 {
+        self.gas_used + self.gas_refunded
+    }
+
+    /// Accounts whose balance, nonce or storage changed.
+    pub fn changed(&self) -> impl Iterator<Item = &AccountDiff> {
+        self.accounts.iter().filter(|account| account.is_changed())
+    }
+}
+
+impl fmt::Display for StateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = if self.success {
+            "succeeded"
+        } else {
+            "reverted"
+        };
+        writeln!(f, "Transaction {}, {} gas used", status, self.gas_used)?;
+        for account in &self.accounts {
+            writeln!(f, "{:?}", account.address)?;
+            if account.balance_before != account.balance_after {
+                writeln!(
+                    f,
+                    "  balance: {} -> {}",
+                    account.balance_before, account.balance_after
+                )?;
+            }
+            if account.nonce_before != account.nonce_after {
+                writeln!(
+                    f,
+                    "  nonce: {} -> {}",
+                    account.nonce_before, account.nonce_after
+                )?;
+            }
+            for slot in &account.slots {
+                let label = slot
+                    .label
+                    .clone()
+                    .unwrap_or_else(|| format!("slot {:#x}", slot.slot));
+                let before = slot
+                    .decoded_before
+                    .clone()
+                    .unwrap_or_else(|| format!("{:#x}", slot.before));
+                let after = slot
+                    .decoded_after
+                    .clone()
+                    .unwrap_or_else(|| format!("{:#x}", slot.after));
+                if slot.is_changed() {
+                    writeln!(f, "  {}: {} -> {}", label, before, after)?;
+                } else {
+                    writeln!(f, "  {}: {} (read)", label, before)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A variable stored in a slot. Variables smaller than a word are packed, so a
+/// slot can hold several of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SlotVariable {
+    label: String,
+    type_label: String,
+    /// Offset in bytes from the right of the slot.
+    offset: usize,
+    size: usize,
+}
+
+/// Names the slots of a contract from its storage layout.
+#[derive(Debug, Clone, Default)]
+pub struct SlotDecoder {
+    variables: HashMap<U256, Vec<SlotVariable>>,
+}
+
+impl SlotDecoder {
+    /// Maps the variables of `layout` to their slots, computing the slots of
+    /// mapping entries for each of `keys`.
+    pub(crate) fn new(layout: &StorageLayout, keys: &[[u8; 32]]) -> Self {
+        let mut decoder = Self::default();
+        for item in &layout.storage {
+            let Ok(slot) = U256::from_dec_str(&item.slot) else {
+                continue;
+            };
+            decoder.insert(
+                layout,
+                keys,
+                &item.type_,
+                slot,
+                item.label.clone(),
+                item.offset,
+                0,
+            );
+        }
+        decoder
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn insert(
+        &mut self,
+        layout: &StorageLayout,
+        keys: &[[u8; 32]],
+        type_id: &str,
+        slot: U256,
+        label: String,
+        offset: usize,
+        depth: usize,
+    ) {
+        match layout.types.get(type_id) {
+            Some(StorageType::Mapping { key, value, .. }) => {
+                if depth == MAX_MAPPING_DEPTH {
+                    return;
+                }
+                let (key_encoding, key_label) = match layout.types.get(key) {
+                    Some(StorageType::Simple {
+                        encoding, label, ..
+                    }) => (encoding.as_str(), label.as_str()),
+                    _ => return,
+                };
+                // String and bytes keys are hashed before use, so their
+                // slots can't be computed from a word.
+                if key_encoding != "inplace" {
+                    return;
+                }
+
+                let mut base = [0u8; 32];
+                slot.to_big_endian(&mut base);
+                for key in keys {
+                    if !key_fits(key, key_label) {
+                        continue;
+                    }
+                    let entry = U256::from(keccak256([key.as_slice(), &base].concat()));
+                    let entry_label = format!("{}[{}]", label, format_value(*key, key_label, 32));
+                    self.insert(layout, keys, value, entry, entry_label, 0, depth + 1);
+                }
+            }
+            Some(StorageType::Simple {
+                encoding,
+                label: type_label,
+                number_of_bytes,
+            }) => {
+                let size = number_of_bytes.parse::<usize>().unwrap_or(32);
+                // Dynamic arrays and long strings only keep their length in the
+                // slot itself.
+                if encoding != "inplace" || size <= 32 {
+                    self.variables.entry(slot).or_default().push(SlotVariable {
+                        label,
+                        type_label: type_label.clone(),
+                        offset,
+                        size: size.min(32),
+                    });
+                    return;
+                }
+                // Structs and static arrays span several words, whose members
+                // are not part of the layout.
+                for word in 0..size.div_ceil(32) {
+                    self.variables
+                        .entry(slot + word)
+                        .or_default()
+                        .push(SlotVariable {
+                            label: format!("{} (word {})", label, word),
+                            type_label: "bytes32".to_string(),
+                            offset: 0,
+                            size: 32,
+                        });
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Names of the variables stored in `slot`.
+    pub fn label(&self, slot: U256) -> Option<String> {
+        let variables = self.variables.get(&slot)?;
+        Some(
+            variables
+                .iter()
+                .map(|variable| variable.label.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+        )
+    }
+
+    /// Decodes `value` of `slot` into the values of its variables.
+    pub fn decode(&self, slot: U256, value: U256) -> Option<String> {
+        let variables = self.variables.get(&slot)?;
+        let mut word = [0u8; 32];
+        value.to_big_endian(&mut word);
+
+        if let [variable] = variables.as_slice() {
+            if variable.offset == 0 && variable.size == 32 {
+                return Some(format_value(word, &variable.type_label, 32));
+            }
+        }
+
+        Some(
+            variables
+                .iter()
+                .map(|variable| {
+                    let end = 32 - variable.offset.min(32);
+                    let start = end.saturating_sub(variable.size);
+                    let mut packed = [0u8; 32];
+                    packed[32 - (end - start)..].copy_from_slice(&word[start..end]);
+                    format!(
+                        "{} = {}",
+                        variable.label,
+                        format_value(packed, &variable.type_label, variable.size)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+        )
+    }
+}
+
+/// Whether `key` is a valid encoding of a key of type `type_label`, to avoid
+/// naming slots with keys that could never have produced them.
+fn key_fits(key: &[u8; 32], type_label: &str) -> bool {
+    if type_label.starts_with("address") || type_label.starts_with("contract") {
+        key[..12].iter().all(|byte| *byte == 0)
+    } else if type_label == "bool" {
+        key[..31].iter().all(|byte| *byte == 0) && key[31] <= 1
+    } else {
+        true
+    }
+}
+
+/// Formats a right-aligned value of `size` bytes as its Solidity type.
+fn format_value(word: [u8; 32], type_label: &str, size: usize) -> String {
+    let value = U256::from_big_endian(&word);
+    if type_label == "bool" {
+        (!value.is_zero()).to_string()
+    } else if type_label.starts_with("address") || type_label.starts_with("contract") {
+        format!("{:?}", Address::from_slice(&word[12..]))
+    } else if type_label.starts_with("uint") || type_label.starts_with("enum") {
+        value.to_string()
+    } else if type_label.starts_with("int") {
+        // Sign extend packed integers before reading them as 256 bits.
+        let mut word = word;
+        if size < 32 && word[32 - size] & 0x80 != 0 {
+            word[..32 - size].fill(0xff);
+        }
+        I256::from_raw(U256::from_big_endian(&word)).to_string()
+    } else {
+        format!("0x{}", hex::encode(&word[32 - size.min(32)..]))
+    }
+}
+
+pub(super) fn to_u256(value: StorageValue) -> U256 {
+    U256(value.into_limbs())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LAYOUT: &str = r#"{
+        "storage": [
+            { "astId": 1, "contract": "Token", "label": "owner", "offset": 0, "slot": "0", "type": "t_address" },
+            { "astId": 2, "contract": "Token", "label": "paused", "offset": 20, "slot": "0", "type": "t_bool" },
+            { "astId": 3, "contract": "Token", "label": "balanceOf", "offset": 0, "slot": "1", "type": "t_mapping(t_address,t_uint256)" },
+            { "astId": 4, "contract": "Token", "label": "allowance", "offset": 0, "slot": "2", "type": "t_mapping(t_address,t_mapping(t_address,t_uint256))" }
+        ],
+        "types": {
+            "t_address": { "encoding": "inplace", "label": "address", "numberOfBytes": "20" },
+            "t_bool": { "encoding": "inplace", "label": "bool", "numberOfBytes": "1" },
+            "t_uint256": { "encoding": "inplace", "label": "uint256", "numberOfBytes": "32" },
+            "t_mapping(t_address,t_uint256)": { "encoding": "mapping", "key": "t_address", "value": "t_uint256", "label": "mapping(address => uint256)", "numberOfBytes": "32" },
+            "t_mapping(t_address,t_mapping(t_address,t_uint256))": { "encoding": "mapping", "key": "t_address", "value": "t_mapping(t_address,t_uint256)", "label": "mapping(address => mapping(address => uint256))", "numberOfBytes": "32" }
+        }
+    }"#;
+
+    fn mapping_slot(key: Address, slot: U256) -> U256 {
+        let mut base = [0u8; 32];
+        slot.to_big_endian(&mut base);
+        U256::from(keccak256([H256::from(key).as_bytes(), &base].concat()))
+    }
+
+    #[test]
+    fn decodes_layout() {
+        let layout: StorageLayout = serde_json::from_str(LAYOUT).unwrap();
+        let (owner, spender) = (Address::repeat_byte(0x11), Address::repeat_byte(0x22));
+        let decoder = SlotDecoder::new(&layout, &[H256::from(owner).0, H256::from(spender).0]);
+
+        // Packed variables.
+        let mut word = [0u8; 32];
+        word[11] = 1;
+        word[12..].copy_from_slice(owner.as_bytes());
+        assert_eq!(decoder.label(U256::zero()).unwrap(), "owner, paused");
+        assert_eq!(
+            decoder.decode(U256::zero(), U256::from(word)).unwrap(),
+            format!("owner = {:?}, paused = true", owner)
+        );
+
+        let balance = mapping_slot(owner, U256::one());
+        assert_eq!(
+            decoder.label(balance).unwrap(),
+            format!("balanceOf[{:?}]", owner)
+        );
+        assert_eq!(decoder.decode(balance, U256::from(42)).unwrap(), "42");
+
+        let allowance = mapping_slot(spender, mapping_slot(owner, U256::from(2)));
+        assert_eq!(
+            decoder.label(allowance).unwrap(),
+            format!("allowance[{:?}][{:?}]", owner, spender)
+        );
+        assert!(decoder.label(U256::from(3)).is_none());
+    }
+
+    #[test]
+    fn combines_diffs() {
+        let account = Address::repeat_byte(0x11);
+        let slot = |slot: u64, before: u64, after: u64| SlotDiff {
+            slot: slot.into(),
+            label: None,
+            before: before.into(),
+            after: after.into(),
+            decoded_before: None,
+            decoded_after: None,
+        };
+        let diff = |nonce: u64, slots: Vec<SlotDiff>| StateDiff {
+            success: true,
+            gas_used: 21_000,
+            gas_refunded: 4_800,
+            accounts: vec![AccountDiff {
+                address: account,
+                balance_before: U256::zero(),
+                balance_after: U256::zero(),
+                nonce_before: nonce,
+                nonce_after: nonce + 1,
+                slots,
+            }],
+        };
+
+        let first = diff(0, vec![slot(1, 0, 5), slot(2, 7, 7)]);
+        let second = diff(1, vec![slot(1, 5, 9), slot(0, 3, 4)]);
+        let combined = StateDiff::combine([&first, &second]);
+        assert!(combined.success);
+        assert_eq!(combined.gas_used, 42_000);
+        assert_eq!(combined.gas_needed(), 51_600);
+
+        let account = &combined.accounts[0];
+        assert_eq!((account.nonce_before, account.nonce_after), (0, 2));
+        assert_eq!(
+            account.slots,
+            vec![slot(0, 3, 4), slot(1, 0, 9), slot(2, 7, 7)]
+        );
+    }
+}
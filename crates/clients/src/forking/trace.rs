@@ -0,0 +1,508 @@
+//! Traces a transaction on a forked database, recording its tree of calls with
+//! their events, gas and revert reasons.
+//!
+//! The trace is decoded with the ABI of the target contract where it can be:
+//! calls and events of the target are named with their arguments, and reverts
+//! are decoded as `Error(string)`, `Panic(uint256)` or any custom error of the
+//! ABI. Frames of other contracts are kept raw.
+
+use std::fmt;
+
+use ethers::{
+    abi::{Abi, RawLog, Token},
+    types::{Address, Block, Bytes, H256, U256},
+    utils::hex,
+};
+use revm::{
+    interpreter::{CallInputs, CallScheme, Gas, InstructionResult},
+    primitives::{
+        Address as RevmAddress, BlockEnv, Bytes as RevmBytes, ResultAndState, State, TransactTo,
+        B256, U256 as StorageValue,
+    },
+    Database, EVMData, Inspector, EVM,
+};
+
+use super::{
+    digest::StorageLayout,
+    state_diff::{to_u256, StateDiff},
+};
+use crate::arguments::format_token;
+
+/// Gas limit of the traced transaction.
+const TRACE_GAS_LIMIT: u64 = 30_000_000;
+/// Selector of `Error(string)`.
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+/// Selector of `Panic(uint256)`.
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CallKind {
+    #[default]
+    Call,
+    StaticCall,
+    DelegateCall,
+    CallCode,
+}
+
+impl From<CallScheme> for CallKind {
+    fn from(scheme: CallScheme) -> Self {
+        match scheme {
+            CallScheme::Call => Self::Call,
+            CallScheme::StaticCall => Self::StaticCall,
+            CallScheme::DelegateCall => Self::DelegateCall,
+            CallScheme::CallCode => Self::CallCode,
+        }
+    }
+}
+
+/// An event emitted during a call.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TraceLog {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    pub data: Bytes,
+    /// Event with its arguments, if it is an event of the target.
+    pub decoded: Option<String>,
+}
+
+/// A call and the calls it made, in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CallFrame {
+    pub kind: CallKind,
+    pub from: Address,
+    pub to: Address,
+    /// Account whose code ran, which differs from `to` in delegate calls.
+    pub code_address: Address,
+    pub value: U256,
+    pub input: Bytes,
+    pub output: Bytes,
+    pub gas_limit: u64,
+    pub gas_used: u64,
+    pub success: bool,
+    /// Function with its arguments, if it is a function of the target.
+    pub function: Option<String>,
+    /// Decoded reason of a revert.
+    pub revert_reason: Option<String>,
+    pub logs: Vec<TraceLog>,
+    pub calls: Vec<CallFrame>,
+}
+
+impl CallFrame {
+    /// The reason the transaction reverted with, if it did. This is the reason
+    /// of the deepest frame that reverted with the same output as this one,
+    /// which is where the revert was raised.
+    pub fn revert_reason(&self) -> Option<&str> {
+        if self.success {
+            return None;
+        }
+        self.calls
+            .iter()
+            .filter(|call| !call.success && call.output == self.output)
+            .find_map(|call| call.revert_reason())
+            .or(self.revert_reason.as_deref())
+    }
+
+    /// Decodes the calls, events and reverts of the frame and its calls with
+    /// the ABI of `target`.
+    fn decode(&mut self, target: Address, abi: Option<&Abi>) {
+        let target_abi = abi.filter(|_| self.code_address == target);
+        if let Some(abi) = target_abi {
+            self.function = decode_function(abi, &self.input);
+            for log in &mut self.logs {
+                log.decoded = decode_event(abi, log);
+            }
+        }
+        if !self.success {
+            self.revert_reason = Some(decode_revert(abi, &self.output));
+        }
+        for call in &mut self.calls {
+            call.decode(target, abi);
+        }
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let indent = "  ".repeat(depth);
+        let call = self
+            .function
+            .clone()
+            .unwrap_or_else(|| match self.input.len() {
+                0 => "fallback()".to_string(),
+                _ => format!("0x{}", hex::encode(&self.input)),
+            });
+        let kind = match self.kind {
+            CallKind::Call => "",
+            CallKind::StaticCall => "[static] ",
+            CallKind::DelegateCall => "[delegate] ",
+            CallKind::CallCode => "[callcode] ",
+        };
+        write!(f, "{}{}{:?}::{}", indent, kind, self.to, call)?;
+        if !self.value.is_zero() {
+            write!(f, " {{value: {}}}", self.value)?;
+        }
+        writeln!(f, " [{} gas]", self.gas_used)?;
+
+        for log in &self.logs {
+            let event = log
+                .decoded
+                .clone()
+                .unwrap_or_else(|| format!("{:?} 0x{}", log.topics, hex::encode(&log.data)));
+            writeln!(f, "{}  emit {}", indent, event)?;
+        }
+        for call in &self.calls {
+            call.fmt_indented(f, depth + 1)?;
+        }
+        match &self.revert_reason {
+            Some(reason) => writeln!(f, "{}  reverted: {}", indent, reason),
+            None if self.output.is_empty() => Ok(()),
+            None => writeln!(f, "{}  returned: 0x{}", indent, hex::encode(&self.output)),
+        }
+    }
+}
+
+impl fmt::Display for CallFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+/// Builds the tree of calls as revm runs them.
+#[derive(Debug, Default)]
+struct CallTracer {
+    /// Frames that have started but not ended, outermost first.
+    stack: Vec<CallFrame>,
+    root: Option<CallFrame>,
+}
+
+impl<DB: Database> Inspector<DB> for CallTracer {
+    fn call(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        inputs: &mut CallInputs,
+    ) -> (InstructionResult, Gas, RevmBytes) {
+        self.stack.push(CallFrame {
+            kind: inputs.context.scheme.into(),
+            from: to_address(inputs.context.caller),
+            to: to_address(inputs.context.address),
+            code_address: to_address(inputs.context.code_address),
+            value: to_u256(inputs.transfer.value),
+            input: inputs.input.to_vec().into(),
+            gas_limit: inputs.gas_limit,
+            ..Default::default()
+        });
+        (InstructionResult::Continue, Gas::new(0), RevmBytes::new())
+    }
+
+    fn call_end(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        inputs: &CallInputs,
+        remaining_gas: Gas,
+        ret: InstructionResult,
+        out: RevmBytes,
+    ) -> (InstructionResult, Gas, RevmBytes) {
+        if let Some(mut frame) = self.stack.pop() {
+            frame.output = out.to_vec().into();
+            frame.gas_used = inputs.gas_limit.saturating_sub(remaining_gas.remaining());
+            frame.success = ret.is_ok();
+            match self.stack.last_mut() {
+                Some(parent) => parent.calls.push(frame),
+                None => self.root = Some(frame),
+            }
+        }
+        (ret, remaining_gas, out)
+    }
+
+    fn log(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        address: &RevmAddress,
+        topics: &[B256],
+        data: &RevmBytes,
+    ) {
+        if let Some(frame) = self.stack.last_mut() {
+            frame.logs.push(TraceLog {
+                address: to_address(*address),
+                topics: topics
+                    .iter()
+                    .map(|topic| H256::from_slice(topic.as_slice()))
+                    .collect(),
+                data: data.to_vec().into(),
+                decoded: None,
+            });
+        }
+    }
+}
+
+/// Block a call is traced in, so the call sees the same block number,
+/// timestamp, base fee, coinbase and chain id as on the network.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TraceBlock {
+    pub env: BlockEnv,
+    pub chain_id: u64,
+}
+
+impl TraceBlock {
+    pub(crate) fn new<T>(block: &Block<T>, chain_id: u64) -> Self {
+        let defaults = BlockEnv::default();
+        let env = BlockEnv {
+            number: block.number.map_or(defaults.number, |number| {
+                StorageValue::from(number.as_u64())
+            }),
+            coinbase: block
+                .author
+                .map_or(defaults.coinbase, |author| author.to_fixed_bytes().into()),
+            timestamp: StorageValue::from_limbs(block.timestamp.0),
+            gas_limit: StorageValue::from_limbs(block.gas_limit.0),
+            basefee: block.base_fee_per_gas.map_or(defaults.basefee, |basefee| {
+                StorageValue::from_limbs(basefee.0)
+            }),
+            difficulty: StorageValue::from_limbs(block.difficulty.0),
+            prevrandao: block
+                .mix_hash
+                .map(|mix_hash| B256::from(mix_hash.to_fixed_bytes()))
+                .or(defaults.prevrandao),
+            ..defaults
+        };
+        Self { env, chain_id }
+    }
+}
+
+/// A call traced by [`trace_call`].
+#[derive(Debug, Clone)]
+pub struct TracedCall {
+    pub state_diff: StateDiff,
+    pub trace: CallFrame,
+    /// State after the call, to commit to the database it ran on.
+    pub state: State,
+}
+
+/// Runs a call from `caller` to `target` in `block` on `db` without
+/// committing it, returning the state it touched and its trace. The caller pays
+/// for gas at the base fee of the block. The storage of `target` is decoded
+/// with `layout` and its calls, events and errors with `abi`, when given.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn trace_call<DB: Database>(
+    db: &mut DB,
+    block: &TraceBlock,
+    caller: Address,
+    target: Address,
+    data: Bytes,
+    value: U256,
+    layout: Option<&StorageLayout>,
+    abi: Option<&Abi>,
+) -> anyhow::Result<TracedCall>
+where
+    DB::Error: fmt::Debug,
+{
+    let value = StorageValue::from_limbs(value.0);
+    let basefee = block.env.basefee;
+    // The gas limit is capped at what the caller can pay for, so a caller
+    // without enough ether for the full limit can still be traced.
+    let gas_limit = if basefee.is_zero() {
+        TRACE_GAS_LIMIT
+    } else {
+        let balance = db
+            .basic(caller.to_fixed_bytes().into())
+            .map_err(|e| anyhow::anyhow!("Failed to fetch the caller: {:?}", e))?
+            .map(|account| account.balance)
+            .unwrap_or_default();
+        let affordable = balance.saturating_sub(value) / basefee;
+        u64::try_from(affordable)
+            .unwrap_or(u64::MAX)
+            .min(TRACE_GAS_LIMIT)
+    };
+
+    let mut evm = EVM::new();
+    evm.database(&mut *db);
+    evm.env.cfg.chain_id = block.chain_id;
+    evm.env.block = block.env.clone();
+    evm.env.tx.caller = caller.to_fixed_bytes().into();
+    evm.env.tx.transact_to = TransactTo::Call(target.to_fixed_bytes().into());
+    evm.env.tx.data = data.to_vec().into();
+    evm.env.tx.value = value;
+    evm.env.tx.gas_limit = gas_limit;
+    evm.env.tx.gas_price = basefee;
+
+    let mut tracer = CallTracer::default();
+    let ResultAndState { result, state } = evm
+        .inspect(&mut tracer)
+        .map_err(|e| anyhow::anyhow!("Failed to trace transaction: {:?}", e))?;
+
+    let mut trace = tracer
+        .root
+        .ok_or_else(|| anyhow::anyhow!("Traced transaction made no call"))?;
+    trace.decode(target, abi);
+
+    let state_diff = StateDiff::new(db, target, &data, &result, &state, layout);
+    Ok(TracedCall {
+        state_diff,
+        trace,
+        state,
+    })
+}
+
+fn to_address(address: RevmAddress) -> Address {
+    Address::from_slice(address.as_slice())
+}
+
+/// Decodes `input` as a call to a function of `abi`.
+fn decode_function(abi: &Abi, input: &[u8]) -> Option<String> {
+    let selector = input.get(..4)?;
+    let function = abi
+        .functions()
+        .find(|function| function.short_signature() == selector)?;
+    let tokens = function.decode_input(&input[4..]).ok()?;
+    let arguments = function
+        .inputs
+        .iter()
+        .zip(&tokens)
+        .map(|(param, token)| match param.name.is_empty() {
+            true => format_token(token),
+            false => format!("{}: {}", param.name, format_token(token)),
+        })
+        .collect::<Vec<_>>();
+    Some(format!("{}({})", function.name, arguments.join(", ")))
+}
+
+/// Decodes `log` as an event of `abi`.
+fn decode_event(abi: &Abi, log: &TraceLog) -> Option<String> {
+    let topic = log.topics.first()?;
+    let event = abi.events().find(|event| event.signature() == *topic)?;
+    let decoded = event
+        .parse_log(RawLog {
+            topics: log.topics.clone(),
+            data: log.data.to_vec(),
+        })
+        .ok()?;
+    let arguments = decoded
+        .params
+        .iter()
+        .map(|param| format!("{}: {}", param.name, format_token(&param.value)))
+        .collect::<Vec<_>>();
+    Some(format!("{}({})", event.name, arguments.join(", ")))
+}
+
+/// Decodes the output of a reverted call. Custom errors are looked up in
+/// `abi`, since contracts commonly share the errors of their interfaces.
+fn decode_revert(abi: Option<&Abi>, output: &[u8]) -> String {
+    let Some(selector) = output.get(..4) else {
+        return match output.is_empty() {
+            true => "reverted without a reason".to_string(),
+            false => format!("0x{}", hex::encode(output)),
+        };
+    };
+    let data = &output[4..];
+
+    if selector == ERROR_SELECTOR {
+        if let Ok(tokens) = ethers::abi::decode(&[ethers::abi::ParamType::String], data) {
+            if let Some(Token::String(reason)) = tokens.first() {
+                return reason.clone();
+            }
+        }
+    }
+    if selector == PANIC_SELECTOR {
+        if let Ok(tokens) = ethers::abi::decode(&[ethers::abi::ParamType::Uint(256)], data) {
+            if let Some(Token::Uint(code)) = tokens.first() {
+                return format!("panic {:#04x} ({})", code.low_u64(), panic_reason(*code));
+            }
+        }
+    }
+
+    let error = abi.and_then(|abi| {
+        abi.errors()
+            .find(|error| error.signature()[..4] == *selector)
+            .and_then(|error| {
+                let tokens = error.decode(data).ok()?;
+                let arguments = error
+                    .inputs
+                    .iter()
+                    .zip(&tokens)
+                    .map(|(param, token)| match param.name.is_empty() {
+                        true => format_token(token),
+                        false => format!("{}: {}", param.name, format_token(token)),
+                    })
+                    .collect::<Vec<_>>();
+                Some(format!("{}({})", error.name, arguments.join(", ")))
+            })
+    });
+    error.unwrap_or_else(|| format!("0x{}", hex::encode(output)))
+}
+
+/// Meaning of the code of a `Panic(uint256)`, as defined by Solidity.
+fn panic_reason(code: U256) -> &'static str {
+    match code.low_u64() {
+        0x01 => "assertion failed",
+        0x11 => "arithmetic overflow or underflow",
+        0x12 => "division or modulo by zero",
+        0x21 => "invalid enum value",
+        0x22 => "invalid storage byte array",
+        0x31 => "pop on an empty array",
+        0x32 => "array index out of bounds",
+        0x41 => "out of memory",
+        0x51 => "call to an uninitialized function",
+        _ => "unknown panic",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::abi::{encode, AbiError, Param, ParamType};
+
+    use super::*;
+
+    #[test]
+    fn decodes_reverts() {
+        let mut output = ERROR_SELECTOR.to_vec();
+        output.extend(encode(&[Token::String("Insufficient balance".into())]));
+        assert_eq!(decode_revert(None, &output), "Insufficient balance");
+
+        let mut output = PANIC_SELECTOR.to_vec();
+        output.extend(encode(&[Token::Uint(0x11.into())]));
+        assert_eq!(
+            decode_revert(None, &output),
+            "panic 0x11 (arithmetic overflow or underflow)"
+        );
+
+        let error = AbiError {
+            name: "InvalidReserves".to_string(),
+            inputs: vec![Param {
+                name: "reserveX".to_string(),
+                kind: ParamType::Uint(256),
+                internal_type: None,
+            }],
+        };
+        let mut abi: Abi = serde_json::from_str("[]").unwrap();
+        abi.errors.insert(error.name.clone(), vec![error.clone()]);
+        let mut output = error.signature()[..4].to_vec();
+        output.extend(encode(&[Token::Uint(5.into())]));
+        assert_eq!(
+            decode_revert(Some(&abi), &output),
+            "InvalidReserves(reserveX: 5)"
+        );
+        assert_eq!(decode_revert(Some(&abi), &[]), "reverted without a reason");
+    }
+
+    #[test]
+    fn finds_innermost_revert() {
+        let inner = CallFrame {
+            output: vec![1].into(),
+            revert_reason: Some("inner".to_string()),
+            ..Default::default()
+        };
+        let outer = CallFrame {
+            output: vec![1].into(),
+            revert_reason: Some("outer".to_string()),
+            calls: vec![inner],
+            ..Default::default()
+        };
+        assert_eq!(outer.revert_reason(), Some("inner"));
+        assert_eq!(
+            CallFrame {
+                success: true,
+                ..outer
+            }
+            .revert_reason(),
+            None
+        );
+    }
+}
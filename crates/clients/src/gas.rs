@@ -0,0 +1,296 @@
+//! Gas limits and EIP-1559 fees for transactions.
+//!
+//! The gas limit of a transaction comes from its simulation, with a margin for
+//! state that moves between the simulation and its inclusion. Fees are
+//! suggested from `eth_feeHistory`: the tips recently paid at a low, middle and
+//! high percentile of each block make the slow, normal and fast presets, and
+//! the max fee leaves room for the base fee to rise while the transaction
+//! waits.
+
+use std::fmt;
+
+use ethers::{
+    providers::Middleware,
+    types::{transaction::eip2718::TypedTransaction, BlockNumber, FeeHistory, U256},
+    utils::format_ether,
+};
+
+/// Added to simulated gas usage, in percent, to get the gas limit.
+pub const GAS_MARGIN_PERCENT: u64 = 20;
+
+/// Number of recent blocks the fees are suggested from.
+pub const FEE_HISTORY_BLOCKS: u64 = 10;
+
+/// Lowest tip suggested, since blocks with no transactions report none.
+const MIN_PRIORITY_FEE: u64 = 100_000_000;
+
+/// Gas limit for a transaction that used `gas_used` in simulation.
+pub fn with_margin(gas_used: u64) -> U256 {
+    U256::from(gas_used) * (100 + GAS_MARGIN_PERCENT) / 100
+}
+
+/// How quickly a transaction should be included.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FeeSpeed {
+    Slow,
+    #[default]
+    Normal,
+    Fast,
+}
+
+impl FeeSpeed {
+    pub const ALL: [FeeSpeed; 3] = [FeeSpeed::Slow, FeeSpeed::Normal, FeeSpeed::Fast];
+
+    /// Percentile of the tips paid in recent blocks to match.
+    fn reward_percentile(self) -> f64 {
+        match self {
+            FeeSpeed::Slow => 10.0,
+            FeeSpeed::Normal => 50.0,
+            FeeSpeed::Fast => 90.0,
+        }
+    }
+
+    /// Max fee over the next base fee, in percent. The base fee rises by at
+    /// most 12.5% a block, so the fast preset can wait about six full blocks
+    /// before being priced out.
+    fn base_fee_headroom_percent(self) -> u64 {
+        match self {
+            FeeSpeed::Slow => 110,
+            FeeSpeed::Normal => 150,
+            FeeSpeed::Fast => 200,
+        }
+    }
+}
+
+impl fmt::Display for FeeSpeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeeSpeed::Slow => write!(f, "Slow"),
+            FeeSpeed::Normal => write!(f, "Normal"),
+            FeeSpeed::Fast => write!(f, "Fast"),
+        }
+    }
+}
+
+/// EIP-1559 fees to send a transaction with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FeePreset {
+    pub speed: FeeSpeed,
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+}
+
+impl FeePreset {
+    /// Sets the fees of `tx`. Transactions without EIP-1559 fees pay the max
+    /// fee as their gas price.
+    pub fn apply(&self, tx: &mut TypedTransaction) {
+        match tx {
+            TypedTransaction::Eip1559(tx) => {
+                tx.max_fee_per_gas = Some(self.max_fee_per_gas);
+                tx.max_priority_fee_per_gas = Some(self.max_priority_fee_per_gas);
+            }
+            tx => {
+                tx.set_gas_price(self.max_fee_per_gas);
+            }
+        }
+    }
+
+    /// Cost of `gas` at these fees, when the base fee is `base_fee`.
+    pub fn cost(&self, gas: U256, base_fee: U256) -> GasCost {
+        let expected_fee = self
+            .max_fee_per_gas
+            .min(base_fee + self.max_priority_fee_per_gas);
+        GasCost {
+            gas,
+            expected_wei: gas * expected_fee,
+            max_wei: gas * self.max_fee_per_gas,
+        }
+    }
+}
+
+/// What a transaction costs in the native token.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GasCost {
+    pub gas: U256,
+    /// Cost if the base fee stays where it is.
+    pub expected_wei: U256,
+    /// Cost if the base fee rises up to the max fee.
+    pub max_wei: U256,
+}
+
+impl GasCost {
+    pub fn expected_eth(&self) -> f64 {
+        format_ether(self.expected_wei).parse().unwrap_or_default()
+    }
+
+    pub fn max_eth(&self) -> f64 {
+        format_ether(self.max_wei).parse().unwrap_or_default()
+    }
+
+    /// Expected cost in USD, given the USD price of the native token.
+    pub fn expected_usd(&self, eth_price: f64) -> f64 {
+        self.expected_eth() * eth_price
+    }
+
+    /// Max cost in USD, given the USD price of the native token.
+    pub fn max_usd(&self, eth_price: f64) -> f64 {
+        self.max_eth() * eth_price
+    }
+
+    /// Describes the cost, in USD too if the price of the native token is
+    /// known.
+    pub fn describe(&self, eth_price: Option<f64>) -> String {
+        match eth_price {
+            Some(price) => format!(
+                "{:.6} ETH (${:.2}), at most {:.6} ETH (${:.2})",
+                self.expected_eth(),
+                self.expected_usd(price),
+                self.max_eth(),
+                self.max_usd(price)
+            ),
+            None => format!(
+                "{:.6} ETH, at most {:.6} ETH",
+                self.expected_eth(),
+                self.max_eth()
+            ),
+        }
+    }
+}
+
+/// Fee presets suggested from recent blocks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeeSuggestion {
+    /// Base fee of the next block.
+    pub base_fee: U256,
+    /// One preset for each [`FeeSpeed`], slowest first.
+    pub presets: Vec<FeePreset>,
+}
+
+impl FeeSuggestion {
+    /// Suggests fees from the last [`FEE_HISTORY_BLOCKS`] blocks of `client`.
+    pub async fn fetch<M: Middleware>(client: &M) -> anyhow::Result<Self> {
+        let percentiles = FeeSpeed::ALL.map(FeeSpeed::reward_percentile);
+        let history = client
+            .fee_history(FEE_HISTORY_BLOCKS, BlockNumber::Latest, &percentiles)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to fetch fee history: {}", e))?;
+        Self::from_history(&history)
+    }
+
+    /// Suggests fees from a fee history fetched with the reward percentiles of
+    /// every [`FeeSpeed`], in order.
+    pub fn from_history(history: &FeeHistory) -> anyhow::Result<Self> {
+        // The history holds the base fee of the block after the last one.
+        let base_fee = *history
+            .base_fee_per_gas
+            .last()
+            .ok_or_else(|| anyhow::anyhow!("Fee history has no base fee"))?;
+
+        let presets = FeeSpeed::ALL
+            .iter()
+            .enumerate()
+            .map(|(index, speed)| {
+                // The median tip paid at this percentile, skipping empty blocks.
+                let mut tips = history
+                    .reward
+                    .iter()
+                    .filter_map(|rewards| rewards.get(index).copied())
+                    .filter(|tip| !tip.is_zero())
+                    .collect::<Vec<_>>();
+                tips.sort();
+                let tip = tips
+                    .get(tips.len() / 2)
+                    .copied()
+                    .unwrap_or_default()
+                    .max(MIN_PRIORITY_FEE.into());
+
+                FeePreset {
+                    speed: *speed,
+                    max_fee_per_gas: base_fee * speed.base_fee_headroom_percent() / 100 + tip,
+                    max_priority_fee_per_gas: tip,
+                }
+            })
+            .collect();
+
+        Ok(Self { base_fee, presets })
+    }
+
+    pub fn preset(&self, speed: FeeSpeed) -> Option<&FeePreset> {
+        self.presets.iter().find(|preset| preset.speed == speed)
+    }
+
+    /// Cost of `gas` at every preset.
+    pub fn costs(&self, gas: U256) -> Vec<(FeeSpeed, GasCost)> {
+        self.presets
+            .iter()
+            .map(|preset| (preset.speed, preset.cost(gas, self.base_fee)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::{
+        providers::{Http, Provider},
+        utils::{parse_units, Anvil},
+    };
+
+    use super::*;
+
+    fn gwei(amount: u64) -> U256 {
+        parse_units(amount, "gwei").unwrap().into()
+    }
+
+    #[test]
+    fn suggests_from_history() {
+        let history = FeeHistory {
+            base_fee_per_gas: vec![gwei(10), gwei(12), gwei(20)],
+            gas_used_ratio: vec![0.5, 1.0],
+            oldest_block: U256::zero(),
+            reward: vec![
+                vec![gwei(1), gwei(2), gwei(5)],
+                vec![U256::zero(), U256::zero(), U256::zero()],
+                vec![gwei(1), gwei(3), gwei(7)],
+            ],
+        };
+        let suggestion = FeeSuggestion::from_history(&history).unwrap();
+        assert_eq!(suggestion.base_fee, gwei(20));
+
+        let slow = suggestion.preset(FeeSpeed::Slow).unwrap();
+        assert_eq!(slow.max_priority_fee_per_gas, gwei(1));
+        assert_eq!(slow.max_fee_per_gas, gwei(23));
+        let fast = suggestion.preset(FeeSpeed::Fast).unwrap();
+        assert_eq!(fast.max_priority_fee_per_gas, gwei(7));
+        assert_eq!(fast.max_fee_per_gas, gwei(47));
+
+        let (_, cost) = suggestion.costs(21_000.into())[0];
+        assert_eq!(cost.expected_wei, gwei(21) * 21_000);
+        assert_eq!(cost.max_wei, gwei(23) * 21_000);
+        assert_eq!(with_margin(100_000), 120_000.into());
+    }
+
+    #[tokio::test]
+    async fn test_fee_suggestion() -> anyhow::Result<()> {
+        let anvil = Anvil::default().chain_id(31337_u64).spawn();
+        let provider = Provider::<Http>::try_from(anvil.endpoint())?;
+
+        let suggestion = FeeSuggestion::fetch(&provider).await?;
+        assert_eq!(suggestion.presets.len(), FeeSpeed::ALL.len());
+        for pair in suggestion.presets.windows(2) {
+            assert!(pair[0].max_fee_per_gas <= pair[1].max_fee_per_gas);
+        }
+        for preset in &suggestion.presets {
+            assert!(preset.max_fee_per_gas >= suggestion.base_fee);
+            assert!(preset.max_priority_fee_per_gas >= MIN_PRIORITY_FEE.into());
+        }
+
+        let cost = suggestion
+            .preset(FeeSpeed::Normal)
+            .unwrap()
+            .cost(with_margin(21_000), suggestion.base_fee);
+        assert!(cost.expected_wei <= cost.max_wei);
+        assert!(cost.describe(Some(2000.0)).contains('$'));
+
+        Ok(())
+    }
+}
@@ -1,27 +1,65 @@
+pub mod transport;
 pub mod types;
+use std::sync::Arc;
+
 use alloy_primitives::{hex, Address};
+use async_trait::async_trait;
 use coins_ledger::{
     common::{APDUData, APDUResponseCodes},
     errors::LedgerError,
     transports::LedgerAsync,
-    APDUCommand, Ledger,
+    APDUAnswer, APDUCommand, Ledger,
+};
+use ethers::{
+    signers::Signer,
+    types::{
+        transaction::{eip2718::TypedTransaction, eip712::Eip712},
+        Signature, TransactionRequest, U256,
+    },
 };
-use ethers::types::{Signature, TransactionRequest, U256};
+use tokio::sync::Mutex;
 
-use self::types::*;
+use self::{transport::Transport, types::*};
 
-#[derive(Debug)]
+/// Largest payload of a single APDU command.
+const MAX_CHUNK_SIZE: usize = 255;
+
+/// Client for the Ethereum app of a Ledger device, signing for the account at
+/// its derivation path.
+///
+/// The client implements [`Signer`], so it can be used with a
+/// `SignerMiddleware` like a local wallet, with every signature confirmed on
+/// the device.
+#[derive(Debug, Clone)]
 pub struct LedgerClient {
-    ledger: Ledger,
+    transport: Arc<dyn Transport>,
     derivation: DerivationType,
+    /// Address at the derivation path, read on connection, as [`Signer`]
+    /// needs it without asking the device.
+    address: ethers::types::Address,
+    chain_id: u64,
 }
 
 impl LedgerClient {
     pub async fn new_connection(derivation: DerivationType) -> anyhow::Result<Self, anyhow::Error> {
-        Ok(Self {
-            ledger: Ledger::init().await?,
+        let transport = Arc::new(Mutex::new(Ledger::init().await?));
+        Ok(Self::with_transport(transport, derivation).await?)
+    }
+
+    /// Connects to a device through `transport`, reading the address at
+    /// `derivation`.
+    pub async fn with_transport(
+        transport: Arc<dyn Transport>,
+        derivation: DerivationType,
+    ) -> Result<Self, LedgerClienError> {
+        let mut client = Self {
+            transport,
             derivation,
-        })
+            address: ethers::types::Address::zero(),
+            chain_id: 1,
+        };
+        client.address = client.get_address().await?.into_array().into();
+        Ok(client)
     }
 
     /// Get the account which corresponds to our derivation path
@@ -41,7 +79,7 @@ impl LedgerClient {
         };
 
         tracing::debug!("Dispatching get_address request to ethereum app");
-        let answer = self.ledger.exchange(&command).await?;
+        let answer = self.transport.exchange(&command).await?;
         let result = answer.data().unwrap();
 
         let address = {
@@ -67,7 +105,7 @@ impl LedgerClient {
         };
 
         tracing::debug!("Dispatching get_version");
-        let answer = self.ledger.exchange(&command).await?;
+        let answer = self.transport.exchange(&command).await?;
         let result = answer.data().unwrap();
         if result.len() < 4 {
             return Err(LedgerClienError::LedgerError(
@@ -81,9 +119,40 @@ impl LedgerClient {
 
     /// Signs an Ethereum transaction (requires confirmation on the ledger)
     pub async fn sign_tx(&self, tx: &TransactionRequest) -> Result<Signature, LedgerClienError> {
+        self.sign_typed_tx(&tx.clone().into()).await
+    }
+
+    /// Signs a transaction of any type (requires confirmation on the ledger).
+    /// Transactions without a chain id are signed for the chain of the client.
+    pub async fn sign_typed_tx(
+        &self,
+        tx: &TypedTransaction,
+    ) -> Result<Signature, LedgerClienError> {
+        let mut tx = tx.clone();
+        if tx.chain_id().is_none() {
+            tx.set_chain_id(self.chain_id);
+        }
+        let chain_id = tx.chain_id().unwrap_or_default().as_u64();
+
         let mut payload = Self::path_to_bytes(&self.derivation);
         payload.extend_from_slice(&tx.rlp());
-        let signature = self.sign_encoded_tx(&payload).await?;
+        let mut signature = self.sign_encoded_tx(&payload).await?;
+
+        // The device returns `v` as a single byte, truncating the EIP-155 `v`
+        // of chains with large ids, so it's rebuilt from its parity.
+        let eip155_chain_id = chain_id * 2 + 35;
+        if eip155_chain_id + 1 > 255 {
+            let one_byte_chain_id = eip155_chain_id % 256;
+            let parity = if signature.v > one_byte_chain_id {
+                signature.v - one_byte_chain_id
+            } else {
+                one_byte_chain_id - signature.v
+            };
+            signature.v = match tx {
+                TypedTransaction::Legacy(_) => eip155_chain_id + parity,
+                _ => (parity % 2 != 1) as u64,
+            };
+        }
         Ok(signature)
     }
 
@@ -91,16 +160,57 @@ impl LedgerClient {
         todo!()
     }
 
-    pub async fn sign_personal_message(&self) -> Result<(), LedgerError> {
-        todo!()
+    /// Signs a message as `personal_sign` does (requires confirmation on the
+    /// ledger).
+    pub async fn sign_personal_message(
+        &self,
+        message: &[u8],
+    ) -> Result<Signature, LedgerClienError> {
+        let mut payload = Self::path_to_bytes(&self.derivation);
+        payload.extend_from_slice(&(message.len() as u32).to_be_bytes());
+        payload.extend_from_slice(message);
+        self.sign_payload(Instruction::SignPersonalMessage, P2_NO_CHAINCODE, &payload)
+            .await
     }
 
-    pub async fn provide_erc20_token_information(&self) -> Result<(), LedgerError> {
-        todo!()
+    /// Gives the device the ticker and decimals of a token, so it shows
+    /// amounts of the token in the next transaction.
+    pub async fn provide_erc20_token_information(
+        &self,
+        token: &Erc20TokenInfo,
+    ) -> Result<(), LedgerClienError> {
+        self.send(
+            Instruction::ProvideErc20TokenInformation,
+            P1_FIRST,
+            P2_NO_CHAINCODE,
+            &token.encode(),
+        )
+        .await?;
+        Ok(())
     }
 
-    pub async fn sign_eip_712_message(&self) -> Result<(), LedgerError> {
-        todo!()
+    /// Signs an EIP-712 message from the hashes of its domain and struct
+    /// (requires confirmation on the ledger). The device only shows the
+    /// hashes.
+    pub async fn sign_eip_712_message(
+        &self,
+        domain_separator: [u8; 32],
+        struct_hash: [u8; 32],
+    ) -> Result<Signature, LedgerClienError> {
+        let mut payload = Self::path_to_bytes(&self.derivation);
+        payload.extend_from_slice(&domain_separator);
+        payload.extend_from_slice(&struct_hash);
+        self.sign_payload(Instruction::SignEip712Message, P2_EIP712_HASHED, &payload)
+            .await
+    }
+
+    /// Signs the EIP-712 message sent with [`Self::eip712_struct_def`] and
+    /// [`Self::eip712_struct_impl`] (requires confirmation on the ledger). The
+    /// device shows the fields of the message.
+    pub async fn sign_eip_712_full(&self) -> Result<Signature, LedgerClienError> {
+        let payload = Self::path_to_bytes(&self.derivation);
+        self.sign_payload(Instruction::SignEip712Message, P2_EIP712_FULL, &payload)
+            .await
     }
 
     pub async fn get_eth2_public_key(&self) -> Result<(), LedgerError> {
@@ -123,16 +233,105 @@ impl LedgerClient {
         todo!()
     }
 
-    pub async fn eip712_struct_def(&self) -> Result<(), LedgerError> {
-        todo!()
+    /// Sends the definition of the struct type `name` with its `fields`. Every
+    /// struct type of a message is defined before its implementation is sent.
+    pub async fn eip712_struct_def(
+        &self,
+        name: &str,
+        fields: &[Eip712Field],
+    ) -> Result<(), LedgerClienError> {
+        self.send(
+            Instruction::Eip712StructDef,
+            P1_COMPLETE,
+            P2_STRUCT_NAME,
+            name.as_bytes(),
+        )
+        .await?;
+        for field in fields {
+            self.send(
+                Instruction::Eip712StructDef,
+                P1_COMPLETE,
+                P2_STRUCT_FIELD,
+                &field.encode(),
+            )
+            .await?;
+        }
+        Ok(())
     }
 
-    pub async fn eip712_struct_impl(&self) -> Result<(), LedgerError> {
-        todo!()
+    /// Sends a part of the message, in the order of the struct definitions.
+    /// Field values longer than a command are sent in chunks.
+    pub async fn eip712_struct_impl(&self, part: Eip712StructImpl) -> Result<(), LedgerClienError> {
+        match part {
+            Eip712StructImpl::Root(name) => {
+                self.send(
+                    Instruction::Eip712StructImpl,
+                    P1_COMPLETE,
+                    P2_STRUCT_NAME,
+                    name.as_bytes(),
+                )
+                .await?;
+            }
+            Eip712StructImpl::Array(size) => {
+                self.send(
+                    Instruction::Eip712StructImpl,
+                    P1_COMPLETE,
+                    P2_STRUCT_ARRAY,
+                    &[size],
+                )
+                .await?;
+            }
+            Eip712StructImpl::Field(value) => {
+                if value.len() > u16::MAX as usize {
+                    return Err(LedgerClienError::CommandError(
+                        "Field value is too long".to_string(),
+                    ));
+                }
+                let mut data = (value.len() as u16).to_be_bytes().to_vec();
+                data.extend(value);
+
+                let mut chunks = data.chunks(MAX_CHUNK_SIZE).peekable();
+                while let Some(chunk) = chunks.next() {
+                    let p1 = if chunks.peek().is_some() {
+                        P1_PARTIAL
+                    } else {
+                        P1_COMPLETE
+                    };
+                    self.send(Instruction::Eip712StructImpl, p1, P2_STRUCT_FIELD, chunk)
+                        .await?;
+                }
+            }
+        }
+        Ok(())
     }
 
-    pub async fn eip712_filtering(&self) -> Result<(), LedgerError> {
-        todo!()
+    /// Sends a filter on what the device shows of the message.
+    pub async fn eip712_filtering(&self, filter: Eip712Filter) -> Result<(), LedgerClienError> {
+        let (p1, p2, data) = match filter {
+            Eip712Filter::Activate => (P1_COMPLETE, P2_FILTER_ACTIVATE, vec![]),
+            Eip712Filter::MessageInfo {
+                display_name,
+                filters_count,
+                signature,
+            } => {
+                let mut data = Self::length_prefixed("Display name", display_name.as_bytes())?;
+                data.push(filters_count);
+                data.extend(Self::length_prefixed("Filter signature", &signature)?);
+                (P1_COMPLETE, P2_FILTER_MESSAGE_INFO, data)
+            }
+            Eip712Filter::ShowField {
+                display_name,
+                signature,
+                discarded,
+            } => {
+                let mut data = Self::length_prefixed("Display name", display_name.as_bytes())?;
+                data.extend(Self::length_prefixed("Filter signature", &signature)?);
+                (discarded as u8, P2_FILTER_SHOW_FIELD, data)
+            }
+        };
+        self.send(Instruction::Eip712Filtering, p1, p2, &data)
+            .await?;
+        Ok(())
     }
 
     pub async fn ens_get_challenge(&self) -> Result<(), LedgerError> {
@@ -143,6 +342,15 @@ impl LedgerClient {
         todo!()
     }
 
+    // helper which prefixes a filter field with its one byte length
+    fn length_prefixed(field: &str, bytes: &[u8]) -> Result<Vec<u8>, LedgerClienError> {
+        let len = u8::try_from(bytes.len())
+            .map_err(|_| LedgerClienError::CommandError(format!("{field} is too long")))?;
+        let mut data = vec![len];
+        data.extend_from_slice(bytes);
+        Ok(data)
+    }
+
     // helper which converts a derivation path to bytes
     fn path_to_bytes(derivation: &DerivationType) -> Vec<u8> {
         let derivation = derivation.to_string();
@@ -171,7 +379,7 @@ impl LedgerClient {
             response_len: None,
         };
 
-        let result = self.ledger.exchange(&command).await?;
+        let result = self.transport.exchange(&command).await?;
         let result = result.data().unwrap();
         let len: usize = result[1].into();
         Ok(String::from_utf8(result[2..2 + len].to_vec()).unwrap())
@@ -180,37 +388,83 @@ impl LedgerClient {
     // Helper function for signing either transaction data, personal messages or
     // EIP712 derived structs
     pub async fn sign_encoded_tx(&self, encoded_tx: &[u8]) -> Result<Signature, LedgerClienError> {
-        if encoded_tx.is_empty() {
+        self.sign_payload(Instruction::Sign, P2_NO_CHAINCODE, encoded_tx)
+            .await
+    }
+
+    // Sends a command that fits in a single APDU.
+    async fn send(
+        &self,
+        instruction: Instruction,
+        p1: u8,
+        p2: u8,
+        data: &[u8],
+    ) -> Result<APDUAnswer, LedgerClienError> {
+        if data.len() > MAX_CHUNK_SIZE {
             return Err(LedgerClienError::CommandError(
-                ("Payload is empty").to_string(),
+                "Command data is too long".to_string(),
             ));
         }
         let command = APDUCommand {
-            ins: Instruction::Sign as u8,
-            p1: P1_FIRST,
-            p2: P2_NO_CHAINCODE,
-            data: APDUData::new(encoded_tx),
+            ins: instruction as u8,
+            p1,
+            p2,
+            data: APDUData::new(data),
             response_len: None,
         };
+        Ok(self.transport.exchange(&command).await?)
+    }
 
-        let result = self.ledger.exchange(&command).await?;
-        if result.len() < 65 {
-            return Err(LedgerClienError::LedgerError(
-                LedgerError::ResponseTooShort(vec![result.len() as u8]),
+    // Sends `payload` to be signed by `instruction`, in chunks of at most
+    // `MAX_CHUNK_SIZE` bytes, and reads the signature from the last answer.
+    async fn sign_payload(
+        &self,
+        instruction: Instruction,
+        p2: u8,
+        payload: &[u8],
+    ) -> Result<Signature, LedgerClienError> {
+        if payload.is_empty() {
+            return Err(LedgerClienError::CommandError(
+                ("Payload is empty").to_string(),
             ));
         }
-        let result = result.data().unwrap();
+        let mut command = APDUCommand {
+            ins: instruction as u8,
+            p1: P1_FIRST,
+            p2,
+            data: APDUData::new(&[]),
+            response_len: None,
+        };
+
+        // The Ethereum app rejects a last chunk of exactly 3 bytes, so chunks
+        // are sized to avoid one.
+        let chunk_size = (1..=MAX_CHUNK_SIZE)
+            .rev()
+            .find(|size| payload.len() % size != 3)
+            .unwrap_or(MAX_CHUNK_SIZE);
+        let mut answer = None;
+        for chunk in payload.chunks(chunk_size) {
+            command.data = APDUData::new(chunk);
+            answer = Some(self.transport.exchange(&command).await?);
+            command.p1 = P1_MORE;
+        }
+
+        let answer = answer.expect("payload is not empty");
+        let result = answer.data().unwrap_or_default();
         if result.is_empty() {
             if self.get_opened_app().await? == "Ethereum" {
                 return Err(LedgerClienError::CommandError("Canceled".to_owned()));
             } else {
                 return Err(LedgerClienError::CommandError(
-                    "Please open Ethereum app on
-        Ledger"
-                        .to_owned(),
+                    "Please open Ethereum app on Ledger".to_owned(),
                 ));
             }
         }
+        if result.len() < 65 {
+            return Err(LedgerClienError::LedgerError(
+                LedgerError::ResponseTooShort(result.to_vec()),
+            ));
+        }
         let v: u64 = result[0].into();
         let r = &result[1..33];
         let s = &result[33..65];
@@ -224,6 +478,52 @@ impl LedgerClient {
     }
 }
 
+#[async_trait]
+impl Signer for LedgerClient {
+    type Error = LedgerClienError;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<Signature, Self::Error> {
+        self.sign_personal_message(message.as_ref()).await
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, Self::Error> {
+        self.sign_typed_tx(tx).await
+    }
+
+    /// Signs the hashes of the domain and struct of `payload`, which the
+    /// device shows to be confirmed.
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(
+        &self,
+        payload: &T,
+    ) -> Result<Signature, Self::Error> {
+        let domain_separator = payload
+            .domain_separator()
+            .map_err(|e| LedgerClienError::CommandError(e.to_string()))?;
+        let struct_hash = payload
+            .struct_hash()
+            .map_err(|e| LedgerClienError::CommandError(e.to_string()))?;
+
+        self.sign_eip_712_message(domain_separator, struct_hash)
+            .await
+    }
+
+    fn address(&self) -> ethers::types::Address {
+        self.address
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    fn with_chain_id<T: Into<u64>>(mut self, chain_id: T) -> Self {
+        self.chain_id = chain_id.into();
+        self
+    }
+}
+
 /// These tests will fail if you don't have a ledger connected
 /// They should probably be ignored in CI and ran manually
 mod tests {
@@ -260,6 +560,359 @@ mod tests {
         let sig = ledger.sign_tx(&tx).await.unwrap();
         println!("Got signature: {:?}", sig);
         // test signing a transaction
-        ledger.ledger.close();
+        drop(ledger);
+    }
+}
+
+#[cfg(test)]
+mod mock_tests {
+    use ethers::{
+        signers::LocalWallet,
+        types::{transaction::eip712::TypedData, Bytes, Eip1559TransactionRequest},
+    };
+
+    use super::{transport::MockTransport, *};
+
+    // First account of the Anvil and Hardhat test mnemonic.
+    const KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const ADDRESS: &str = "f39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    const OK: [u8; 2] = [0x90, 0x00];
+
+    // m/44'/60'/0'/0/0
+    fn path() -> Vec<u8> {
+        LedgerClient::path_to_bytes(&DerivationType::LedgerLive(0))
+    }
+
+    fn command(ins: u8, p1: u8, p2: u8, data: &[u8]) -> Vec<u8> {
+        let mut command = vec![0xe0, ins, p1, p2, data.len() as u8];
+        command.extend_from_slice(data);
+        command
+    }
+
+    // The device answers with the public key, then the address in hex.
+    fn get_address(mock: MockTransport) -> MockTransport {
+        let mut response = vec![65];
+        response.extend([0x04; 65]);
+        response.push(40);
+        response.extend(ADDRESS.as_bytes());
+        response.extend(OK);
+        mock.expect(command(0x02, 0x00, 0x00, &path()), response)
+    }
+
+    fn signature_response(signature: &Signature) -> Vec<u8> {
+        let mut response = vec![signature.v as u8];
+        let mut word = [0u8; 32];
+        signature.r.to_big_endian(&mut word);
+        response.extend(word);
+        signature.s.to_big_endian(&mut word);
+        response.extend(word);
+        response.extend(OK);
+        response
+    }
+
+    #[test]
+    fn derivation_path_bytes() {
+        let mut expected = vec![5];
+        for index in [0x8000002c_u32, 0x8000003c, 0x80000000, 0, 0] {
+            expected.extend(index.to_be_bytes());
+        }
+        assert_eq!(path(), expected);
+    }
+
+    #[tokio::test]
+    async fn signs_chunked_transaction() -> anyhow::Result<()> {
+        let wallet = KEY.parse::<LocalWallet>()?.with_chain_id(31337_u64);
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(wallet.address())
+            .value(1)
+            .nonce(7)
+            .gas(100_000)
+            .gas_price(1_000_000_000)
+            .data(Bytes::from(vec![0xab; 300]))
+            .chain_id(31337_u64)
+            .into();
+        let expected = wallet.sign_transaction(&tx).await?;
+
+        let mut payload = path();
+        payload.extend_from_slice(&tx.rlp());
+        assert!(payload.len() > MAX_CHUNK_SIZE && payload.len() % MAX_CHUNK_SIZE != 3);
+        let (first, rest) = payload.split_at(MAX_CHUNK_SIZE);
+
+        // The device truncates the EIP-155 `v` of chain 31337 to a byte.
+        let mut truncated = expected;
+        truncated.v = (31337 * 2 + 35) % 256 + (expected.v - (31337 * 2 + 35));
+
+        let mock = Arc::new(
+            get_address(MockTransport::new())
+                .expect(command(0x04, P1_FIRST, 0x00, first), OK)
+                .expect(
+                    command(0x04, P1_MORE, 0x00, rest),
+                    signature_response(&truncated),
+                ),
+        );
+        let ledger = LedgerClient::with_transport(mock.clone(), DerivationType::LedgerLive(0))
+            .await?
+            .with_chain_id(31337_u64);
+        assert_eq!(Signer::address(&ledger), wallet.address());
+
+        let signature = ledger.sign_transaction(&tx).await?;
+        assert_eq!(signature, expected);
+        signature.verify(tx.sighash(), wallet.address())?;
+        assert!(mock.is_done());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn signs_eip1559_transaction() -> anyhow::Result<()> {
+        let wallet = KEY.parse::<LocalWallet>()?.with_chain_id(1_u64);
+        let tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .to(wallet.address())
+            .value(1)
+            .nonce(0)
+            .gas(21_000)
+            .max_fee_per_gas(2_000_000_000)
+            .max_priority_fee_per_gas(1_000_000_000)
+            .chain_id(1_u64)
+            .into();
+        let expected = wallet.sign_transaction(&tx).await?;
+
+        let mut payload = path();
+        payload.extend_from_slice(&tx.rlp());
+        let mock = Arc::new(get_address(MockTransport::new()).expect(
+            command(0x04, P1_FIRST, 0x00, &payload),
+            signature_response(&expected),
+        ));
+        let ledger =
+            LedgerClient::with_transport(mock.clone(), DerivationType::LedgerLive(0)).await?;
+
+        let signature = ledger.sign_transaction(&tx).await?;
+        signature.verify(tx.sighash(), wallet.address())?;
+        assert!(mock.is_done());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn empty_answer_is_canceled() -> anyhow::Result<()> {
+        let tx: TypedTransaction = TransactionRequest::new().nonce(0).chain_id(1_u64).into();
+        let mut payload = path();
+        payload.extend_from_slice(&tx.rlp());
+
+        // An empty answer is a rejection if the Ethereum app is open.
+        let mut app = vec![0x01, 8];
+        app.extend(b"Ethereum");
+        app.extend([0x05, b'1', b'.', b'1', b'0', b'.', b'3']);
+        app.extend(OK);
+        let mock = Arc::new(
+            get_address(MockTransport::new())
+                .expect(command(0x04, P1_FIRST, 0x00, &payload), OK)
+                .expect(command(0x01, 0x00, 0x00, &[]), app),
+        );
+        let ledger =
+            LedgerClient::with_transport(mock.clone(), DerivationType::LedgerLive(0)).await?;
+
+        let result = ledger.sign_transaction(&tx).await;
+        assert!(matches!(result, Err(LedgerClienError::CommandError(e)) if e == "Canceled"));
+        assert!(mock.is_done());
+        Ok(())
+    }
+
+    async fn client(mock: &Arc<MockTransport>) -> anyhow::Result<LedgerClient> {
+        Ok(LedgerClient::with_transport(mock.clone(), DerivationType::LedgerLive(0)).await?)
+    }
+
+    #[tokio::test]
+    async fn signs_chunked_personal_message() -> anyhow::Result<()> {
+        let wallet = KEY.parse::<LocalWallet>()?;
+        let message = vec![0x42; 300];
+        let expected = wallet.sign_message(&message).await?;
+
+        let mut payload = path();
+        payload.extend_from_slice(&(message.len() as u32).to_be_bytes());
+        payload.extend_from_slice(&message);
+        let (first, rest) = payload.split_at(MAX_CHUNK_SIZE);
+        let mock = Arc::new(
+            get_address(MockTransport::new())
+                .expect(command(0x08, P1_FIRST, 0x00, first), OK)
+                .expect(
+                    command(0x08, P1_MORE, 0x00, rest),
+                    signature_response(&expected),
+                ),
+        );
+        let ledger = client(&mock).await?;
+
+        let signature = ledger.sign_message(&message).await?;
+        assert_eq!(signature, expected);
+        signature.verify(message, wallet.address())?;
+        assert!(mock.is_done());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn signs_hashed_typed_data() -> anyhow::Result<()> {
+        let wallet = KEY.parse::<LocalWallet>()?;
+        let typed_data: TypedData = serde_json::from_value(serde_json::json!({
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "chainId", "type": "uint256" }
+                ],
+                "Permit": [
+                    { "name": "spender", "type": "address" },
+                    { "name": "value", "type": "uint256" }
+                ]
+            },
+            "primaryType": "Permit",
+            "domain": { "name": "Token", "chainId": 1 },
+            "message": {
+                "spender": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "value": "1000"
+            }
+        }))?;
+        let expected = wallet.sign_typed_data(&typed_data).await?;
+
+        let mut payload = path();
+        payload.extend_from_slice(&typed_data.domain_separator()?);
+        payload.extend_from_slice(&typed_data.struct_hash()?);
+        let mock = Arc::new(get_address(MockTransport::new()).expect(
+            command(0x0c, P1_FIRST, 0x00, &payload),
+            signature_response(&expected),
+        ));
+        let ledger = client(&mock).await?;
+
+        let signature = ledger.sign_typed_data(&typed_data).await?;
+        signature.verify(typed_data.encode_eip712()?, wallet.address())?;
+        assert!(mock.is_done());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_eip712_fields() -> anyhow::Result<()> {
+        let field = Eip712Field::new("amounts", "uint128[2][]")?;
+        assert_eq!(field.kind, Eip712FieldType::Uint(16));
+        assert_eq!(field.array_levels, vec![Some(2), None]);
+        assert_eq!(
+            Eip712Field::new("salt", "bytes32")?.kind,
+            Eip712FieldType::FixedBytes(32)
+        );
+        assert_eq!(
+            Eip712Field::new("interest", "Interest")?.kind,
+            Eip712FieldType::Custom("Interest".to_string())
+        );
+        assert!(Eip712Field::new("bad", "uint256[x]").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sends_struct_definitions() -> anyhow::Result<()> {
+        let fields = vec![
+            Eip712Field::new("from", "Person")?,
+            Eip712Field::new("to", "Person[]")?,
+            Eip712Field::new("amount", "uint256")?,
+            Eip712Field::new("contents", "string")?,
+        ];
+
+        let mut to = vec![0x80, 6];
+        to.extend(b"Person");
+        to.extend([1, 0x00, 2]);
+        to.extend(b"to");
+        let mut from = vec![0x00, 6];
+        from.extend(b"Person");
+        from.push(4);
+        from.extend(b"from");
+        let mut amount = vec![0x42, 32, 6];
+        amount.extend(b"amount");
+        let mut contents = vec![0x05, 8];
+        contents.extend(b"contents");
+
+        let mock = Arc::new(
+            get_address(MockTransport::new())
+                .expect(command(0x1a, 0x00, 0x00, b"Mail"), OK)
+                .expect(command(0x1a, 0x00, 0xff, &from), OK)
+                .expect(command(0x1a, 0x00, 0xff, &to), OK)
+                .expect(command(0x1a, 0x00, 0xff, &amount), OK)
+                .expect(command(0x1a, 0x00, 0xff, &contents), OK),
+        );
+        let ledger = client(&mock).await?;
+
+        ledger.eip712_struct_def("Mail", &fields).await?;
+        assert!(mock.is_done());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sends_struct_implementation_and_filters() -> anyhow::Result<()> {
+        let value = vec![0x61; 300];
+        let mut data = vec![0x01, 0x2c];
+        data.extend(&value);
+        let (first, rest) = data.split_at(MAX_CHUNK_SIZE);
+
+        let mut show = vec![2];
+        show.extend(b"To");
+        show.extend([3, 0xaa, 0xbb, 0xcc]);
+
+        let token = Erc20TokenInfo {
+            ticker: "USDC".to_string(),
+            address: [0x11; 20],
+            decimals: 6,
+            chain_id: 1,
+            signature: vec![0xdd; 2],
+        };
+        let mut token_data = vec![4];
+        token_data.extend(b"USDC");
+        token_data.extend([0x11; 20]);
+        token_data.extend([0, 0, 0, 6, 0, 0, 0, 1, 0xdd, 0xdd]);
+
+        let mock = Arc::new(
+            get_address(MockTransport::new())
+                .expect(command(0x1e, 0x00, 0x00, &[]), OK)
+                .expect(command(0x1e, 0x01, 0xff, &show), OK)
+                .expect(command(0x1c, 0x00, 0x00, b"Mail"), OK)
+                .expect(command(0x1c, 0x00, 0x0f, &[2]), OK)
+                .expect(command(0x1c, 0x01, 0xff, first), OK)
+                .expect(command(0x1c, 0x00, 0xff, rest), OK)
+                .expect(command(0x0a, 0x00, 0x00, &token_data), OK),
+        );
+        let ledger = client(&mock).await?;
+
+        ledger.eip712_filtering(Eip712Filter::Activate).await?;
+        ledger
+            .eip712_filtering(Eip712Filter::ShowField {
+                display_name: "To".to_string(),
+                signature: vec![0xaa, 0xbb, 0xcc],
+                discarded: true,
+            })
+            .await?;
+        ledger
+            .eip712_struct_impl(Eip712StructImpl::Root("Mail".to_string()))
+            .await?;
+        ledger
+            .eip712_struct_impl(Eip712StructImpl::Array(2))
+            .await?;
+        ledger
+            .eip712_struct_impl(Eip712StructImpl::Field(value))
+            .await?;
+        ledger.provide_erc20_token_information(&token).await?;
+        assert!(mock.is_done());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rejects_overlong_filter_names() -> anyhow::Result<()> {
+        let mock = Arc::new(get_address(MockTransport::new()));
+        let ledger = client(&mock).await?;
+
+        let result = ledger
+            .eip712_filtering(Eip712Filter::ShowField {
+                display_name: "a".repeat(256),
+                signature: vec![0xaa],
+                discarded: false,
+            })
+            .await;
+        assert!(
+            matches!(result, Err(LedgerClienError::CommandError(e)) if e == "Display name is too long")
+        );
+        // Nothing reaches the device.
+        assert!(mock.is_done());
+        Ok(())
     }
 }
@@ -0,0 +1,68 @@
+//! Transports carry APDU commands to a Ledger device and its answers back.
+//!
+//! [`LedgerClient`](super::LedgerClient) talks to the device through the
+//! [`Transport`] trait, so the HID connection can be swapped for a
+//! [`MockTransport`] that replays recorded exchanges, and the APDU flows can be
+//! tested without a device.
+
+use std::{collections::VecDeque, fmt, sync::Mutex as SyncMutex};
+
+use async_trait::async_trait;
+use coins_ledger::{errors::LedgerError, transports::LedgerAsync, APDUAnswer, APDUCommand, Ledger};
+use tokio::sync::Mutex;
+
+/// Sends APDU commands to a device.
+#[async_trait]
+pub trait Transport: fmt::Debug + Send + Sync {
+    async fn exchange(&self, command: &APDUCommand) -> Result<APDUAnswer, LedgerError>;
+}
+
+/// The device only handles one exchange at a time, so its connection is locked
+/// for each.
+#[async_trait]
+impl Transport for Mutex<Ledger> {
+    async fn exchange(&self, command: &APDUCommand) -> Result<APDUAnswer, LedgerError> {
+        self.lock().await.exchange(command).await
+    }
+}
+
+/// Replays recorded exchanges, checking every command is the one expected.
+#[derive(Debug, Default)]
+pub struct MockTransport {
+    exchanges: SyncMutex<VecDeque<(Vec<u8>, Vec<u8>)>>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expects `command`, serialized, and answers it with `response`, which
+    /// ends with the status word.
+    pub fn expect(self, command: impl Into<Vec<u8>>, response: impl Into<Vec<u8>>) -> Self {
+        self.exchanges
+            .lock()
+            .unwrap()
+            .push_back((command.into(), response.into()));
+        self
+    }
+
+    /// Whether every expected command was sent.
+    pub fn is_done(&self) -> bool {
+        self.exchanges.lock().unwrap().is_empty()
+    }
+}
+
+#[async_trait]
+impl Transport for MockTransport {
+    async fn exchange(&self, command: &APDUCommand) -> Result<APDUAnswer, LedgerError> {
+        let (expected, response) = self
+            .exchanges
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or_else(|| panic!("Unexpected APDU command: {:?}", command));
+        assert_eq!(command.serialize(), expected, "APDU command mismatch");
+        APDUAnswer::from_answer(response)
+    }
+}
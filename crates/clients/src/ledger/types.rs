@@ -27,17 +27,26 @@ pub const P1_NON_CONFIRM: u8 = 0x00;
 pub const P1_MORE: u8 = 0x80;
 pub const P2_NO_CHAINCODE: u8 = 0x00;
 
-#[derive(Debug)]
+// P1 and P2 of the EIP-712 instructions
+pub const P1_COMPLETE: u8 = 0x00;
+pub const P1_PARTIAL: u8 = 0x01;
+pub const P2_EIP712_HASHED: u8 = 0x00;
+pub const P2_EIP712_FULL: u8 = 0x01;
+pub const P2_STRUCT_NAME: u8 = 0x00;
+pub const P2_STRUCT_ARRAY: u8 = 0x0F;
+pub const P2_STRUCT_FIELD: u8 = 0xFF;
+pub const P2_FILTER_ACTIVATE: u8 = 0x00;
+pub const P2_FILTER_MESSAGE_INFO: u8 = 0x0F;
+pub const P2_FILTER_SHOW_FIELD: u8 = 0xFF;
+
+#[derive(Debug, thiserror::Error)]
 pub enum LedgerClienError {
-    LedgerError(coins_ledger::LedgerError),
+    #[error("Ledger error: {0}")]
+    LedgerError(#[from] coins_ledger::LedgerError),
+    #[error("{0}")]
     CommandError(String),
     // ... other error variants ...
 }
-impl From<coins_ledger::LedgerError> for LedgerClienError {
-    fn from(err: coins_ledger::LedgerError) -> LedgerClienError {
-        LedgerClienError::LedgerError(err)
-    }
-}
 
 #[derive(Clone, Debug)]
 /// Ledger wallet type
@@ -63,3 +72,182 @@ impl fmt::Display for DerivationType {
         )
     }
 }
+
+/// Type of a field in an EIP-712 struct definition.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Eip712FieldType {
+    /// Another struct, by name.
+    Custom(String),
+    /// Signed integer, with its size in bytes.
+    Int(u8),
+    /// Unsigned integer, with its size in bytes.
+    Uint(u8),
+    Address,
+    Bool,
+    String,
+    /// Fixed size bytes, with their size.
+    FixedBytes(u8),
+    DynamicBytes,
+}
+
+/// A field of an EIP-712 struct definition, as the device expects it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Eip712Field {
+    pub name: String,
+    pub kind: Eip712FieldType,
+    /// Array dimensions, innermost first, `None` for dynamic ones.
+    pub array_levels: Vec<Option<u8>>,
+}
+
+impl Eip712Field {
+    /// Parses a field from its Solidity type, like `uint256` or `Person[2][]`.
+    pub fn new(name: &str, solidity_type: &str) -> Result<Self, LedgerClienError> {
+        let invalid = || LedgerClienError::CommandError(format!("Invalid type: {solidity_type}"));
+
+        let base_end = solidity_type.find('[').unwrap_or(solidity_type.len());
+        let (base, arrays) = solidity_type.split_at(base_end);
+        let mut array_levels = vec![];
+        for level in arrays.split_terminator(']') {
+            let size = level.strip_prefix('[').ok_or_else(invalid)?;
+            array_levels.push(match size {
+                "" => None,
+                size => Some(size.parse().map_err(|_| invalid())?),
+            });
+        }
+
+        // The size after `uint`, `int` or `bytes`, if `base` is that type.
+        let suffix = |prefix: &str| {
+            base.strip_prefix(prefix)
+                .filter(|rest| rest.chars().all(|c| c.is_ascii_digit()))
+        };
+        let bits = |rest: &str| -> Result<u16, LedgerClienError> {
+            match rest {
+                "" => Ok(256),
+                bits => bits.parse().map_err(|_| invalid()),
+            }
+        };
+        let kind = match base {
+            "" => return Err(invalid()),
+            "address" => Eip712FieldType::Address,
+            "bool" => Eip712FieldType::Bool,
+            "string" => Eip712FieldType::String,
+            "bytes" => Eip712FieldType::DynamicBytes,
+            _ => {
+                if let Some(rest) = suffix("uint") {
+                    Eip712FieldType::Uint((bits(rest)? / 8) as u8)
+                } else if let Some(rest) = suffix("int") {
+                    Eip712FieldType::Int((bits(rest)? / 8) as u8)
+                } else if let Some(rest) = suffix("bytes") {
+                    Eip712FieldType::FixedBytes(rest.parse().map_err(|_| invalid())?)
+                } else {
+                    Eip712FieldType::Custom(base.to_string())
+                }
+            }
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            kind,
+            array_levels,
+        })
+    }
+
+    /// Encodes the field for an `Eip712StructDef` command.
+    pub fn encode(&self) -> Vec<u8> {
+        let (type_id, type_size) = match &self.kind {
+            Eip712FieldType::Custom(_) => (0, None),
+            Eip712FieldType::Int(size) => (1, Some(*size)),
+            Eip712FieldType::Uint(size) => (2, Some(*size)),
+            Eip712FieldType::Address => (3, None),
+            Eip712FieldType::Bool => (4, None),
+            Eip712FieldType::String => (5, None),
+            Eip712FieldType::FixedBytes(size) => (6, Some(*size)),
+            Eip712FieldType::DynamicBytes => (7, None),
+        };
+
+        let mut type_desc = type_id;
+        if !self.array_levels.is_empty() {
+            type_desc |= 0x80;
+        }
+        if type_size.is_some() {
+            type_desc |= 0x40;
+        }
+
+        let mut data = vec![type_desc];
+        if let Eip712FieldType::Custom(name) = &self.kind {
+            data.push(name.len() as u8);
+            data.extend_from_slice(name.as_bytes());
+        }
+        if let Some(size) = type_size {
+            data.push(size);
+        }
+        if !self.array_levels.is_empty() {
+            data.push(self.array_levels.len() as u8);
+            for level in &self.array_levels {
+                match level {
+                    None => data.push(0x00),
+                    Some(size) => data.extend([0x01, *size]),
+                }
+            }
+        }
+        data.push(self.name.len() as u8);
+        data.extend_from_slice(self.name.as_bytes());
+        data
+    }
+}
+
+/// A part of the implementation of an EIP-712 message, sent in the order of
+/// the struct definitions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Eip712StructImpl {
+    /// Name of the struct the message starts at.
+    Root(String),
+    /// Number of elements of the array field that follows.
+    Array(u8),
+    /// Value of the next field, encoded big endian.
+    Field(Vec<u8>),
+}
+
+/// A filter on what the device shows of an EIP-712 message. Filters are signed
+/// by Ledger, so they can only be passed through.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Eip712Filter {
+    /// Turns filtering on, before the struct definitions are sent.
+    Activate,
+    /// Name shown for the message, with the number of field filters that follow.
+    MessageInfo {
+        display_name: String,
+        filters_count: u8,
+        signature: Vec<u8>,
+    },
+    /// Shows the next field with a name, or hides it if `discarded`.
+    ShowField {
+        display_name: String,
+        signature: Vec<u8>,
+        discarded: bool,
+    },
+}
+
+/// Ticker and decimals of a token, signed by Ledger, for the device to show
+/// amounts of the token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Erc20TokenInfo {
+    pub ticker: String,
+    pub address: [u8; 20],
+    pub decimals: u32,
+    pub chain_id: u32,
+    pub signature: Vec<u8>,
+}
+
+impl Erc20TokenInfo {
+    /// Encodes the token for a `ProvideErc20TokenInformation` command.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = vec![self.ticker.len() as u8];
+        data.extend_from_slice(self.ticker.as_bytes());
+        data.extend_from_slice(&self.address);
+        data.extend_from_slice(&self.decimals.to_be_bytes());
+        data.extend_from_slice(&self.chain_id.to_be_bytes());
+        data.extend_from_slice(&self.signature);
+        data
+    }
+}
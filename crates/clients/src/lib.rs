@@ -1,11 +1,16 @@
 //! Exposes all interfaces with external systems, including arbiter simulations
 //! and connections to live networks.
 
+pub mod arguments;
 pub mod client;
 pub mod dev;
 pub mod forking;
+pub mod gas;
 pub mod ledger;
 pub mod protocol;
+pub mod rpc;
 pub mod scroll;
+pub mod signer;
+pub mod transactions;
 
 use ethers::prelude::*;
@@ -0,0 +1,435 @@
+//! Batched reads of pool state through Multicall3.
+//!
+//! Reading a pool one value at a time costs an RPC per value, which adds up
+//! quickly when tracking dozens of pools. The reads here are grouped into a
+//! few `aggregate3` calls. Snapshots pin every call to the same block so the
+//! values are consistent with one another.
+//!
+//! Chains without Multicall3 (such as a fresh simulation environment) fall back
+//! to issuing the same calls one at a time.
+
+use bindings::erc20::ERC20;
+use ethers::{
+    abi::{Detokenize, Function},
+    utils::id,
+};
+
+use super::*;
+
+/// Address Multicall3 is deployed at on most chains.
+pub const MULTICALL3_ADDRESS: &str = "0xcA11bde05977b3631167028862bE2a173976CA11";
+/// Largest number of calls sent in a single `aggregate3`.
+pub const MAX_CALLS_PER_BATCH: usize = 500;
+
+/// State of a pool read in a single batch.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PoolSnapshot {
+    pub pool_id: U256,
+    /// Block the state was read at.
+    pub block: u64,
+    pub pool: PoolStruct,
+    /// Strategy parameters, as encoded by the strategy's `getPoolParams`.
+    pub params: Bytes,
+    /// Price of `x` in terms of `y`, if it could be computed.
+    pub internal_price: Option<U256>,
+    pub liquidity_token_supply: U256,
+    /// Liquidity tokens held by the account passed to the batch, if any.
+    pub liquidity_token_balance: Option<U256>,
+}
+
+/// ERC20 metadata read in a single batch. Missing fields are tokens that do
+/// not implement the optional metadata methods.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TokenMetadata {
+    pub address: Address,
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    pub decimals: Option<u8>,
+}
+
+/// A call to add to a batch.
+#[derive(Debug, Clone)]
+pub struct BatchCall {
+    target: Address,
+    data: Bytes,
+    function: Function,
+}
+
+impl BatchCall {
+    pub fn new<C: Middleware, D: Detokenize>(call: &ContractCall<C, D>) -> Result<Self> {
+        let target = *call
+            .tx
+            .to_addr()
+            .ok_or_else(|| anyhow::anyhow!("Call without a target"))?;
+        let data = call
+            .calldata()
+            .ok_or_else(|| anyhow::anyhow!("Call without calldata"))?;
+        Ok(Self {
+            target,
+            data,
+            function: call.function.clone(),
+        })
+    }
+}
+
+/// Sends batches of calls through Multicall3, or one at a time on chains
+/// without it.
+#[derive(Debug, Clone)]
+pub struct Batcher<C> {
+    pub client: Arc<C>,
+    pub has_multicall: bool,
+}
+
+impl<C: Middleware + 'static> Batcher<C> {
+    /// Checks whether the chain `client` is connected to has Multicall3.
+    pub async fn new(client: Arc<C>) -> Result<Self> {
+        let multicall: Address = MULTICALL3_ADDRESS.parse()?;
+        let has_multicall = !client.get_code(multicall, None).await?.is_empty();
+        Ok(Self {
+            client,
+            has_multicall,
+        })
+    }
+
+    /// Like `new`, but only checks for Multicall3 if `has_multicall` is unset,
+    /// and records the answer there for the next batchers.
+    pub async fn cached(client: Arc<C>, has_multicall: &OnceLock<bool>) -> Result<Self> {
+        if let Some(has_multicall) = has_multicall.get() {
+            return Ok(Self {
+                client,
+                has_multicall: *has_multicall,
+            });
+        }
+        let batcher = Self::new(client).await?;
+        let _ = has_multicall.set(batcher.has_multicall);
+        Ok(batcher)
+    }
+
+    /// Executes `calls` at `block`, or the latest block, returning the decoded
+    /// output of each call or `None` if it reverted.
+    pub async fn aggregate(
+        &self,
+        block: Option<u64>,
+        calls: Vec<BatchCall>,
+    ) -> Result<Vec<Option<Vec<Token>>>> {
+        let block_id = block.map(BlockId::from);
+
+        if !self.has_multicall {
+            let mut results = Vec::with_capacity(calls.len());
+            for call in calls {
+                let tx: TypedTransaction = TransactionRequest::new()
+                    .to(call.target)
+                    .data(call.data)
+                    .into();
+                let result = self.client.call(&tx, block_id).await.ok();
+                results.push(result.and_then(|data| call.function.decode_output(&data).ok()));
+            }
+            return Ok(results);
+        }
+
+        let multicall: Address = MULTICALL3_ADDRESS.parse()?;
+        let mut results = Vec::with_capacity(calls.len());
+        for chunk in calls.chunks(MAX_CALLS_PER_BATCH) {
+            let tx: TypedTransaction = TransactionRequest::new()
+                .to(multicall)
+                .data(encode_aggregate3(chunk))
+                .into();
+            let data = self.client.call(&tx, block_id).await?;
+            let outputs = decode_aggregate3(&data)?;
+            if outputs.len() != chunk.len() {
+                anyhow::bail!(
+                    "Multicall returned {} results for {} calls",
+                    outputs.len(),
+                    chunk.len()
+                );
+            }
+            results.extend(chunk.iter().zip(outputs).map(|(call, output)| {
+                output.and_then(|data| call.function.decode_output(&data).ok())
+            }));
+        }
+        Ok(results)
+    }
+
+    /// Reads the name, symbol and decimals of every token in `tokens`.
+    pub async fn token_metadata(&self, tokens: &[Address]) -> Result<Vec<TokenMetadata>> {
+        let mut calls = Vec::with_capacity(tokens.len() * 3);
+        for token in tokens {
+            let token = ERC20::new(*token, self.client.clone());
+            calls.push(BatchCall::new(&token.name())?);
+            calls.push(BatchCall::new(&token.symbol())?);
+            calls.push(BatchCall::new(&token.decimals())?);
+        }
+        let mut results = self.aggregate(None, calls).await?.into_iter();
+
+        Ok(tokens
+            .iter()
+            .map(|address| TokenMetadata {
+                address: *address,
+                name: decode(results.next().flatten()),
+                symbol: decode(results.next().flatten()),
+                decimals: decode(results.next().flatten()),
+            })
+            .collect())
+    }
+
+    /// Reads the balance of `account` of every token in `tokens`, or `None`
+    /// for the tokens that failed to answer.
+    pub async fn balances(
+        &self,
+        tokens: &[Address],
+        account: Address,
+    ) -> Result<Vec<Option<U256>>> {
+        let calls = tokens
+            .iter()
+            .map(|token| {
+                BatchCall::new(&ERC20::new(*token, self.client.clone()).balance_of(account))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(self
+            .aggregate(None, calls)
+            .await?
+            .into_iter()
+            .map(decode)
+            .collect())
+    }
+}
+
+impl<C: Middleware + 'static> ProtocolClient<C> {
+    /// Reads the state of every pool in `pool_ids` at the latest block, along
+    /// with the liquidity token balance of `account` if given.
+    pub async fn batch_pool_snapshots(
+        &self,
+        pool_ids: &[U256],
+        account: Option<Address>,
+    ) -> Result<Vec<PoolSnapshot>> {
+        let batcher = self.batcher().await?;
+        let block = self.client.get_block_number().await?.as_u64();
+
+        // The strategy and liquidity token of each pool are needed to know
+        // what else to read, so pools are read first.
+        let pools = self.batch_pools(&batcher, Some(block), pool_ids).await?;
+
+        let mut calls = Vec::new();
+        for (pool_id, pool) in pool_ids.iter().zip(&pools) {
+            let strategy = IStrategy::new(pool.strategy, self.client.clone());
+            let liquidity_token = ERC20::new(pool.liquidity_token, self.client.clone());
+            calls.push(BatchCall::new(&strategy.get_pool_params(*pool_id))?);
+            calls.push(BatchCall::new(&liquidity_token.total_supply())?);
+            if let Some(account) = account {
+                calls.push(BatchCall::new(&liquidity_token.balance_of(account))?);
+            }
+            match self.pool_kind(pool.strategy) {
+                Ok(PoolKind::G3M) => {
+                    calls.push(BatchCall::new(&self.g_solver()?.internal_price(*pool_id))?)
+                }
+                Ok(PoolKind::LogNormal) => {
+                    calls.push(BatchCall::new(&self.ln_solver.internal_price(*pool_id))?)
+                }
+                // StableSwap has no solver, its price is computed from the
+                // snapshot below.
+                Ok(PoolKind::StableSwap) | Err(_) => {}
+            }
+        }
+        let mut results = batcher.aggregate(Some(block), calls).await?.into_iter();
+
+        let mut snapshots = Vec::with_capacity(pools.len());
+        for (pool_id, pool) in pool_ids.iter().zip(pools) {
+            let params = decode::<Bytes>(results.next().flatten()).unwrap_or_default();
+            let liquidity_token_supply =
+                decode::<U256>(results.next().flatten()).unwrap_or_default();
+            let liquidity_token_balance = match account {
+                Some(_) => decode::<U256>(results.next().flatten()),
+                None => None,
+            };
+            let internal_price = match self.pool_kind(pool.strategy) {
+                Ok(PoolKind::StableSwap) => decode_stable_swap_params(&params)
+                    .and_then(|params| stable_swap_price(pool.reserve_x, pool.reserve_y, &params))
+                    .ok(),
+                Ok(_) => decode::<U256>(results.next().flatten()),
+                Err(_) => None,
+            };
+
+            snapshots.push(PoolSnapshot {
+                pool_id: *pool_id,
+                block,
+                pool,
+                params,
+                internal_price,
+                liquidity_token_supply,
+                liquidity_token_balance,
+            });
+        }
+
+        Ok(snapshots)
+    }
+
+    /// Reads the reserves and liquidity of every pool in `pool_ids`.
+    pub async fn batch_reserves_and_liquidity(
+        &self,
+        pool_ids: &[U256],
+    ) -> Result<Vec<(U256, U256, U256)>> {
+        let calls = pool_ids
+            .iter()
+            .map(|pool_id| BatchCall::new(&self.protocol.get_reserves_and_liquidity(*pool_id)))
+            .collect::<Result<Vec<_>>>()?;
+        self.batcher()
+            .await?
+            .aggregate(None, calls)
+            .await?
+            .into_iter()
+            .zip(pool_ids)
+            .map(|(result, pool_id)| {
+                decode(result).ok_or_else(|| {
+                    anyhow::anyhow!("Failed to read the reserves of pool {}", pool_id)
+                })
+            })
+            .collect()
+    }
+
+    /// Reads the parameters of every pool in `pool_ids` through the solver of
+    /// its strategy.
+    pub async fn batch_params(&self, pool_ids: &[U256]) -> Result<Vec<PoolParams>> {
+        let batcher = self.batcher().await?;
+        let pools = self.batch_pools(&batcher, None, pool_ids).await?;
+        let kinds = pools
+            .iter()
+            .map(|pool| self.pool_kind(pool.strategy))
+            .collect::<Result<Vec<_>>>()?;
+
+        let calls = pool_ids
+            .iter()
+            .zip(pools.iter().zip(&kinds))
+            .map(|(pool_id, (pool, kind))| match kind {
+                PoolKind::G3M => BatchCall::new(&self.g_solver()?.fetch_pool_params(*pool_id)),
+                PoolKind::LogNormal => BatchCall::new(&self.ln_solver.fetch_pool_params(*pool_id)),
+                PoolKind::StableSwap => BatchCall::new(
+                    &IStrategy::new(pool.strategy, self.client.clone()).get_pool_params(*pool_id),
+                ),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        batcher
+            .aggregate(None, calls)
+            .await?
+            .into_iter()
+            .zip(kinds)
+            .zip(pool_ids)
+            .map(|((result, kind), pool_id)| {
+                let params = match kind {
+                    PoolKind::G3M => decode(result).map(PoolParams::G3M),
+                    PoolKind::LogNormal => decode(result).map(PoolParams::LogNormal),
+                    PoolKind::StableSwap => decode::<Bytes>(result)
+                        .and_then(|data| decode_stable_swap_params(&data).ok())
+                        .map(PoolParams::StableSwap),
+                };
+                params
+                    .ok_or_else(|| anyhow::anyhow!("Failed to read the params of pool {}", pool_id))
+            })
+            .collect()
+    }
+
+    /// Reads the name, symbol and decimals of every token in `tokens`.
+    pub async fn batch_token_metadata(&self, tokens: &[Address]) -> Result<Vec<TokenMetadata>> {
+        self.batcher().await?.token_metadata(tokens).await
+    }
+
+    async fn batch_pools(
+        &self,
+        batcher: &Batcher<C>,
+        block: Option<u64>,
+        pool_ids: &[U256],
+    ) -> Result<Vec<PoolStruct>> {
+        let calls = pool_ids
+            .iter()
+            .map(|pool_id| BatchCall::new(&self.protocol.get_pool(*pool_id)))
+            .collect::<Result<Vec<_>>>()?;
+        batcher
+            .aggregate(block, calls)
+            .await?
+            .into_iter()
+            .zip(pool_ids)
+            .map(|(result, pool_id)| {
+                decode::<PoolStruct>(result)
+                    .ok_or_else(|| anyhow::anyhow!("Failed to read pool {}", pool_id))
+            })
+            .collect()
+    }
+
+    /// Batcher over the client. Whether the chain has Multicall3 is only
+    /// checked once, and shared by the clones of the client.
+    async fn batcher(&self) -> Result<Batcher<C>> {
+        Batcher::cached(self.client.clone(), &self.has_multicall).await
+    }
+}
+
+/// Decodes the output of a batched call as `D`.
+pub fn decode<D: Detokenize>(tokens: Option<Vec<Token>>) -> Option<D> {
+    tokens.and_then(|tokens| D::from_tokens(tokens).ok())
+}
+
+/// Encodes `aggregate3((address,bool,bytes)[])`, allowing every call to fail.
+fn encode_aggregate3(calls: &[BatchCall]) -> Bytes {
+    let calls = calls
+        .iter()
+        .map(|call| {
+            Token::Tuple(vec![
+                Token::Address(call.target),
+                Token::Bool(true),
+                Token::Bytes(call.data.to_vec()),
+            ])
+        })
+        .collect();
+    let mut data = id("aggregate3((address,bool,bytes)[])").to_vec();
+    data.extend(abi::encode(&[Token::Array(calls)]));
+    data.into()
+}
+
+/// Decodes the `(bool success, bytes returnData)[]` returned by `aggregate3`.
+fn decode_aggregate3(data: &[u8]) -> Result<Vec<Option<Vec<u8>>>> {
+    let result_type = ParamType::Tuple(vec![ParamType::Bool, ParamType::Bytes]);
+    let tokens = abi::decode(&[ParamType::Array(Box::new(result_type))], data)?;
+    let Some(Token::Array(results)) = tokens.into_iter().next() else {
+        anyhow::bail!("Failed to decode multicall results");
+    };
+    results
+        .into_iter()
+        .map(|result| match result {
+            Token::Tuple(fields) => match fields.as_slice() {
+                [Token::Bool(success), Token::Bytes(data)] => Ok(success.then(|| data.clone())),
+                _ => anyhow::bail!("Failed to decode multicall result"),
+            },
+            _ => anyhow::bail!("Failed to decode multicall result"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[allow(deprecated)]
+    fn aggregate3_encoding() {
+        let call = BatchCall {
+            target: Address::repeat_byte(1),
+            data: vec![0xde, 0xad].into(),
+            function: Function {
+                name: "f".to_string(),
+                inputs: vec![],
+                outputs: vec![],
+                constant: None,
+                state_mutability: abi::StateMutability::View,
+            },
+        };
+        let data = encode_aggregate3(&[call]);
+        assert_eq!(data[..4], [0x82, 0xad, 0x56, 0xcb]);
+
+        let returned = abi::encode(&[Token::Array(vec![
+            Token::Tuple(vec![Token::Bool(true), Token::Bytes(vec![1, 2])]),
+            Token::Tuple(vec![Token::Bool(false), Token::Bytes(vec![])]),
+        ])]);
+        assert_eq!(
+            decode_aggregate3(&returned).unwrap(),
+            vec![Some(vec![1, 2]), None]
+        );
+    }
+}
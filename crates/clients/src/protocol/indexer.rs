@@ -0,0 +1,230 @@
+//! Discovers every pool created on a DFMM deployment.
+//!
+//! The index scans the `Init` events of the DFMM contract and keeps a catalog
+//! of the pools it finds. Syncs are incremental: each one only scans the blocks
+//! after `last_sync_block`, and the index can be saved to disk and loaded back
+//! to pick up where it left off.
+
+use std::{fs, path::Path};
+
+use bindings::dfmm::InitFilter;
+use ethers::contract::parse_log;
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+/// Largest range of blocks requested in a single `eth_getLogs` call, since most
+/// providers reject larger ones.
+pub const MAX_BLOCK_RANGE: u64 = 10_000;
+
+/// A pool found by the indexer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolRecord {
+    pub pool_id: U256,
+    pub strategy: Address,
+    pub token_x: Address,
+    pub token_y: Address,
+    pub controller: Address,
+    pub liquidity_token: Address,
+    /// Account that initialized the pool.
+    pub creator: Address,
+    pub creation_block: u64,
+}
+
+/// Catalog of the pools of a DFMM deployment.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolIndex {
+    pub dfmm: Address,
+    /// First block scanned, usually the deployment block of DFMM.
+    pub start_block: u64,
+    /// Last block scanned, if the index has been synced.
+    pub last_sync_block: Option<u64>,
+    /// Pools ordered by id.
+    pub pools: Vec<PoolRecord>,
+}
+
+impl PoolIndex {
+    pub fn new(dfmm: Address, start_block: u64) -> Self {
+        Self {
+            dfmm,
+            start_block,
+            last_sync_block: None,
+            pools: Vec::new(),
+        }
+    }
+
+    /// Index of the DFMM deployment at `protocol`, starting at the block it was
+    /// deployed in so the blocks before it are never scanned.
+    pub async fn for_deployment<C: Middleware + 'static>(protocol: &DFMM<C>) -> Result<Self> {
+        let start_block = deployment_block(protocol.client().as_ref(), protocol.address()).await?;
+        Ok(Self::new(protocol.address(), start_block))
+    }
+
+    /// Scans the blocks since the last sync up to the latest block, and adds
+    /// the pools initialized in them. Returns the number of new pools.
+    pub async fn sync<C: Middleware + 'static>(&mut self, protocol: &DFMM<C>) -> Result<usize> {
+        if protocol.address() != self.dfmm {
+            anyhow::bail!("Protocol is a different DFMM deployment");
+        }
+
+        let latest_block = protocol.client().get_block_number().await?.as_u64();
+        let mut from_block = self
+            .last_sync_block
+            .map_or(self.start_block, |block| block + 1);
+        let count = self.pools.len();
+
+        while from_block <= latest_block {
+            let to_block = (from_block + MAX_BLOCK_RANGE - 1).min(latest_block);
+            self.scan(protocol, from_block, to_block).await?;
+            // Record progress after every range, so a failed sync can resume.
+            self.last_sync_block = Some(to_block);
+            from_block = to_block + 1;
+        }
+
+        Ok(self.pools.len() - count)
+    }
+
+    async fn scan<C: Middleware + 'static>(
+        &mut self,
+        protocol: &DFMM<C>,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<()> {
+        let filter = protocol
+            .init_filter()
+            .filter
+            .from_block(from_block)
+            .to_block(to_block);
+        let logs = protocol.client().get_logs(&filter).await?;
+
+        for log in logs {
+            let creation_block = log
+                .block_number
+                .ok_or_else(|| anyhow::anyhow!("Init log without block number"))?
+                .as_u64();
+            let event = parse_log::<InitFilter>(log)?;
+            if self.get(event.pool_id).is_some() {
+                continue;
+            }
+
+            let pool = protocol.get_pool(event.pool_id).call().await?;
+            self.pools.push(PoolRecord {
+                pool_id: event.pool_id,
+                strategy: event.strategy,
+                token_x: event.token_x,
+                token_y: event.token_y,
+                controller: pool.controller,
+                liquidity_token: pool.liquidity_token,
+                creator: event.account,
+                creation_block,
+            });
+        }
+
+        self.pools.sort_by_key(|pool| pool.pool_id);
+        Ok(())
+    }
+
+    pub fn get(&self, pool_id: U256) -> Option<&PoolRecord> {
+        self.pools
+            .binary_search_by_key(&pool_id, |pool| pool.pool_id)
+            .ok()
+            .map(|index| &self.pools[index])
+    }
+
+    /// Pools trading `token` on either side.
+    pub fn with_token(&self, token: Address) -> impl Iterator<Item = &PoolRecord> {
+        self.pools
+            .iter()
+            .filter(move |pool| pool.token_x == token || pool.token_y == token)
+    }
+
+    /// Pools using the strategy at `strategy`.
+    pub fn with_strategy(&self, strategy: Address) -> impl Iterator<Item = &PoolRecord> {
+        self.pools
+            .iter()
+            .filter(move |pool| pool.strategy == strategy)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let data = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&data)?)
+    }
+}
+
+/// Finds the block the contract at `address` was deployed in, by searching for
+/// the first block it has code at. Needs a node that serves historical state.
+pub async fn deployment_block<C: Middleware + 'static>(
+    client: &C,
+    address: Address,
+) -> Result<u64> {
+    let latest_block = client.get_block_number().await?.as_u64();
+    if client
+        .get_code(address, Some(latest_block.into()))
+        .await?
+        .is_empty()
+    {
+        anyhow::bail!("No contract deployed at {:?}", address);
+    }
+
+    // The contract has code at `high`, and none before `low`.
+    let (mut low, mut high) = (0, latest_block);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if client.get_code(address, Some(mid.into())).await?.is_empty() {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    Ok(high)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(pool_id: u64, token_x: Address, token_y: Address) -> PoolRecord {
+        PoolRecord {
+            pool_id: U256::from(pool_id),
+            strategy: Address::repeat_byte(0xaa),
+            token_x,
+            token_y,
+            controller: Address::zero(),
+            liquidity_token: Address::repeat_byte(pool_id as u8),
+            creator: Address::repeat_byte(0xcc),
+            creation_block: 100 + pool_id,
+        }
+    }
+
+    #[test]
+    fn lookup_and_persistence() {
+        let (x, y, z) = (
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+            Address::repeat_byte(3),
+        );
+        let mut index = PoolIndex::new(Address::repeat_byte(0xdf), 42);
+        index.pools = vec![record(0, x, y), record(1, y, z), record(2, x, z)];
+        index.last_sync_block = Some(1_000);
+
+        assert_eq!(index.get(U256::from(1)), Some(&index.pools[1]));
+        assert_eq!(index.get(U256::from(3)), None);
+        assert_eq!(index.with_token(z).count(), 2);
+        assert_eq!(index.with_strategy(Address::repeat_byte(0xaa)).count(), 3);
+
+        let dir = std::env::temp_dir().join(format!("dfmm_pool_index_test_{}", std::process::id()));
+        let path = dir.join("index.json");
+        index.save(&path).unwrap();
+        assert_eq!(PoolIndex::load(&path).unwrap(), index);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
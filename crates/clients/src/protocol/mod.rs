@@ -1,9 +1,15 @@
 //! Dynamic Function Market Making Protocol Client
 //!
 //! Middleware layer for agents to communicate with the DFMM protocol.
+pub mod batch;
+pub mod indexer;
 pub mod pool;
+pub mod trade;
 
-use std::{sync::Arc, time::Duration};
+use std::{
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
 
 use anyhow::Result;
 use bindings::{
@@ -11,6 +17,7 @@ use bindings::{
     g3m::G3M,
     g3m_helper::G3MHelper,
     g3m_solver::G3MSolver,
+    i_strategy::IStrategy,
     log_normal::LogNormal,
     log_normal_helper::LogNormalHelper,
     log_normal_solver::LogNormalSolver,
@@ -18,10 +25,18 @@ use bindings::{
         G3Mparams as G3mParameters, InitParams, LogNormalParams as LogNormalParameters,
     },
 };
-use ethers::utils::parse_ether;
+use cfmm_math::trading_functions::stable_swap;
+use ethers::{
+    abi::{self, ParamType, Token},
+    types::transaction::eip2718::TypedTransaction,
+    utils::{format_ether, parse_ether},
+};
 use pool::{Pool, PoolKind};
 
-use super::*;
+use super::{
+    gas::{self, FeePreset, FeeSuggestion},
+    *,
+};
 
 #[derive(Debug, Clone)]
 pub struct G3mF64 {
@@ -37,16 +52,31 @@ pub struct LogNormalF64 {
     pub swap_fee: f64,
 }
 
+#[derive(Debug, Clone)]
+pub struct StableSwapF64 {
+    pub amplification: f64,
+    pub swap_fee: f64,
+}
+
 #[derive(Debug, Clone)]
 pub enum PoolInitParamsF64 {
     G3M(G3mF64),
     LogNormal(LogNormalF64),
+    StableSwap(StableSwapF64),
+}
+
+/// Parameterization of the StableSwap curve, in WAD units.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StableSwapParameters {
+    pub amplification: U256,
+    pub swap_fee: U256,
 }
 
 #[derive(Debug, Clone)]
 pub enum PoolParams {
     G3M(G3mParameters),
     LogNormal(LogNormalParameters),
+    StableSwap(StableSwapParameters),
 }
 
 #[derive(Debug)]
@@ -55,10 +85,18 @@ pub struct ProtocolClient<C> {
     pub protocol: DFMM<C>,
     pub ln_solver: LogNormalSolver<C>,
     pub ln_strategy: LogNormal<C>,
-    pub ln_helper: LogNormalHelper<C>,
-    pub g_solver: G3MSolver<C>,
-    pub g_strategy: G3M<C>,
-    pub g_helper: G3MHelper<C>,
+    /// The helper and G3M contracts are missing from networks they weren't
+    /// deployed to along with the protocol. See [`Self::ln_helper()`] and the
+    /// other accessors.
+    pub ln_helper: Option<LogNormalHelper<C>>,
+    pub g_solver: Option<G3MSolver<C>>,
+    pub g_strategy: Option<G3M<C>>,
+    pub g_helper: Option<G3MHelper<C>>,
+    /// Address of a deployed StableSwap strategy, if any. StableSwap has no
+    /// solver contract, so its math is computed off-chain with `cfmm_math`.
+    pub ss_strategy: Option<Address>,
+    /// Whether the chain has Multicall3, once checked by the batched reads.
+    has_multicall: Arc<OnceLock<bool>>,
 }
 
 impl<C> Clone for ProtocolClient<C> {
@@ -72,6 +110,8 @@ impl<C> Clone for ProtocolClient<C> {
             g_solver: self.g_solver.clone(),
             g_strategy: self.g_strategy.clone(),
             g_helper: self.g_helper.clone(),
+            ss_strategy: self.ss_strategy,
+            has_multicall: self.has_multicall.clone(),
         }
     }
 }
@@ -114,46 +154,102 @@ impl<C: Middleware + 'static> ProtocolClient<C> {
             protocol,
             ln_solver,
             ln_strategy,
-            ln_helper,
-            g_solver,
-            g_strategy,
-            g_helper,
+            ln_helper: Some(ln_helper),
+            g_solver: Some(g_solver),
+            g_strategy: Some(g_strategy),
+            g_helper: Some(g_helper),
+            ss_strategy: None,
+            has_multicall: Arc::default(),
         })
     }
 
+    /// Client of contracts that are already deployed. The helper and G3M
+    /// contracts are optional, and the calls that need them fail without
+    /// them.
     #[allow(clippy::too_many_arguments)]
     pub fn from_deployed(
         client: Arc<C>,
         protocol_addr: Address,
         ln_strategy_addr: Address,
         ln_solver_addr: Address,
-        ln_helper_addr: Address,
-        g_strategy_addr: Address,
-        g_solver_addr: Address,
-        g_helper_addr: Address,
+        ln_helper_addr: Option<Address>,
+        g_strategy_addr: Option<Address>,
+        g_solver_addr: Option<Address>,
+        g_helper_addr: Option<Address>,
     ) -> Result<Self> {
         Ok(Self {
             client: client.clone(),
             protocol: DFMM::new(protocol_addr, client.clone()),
             ln_strategy: LogNormal::new(ln_strategy_addr, client.clone()),
             ln_solver: LogNormalSolver::new(ln_solver_addr, client.clone()),
-            ln_helper: LogNormalHelper::new(ln_helper_addr, client.clone()),
-            g_strategy: G3M::new(g_strategy_addr, client.clone()),
-            g_solver: G3MSolver::new(g_solver_addr, client.clone()),
-            g_helper: G3MHelper::new(g_helper_addr, client.clone()),
+            ln_helper: ln_helper_addr.map(|addr| LogNormalHelper::new(addr, client.clone())),
+            g_strategy: g_strategy_addr.map(|addr| G3M::new(addr, client.clone())),
+            g_solver: g_solver_addr.map(|addr| G3MSolver::new(addr, client.clone())),
+            g_helper: g_helper_addr.map(|addr| G3MHelper::new(addr, client.clone())),
+            ss_strategy: None,
+            has_multicall: Arc::default(),
         })
     }
 
+    /// The LogNormal helper, if it's deployed.
+    pub fn ln_helper(&self) -> Result<&LogNormalHelper<C>> {
+        self.ln_helper
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No LogNormal helper deployed"))
+    }
+
+    /// The G3M strategy, if it's deployed.
+    pub fn g_strategy(&self) -> Result<&G3M<C>> {
+        self.g_strategy
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No G3M strategy deployed"))
+    }
+
+    /// The G3M solver, if it's deployed.
+    pub fn g_solver(&self) -> Result<&G3MSolver<C>> {
+        self.g_solver
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No G3M solver deployed"))
+    }
+
+    /// The G3M helper, if it's deployed.
+    pub fn g_helper(&self) -> Result<&G3MHelper<C>> {
+        self.g_helper
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No G3M helper deployed"))
+    }
+
+    /// Registers a deployed StableSwap strategy, so pools using it can be
+    /// initialized and read through this client.
+    pub fn with_stable_swap(mut self, ss_strategy_addr: Address) -> Self {
+        self.ss_strategy = Some(ss_strategy_addr);
+        self
+    }
+
     pub fn connect(&self, client: Arc<C>) -> Result<Self> {
         Ok(Self {
             client: client.clone(),
             protocol: self.protocol.connect(client.clone()).into(),
             ln_strategy: self.ln_strategy.connect(client.clone()).into(),
             ln_solver: self.ln_solver.connect(client.clone()).into(),
-            ln_helper: self.ln_helper.connect(client.clone()).into(),
-            g_strategy: self.g_strategy.connect(client.clone()).into(),
-            g_solver: self.g_solver.connect(client.clone()).into(),
-            g_helper: self.g_helper.connect(client.clone()).into(),
+            ln_helper: self
+                .ln_helper
+                .as_ref()
+                .map(|helper| helper.connect(client.clone()).into()),
+            g_strategy: self
+                .g_strategy
+                .as_ref()
+                .map(|strategy| strategy.connect(client.clone()).into()),
+            g_solver: self
+                .g_solver
+                .as_ref()
+                .map(|solver| solver.connect(client.clone()).into()),
+            g_helper: self
+                .g_helper
+                .as_ref()
+                .map(|helper| helper.connect(client.clone()).into()),
+            ss_strategy: self.ss_strategy,
+            has_multicall: self.has_multicall.clone(),
         })
     }
 
@@ -163,25 +259,31 @@ impl<C: Middleware + 'static> ProtocolClient<C> {
         let token_x = pool_data.token_x;
         let token_y = pool_data.token_y;
         let strategy = pool_data.strategy;
-        let ln_address = self.ln_strategy.address();
-        let g_address = self.g_strategy.address();
-
-        let kind = match strategy {
-            _ if strategy == ln_address => PoolKind::LogNormal,
-            _ if strategy == g_address => PoolKind::G3M,
-            _ => anyhow::bail!("Invalid strategy address"),
-        };
+        let kind = self.pool_kind(strategy)?;
 
         let pool = Pool {
             kind,
             token_x,
             token_y,
             pool_id,
+            strategy,
         };
 
         Ok(pool)
     }
 
+    /// Kind of the pools using the strategy at `strategy`.
+    pub fn pool_kind(&self, strategy: Address) -> Result<PoolKind> {
+        match strategy {
+            _ if strategy == self.ln_strategy.address() => Ok(PoolKind::LogNormal),
+            _ if Some(strategy) == self.g_strategy.as_ref().map(|g3m| g3m.address()) => {
+                Ok(PoolKind::G3M)
+            }
+            _ if Some(strategy) == self.ss_strategy => Ok(PoolKind::StableSwap),
+            _ => anyhow::bail!("Invalid strategy address"),
+        }
+    }
+
     pub async fn update_controller(&self, pool_id: U256, new_controller: Address) -> Result<()> {
         self.protocol
             .update_controller(pool_id, new_controller)
@@ -204,12 +306,12 @@ impl<C: Middleware + 'static> ProtocolClient<C> {
         match pool_params {
             PoolParams::G3M(g3m_params) => {
                 let init_data = self
-                    .g_solver
+                    .g_solver()?
                     .get_initial_pool_data(init_reserve_x_wad, init_price_wad, g3m_params)
                     .call()
                     .await?;
                 let init_params: InitParams = InitParams {
-                    strategy: self.g_strategy.address(),
+                    strategy: self.g_strategy()?.address(),
                     token_x,
                     token_y,
                     data: init_data,
@@ -234,6 +336,19 @@ impl<C: Middleware + 'static> ProtocolClient<C> {
 
                 let tx = self.initialize_pool(init_params).await?.unwrap();
 
+                Ok(tx)
+            }
+            PoolParams::StableSwap(stable_swap_params) => {
+                let init_params = self.get_stable_swap_init_payload(
+                    token_x,
+                    token_y,
+                    init_reserve_x_wad,
+                    init_price_wad,
+                    stable_swap_params,
+                )?;
+
+                let tx = self.initialize_pool(init_params).await?.unwrap();
+
                 Ok(tx)
             }
         }
@@ -256,12 +371,12 @@ impl<C: Middleware + 'static> ProtocolClient<C> {
         match pool_params {
             PoolParams::G3M(g3m_params) => {
                 let init_data = self
-                    .g_solver
+                    .g_solver()?
                     .get_initial_pool_data(init_reserve_x_wad, init_price_wad, g3m_params)
                     .call()
                     .await?;
                 let init_params: InitParams = InitParams {
-                    strategy: self.g_strategy.address(),
+                    strategy: self.g_strategy()?.address(),
                     token_x,
                     token_y,
                     data: init_data,
@@ -283,13 +398,69 @@ impl<C: Middleware + 'static> ProtocolClient<C> {
                 };
                 Ok(init_params)
             }
+            PoolParams::StableSwap(stable_swap_params) => self.get_stable_swap_init_payload(
+                token_x,
+                token_y,
+                init_reserve_x_wad,
+                init_price_wad,
+                stable_swap_params,
+            ),
         }
     }
 
+    /// Computes the initial reserves and liquidity of a StableSwap pool
+    /// off-chain, encoded as `(rx, ry, L, amplification, swapFee, controller)`.
+    fn get_stable_swap_init_payload(
+        &self,
+        token_x: Address,
+        token_y: Address,
+        init_reserve_x_wad: U256,
+        init_price_wad: U256,
+        params: StableSwapParameters,
+    ) -> Result<InitParams> {
+        let Some(strategy) = self.ss_strategy else {
+            anyhow::bail!("No StableSwap strategy registered on the protocol client");
+        };
+
+        let reserve_x = from_wad(init_reserve_x_wad)?;
+        let price = from_wad(init_price_wad)?;
+        let amplification = from_wad(params.amplification)?;
+        let (reserve_y, liquidity) =
+            stable_swap::compute_initial_reserves(reserve_x, price, amplification);
+
+        let controller = self.client.default_sender().unwrap_or_default();
+        let data = abi::encode(&[
+            Token::Uint(init_reserve_x_wad),
+            Token::Uint(to_wad(reserve_y)?),
+            Token::Uint(to_wad(liquidity)?),
+            Token::Uint(params.amplification),
+            Token::Uint(params.swap_fee),
+            Token::Address(controller),
+        ]);
+
+        Ok(InitParams {
+            strategy,
+            token_x,
+            token_y,
+            data: data.into(),
+        })
+    }
+
     pub async fn initialize_pool(&self, payload: InitParams) -> Result<Option<TransactionReceipt>> {
-        let tx = self
-            .protocol
-            .init(payload)
+        self.initialize_pool_with_fees(payload, None).await
+    }
+
+    /// Initializes a pool, paying `fees` if given or the provider's otherwise.
+    pub async fn initialize_pool_with_fees(
+        &self,
+        payload: InitParams,
+        fees: Option<&FeePreset>,
+    ) -> Result<Option<TransactionReceipt>> {
+        let mut call = self.protocol.init(payload);
+        if let Some(fees) = fees {
+            fees.apply(&mut call.tx);
+        }
+        let tx = call
             .send()
             .await?
             .confirmations(0)
@@ -307,6 +478,7 @@ impl<C: Middleware + 'static> ProtocolClient<C> {
         init_reserve_x_wad: U256,
         init_price_wad: U256,
         init_params: PoolInitParamsF64,
+        fees: Option<&FeePreset>,
     ) -> Result<Option<TransactionReceipt>> {
         let payload = self
             .get_init_payload(
@@ -318,7 +490,67 @@ impl<C: Middleware + 'static> ProtocolClient<C> {
             )
             .await?;
 
-        self.initialize_pool(payload).await
+        self.initialize_pool_with_fees(payload, fees).await
+    }
+
+    /// The transaction `create_position` would send, to send it some other
+    /// way, such as through a `TransactionManager`.
+    pub async fn create_position_transaction(
+        &self,
+        token_x: Address,
+        token_y: Address,
+        init_reserve_x_wad: U256,
+        init_price_wad: U256,
+        init_params: PoolInitParamsF64,
+        fees: Option<&FeePreset>,
+    ) -> Result<TypedTransaction> {
+        let payload = self
+            .get_init_payload(
+                token_x,
+                token_y,
+                init_reserve_x_wad,
+                init_price_wad,
+                init_params,
+            )
+            .await?;
+
+        Ok(self.init_transaction(payload, fees))
+    }
+
+    /// The transaction initializing a pool, paying `fees` if given or the
+    /// provider's otherwise.
+    fn init_transaction(&self, payload: InitParams, fees: Option<&FeePreset>) -> TypedTransaction {
+        let mut tx = self.protocol.init(payload).tx;
+        if let Some(fees) = fees {
+            fees.apply(&mut tx);
+        }
+        tx
+    }
+
+    /// Gas limit of the transaction `create_position` would send, with a
+    /// margin, and the fees suggested for it, so its cost can be shown before
+    /// it is sent.
+    pub async fn estimate_create_position(
+        &self,
+        token_x: Address,
+        token_y: Address,
+        init_reserve_x_wad: U256,
+        init_price_wad: U256,
+        init_params: PoolInitParamsF64,
+    ) -> Result<(U256, FeeSuggestion)> {
+        let payload = self
+            .get_init_payload(
+                token_x,
+                token_y,
+                init_reserve_x_wad,
+                init_price_wad,
+                init_params,
+            )
+            .await?;
+
+        let gas = self.protocol.init(payload).estimate_gas().await?;
+        let suggestion = FeeSuggestion::fetch(self.client.as_ref()).await?;
+        Ok((gas::with_margin(gas.as_u64()), suggestion))
     }
 
     #[tracing::instrument(skip(self), level = "trace", ret)]
@@ -326,31 +558,31 @@ impl<C: Middleware + 'static> ProtocolClient<C> {
         let pool = self.get_pool(pool_id).await?;
 
         match pool.kind {
-            PoolKind::G3M => Ok(self.g_solver.internal_price(pool_id).call().await?),
+            PoolKind::G3M => Ok(self.g_solver()?.internal_price(pool_id).call().await?),
             PoolKind::LogNormal => Ok(self.ln_solver.internal_price(pool_id).call().await?),
+            PoolKind::StableSwap => {
+                let PoolParams::StableSwap(params) = self.get_params(pool_id).await? else {
+                    anyhow::bail!("Failed to parse StableSwap params");
+                };
+                let (rx, ry, _) = self.get_reserves_and_liquidity(pool_id).await?;
+                stable_swap_price(rx, ry, &params)
+            }
         }
     }
 
     pub async fn get_reserves_and_liquidity(&self, pool_id: U256) -> Result<(U256, U256, U256)> {
-        Ok(self
-            .protocol
-            .get_reserves_and_liquidity(pool_id)
-            .call()
-            .await?)
+        self.batch_reserves_and_liquidity(&[pool_id])
+            .await?
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("Failed to read the reserves of pool {}", pool_id))
     }
 
     #[tracing::instrument(skip(self), level = "trace", ret)]
     pub async fn get_params(&self, pool_id: U256) -> Result<PoolParams> {
-        let pool = self.get_pool(pool_id).await?;
-
-        match pool.kind {
-            PoolKind::G3M => Ok(PoolParams::G3M(
-                self.g_solver.fetch_pool_params(pool_id).call().await?,
-            )),
-            PoolKind::LogNormal => Ok(PoolParams::LogNormal(
-                self.ln_solver.fetch_pool_params(pool_id).call().await?,
-            )),
-        }
+        self.batch_params(&[pool_id])
+            .await?
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("Failed to read the params of pool {}", pool_id))
     }
 
     #[tracing::instrument(skip(self), level = "trace", ret)]
@@ -360,10 +592,10 @@ impl<C: Middleware + 'static> ProtocolClient<C> {
         target_strike_price: f64,
         next_timestamp: u64,
     ) -> Result<Option<TransactionReceipt>> {
-        let target_strike_wad = to_wad(target_strike_price);
+        let target_strike_wad = to_wad(target_strike_price)?;
         let timestamp_wad = ethers::types::U256::from(next_timestamp);
         let update_data = self
-            .ln_helper
+            .ln_helper()?
             .prepare_strike_update(target_strike_wad, timestamp_wad)
             .call()
             .await?;
@@ -376,16 +608,39 @@ impl<C: Middleware + 'static> ProtocolClient<C> {
         Ok(tx)
     }
 
+    #[tracing::instrument(skip(self), level = "trace", ret)]
+    pub async fn set_tau(
+        &self,
+        pool_id: U256,
+        target_tau: f64,
+        next_timestamp: u64,
+    ) -> Result<Option<TransactionReceipt>> {
+        let target_tau_wad = to_wad(target_tau)?;
+        let timestamp_wad = ethers::types::U256::from(next_timestamp);
+        let update_data = self
+            .ln_helper()?
+            .prepare_tau_update(target_tau_wad, timestamp_wad)
+            .call()
+            .await?;
+        let tx = self
+            .protocol
+            .update(pool_id, update_data)
+            .send()
+            .await?
+            .await?;
+        Ok(tx)
+    }
+
     pub async fn set_weight_x(
         &self,
         pool_id: U256,
         target_wx: f64,
         next_timestamp: u64,
     ) -> Result<Option<TransactionReceipt>> {
-        let target_wx_wad = to_wad(target_wx);
+        let target_wx_wad = to_wad(target_wx)?;
         let timestamp_wad = ethers::types::U256::from(next_timestamp);
         let update_data = self
-            .g_helper
+            .g_helper()?
             .prepare_weight_x_update(target_wx_wad, timestamp_wad)
             .call()
             .await?;
@@ -399,22 +654,65 @@ impl<C: Middleware + 'static> ProtocolClient<C> {
     }
 }
 
-fn to_wad(value: f64) -> U256 {
-    parse_ether(value).unwrap()
+fn to_wad(value: f64) -> Result<U256> {
+    if !value.is_finite() || value < 0.0 {
+        anyhow::bail!("Cannot convert {} to a WAD", value);
+    }
+    Ok(parse_ether(value)?)
+}
+
+fn from_wad(value: U256) -> Result<f64> {
+    Ok(format_ether(value).parse::<f64>()?)
+}
+
+/// StableSwap has no solver contract, so its price is computed off-chain from
+/// the reserves.
+fn stable_swap_price(
+    reserve_x: U256,
+    reserve_y: U256,
+    params: &StableSwapParameters,
+) -> Result<U256> {
+    let price = stable_swap::compute_spot_price(
+        from_wad(reserve_x)?,
+        from_wad(reserve_y)?,
+        from_wad(params.amplification)?,
+    );
+    to_wad(price)
+}
+
+/// Decodes the `(amplification, swapFee)` returned by the StableSwap
+/// strategy's `getPoolParams`.
+fn decode_stable_swap_params(data: &Bytes) -> Result<StableSwapParameters> {
+    let tokens = abi::decode(&[ParamType::Uint(256), ParamType::Uint(256)], data)?;
+    match tokens.as_slice() {
+        [Token::Uint(amplification), Token::Uint(swap_fee)] => Ok(StableSwapParameters {
+            amplification: *amplification,
+            swap_fee: *swap_fee,
+        }),
+        _ => anyhow::bail!("Failed to decode StableSwap params"),
+    }
 }
 
 fn to_init_params_wad(init_params: PoolInitParamsF64) -> Result<PoolParams> {
     match init_params {
         PoolInitParamsF64::G3M(g3m_params) => Ok(PoolParams::G3M(G3mParameters {
-            w_x: to_wad(g3m_params.wx),
-            w_y: to_wad(1.0) - to_wad(g3m_params.wx),
-            swap_fee: to_wad(g3m_params.swap_fee),
+            w_x: to_wad(g3m_params.wx)?,
+            w_y: to_wad(1.0)?
+                .checked_sub(to_wad(g3m_params.wx)?)
+                .ok_or_else(|| anyhow::anyhow!("Weight {} is above 1", g3m_params.wx))?,
+            swap_fee: to_wad(g3m_params.swap_fee)?,
         })),
         PoolInitParamsF64::LogNormal(ln_params) => Ok(PoolParams::LogNormal(LogNormalParameters {
-            sigma: to_wad(ln_params.sigma),
-            strike: to_wad(ln_params.strike),
-            tau: to_wad(ln_params.tau),
-            swap_fee: to_wad(ln_params.swap_fee),
+            sigma: to_wad(ln_params.sigma)?,
+            strike: to_wad(ln_params.strike)?,
+            tau: to_wad(ln_params.tau)?,
+            swap_fee: to_wad(ln_params.swap_fee)?,
         })),
+        PoolInitParamsF64::StableSwap(ss_params) => {
+            Ok(PoolParams::StableSwap(StableSwapParameters {
+                amplification: to_wad(ss_params.amplification)?,
+                swap_fee: to_wad(ss_params.swap_fee)?,
+            }))
+        }
     }
 }
@@ -4,6 +4,7 @@ use super::*;
 pub enum PoolKind {
     G3M,
     LogNormal,
+    StableSwap,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -12,6 +13,7 @@ pub struct Pool {
     pub token_x: Address,
     pub token_y: Address,
     pub pool_id: U256,
+    pub strategy: Address,
 }
 
 impl Pool {
@@ -20,12 +22,14 @@ impl Pool {
         token_x: Address,
         token_y: Address,
         pool_id: U256,
+        strategy: Address,
     ) -> Result<Self> {
         Ok(Self {
             kind,
             token_x,
             token_y,
             pool_id,
+            strategy,
         })
     }
 }
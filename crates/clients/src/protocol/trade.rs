@@ -0,0 +1,597 @@
+//! Swaps, allocations and deallocations through the DFMM contract.
+//!
+//! Each operation is quoted through the solver of the pool's strategy (or
+//! off-chain with `cfmm_math` for StableSwap, which has no solver), checked
+//! against a slippage tolerance and deadline, and the settled amounts are
+//! decoded from the event DFMM emits.
+//!
+//! DFMM takes the next reserves and liquidity rather than amounts, so it has no
+//! notion of slippage or deadline itself. Both are enforced here: the deadline
+//! against the latest block, and the slippage against a simulation of the call
+//! on the latest state right before it is sent. The state can still move before
+//! the transaction is mined, so the amounts settled in the receipt are checked
+//! against the same bounds and an error is returned if they were crossed.
+
+use bindings::dfmm::{AllocateFilter, DeallocateFilter, SwapFilter};
+use cfmm_math::trading_functions::{stable_swap::StableSwap, TradingFunction};
+use ethers::contract::{parse_log, EthEvent};
+
+use super::*;
+
+/// Quote for a swap, along with the payload to submit it.
+#[derive(Debug, Clone)]
+pub struct SwapQuote {
+    pub amount_out: U256,
+    pub next_reserve_x: U256,
+    pub next_reserve_y: U256,
+    pub next_liquidity: U256,
+    pub payload: Bytes,
+}
+
+/// Amounts settled by a swap, and the pool's state after it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SwapResult {
+    pub swap_x_in: bool,
+    pub amount_in: U256,
+    pub amount_out: U256,
+    /// Fee paid on the input, in the input token.
+    pub fee: U256,
+    pub reserve_x: U256,
+    pub reserve_y: U256,
+    pub liquidity: U256,
+}
+
+/// Amounts settled by an allocation or deallocation, and the pool's state
+/// after it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LiquidityResult {
+    pub delta_x: U256,
+    pub delta_y: U256,
+    pub delta_liquidity: U256,
+    pub reserve_x: U256,
+    pub reserve_y: U256,
+    pub liquidity: U256,
+}
+
+impl<C: Middleware + 'static> ProtocolClient<C> {
+    /// Quotes swapping `amount_in` of `x` (or `y` if `swap_x_in` is false) into
+    /// the pool.
+    pub async fn quote_swap(
+        &self,
+        pool_id: U256,
+        swap_x_in: bool,
+        amount_in: U256,
+    ) -> Result<SwapQuote> {
+        let pool = self.get_pool(pool_id).await?;
+        let (valid, amount_out, payload) = match pool.kind {
+            PoolKind::G3M => {
+                let (valid, amount_out, _, payload) = self
+                    .g_solver()?
+                    .simulate_swap(pool_id, swap_x_in, amount_in)
+                    .call()
+                    .await?;
+                (valid, amount_out, payload)
+            }
+            PoolKind::LogNormal => {
+                let (valid, amount_out, _, payload) = self
+                    .ln_solver
+                    .simulate_swap(pool_id, swap_x_in, amount_in)
+                    .call()
+                    .await?;
+                (valid, amount_out, payload)
+            }
+            PoolKind::StableSwap => {
+                let (amount_out, payload) = self
+                    .simulate_stable_swap(pool_id, swap_x_in, amount_in)
+                    .await?;
+                (true, amount_out, payload)
+            }
+        };
+        if !valid {
+            anyhow::bail!("Solver returned an invalid swap for pool {}", pool_id);
+        }
+
+        let (next_reserve_x, next_reserve_y, next_liquidity) = decode_reserves(&payload)?;
+        Ok(SwapQuote {
+            amount_out,
+            next_reserve_x,
+            next_reserve_y,
+            next_liquidity,
+            payload,
+        })
+    }
+
+    /// Swaps `amount_in` into the pool, receiving at least the quoted output
+    /// less `slippage` (e.g. 0.005 for 0.5%). Fails without sending if the
+    /// latest block is past `deadline` or the swap would fill below that.
+    #[tracing::instrument(skip(self), level = "trace", ret)]
+    pub async fn swap(
+        &self,
+        pool_id: U256,
+        swap_x_in: bool,
+        amount_in: U256,
+        slippage: f64,
+        deadline: u64,
+    ) -> Result<SwapResult> {
+        check_slippage(slippage)?;
+        let quote = self.quote_swap(pool_id, swap_x_in, amount_in).await?;
+        let min_amount_out = with_slippage(quote.amount_out, -slippage)?;
+
+        self.check_deadline(deadline).await?;
+        let call = self.protocol.swap(pool_id, quote.payload);
+        let (_, simulated_out) = call.call().await?;
+        if simulated_out < min_amount_out {
+            anyhow::bail!(
+                "Swap output {} is below the minimum of {}",
+                simulated_out,
+                min_amount_out
+            );
+        }
+
+        let receipt = self.send_and_confirm(call).await?;
+        let event: SwapFilter = self.decode_event(&receipt, pool_id)?;
+        if event.output_amount < min_amount_out {
+            anyhow::bail!(
+                "Swap settled in {:?} for {}, below the minimum of {}",
+                receipt.transaction_hash,
+                event.output_amount,
+                min_amount_out
+            );
+        }
+        let (reserve_x, reserve_y, liquidity) = self.get_reserves_and_liquidity(pool_id).await?;
+        let fee = event.input_amount * self.get_swap_fee(pool_id).await? / to_wad(1.0)?;
+
+        Ok(SwapResult {
+            swap_x_in: event.is_swap_x_for_y,
+            amount_in: event.input_amount,
+            amount_out: event.output_amount,
+            fee,
+            reserve_x,
+            reserve_y,
+            liquidity,
+        })
+    }
+
+    /// Quotes the next reserves and liquidity of the pool after allocating
+    /// `amount_x` of `x` and the proportional amount of `y`.
+    pub async fn quote_allocate(
+        &self,
+        pool_id: U256,
+        amount_x: U256,
+    ) -> Result<(U256, U256, U256)> {
+        let pool = self.get_pool(pool_id).await?;
+        match pool.kind {
+            PoolKind::G3M => Ok(self
+                .g_solver()?
+                .allocate_given_x(pool_id, amount_x)
+                .call()
+                .await?),
+            PoolKind::LogNormal => Ok(self
+                .ln_solver
+                .allocate_given_x(pool_id, amount_x)
+                .call()
+                .await?),
+            PoolKind::StableSwap => self.scale_reserves(pool_id, amount_x, true).await,
+        }
+    }
+
+    /// Quotes the next reserves and liquidity of the pool after deallocating
+    /// `amount_x` of `x` and the proportional amount of `y`.
+    pub async fn quote_deallocate(
+        &self,
+        pool_id: U256,
+        amount_x: U256,
+    ) -> Result<(U256, U256, U256)> {
+        let pool = self.get_pool(pool_id).await?;
+        match pool.kind {
+            PoolKind::G3M => Ok(self
+                .g_solver()?
+                .deallocate_given_x(pool_id, amount_x)
+                .call()
+                .await?),
+            PoolKind::LogNormal => Ok(self
+                .ln_solver
+                .deallocate_given_x(pool_id, amount_x)
+                .call()
+                .await?),
+            PoolKind::StableSwap => self.scale_reserves(pool_id, amount_x, false).await,
+        }
+    }
+
+    /// Allocates `amount_x` of `x` and the proportional amount of `y`, paying
+    /// at most the quoted amount of `y` plus `slippage`.
+    #[tracing::instrument(skip(self), level = "trace", ret)]
+    pub async fn allocate(
+        &self,
+        pool_id: U256,
+        amount_x: U256,
+        slippage: f64,
+        deadline: u64,
+    ) -> Result<LiquidityResult> {
+        check_slippage(slippage)?;
+        let (_, reserve_y, _) = self.get_reserves_and_liquidity(pool_id).await?;
+        let next = self.quote_allocate(pool_id, amount_x).await?;
+        let delta_y = next
+            .1
+            .checked_sub(reserve_y)
+            .ok_or_else(|| anyhow::anyhow!("Allocation would lower the reserve of y"))?;
+        let max_delta_y = with_slippage(delta_y, slippage)?;
+
+        self.check_deadline(deadline).await?;
+        let call = self.protocol.allocate(pool_id, encode_reserves(next));
+        let (_, simulated_delta_y, _) = call.call().await?;
+        if simulated_delta_y > max_delta_y {
+            anyhow::bail!(
+                "Allocation requires {} of y, above the maximum of {}",
+                simulated_delta_y,
+                max_delta_y
+            );
+        }
+
+        let receipt = self.send_and_confirm(call).await?;
+        let event: AllocateFilter = self.decode_event(&receipt, pool_id)?;
+        if event.delta_y > max_delta_y {
+            anyhow::bail!(
+                "Allocation settled in {:?} for {} of y, above the maximum of {}",
+                receipt.transaction_hash,
+                event.delta_y,
+                max_delta_y
+            );
+        }
+        self.liquidity_result(pool_id, event.delta_x, event.delta_y, event.delta_l)
+            .await
+    }
+
+    /// Deallocates `amount_x` of `x` and the proportional amount of `y`,
+    /// receiving at least the quoted amount of `y` less `slippage`.
+    #[tracing::instrument(skip(self), level = "trace", ret)]
+    pub async fn deallocate(
+        &self,
+        pool_id: U256,
+        amount_x: U256,
+        slippage: f64,
+        deadline: u64,
+    ) -> Result<LiquidityResult> {
+        check_slippage(slippage)?;
+        let (reserve_x, reserve_y, _) = self.get_reserves_and_liquidity(pool_id).await?;
+        if amount_x > reserve_x {
+            anyhow::bail!(
+                "Cannot deallocate {} of x from a reserve of {}",
+                amount_x,
+                reserve_x
+            );
+        }
+        let next = self.quote_deallocate(pool_id, amount_x).await?;
+        let delta_y = reserve_y
+            .checked_sub(next.1)
+            .ok_or_else(|| anyhow::anyhow!("Deallocation would raise the reserve of y"))?;
+        let min_delta_y = with_slippage(delta_y, -slippage)?;
+
+        self.check_deadline(deadline).await?;
+        let call = self.protocol.deallocate(pool_id, encode_reserves(next));
+        let (_, simulated_delta_y, _) = call.call().await?;
+        if simulated_delta_y < min_delta_y {
+            anyhow::bail!(
+                "Deallocation returns {} of y, below the minimum of {}",
+                simulated_delta_y,
+                min_delta_y
+            );
+        }
+
+        let receipt = self.send_and_confirm(call).await?;
+        let event: DeallocateFilter = self.decode_event(&receipt, pool_id)?;
+        if event.delta_y < min_delta_y {
+            anyhow::bail!(
+                "Deallocation settled in {:?} for {} of y, below the minimum of {}",
+                receipt.transaction_hash,
+                event.delta_y,
+                min_delta_y
+            );
+        }
+        self.liquidity_result(pool_id, event.delta_x, event.delta_y, event.delta_l)
+            .await
+    }
+
+    async fn get_swap_fee(&self, pool_id: U256) -> Result<U256> {
+        Ok(match self.get_params(pool_id).await? {
+            PoolParams::G3M(params) => params.swap_fee,
+            PoolParams::LogNormal(params) => params.swap_fee,
+            PoolParams::StableSwap(params) => params.swap_fee,
+        })
+    }
+
+    /// StableSwap has no solver contract, so swaps are simulated off-chain.
+    async fn simulate_stable_swap(
+        &self,
+        pool_id: U256,
+        swap_x_in: bool,
+        amount_in: U256,
+    ) -> Result<(U256, Bytes)> {
+        let PoolParams::StableSwap(params) = self.get_params(pool_id).await? else {
+            anyhow::bail!("Failed to parse StableSwap params");
+        };
+        let curve = StableSwap {
+            amplification: from_wad(params.amplification)?,
+            swap_fee: from_wad(params.swap_fee)?,
+        };
+        let (rx, ry, liquidity) = self.get_reserves_and_liquidity(pool_id).await?;
+        let (rx, ry, liquidity) = (from_wad(rx)?, from_wad(ry)?, from_wad(liquidity)?);
+        let amount_in_float = from_wad(amount_in)?;
+
+        let (next_rx, next_ry, next_l, amount_out) = if swap_x_in {
+            let (out, next_l) = curve.swap_x_in(rx, ry, liquidity, amount_in_float);
+            (rx + amount_in_float, ry - out, next_l, out)
+        } else {
+            let (out, next_l) = curve.swap_y_in(rx, ry, liquidity, amount_in_float);
+            (rx - out, ry + amount_in_float, next_l, out)
+        };
+
+        let payload = encode_reserves((to_wad(next_rx)?, to_wad(next_ry)?, to_wad(next_l)?));
+        Ok((to_wad(amount_out)?, payload))
+    }
+
+    /// Scales the reserves and liquidity of the pool by the ratio that adds or
+    /// removes `amount_x` of `x`.
+    async fn scale_reserves(
+        &self,
+        pool_id: U256,
+        amount_x: U256,
+        is_allocate: bool,
+    ) -> Result<(U256, U256, U256)> {
+        let (rx, ry, liquidity) = self.get_reserves_and_liquidity(pool_id).await?;
+        if rx.is_zero() {
+            anyhow::bail!("Pool {} has no reserve of x to scale", pool_id);
+        }
+        let next_rx = if is_allocate {
+            rx + amount_x
+        } else {
+            rx.checked_sub(amount_x)
+                .ok_or_else(|| anyhow::anyhow!("Cannot remove {} of x from {}", amount_x, rx))?
+        };
+        Ok((next_rx, ry * next_rx / rx, liquidity * next_rx / rx))
+    }
+
+    async fn check_deadline(&self, deadline: u64) -> Result<()> {
+        let Some(block) = self.client.get_block(BlockNumber::Latest).await? else {
+            anyhow::bail!("Failed to fetch the latest block");
+        };
+        if block.timestamp > U256::from(deadline) {
+            anyhow::bail!("Deadline {} has passed at {}", deadline, block.timestamp);
+        }
+        Ok(())
+    }
+
+    async fn send_and_confirm<D: abi::Detokenize>(
+        &self,
+        call: ContractCall<C, D>,
+    ) -> Result<TransactionReceipt> {
+        let Some(receipt) = call
+            .send()
+            .await?
+            .confirmations(0)
+            .interval(Duration::from_millis(100))
+            .await?
+        else {
+            anyhow::bail!("Transaction was dropped");
+        };
+        Ok(receipt)
+    }
+
+    /// Finds the event `E` DFMM emitted for `pool_id` in `receipt`.
+    fn decode_event<E: EthEvent + PoolEvent>(
+        &self,
+        receipt: &TransactionReceipt,
+        pool_id: U256,
+    ) -> Result<E> {
+        receipt
+            .logs
+            .iter()
+            .filter(|log| log.address == self.protocol.address())
+            .filter_map(|log| parse_log::<E>(log.clone()).ok())
+            .find(|event| event.pool_id() == pool_id)
+            .ok_or_else(|| anyhow::anyhow!("No {} event in receipt", E::name()))
+    }
+
+    async fn liquidity_result(
+        &self,
+        pool_id: U256,
+        delta_x: U256,
+        delta_y: U256,
+        delta_liquidity: U256,
+    ) -> Result<LiquidityResult> {
+        let (reserve_x, reserve_y, liquidity) = self.get_reserves_and_liquidity(pool_id).await?;
+        Ok(LiquidityResult {
+            delta_x,
+            delta_y,
+            delta_liquidity,
+            reserve_x,
+            reserve_y,
+            liquidity,
+        })
+    }
+}
+
+/// DFMM events that belong to a pool.
+trait PoolEvent {
+    fn pool_id(&self) -> U256;
+}
+
+impl PoolEvent for SwapFilter {
+    fn pool_id(&self) -> U256 {
+        self.pool_id
+    }
+}
+
+impl PoolEvent for AllocateFilter {
+    fn pool_id(&self) -> U256 {
+        self.pool_id
+    }
+}
+
+impl PoolEvent for DeallocateFilter {
+    fn pool_id(&self) -> U256 {
+        self.pool_id
+    }
+}
+
+/// Slippage tolerances are fractions of the quoted amount, from 0 to 1.
+fn check_slippage(slippage: f64) -> Result<()> {
+    if !(0.0..=1.0).contains(&slippage) {
+        anyhow::bail!("Slippage {} is not between 0 and 1", slippage);
+    }
+    Ok(())
+}
+
+/// Moves `amount` by `slippage`, e.g. -0.01 for 1% less.
+fn with_slippage(amount: U256, slippage: f64) -> Result<U256> {
+    Ok(amount * to_wad(1.0 + slippage)? / to_wad(1.0)?)
+}
+
+/// Encodes the next reserves and liquidity as DFMM expects them.
+fn encode_reserves((rx, ry, liquidity): (U256, U256, U256)) -> Bytes {
+    abi::encode(&[Token::Uint(rx), Token::Uint(ry), Token::Uint(liquidity)]).into()
+}
+
+fn decode_reserves(data: &Bytes) -> Result<(U256, U256, U256)> {
+    let tokens = abi::decode(
+        &[
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+        ],
+        data,
+    )?;
+    match tokens.as_slice() {
+        [Token::Uint(rx), Token::Uint(ry), Token::Uint(liquidity)] => Ok((*rx, *ry, *liquidity)),
+        _ => anyhow::bail!("Failed to decode reserves"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::utils::{Anvil, AnvilInstance};
+
+    use super::*;
+    use crate::dev::DevClient;
+
+    type Client = SignerMiddleware<Provider<Ws>, LocalWallet>;
+
+    /// Deploys the protocol on anvil and creates a LogNormal pool around a
+    /// price of 1, returning its id.
+    async fn setup() -> Result<(AnvilInstance, DevClient<Client>, U256)> {
+        let anvil = Anvil::default()
+            .arg("--gas-limit")
+            .arg("20000000")
+            .chain_id(31337_u64)
+            .spawn();
+        let wallet: LocalWallet = anvil.keys()[0].clone().into();
+        let wallet = wallet.with_chain_id(anvil.chain_id());
+        let provider = Provider::<Ws>::connect(anvil.ws_endpoint()).await?;
+        let client = Arc::new(SignerMiddleware::new(provider, wallet));
+
+        let mut dev = DevClient::deploy(client.clone(), client.address()).await?;
+        let pool_id = dev.protocol.get_next_pool_id().await?;
+        dev.create_position(client.address(), 10.0, 1.0, 1.0, 0.5, 1.0)
+            .await?;
+        Ok((anvil, dev, pool_id))
+    }
+
+    #[test]
+    fn reserves_round_trip() {
+        let reserves = (
+            to_wad(1.5).unwrap(),
+            to_wad(2.0).unwrap(),
+            to_wad(3.25).unwrap(),
+        );
+        assert_eq!(
+            decode_reserves(&encode_reserves(reserves)).unwrap(),
+            reserves
+        );
+    }
+
+    #[test]
+    fn slippage_bounds() {
+        let amount = to_wad(100.0).unwrap();
+        assert_eq!(with_slippage(amount, -0.01).unwrap(), to_wad(99.0).unwrap());
+        assert_eq!(
+            with_slippage(amount, 0.005).unwrap(),
+            to_wad(100.5).unwrap()
+        );
+    }
+
+    #[test]
+    fn slippage_out_of_range() {
+        assert!(check_slippage(0.0).is_ok());
+        assert!(check_slippage(1.0).is_ok());
+        assert!(check_slippage(1.5).is_err());
+        assert!(check_slippage(-0.01).is_err());
+        assert!(check_slippage(f64::NAN).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn swap_settles_quote() -> Result<()> {
+        let (_anvil, dev, pool_id) = setup().await?;
+        let amount_in = to_wad(1.0)?;
+
+        let quote = dev.protocol.quote_swap(pool_id, true, amount_in).await?;
+        let result = dev
+            .protocol
+            .swap(pool_id, true, amount_in, 0.01, u64::MAX)
+            .await?;
+
+        assert!(result.swap_x_in);
+        assert_eq!(result.amount_in, amount_in);
+        assert!(result.amount_out >= with_slippage(quote.amount_out, -0.01)?);
+        assert!(result.fee > U256::zero());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn allocate_and_deallocate() -> Result<()> {
+        let (_anvil, dev, pool_id) = setup().await?;
+        let (reserve_x, _, liquidity) = dev.protocol.get_reserves_and_liquidity(pool_id).await?;
+        let amount_x = to_wad(1.0)?;
+
+        let allocated = dev
+            .protocol
+            .allocate(pool_id, amount_x, 0.01, u64::MAX)
+            .await?;
+        assert_eq!(allocated.delta_x, amount_x);
+        assert_eq!(allocated.reserve_x, reserve_x + amount_x);
+        assert!(allocated.liquidity > liquidity);
+
+        let deallocated = dev
+            .protocol
+            .deallocate(pool_id, amount_x, 0.01, u64::MAX)
+            .await?;
+        assert_eq!(deallocated.delta_x, amount_x);
+        assert_eq!(deallocated.reserve_x, reserve_x);
+        assert!(deallocated.liquidity < allocated.liquidity);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn deallocate_beyond_reserves() -> Result<()> {
+        let (_anvil, dev, pool_id) = setup().await?;
+        let (reserve_x, _, _) = dev.protocol.get_reserves_and_liquidity(pool_id).await?;
+
+        let result = dev
+            .protocol
+            .deallocate(pool_id, reserve_x + 1, 0.01, u64::MAX)
+            .await;
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn swap_rejects_slippage_above_one() -> Result<()> {
+        let (_anvil, dev, pool_id) = setup().await?;
+
+        let result = dev
+            .protocol
+            .swap(pool_id, true, to_wad(1.0)?, 1.5, u64::MAX)
+            .await;
+        assert!(result.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,488 @@
+//! Connections that spread requests over several endpoints of one chain.
+//!
+//! [`Failover`] sends each request to the healthiest endpoint with a request to
+//! spare under its rate limit. When an endpoint can't be reached, times out or
+//! is rate limiting, it's put in a cooldown that doubles with every failure in
+//! a row, and the request is retried on the next endpoint. Endpoints reconnect
+//! once their cooldown ends. JSON-RPC errors like reverts, and answers that
+//! can't be decoded, are answers, not failures, and are returned as they are.
+//! Transactions are not sent again once an endpoint could have broadcast them.
+
+use std::{sync::Mutex as SyncMutex, time::Duration};
+
+use ethers::providers::{JsonRpcClient, RpcError};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::{sync::Mutex, time::Instant};
+
+use super::{RpcTransport, RpcTransportError};
+
+/// JSON-RPC error codes servers answer with when they are rate limiting.
+const RATE_LIMITED_CODES: [i64; 3] = [429, -32005, -32029];
+
+/// Methods that can't be sent twice, since the first request could have been
+/// carried out even if its answer was lost.
+const NON_IDEMPOTENT_METHODS: [&str; 2] = ["eth_sendRawTransaction", "eth_sendTransaction"];
+
+/// How requests are retried and spread over endpoints.
+#[derive(Debug, Clone, Copy)]
+pub struct FailoverConfig {
+    /// Attempts after the first, across all endpoints.
+    pub max_retries: u32,
+    /// Cooldown after an endpoint's first failure, doubled for every failure
+    /// in a row after it.
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+    /// How long a request can take before it counts as a failure.
+    pub request_timeout: Duration,
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 4,
+            base_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(30),
+            request_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl FailoverConfig {
+    fn backoff(&self, failures: u32) -> Duration {
+        self.base_backoff
+            .saturating_mul(2_u32.saturating_pow(failures.saturating_sub(1)))
+            .min(self.max_backoff)
+    }
+}
+
+/// An endpoint to connect to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointConfig {
+    pub url: String,
+    /// Requests per second the endpoint allows, if it's limited.
+    pub rate_limit: Option<u32>,
+}
+
+/// Stats of an endpoint since it was added.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndpointHealth {
+    pub url: String,
+    pub requests: u64,
+    pub errors: u64,
+    /// Moving average of the time to answer requests.
+    pub latency: Option<Duration>,
+    /// From 0 to 1, higher is healthier. See [`Failover`].
+    pub score: f64,
+    /// Whether the endpoint is out of its cooldown.
+    pub available: bool,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct Stats {
+    requests: u64,
+    errors: u64,
+    failures_in_row: u32,
+    /// Moving average of failures, so endpoints recover their score.
+    error_rate: f64,
+    latency: Option<Duration>,
+    cooldown_until: Option<Instant>,
+    last_error: Option<String>,
+}
+
+impl Stats {
+    fn record_success(&mut self, latency: Duration) {
+        self.requests += 1;
+        self.failures_in_row = 0;
+        self.error_rate *= 0.8;
+        self.cooldown_until = None;
+        self.latency = Some(match self.latency {
+            Some(average) => (average * 4 + latency) / 5,
+            None => latency,
+        });
+    }
+
+    fn record_failure(&mut self, error: String, cooldown: impl FnOnce(u32) -> Duration) {
+        self.requests += 1;
+        self.errors += 1;
+        self.failures_in_row += 1;
+        self.error_rate = self.error_rate * 0.8 + 0.2;
+        self.cooldown_until = Some(Instant::now() + cooldown(self.failures_in_row));
+        self.last_error = Some(error);
+    }
+
+    /// Time left in the cooldown, if the endpoint is in one.
+    fn cooldown(&self, now: Instant) -> Option<Duration> {
+        self.cooldown_until
+            .filter(|until| *until > now)
+            .map(|until| until - now)
+    }
+
+    /// Halves for every 100ms of latency, and drops with the error rate.
+    fn score(&self) -> f64 {
+        let latency = self.latency.map_or(0.0, |latency| latency.as_secs_f64());
+        (1.0 - self.error_rate) / (1.0 + latency * 10.0)
+    }
+}
+
+/// Token bucket that refills `per_second` tokens every second, up to
+/// `per_second`.
+#[derive(Debug)]
+struct RateLimiter {
+    per_second: f64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl RateLimiter {
+    fn new(per_second: u32) -> Self {
+        let per_second = f64::from(per_second.max(1));
+        Self {
+            per_second,
+            tokens: per_second,
+            refilled: Instant::now(),
+        }
+    }
+
+    /// Time until a token is available, if there is none now.
+    fn wait(&mut self, now: Instant) -> Option<Duration> {
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.per_second);
+        self.refilled = now;
+        (self.tokens < 1.0).then(|| Duration::from_secs_f64((1.0 - self.tokens) / self.per_second))
+    }
+
+    fn take(&mut self) {
+        self.tokens -= 1.0;
+    }
+}
+
+#[derive(Debug)]
+struct EndpointState {
+    stats: Stats,
+    limiter: Option<RateLimiter>,
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    config: EndpointConfig,
+    /// Connected on first use, and dropped on failures to reconnect.
+    transport: Mutex<Option<RpcTransport>>,
+    state: SyncMutex<EndpointState>,
+}
+
+impl Endpoint {
+    fn new(config: EndpointConfig) -> Self {
+        let limiter = config.rate_limit.map(RateLimiter::new);
+        Self {
+            config,
+            transport: Mutex::new(None),
+            state: SyncMutex::new(EndpointState {
+                stats: Stats::default(),
+                limiter,
+            }),
+        }
+    }
+
+    /// The transport to the endpoint, connecting it within `timeout` if it
+    /// isn't connected.
+    async fn transport(&self, timeout: Duration) -> Result<RpcTransport, RpcTransportError> {
+        let mut transport = self.transport.lock().await;
+        if let Some(transport) = transport.as_ref() {
+            return Ok(transport.clone());
+        }
+
+        let connected = tokio::time::timeout(timeout, RpcTransport::connect(&self.config.url))
+            .await
+            .map_err(|_| RpcTransportError::Timeout(timeout))?
+            .map_err(|e| RpcTransportError::Connect(e.to_string()))?;
+        *transport = Some(connected.clone());
+        Ok(connected)
+    }
+
+    fn health(&self) -> EndpointHealth {
+        let state = self.state.lock().unwrap();
+        EndpointHealth {
+            url: self.config.url.clone(),
+            requests: state.stats.requests,
+            errors: state.stats.errors,
+            latency: state.stats.latency,
+            score: state.stats.score(),
+            available: state.stats.cooldown(Instant::now()).is_none(),
+            last_error: state.stats.last_error.clone(),
+        }
+    }
+}
+
+/// Requests over several endpoints of one chain. Only requests are spread,
+/// subscriptions are not, so blocks have to be polled for.
+#[derive(Debug)]
+pub struct Failover {
+    endpoints: Vec<Endpoint>,
+    config: FailoverConfig,
+}
+
+impl Failover {
+    /// Endpoints are preferred in order until they have stats to be scored
+    /// by.
+    pub fn new(
+        endpoints: Vec<EndpointConfig>,
+        config: FailoverConfig,
+    ) -> Result<Self, RpcTransportError> {
+        if endpoints.is_empty() {
+            return Err(RpcTransportError::NoEndpoints);
+        }
+
+        Ok(Self {
+            endpoints: endpoints.into_iter().map(Endpoint::new).collect(),
+            config,
+        })
+    }
+
+    /// Stats of every endpoint, in the order they were given.
+    pub fn health(&self) -> Vec<EndpointHealth> {
+        self.endpoints.iter().map(Endpoint::health).collect()
+    }
+
+    /// Picks the endpoint with the best score that is out of its cooldown and
+    /// has a request to spare, taking the request. Otherwise returns how long
+    /// until one will be.
+    fn pick(&self) -> Result<&Endpoint, Duration> {
+        let now = Instant::now();
+        let mut best: Option<(&Endpoint, f64)> = None;
+        let mut wait = self.config.max_backoff;
+
+        for endpoint in self.endpoints.iter() {
+            let mut state = endpoint.state.lock().unwrap();
+            let blocked = state
+                .stats
+                .cooldown(now)
+                .or_else(|| state.limiter.as_mut().and_then(|limiter| limiter.wait(now)));
+            if let Some(blocked) = blocked {
+                wait = wait.min(blocked);
+                continue;
+            }
+
+            let score = state.stats.score();
+            let better = best.map(|(_, best)| score > best).unwrap_or(true);
+            if better {
+                best = Some((endpoint, score));
+            }
+        }
+
+        let (endpoint, _) = best.ok_or(wait)?;
+        if let Some(limiter) = endpoint.state.lock().unwrap().limiter.as_mut() {
+            limiter.take();
+        }
+        Ok(endpoint)
+    }
+
+    pub async fn request<T, R>(&self, method: &str, params: T) -> Result<R, RpcTransportError>
+    where
+        T: Serialize,
+        R: DeserializeOwned + Send,
+    {
+        // Subscriptions live on the endpoint that made them, which would break
+        // on failover.
+        if method == "eth_subscribe" {
+            return Err(RpcTransportError::NoSubscriptions);
+        }
+
+        let params = serde_json::to_value(params)?;
+        let mut last_error = None;
+
+        for _ in 0..=self.config.max_retries {
+            let endpoint = loop {
+                match self.pick() {
+                    Ok(endpoint) => break endpoint,
+                    Err(wait) => tokio::time::sleep(wait).await,
+                }
+            };
+
+            let start = Instant::now();
+            let (result, sent) = match endpoint.transport(self.config.request_timeout).await {
+                Ok(transport) => (
+                    tokio::time::timeout(
+                        self.config.request_timeout,
+                        transport.request(method, params.clone()),
+                    )
+                    .await
+                    .unwrap_or(Err(RpcTransportError::Timeout(self.config.request_timeout))),
+                    true,
+                ),
+                Err(e) => (Err(e), false),
+            };
+
+            let error = match result {
+                Err(e) if is_retryable(&e) => e,
+                result => {
+                    let latency = start.elapsed();
+                    endpoint.state.lock().unwrap().stats.record_success(latency);
+                    return result;
+                }
+            };
+
+            tracing::warn!("Request to {} failed: {}", endpoint.config.url, error);
+            endpoint
+                .state
+                .lock()
+                .unwrap()
+                .stats
+                .record_failure(error.to_string(), |failures| self.config.backoff(failures));
+            // Rate limited endpoints still work, unlike ones that errored.
+            let rate_limited = error.as_error_response().is_some();
+            if !rate_limited {
+                endpoint.transport.lock().await.take();
+            }
+            // A transaction could have been broadcast before the endpoint
+            // failed, and sending it again would fail as already known.
+            if sent && !rate_limited && NON_IDEMPOTENT_METHODS.contains(&method) {
+                return Err(error);
+            }
+            last_error = Some(error);
+        }
+
+        Err(RpcTransportError::Exhausted(Box::new(
+            last_error.expect("at least one attempt is made"),
+        )))
+    }
+}
+
+/// Whether another endpoint could answer the request. JSON-RPC errors are
+/// answers, unless the endpoint is rate limiting, and so are answers that
+/// don't decode to the type asked for.
+fn is_retryable(error: &RpcTransportError) -> bool {
+    match error.as_error_response() {
+        Some(response) => RATE_LIMITED_CODES.contains(&response.code),
+        None if error.as_serde_error().is_some() => false,
+        None => !matches!(error, RpcTransportError::NoSubscriptions),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::{
+        providers::{Middleware, Provider},
+        utils::Anvil,
+    };
+
+    use super::*;
+
+    fn config() -> FailoverConfig {
+        FailoverConfig {
+            max_retries: 3,
+            base_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_millis(400),
+            request_timeout: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn limits_rate() {
+        let now = Instant::now();
+        let mut limiter = RateLimiter::new(2);
+        for _ in 0..2 {
+            assert_eq!(limiter.wait(now), None);
+            limiter.take();
+        }
+        assert!(limiter.wait(now).is_some());
+        assert_eq!(limiter.wait(now + Duration::from_millis(500)), None);
+    }
+
+    #[test]
+    fn backs_off() {
+        let config = config();
+        assert_eq!(config.backoff(1), Duration::from_millis(50));
+        assert_eq!(config.backoff(3), Duration::from_millis(200));
+        assert_eq!(config.backoff(10), Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn fails_over_when_endpoints_die() -> anyhow::Result<()> {
+        let first = Anvil::default().chain_id(31337_u64).spawn();
+        let second = Anvil::default().chain_id(31337_u64).spawn();
+        let failover = Failover::new(
+            vec![
+                EndpointConfig {
+                    url: first.ws_endpoint(),
+                    rate_limit: None,
+                },
+                EndpointConfig {
+                    url: second.endpoint(),
+                    rate_limit: Some(100),
+                },
+            ],
+            config(),
+        )?;
+        let provider = Provider::new(RpcTransport::Failover(failover.into()));
+
+        for _ in 0..4 {
+            provider.get_block_number().await?;
+        }
+
+        // JSON-RPC errors are answers, and are not retried.
+        let unknown = provider.request::<_, u64>("excalibur_unknown", ()).await;
+        assert!(unknown.is_err());
+        let health = provider.as_ref().health();
+        assert!(health.iter().all(|endpoint| endpoint.errors == 0));
+
+        drop(first);
+        for _ in 0..4 {
+            provider.get_block_number().await?;
+        }
+
+        drop(second);
+        assert!(provider.get_block_number().await.is_err());
+        let health = provider.as_ref().health();
+        assert!(health.iter().all(|endpoint| endpoint.errors > 0));
+        assert!(health.iter().all(|endpoint| !endpoint.available));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn does_not_resend_transactions() -> anyhow::Result<()> {
+        // An endpoint that takes requests and never answers them.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let silent = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move {
+            let mut connections = vec![];
+            while let Ok((connection, _)) = listener.accept().await {
+                connections.push(connection);
+            }
+        });
+        let anvil = Anvil::default().chain_id(31337_u64).spawn();
+        let failover = Failover::new(
+            vec![
+                EndpointConfig {
+                    url: silent,
+                    rate_limit: None,
+                },
+                EndpointConfig {
+                    url: anvil.endpoint(),
+                    rate_limit: None,
+                },
+            ],
+            config(),
+        )?;
+
+        let sent = failover
+            .request::<_, String>("eth_sendRawTransaction", ["0x00"])
+            .await;
+        assert!(matches!(sent, Err(RpcTransportError::Timeout(_))));
+        let health = failover.health();
+        assert_eq!(health[0].errors, 1);
+        assert_eq!(health[1].requests, 0);
+
+        // Reads go on to the endpoint that answers.
+        failover.request::<_, String>("eth_blockNumber", ()).await?;
+
+        // Answers that don't decode are not endpoint failures.
+        let errors = failover.health()[1].errors;
+        let decoded = failover.request::<_, bool>("eth_blockNumber", ()).await;
+        assert!(decoded.is_err());
+        assert_eq!(failover.health()[1].errors, errors);
+
+        Ok(())
+    }
+}
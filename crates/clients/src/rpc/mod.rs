@@ -0,0 +1,220 @@
+//! Connections to RPC endpoints over websockets or HTTP.
+//!
+//! A `Provider` is typed by its transport, so [`RpcTransport`] wraps both in
+//! one type, along with [`Failover`] connections over several endpoints, and a
+//! client can move between endpoints of any kind. Only websockets can
+//! subscribe to new blocks; subscribing over HTTP or a failover connection
+//! fails, and callers poll instead.
+
+pub mod failover;
+
+use std::{fmt, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use ethers::{
+    providers::{
+        Http, HttpClientError, JsonRpcClient, JsonRpcError, Middleware, Provider, ProviderError,
+        PubsubClient, RpcError, Ws, WsClientError,
+    },
+    types::U256,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::time::Instant;
+
+pub use self::failover::{EndpointConfig, EndpointHealth, Failover, FailoverConfig};
+
+/// How long an endpoint has to answer a health check.
+pub const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub enum RpcTransport {
+    Ws(Ws),
+    Http(Http),
+    Failover(Arc<Failover>),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RpcTransportError {
+    #[error(transparent)]
+    Ws(#[from] WsClientError),
+    #[error(transparent)]
+    Http(#[from] HttpClientError),
+    #[error(transparent)]
+    Serde(#[from] serde_json::Error),
+    #[error("failed to connect: {0}")]
+    Connect(String),
+    #[error("no answer within {0:?}")]
+    Timeout(Duration),
+    #[error("no endpoints to connect to")]
+    NoEndpoints,
+    #[error("all attempts failed, last error: {0}")]
+    Exhausted(Box<RpcTransportError>),
+    #[error("only websocket endpoints can subscribe, poll instead")]
+    NoSubscriptions,
+}
+
+impl RpcError for RpcTransportError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            RpcTransportError::Ws(e) => e.as_error_response(),
+            RpcTransportError::Http(e) => e.as_error_response(),
+            RpcTransportError::Exhausted(e) => e.as_error_response(),
+            _ => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            RpcTransportError::Ws(e) => e.as_serde_error(),
+            RpcTransportError::Http(e) => e.as_serde_error(),
+            RpcTransportError::Serde(e) => Some(e),
+            RpcTransportError::Exhausted(e) => e.as_serde_error(),
+            _ => None,
+        }
+    }
+}
+
+impl From<RpcTransportError> for ProviderError {
+    fn from(error: RpcTransportError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(error))
+    }
+}
+
+impl RpcTransport {
+    /// Connects to `url`, over websockets for `ws://` and `wss://` urls, and
+    /// over HTTP otherwise.
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        if url.starts_with("ws://") || url.starts_with("wss://") {
+            Ok(RpcTransport::Ws(Ws::connect(url).await?))
+        } else {
+            Ok(RpcTransport::Http(url.parse::<Http>()?))
+        }
+    }
+
+    /// Connects to several endpoints of one chain, failing over between them.
+    /// Endpoints connect on first use.
+    pub fn failover(
+        endpoints: Vec<EndpointConfig>,
+        config: FailoverConfig,
+    ) -> Result<Self, RpcTransportError> {
+        Ok(RpcTransport::Failover(Arc::new(Failover::new(
+            endpoints, config,
+        )?)))
+    }
+
+    /// Whether the transport can subscribe to new blocks.
+    pub fn is_pubsub(&self) -> bool {
+        matches!(self, RpcTransport::Ws(_))
+    }
+
+    /// Stats of the endpoints of a failover connection, or none for a single
+    /// endpoint.
+    pub fn health(&self) -> Vec<EndpointHealth> {
+        match self {
+            RpcTransport::Failover(failover) => failover.health(),
+            _ => vec![],
+        }
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for RpcTransport {
+    type Error = RpcTransportError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: fmt::Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        match self {
+            RpcTransport::Ws(ws) => Ok(ws.request(method, params).await?),
+            RpcTransport::Http(http) => Ok(http.request(method, params).await?),
+            RpcTransport::Failover(failover) => failover.request(method, params).await,
+        }
+    }
+}
+
+impl PubsubClient for RpcTransport {
+    type NotificationStream = <Ws as PubsubClient>::NotificationStream;
+
+    fn subscribe<T: Into<U256>>(&self, id: T) -> Result<Self::NotificationStream, Self::Error> {
+        match self {
+            RpcTransport::Ws(ws) => Ok(ws.subscribe(id)?),
+            _ => Err(RpcTransportError::NoSubscriptions),
+        }
+    }
+
+    fn unsubscribe<T: Into<U256>>(&self, id: T) -> Result<(), Self::Error> {
+        match self {
+            RpcTransport::Ws(ws) => Ok(ws.unsubscribe(id)?),
+            _ => Err(RpcTransportError::NoSubscriptions),
+        }
+    }
+}
+
+/// State of an endpoint when it was last checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RpcHealth {
+    pub chain_id: u64,
+    pub block_number: u64,
+    /// Time to answer the chain id and block number requests.
+    pub latency: Duration,
+}
+
+/// Checks `provider` answers in time, and is on `expected_chain_id` if given.
+pub async fn check_health<P: JsonRpcClient>(
+    provider: &Provider<P>,
+    expected_chain_id: Option<u64>,
+) -> anyhow::Result<RpcHealth> {
+    let start = Instant::now();
+    let (chain_id, block_number) = tokio::time::timeout(HEALTH_CHECK_TIMEOUT, async {
+        let chain_id = provider.get_chainid().await?;
+        let block_number = provider.get_block_number().await?;
+        Ok::<_, ProviderError>((chain_id.as_u64(), block_number.as_u64()))
+    })
+    .await
+    .map_err(|_| anyhow::anyhow!("Endpoint did not answer within {:?}", HEALTH_CHECK_TIMEOUT))??;
+
+    if let Some(expected) = expected_chain_id {
+        if expected != chain_id {
+            return Err(anyhow::anyhow!(
+                "Endpoint is on chain {}, expected chain {}",
+                chain_id,
+                expected
+            ));
+        }
+    }
+
+    Ok(RpcHealth {
+        chain_id,
+        block_number,
+        latency: start.elapsed(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::utils::Anvil;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rpc_transport() -> anyhow::Result<()> {
+        let anvil = Anvil::default().chain_id(31337_u64).spawn();
+
+        for url in [anvil.endpoint(), anvil.ws_endpoint()] {
+            let transport = RpcTransport::connect(&url).await?;
+            let is_pubsub = transport.is_pubsub();
+            let provider = Provider::new(transport);
+
+            let health = check_health(&provider, Some(31337)).await?;
+            assert_eq!(health.chain_id, 31337);
+            assert!(check_health(&provider, Some(1)).await.is_err());
+
+            // Only websockets can subscribe.
+            assert_eq!(provider.subscribe_blocks().await.is_ok(), is_pubsub);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,210 @@
+//! A [`ScrollBundle`] is an ordered list of transactions handled as one.
+//!
+//! The steps are simulated one after the other on a single fork, each seeing
+//! the state left by the previous ones, so a bundle like an approval followed
+//! by a swap simulates like it would run on the network. Every step keeps its
+//! own outcome and state diff, and the bundle keeps the diff of all of them.
+//!
+//! Once simulated, the steps are sent in order from the client, with
+//! consecutive nonces. The first step that fails stops the rest from being
+//! sent.
+
+use ethers::types::BlockNumber;
+
+use super::*;
+
+#[derive(Default, Debug, Clone)]
+pub struct ScrollBundle {
+    pub steps: Vec<Scroll>,
+    /// State before the first step, and after the last step that was
+    /// simulated.
+    pub stages: Stages,
+    /// Changes made by all the simulated steps together.
+    pub state_diff: Option<StateDiff>,
+}
+
+impl ScrollBundle {
+    pub fn new(transactions: Vec<UnsealedTransaction>) -> Self {
+        Self {
+            steps: transactions
+                .into_iter()
+                .map(UnsealedTransaction::seal)
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Adds a transaction to the end of the bundle.
+    pub fn push(&mut self, transaction: UnsealedTransaction) {
+        self.steps.push(transaction.seal());
+    }
+
+    /// Index of the first step that reverted in simulation, if any.
+    pub fn failed_step(&self) -> Option<usize> {
+        self.steps.iter().position(|step| {
+            step.simulated_outcome
+                .as_ref()
+                .is_some_and(|outcome| outcome.revert_reason().is_some())
+        })
+    }
+
+    /// Simulates the steps in order on one fork of the network at `block`, or
+    /// at the forker's block. Simulation stops at the first step that reverts,
+    /// leaving the steps after it unsimulated.
+    #[tracing::instrument(skip(self, forker))]
+    pub async fn simulate(&mut self, forker: &Forker, block: Option<u64>) -> anyhow::Result<()> {
+        if self.steps.is_empty() {
+            return Err(anyhow::anyhow!("Bundle has no transactions."));
+        }
+        let client = forker.client.clone().ok_or(anyhow::anyhow!("No client"))?;
+        let from_address = client.address();
+
+        let calls = self
+            .steps
+            .iter()
+            .map(|step| step.payload.fork_call(from_address))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let (traced, before) = trace_on_fork(forker, block, calls).await?;
+
+        // Forget the results of an earlier simulation, as the state may have
+        // moved since.
+        for step in &mut self.steps {
+            step.stages = Stages::default();
+            step.simulated_outcome = None;
+            step.state_diff = None;
+            step.gas_estimate = None;
+        }
+
+        let mut db = before.clone();
+        for (step, call) in self.steps.iter_mut().zip(traced) {
+            let TracedCall {
+                state_diff, trace, ..
+            } = call;
+            tracing::debug!("Simulated state diff:\n{}", state_diff);
+            tracing::debug!("Simulated trace:\n{}", trace);
+
+            let step_before = db.clone();
+            apply_diff(&mut db, &state_diff)?;
+            step.stages = Stages {
+                before: Some(step_before),
+                after: Some(db.clone()),
+            };
+            step.gas_estimate = state_diff
+                .success
+                .then(|| gas::with_margin(state_diff.gas_needed()));
+            step.state_diff = Some(state_diff);
+            step.simulated_outcome = Some(Outcome {
+                trace: Some(trace),
+                ..Default::default()
+            });
+        }
+
+        self.stages = Stages {
+            before: Some(before),
+            after: Some(db),
+        };
+        self.state_diff = Some(StateDiff::combine(
+            self.steps
+                .iter()
+                .filter_map(|step| step.state_diff.as_ref()),
+        ));
+
+        if let Some(index) = self.failed_step() {
+            let reason = self.steps[index]
+                .simulated_outcome
+                .as_ref()
+                .and_then(Outcome::revert_reason)
+                .unwrap_or_default();
+            return Err(anyhow::anyhow!(
+                "Step {} of the bundle reverted: {}",
+                index,
+                reason
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Sends the steps in order from the client, each with the nonce following
+    /// the previous one, waiting for each to be mined before sending the next.
+    /// Stops at the first step that fails to send or reverts.
+    #[tracing::instrument(skip(self, forker))]
+    pub async fn execute(&mut self, forker: &Forker) -> anyhow::Result<Vec<TransactionReceipt>> {
+        let client = forker.client.clone().ok_or(anyhow::anyhow!("No client"))?;
+        let from_address = client.address();
+
+        // Every step must have been simulated without reverting, and must be
+        // sent by the client, which is the only one that can sign.
+        if self.steps.is_empty() {
+            return Err(anyhow::anyhow!("Bundle has no transactions."));
+        }
+        for (index, step) in self.steps.iter().enumerate() {
+            if step.live_outcome.is_some() || step.submitted.is_some() {
+                return Err(anyhow::anyhow!("Step {} has already been executed.", index));
+            }
+            match &step.simulated_outcome {
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "Step {} has not been simulated yet.",
+                        index
+                    ))
+                }
+                Some(outcome) => {
+                    if let Some(reason) = outcome.revert_reason() {
+                        return Err(anyhow::anyhow!(
+                            "Step {} reverted in simulation: {}",
+                            index,
+                            reason
+                        ));
+                    }
+                }
+            }
+            if let Some(from) = step.payload.from.filter(|from| *from != from_address) {
+                return Err(anyhow::anyhow!(
+                    "Step {} is sent from {:?}, but the client signs for {:?}",
+                    index,
+                    from,
+                    from_address
+                ));
+            }
+        }
+
+        // Counting pending transactions avoids reusing the nonce of one that
+        // hasn't been mined yet.
+        let mut nonce = client
+            .get_transaction_count(from_address, Some(BlockNumber::Pending.into()))
+            .await?;
+
+        let mut receipts = Vec::with_capacity(self.steps.len());
+        for (index, step) in self.steps.iter_mut().enumerate() {
+            let mut payload = step.transaction()?;
+            payload.set_from(from_address);
+            payload.set_nonce(nonce);
+
+            tracing::debug!("Sending step {}: {:?}", index, payload);
+            let receipt = client
+                .send_transaction(payload, None)
+                .await?
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Step {} was dropped from the mempool", index))?;
+            tracing::debug!("Executed receipt: {:?}", receipt);
+            nonce += U256::one();
+
+            step.live_outcome = Some(Outcome {
+                tx_hash: receipt.transaction_hash,
+                receipt: receipt.clone(),
+                trace: None,
+            });
+            if receipt.status != Some(1.into()) {
+                return Err(anyhow::anyhow!(
+                    "Step {} reverted in transaction {:?}, the remaining steps were not sent",
+                    index,
+                    receipt.transaction_hash
+                ));
+            }
+            receipts.push(receipt);
+        }
+
+        Ok(receipts)
+    }
+}
@@ -0,0 +1,720 @@
+//! A [`Scroll`] is a transaction over its entire lifetime.
+//! 1. Crafting the transaction. Build its target, payload, amounts, gas
+//!    settings, etc.
+//! 2. Seal the payload. Sealing the payload lets the application know to only
+//!    handle modifications
+//! if we revert to the crafting phase.
+//! 3. Load the target account's database information. This is used to compare
+//!    the account's storage slots,
+//! before and after a transaction.
+//! 4. Trace the payload on the loaded database, recording its calls, events
+//!    and revert reason, and every account and storage slot it touches.
+//! 5. Load the database into an Arbiter environment instance.
+//! 6. Execute the payload on the Arbiter instance, using the loaded database.
+//! 7. Finally, execute the transaction.
+//!
+//! Several transactions that depend on each other are handled together as a
+//! [`bundle::ScrollBundle`].
+
+pub mod bundle;
+
+use std::{
+    collections::HashMap,
+    convert::{Infallible, TryFrom},
+    fs::File,
+    io::BufReader,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use ::revm::{
+    db::{CacheDB, EmptyDB, EmptyDBTyped},
+    primitives::{hash_map::HashMap as StorageMap, AccountInfo, U256 as StorageValue},
+};
+use arbiter_core::middleware::RevmMiddleware;
+use bindings::coin::Coin;
+use datatypes::units::address_to_string;
+use ethers::types::{transaction::eip2718::TypedTransaction, Address};
+
+use super::{
+    arguments::encode_call,
+    forking::{
+        client_forking::*,
+        digest::{self, StorageLayout, StorageType},
+        lazy_fork::LazyFork,
+        overlay::Overlay,
+        state_diff::StateDiff,
+        trace::{trace_call, CallFrame, TraceBlock, TracedCall},
+    },
+    gas::{self, FeePreset, FeeSpeed, FeeSuggestion, GasCost},
+    transactions::TransactionManager,
+    *,
+};
+
+#[derive(Default, Debug, Clone)]
+pub struct Stages {
+    pub before: Option<CacheDB<EmptyDB>>,
+    pub after: Option<CacheDB<EmptyDB>>,
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct Outcome {
+    pub tx_hash: H256,
+    pub receipt: TransactionReceipt,
+    /// Decoded call trace, for simulated transactions.
+    pub trace: Option<CallFrame>,
+}
+
+impl Outcome {
+    /// The decoded reason the transaction reverted with, if it did.
+    pub fn revert_reason(&self) -> Option<&str> {
+        self.trace.as_ref().and_then(|trace| trace.revert_reason())
+    }
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct Scroll {
+    pub payload: UnsealedTransaction,
+    pub stages: Stages,
+    pub simulated_outcome: Option<Outcome>,
+    pub live_outcome: Option<Outcome>,
+    pub mappings: HashMap<String, Vec<String>>,
+    /// Accounts and slots touched by the simulated transaction.
+    pub state_diff: Option<StateDiff>,
+    /// Gas limit to execute with, from the gas used in simulation.
+    pub gas_estimate: Option<U256>,
+    /// Fees to execute with, or the provider's if unset.
+    pub fees: Option<FeePreset>,
+    /// Hash the transaction was submitted with, to track its status.
+    pub submitted: Option<H256>,
+}
+
+impl Scroll {
+    /// Sets the fees to execute the transaction with.
+    pub fn with_fees(mut self, fees: FeePreset) -> Self {
+        self.fees = Some(fees);
+        self
+    }
+
+    /// What executing the simulated transaction costs at each of the
+    /// `suggestion`'s presets.
+    pub fn costs(&self, suggestion: &FeeSuggestion) -> Option<Vec<(FeeSpeed, GasCost)>> {
+        self.gas_estimate.map(|gas| suggestion.costs(gas))
+    }
+
+    /// The transaction to execute, with the estimated gas limit and the chosen
+    /// fees.
+    pub fn transaction(&self) -> anyhow::Result<TypedTransaction> {
+        let mut tx: TypedTransaction = self.payload.clone().try_into()?;
+        if let Some(gas) = self.gas_estimate {
+            tx.set_gas(gas);
+        }
+        if let Some(fees) = &self.fees {
+            fees.apply(&mut tx);
+        }
+        Ok(tx)
+    }
+
+    /// Tries getting an account's storage from a [`CacheDB`].
+    #[tracing::instrument(skip(self, db), ret)]
+    pub fn try_storage(
+        &self,
+        db: &CacheDB<EmptyDBTyped<Infallible>>,
+    ) -> anyhow::Result<StorageMap<StorageValue, StorageValue>, anyhow::Error> {
+        let address: revm::primitives::Address = self.payload.target.to_fixed_bytes().into();
+        let account = db.accounts.get(&address).unwrap();
+        Ok(account.storage.clone())
+    }
+
+    /// Tries getting the already-loaded storage of an account before its been
+    /// simulated/executed against. Must be loaded already via `simulate`.
+    #[tracing::instrument(skip(self), ret)]
+    pub fn try_storage_before(
+        &self,
+        account: Address,
+    ) -> anyhow::Result<StorageMap<StorageValue, StorageValue>, anyhow::Error> {
+        let storage = match &self.stages.before {
+            Some(storage) => self.try_storage(storage)?,
+            None => {
+                tracing::error!("No before storage found in scroll");
+                return Err(anyhow::anyhow!(
+                    "Could not get before storage for account: {:?}",
+                    account
+                ));
+            }
+        };
+
+        Ok(storage)
+    }
+
+    /// Tries getting the already-loaded storage of an account after its been
+    /// simulated. Must be loaded already via `load_after`.
+    #[tracing::instrument(skip(self), ret)]
+    pub fn try_storage_after(
+        &self,
+        account: Address,
+    ) -> anyhow::Result<StorageMap<StorageValue, StorageValue>, anyhow::Error> {
+        let storage = match &self.stages.after {
+            Some(storage) => self.try_storage(storage)?,
+            None => {
+                tracing::error!("No after storage found in scroll");
+                return Err(anyhow::anyhow!(
+                    "Could not get before storage for account: {:?}",
+                    account
+                ));
+            }
+        };
+
+        Ok(storage)
+    }
+
+    /// Loads the target account's database information into a [`CacheDB`].
+    #[tracing::instrument(skip(self, forker))]
+    fn load(
+        &self,
+        forker: &Forker,
+        block: Option<u64>,
+    ) -> anyhow::Result<CacheDB<EmptyDB>, anyhow::Error> {
+        let target = self.payload.target;
+        let artifacts_path = self.payload.artifact.clone().to_str().unwrap().to_string();
+        let ingest_payload = IngestPayload {
+            target,
+            artifacts_path,
+            mappings: self.mappings.clone(),
+        };
+        let db = forker.load_cached_db(ingest_payload, block)?;
+
+        Ok(db)
+    }
+
+    /// Loads the target account's database information after the transaction is
+    /// executed.
+    #[tracing::instrument(skip(self, forker))]
+    pub fn load_after(&mut self, forker: &Forker, block: Option<u64>) -> anyhow::Result<()> {
+        let db = self.load(forker, block)?;
+
+        self.stages.after = Some(db);
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self, forker))]
+    /// Simulates the transaction on a fork of the network at `block`, or at
+    /// the forker's block. Any state the transaction reads is fetched as it
+    /// runs, so it can call any contract.
+    pub async fn simulate(&mut self, forker: &Forker, block: Option<u64>) -> anyhow::Result<()> {
+        let client = forker.client.clone().ok_or(anyhow::anyhow!("No client"))?;
+        let from_address = client.address();
+        let payload: TypedTransaction = self.payload.clone().try_into()?;
+
+        // Traces the transaction on a lazy fork of the network, which fetches
+        // whatever state the transaction reads.
+        let call = self.payload.fork_call(from_address)?;
+        let (mut traced, before) = trace_on_fork(forker, block, vec![call]).await?;
+        let TracedCall {
+            state_diff, trace, ..
+        } = traced.remove(0);
+        tracing::debug!("Simulated state diff:\n{}", state_diff);
+        tracing::debug!("Simulated trace:\n{}", trace);
+
+        // The before stage is everything the fork fetched, and the after stage
+        // is the same with the changes made by the transaction.
+        let mut db = before.clone();
+        apply_diff(&mut db, &state_diff)?;
+
+        self.stages.before = Some(before.clone());
+        self.stages.after = Some(db);
+        self.gas_estimate = state_diff
+            .success
+            .then(|| gas::with_margin(state_diff.gas_needed()));
+        self.state_diff = Some(state_diff);
+
+        // Keep the trace of a reverting transaction, so the reason can be shown
+        // to the user before they sign it.
+        if !trace.success {
+            let outcome = Outcome {
+                trace: Some(trace),
+                ..Default::default()
+            };
+            let reason = outcome.revert_reason().unwrap_or_default().to_string();
+            tracing::debug!("Simulated transaction reverted: {}", reason);
+            self.simulated_outcome = Some(outcome);
+            return Err(anyhow::anyhow!(
+                "Simulated transaction reverted: {}",
+                reason
+            ));
+        }
+
+        // Loads the before stage into Arbiter and gets an Arbiter client.
+        let environment = forker.load_env(before);
+        let client = RevmMiddleware::new_from_forked_eoa(&environment, from_address)?;
+
+        tracing::warn!(
+            "Balance of client with address 0x{:x} : {:?}",
+            from_address,
+            Coin::new(self.payload.target, client.clone())
+                .balance_of(from_address)
+                .call()
+                .await?
+        );
+
+        // Executes the transaction on the Arbiter client.
+        tracing::debug!("Sending simulation payload: {:?}", payload);
+        let tx = client
+            .clone()
+            .send_transaction(payload, None)
+            .await?
+            .await?;
+
+        tracing::debug!("Simulated transaction: {:?}", tx);
+        match tx {
+            Some(tx) => {
+                tracing::debug!("Simulated receipt: {:?}", tx.clone());
+                self.simulated_outcome = Some(Outcome {
+                    tx_hash: tx.clone().transaction_hash,
+                    receipt: tx.clone(),
+                    trace: Some(trace),
+                });
+            }
+            None => {
+                tracing::debug!("Simulated transaction failed");
+            }
+        }
+
+        // Stop the Arbiter instance.
+        environment.stop()?;
+
+        Ok(())
+    }
+
+    /// Sends the transaction without waiting for it to be mined, leaving
+    /// `manager` to track it until it is final.
+    #[tracing::instrument(skip(self, manager))]
+    pub async fn submit<M: Middleware + 'static>(
+        &mut self,
+        manager: &mut TransactionManager<M>,
+    ) -> anyhow::Result<H256> {
+        self.check_executable()?;
+        let hash = manager.send(self.transaction()?).await?;
+        self.submitted = Some(hash);
+        Ok(hash)
+    }
+
+    /// Checks the transaction was simulated without reverting and hasn't been
+    /// sent yet.
+    fn check_executable(&self) -> anyhow::Result<()> {
+        // Return if the transaction has already been executed.
+        if self.live_outcome.is_some() || self.submitted.is_some() {
+            return Err(anyhow::anyhow!("Transaction has already been executed."));
+        }
+
+        // Return if the transaction has not been simulated yet, or would revert.
+        match &self.simulated_outcome {
+            None => return Err(anyhow::anyhow!("Transaction has not been simulated yet.")),
+            Some(outcome) => {
+                if let Some(reason) = outcome.revert_reason() {
+                    return Err(anyhow::anyhow!(
+                        "Simulated transaction reverted: {}",
+                        reason
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self, forker))]
+    pub async fn execute(
+        &mut self,
+        forker: &Forker,
+        block: Option<u64>,
+    ) -> anyhow::Result<TransactionReceipt, anyhow::Error> {
+        self.check_executable()?;
+
+        let block: Option<BlockId> = block.map(|block| BlockId::Number(block.into()));
+
+        // Executes the transaction on the live client.
+        let payload = self.transaction()?;
+        let client = forker.client.clone().unwrap();
+        let tx = client.send_transaction(payload, block).await?.await?;
+        tracing::debug!("Executed transaction: {:?}", tx);
+
+        match tx {
+            Some(tx) => {
+                tracing::debug!("Executed receipt: {:?}", tx.clone());
+                self.live_outcome = Some(Outcome {
+                    tx_hash: tx.clone().transaction_hash,
+                    receipt: tx.clone(),
+                    trace: None,
+                });
+
+                Ok(tx.clone())
+            }
+            None => {
+                tracing::debug!("Executed transaction failed");
+                Err(anyhow::anyhow!("Executed transaction failed"))
+            }
+        }
+    }
+}
+
+/// A call to trace on a fork, with what is needed to decode it.
+pub(crate) struct ForkCall {
+    caller: Address,
+    target: Address,
+    data: Bytes,
+    value: U256,
+    layout: Option<StorageLayout>,
+    abi: Option<ethers::abi::Abi>,
+}
+
+/// Traces `calls` in order on a lazy fork of the network at `block`, or at the
+/// forker's block, each on the state left by the previous one. Tracing stops
+/// after the first call that reverts. Returns the traced calls and the state
+/// fetched from the network.
+pub(crate) async fn trace_on_fork(
+    forker: &Forker,
+    block: Option<u64>,
+    calls: Vec<ForkCall>,
+) -> anyhow::Result<(Vec<TracedCall>, CacheDB<EmptyDB>)> {
+    let client = forker.client.clone().ok_or(anyhow::anyhow!("No client"))?;
+    let provider = Arc::new(client.provider().clone());
+    let target = forker.fork_target().await?;
+    let chain_id = target.chain_id;
+    let block_number = block.unwrap_or(forker.block_number);
+    let cache_dir = forker.fork_cache_dir_for(&target);
+    let Some(fork_block) = provider.get_block(block_number).await? else {
+        anyhow::bail!("Block {} to fork at was not found", block_number);
+    };
+    let trace_block = TraceBlock::new(&fork_block, chain_id);
+
+    // Forks fetch state by blocking on the client, so trace on a thread where
+    // blocking doesn't stall the runtime.
+    tokio::task::spawn_blocking(move || {
+        let fork = LazyFork::new(provider, chain_id, block_number, cache_dir.as_deref())?;
+        let mut fork = Overlay::new(fork);
+        let mut traced = Vec::with_capacity(calls.len());
+        for call in calls {
+            let result = trace_call(
+                &mut fork,
+                &trace_block,
+                call.caller,
+                call.target,
+                call.data,
+                call.value,
+                call.layout.as_ref(),
+                call.abi.as_ref(),
+            )?;
+            fork.commit(&result.state);
+            let success = result.trace.success;
+            traced.push(result);
+            if !success {
+                break;
+            }
+        }
+
+        // Only what was fetched from the network is cached, not the changes.
+        let fork = fork.into_inner();
+        fork.save()?;
+        anyhow::Ok((traced, fork.cache().to_cache_db()))
+    })
+    .await
+    .map_err(|_| anyhow::anyhow!("Tracing the transaction panicked"))?
+}
+
+/// Applies the changes recorded in `state_diff` to `db`.
+pub(crate) fn apply_diff(db: &mut CacheDB<EmptyDB>, state_diff: &StateDiff) -> anyhow::Result<()> {
+    for account in &state_diff.accounts {
+        let address: revm::primitives::Address = account.address.to_fixed_bytes().into();
+        if let Some(info) = db
+            .accounts
+            .get(&address)
+            .map(|account| account.info.clone())
+        {
+            db.insert_account_info(
+                address,
+                AccountInfo {
+                    balance: StorageValue::from_limbs(account.balance_after.0),
+                    nonce: account.nonce_after,
+                    ..info
+                },
+            );
+        }
+        for slot in &account.slots {
+            db.insert_account_storage(
+                address,
+                StorageValue::from_limbs(slot.slot.0),
+                StorageValue::from_limbs(slot.after.0),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct UnsealedTransaction {
+    pub artifact: PathBuf,
+    pub target: Address,
+    pub value: Option<U256>,
+    /// Name or full signature of the function to call.
+    pub method: Option<String>,
+    /// Arguments as typed by the user, parsed against the artifact's ABI when
+    /// the transaction is built. See [`crate::arguments`] for the format.
+    pub arguments: Vec<String>,
+    pub from: Option<Address>,
+    /// Addresses that can be referred to by name in `arguments`.
+    pub contacts: HashMap<String, Address>,
+}
+
+impl TryFrom<UnsealedTransaction> for TypedTransaction {
+    type Error = anyhow::Error;
+
+    fn try_from(payload: UnsealedTransaction) -> anyhow::Result<Self, Self::Error> {
+        let req = Eip1559TransactionRequest {
+            from: payload.from,
+            to: Some(payload.target.into()),
+            value: payload.value,
+            gas: None,
+            max_priority_fee_per_gas: None,
+            max_fee_per_gas: None,
+            nonce: None,
+            chain_id: None,
+            access_list: vec![].into(),
+            data: Some(payload.calldata()?),
+        };
+
+        let tx = TypedTransaction::Eip1559(req);
+
+        Ok(tx)
+    }
+}
+
+#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ContractAbi {
+    pub abi: ethers::abi::Abi,
+}
+
+impl UnsealedTransaction {
+    /// Creates a new UnsealedTransaction that can be built into a Scroll.
+    pub fn new() -> Self {
+        Self {
+            ..Default::default()
+        }
+    }
+
+    pub fn target(mut self, target: Address) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn artifact(mut self, artifact: PathBuf) -> Self {
+        self.artifact = artifact;
+        self
+    }
+
+    /// Sets the value of the transaction.
+    pub fn value(mut self, value: U256) -> Self {
+        self.value = Some(value);
+        self
+    }
+
+    /// Sets the method of the transaction.
+    pub fn method(mut self, method: &str) -> Self {
+        self.method = Some(method.to_string());
+        self
+    }
+
+    /// Sets the arguments of the transaction.
+    pub fn arguments(mut self, arguments: Vec<String>) -> Self {
+        self.arguments = arguments;
+        self
+    }
+
+    /// Adds an argument to the transaction.
+    pub fn arg(mut self, arg: String) -> Self {
+        self.arguments.push(arg);
+        self
+    }
+
+    /// Sets the contacts that arguments can refer to by name.
+    pub fn contacts(mut self, contacts: HashMap<String, Address>) -> Self {
+        self.contacts = contacts;
+        self
+    }
+
+    /// Reads the ABI of the target from its artifact.
+    pub fn abi(&self) -> anyhow::Result<ethers::abi::Abi> {
+        let file = File::open(&self.artifact)
+            .map_err(|e| anyhow::anyhow!("Failed to open artifact {:?}: {}", self.artifact, e))?;
+        let contract_abi: ContractAbi = serde_json::from_reader(BufReader::new(file))?;
+        Ok(contract_abi.abi)
+    }
+
+    /// Encodes the call to `method` with `arguments`.
+    pub fn calldata(&self) -> anyhow::Result<Bytes> {
+        let Some(method) = &self.method else {
+            return Err(anyhow::anyhow!("No method specified in payload."));
+        };
+        encode_call(&self.abi()?, method, &self.arguments, &self.contacts)
+    }
+
+    /// The call to trace for this transaction, sent from `default_from` unless
+    /// it has its own sender. The target's storage is decoded with its layout
+    /// and the calls with its ABI, when the artifact has them.
+    pub(crate) fn fork_call(&self, default_from: Address) -> anyhow::Result<ForkCall> {
+        let layout = digest::digest_artifacts(self.artifact.to_str().unwrap())
+            .map(|artifacts| artifacts.storage_layout)
+            .map_err(|e| tracing::warn!("Could not read storage layout: {:?}", e))
+            .ok();
+        Ok(ForkCall {
+            caller: self.from.unwrap_or(default_from),
+            target: self.target,
+            data: self.calldata()?,
+            value: self.value.unwrap_or_default(),
+            layout,
+            abi: self.abi().ok(),
+        })
+    }
+
+    fn get_mappings(&self) -> HashMap<String, Vec<String>> {
+        let mut mappings = HashMap::new();
+
+        if self.method.is_none() {
+            return mappings;
+        }
+
+        // Any address or number in the arguments could key a mapping, as could
+        // the caller.
+        let mut address_keys = Vec::new();
+        let mut number_keys = Vec::new();
+        for arg in &self.arguments {
+            if let Some(address) = self.contacts.get(arg) {
+                address_keys.push(address_to_string(address));
+            } else if let Ok(address) = arg.parse::<Address>() {
+                address_keys.push(address_to_string(&address));
+            } else if let Ok(number) = U256::from_dec_str(arg) {
+                number_keys.push(format!("{:064x}", number));
+            }
+        }
+        if let Some(from) = self.from {
+            address_keys.push(address_to_string(&from));
+        }
+
+        let Some(Ok(artifacts)) = self.artifact.to_str().map(digest::digest_artifacts) else {
+            return mappings;
+        };
+        let layout = artifacts.storage_layout;
+
+        // Only one mapping deep is loaded into the database, so the keys are
+        // guessed for every mapping of the target to a value.
+        for item in &layout.storage {
+            let Some(StorageType::Mapping { key, value, .. }) = layout.types.get(&item.type_)
+            else {
+                continue;
+            };
+            if let Some(StorageType::Mapping { .. }) = layout.types.get(value) {
+                continue;
+            }
+            let keys = match layout.types.get(key) {
+                Some(StorageType::Simple { label, .. })
+                    if label.starts_with("address") || label.starts_with("contract") =>
+                {
+                    &address_keys
+                }
+                Some(StorageType::Simple { label, .. }) if label.starts_with("uint") => {
+                    &number_keys
+                }
+                _ => continue,
+            };
+            if !keys.is_empty() {
+                mappings.insert(item.label.clone(), keys.clone());
+            }
+        }
+
+        mappings
+    }
+
+    /// Builds the transaction into a Scroll.
+    pub fn seal(self) -> Scroll {
+        let mappings = self.get_mappings();
+        Scroll {
+            payload: self,
+            mappings,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arbiter_bindings::bindings::counter::Counter;
+    use ethers::{prelude::*, utils::Anvil};
+
+    use super::scroll::*;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_scroll() -> anyhow::Result<(), anyhow::Error> {
+        // Start anvil in the background.
+        let anvil = Anvil::default()
+            .arg("--gas-limit")
+            .arg("20000000")
+            .chain_id(31337_u64)
+            .spawn();
+
+        // Get the dev wallet.
+        let wallet: LocalWallet = anvil.keys().get(0).unwrap().clone().into();
+
+        // Create a forker.
+        let forker = Forker::connect(Some(anvil.endpoint()), Some(wallet)).await?;
+
+        // Get the block number.
+        let anvil_client = forker.client.clone().unwrap();
+        let block_number = anvil_client.get_block_number().await?;
+        tracing::info!("Block number: {:?}", block_number);
+        assert_eq!(block_number, 0.into());
+
+        // Deploy the counter contract.
+        let counter = Counter::deploy(anvil_client.clone(), ())?.send().await?;
+        let counter_address = counter.address();
+
+        // Build the unsealed "increment" transaction to get the Scroll.
+        let target = counter_address;
+        let value = U256::zero();
+        let method = "increment";
+        let arguments = vec![];
+        let mut scroll = UnsealedTransaction::new()
+            .target(target)
+            .artifact(get_counter_path().unwrap())
+            .value(value)
+            .method(method)
+            .arguments(arguments)
+            .seal();
+
+        let forker = forker.with_block_number(1_u64);
+
+        // Simulate the transaction.
+        scroll.simulate(&forker, Some(1_u64)).await?;
+
+        // Execute the transaction.
+        scroll.execute(&forker, None).await?;
+
+        // Get the current block.
+        let block_number = anvil_client.get_block_number().await?;
+
+        // Update the forker.
+        let forker = forker.with_block_number(block_number.as_u64());
+
+        // Load the after db.
+        scroll.load_after(&forker, None)?;
+
+        // Log both the before and after dbs and the outcomes!
+        tracing::debug!("Before: {:?}", scroll.stages.before);
+        tracing::debug!("After: {:?}", scroll.stages.after);
+        tracing::debug!("Simulated outcome: {:?}", scroll.simulated_outcome);
+
+        // it works!
+
+        Ok(())
+    }
+}
@@ -0,0 +1,180 @@
+//! Signers that can be swapped while the app is running.
+//!
+//! A `SignerMiddleware` is typed by its signer, so [`ExcaliburSigner`] wraps
+//! each kind of signer in one type, and a client can move between a local key,
+//! a Ledger and no key at all without changing type.
+
+use async_trait::async_trait;
+use ethers::{
+    signers::{LocalWallet, Signer, WalletError},
+    types::{
+        transaction::{eip2718::TypedTransaction, eip712::Eip712},
+        Address, Signature,
+    },
+};
+
+use crate::ledger::{types::LedgerClienError, LedgerClient};
+
+#[derive(Debug, Clone)]
+pub enum ExcaliburSigner {
+    /// A key held in memory.
+    Local(LocalWallet),
+    /// A key on a Ledger device, which confirms every signature.
+    Ledger(LedgerClient),
+    /// No key, for read only connections. Signing fails.
+    ReadOnly {
+        /// Chain id the client is connected to.
+        chain_id: u64,
+    },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExcaliburSignerError {
+    #[error(transparent)]
+    Local(#[from] WalletError),
+    #[error(transparent)]
+    Ledger(#[from] LedgerClienError),
+    #[error("No signer connected, the client is read only.")]
+    ReadOnly,
+}
+
+impl ExcaliburSigner {
+    /// Whether signatures need to be confirmed on a device.
+    pub fn is_hardware(&self) -> bool {
+        matches!(self, ExcaliburSigner::Ledger(_))
+    }
+
+    /// A signer that can't sign, to read from a network without a key.
+    pub fn read_only(chain_id: u64) -> Self {
+        ExcaliburSigner::ReadOnly { chain_id }
+    }
+}
+
+impl From<LocalWallet> for ExcaliburSigner {
+    fn from(wallet: LocalWallet) -> Self {
+        ExcaliburSigner::Local(wallet)
+    }
+}
+
+impl From<LedgerClient> for ExcaliburSigner {
+    fn from(ledger: LedgerClient) -> Self {
+        ExcaliburSigner::Ledger(ledger)
+    }
+}
+
+#[async_trait]
+impl Signer for ExcaliburSigner {
+    type Error = ExcaliburSignerError;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<Signature, Self::Error> {
+        match self {
+            ExcaliburSigner::Local(wallet) => Ok(wallet.sign_message(message).await?),
+            ExcaliburSigner::Ledger(ledger) => Ok(ledger.sign_message(message).await?),
+            ExcaliburSigner::ReadOnly { .. } => Err(ExcaliburSignerError::ReadOnly),
+        }
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, Self::Error> {
+        match self {
+            ExcaliburSigner::Local(wallet) => Ok(wallet.sign_transaction(tx).await?),
+            ExcaliburSigner::Ledger(ledger) => Ok(ledger.sign_transaction(tx).await?),
+            ExcaliburSigner::ReadOnly { .. } => Err(ExcaliburSignerError::ReadOnly),
+        }
+    }
+
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(
+        &self,
+        payload: &T,
+    ) -> Result<Signature, Self::Error> {
+        match self {
+            ExcaliburSigner::Local(wallet) => Ok(wallet.sign_typed_data(payload).await?),
+            ExcaliburSigner::Ledger(ledger) => Ok(ledger.sign_typed_data(payload).await?),
+            ExcaliburSigner::ReadOnly { .. } => Err(ExcaliburSignerError::ReadOnly),
+        }
+    }
+
+    fn address(&self) -> Address {
+        match self {
+            ExcaliburSigner::Local(wallet) => wallet.address(),
+            ExcaliburSigner::Ledger(ledger) => ledger.address(),
+            ExcaliburSigner::ReadOnly { .. } => Address::zero(),
+        }
+    }
+
+    fn chain_id(&self) -> u64 {
+        match self {
+            ExcaliburSigner::Local(wallet) => wallet.chain_id(),
+            ExcaliburSigner::Ledger(ledger) => ledger.chain_id(),
+            ExcaliburSigner::ReadOnly { chain_id } => *chain_id,
+        }
+    }
+
+    fn with_chain_id<T: Into<u64>>(self, chain_id: T) -> Self {
+        match self {
+            ExcaliburSigner::Local(wallet) => wallet.with_chain_id(chain_id).into(),
+            ExcaliburSigner::Ledger(ledger) => ledger.with_chain_id(chain_id).into(),
+            ExcaliburSigner::ReadOnly { .. } => ExcaliburSigner::read_only(chain_id.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ethers::types::TransactionRequest;
+
+    use super::*;
+    use crate::ledger::{transport::MockTransport, types::DerivationType};
+
+    const KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    #[tokio::test]
+    async fn swaps_signers() -> anyhow::Result<()> {
+        let wallet = KEY.parse::<LocalWallet>()?;
+        let local = ExcaliburSigner::from(wallet.clone()).with_chain_id(5_u64);
+        assert_eq!(local.address(), wallet.address());
+        assert_eq!(local.chain_id(), 5);
+        assert!(!local.is_hardware());
+
+        let tx: TypedTransaction = TransactionRequest::new().nonce(0).chain_id(5_u64).into();
+        let signature = local.sign_transaction(&tx).await?;
+        signature.verify(tx.sighash(), wallet.address())?;
+
+        // The same account on a device.
+        let mut response = vec![65];
+        response.extend([0x04; 65]);
+        response.push(40);
+        response.extend(format!("{:x}", wallet.address()).as_bytes());
+        response.extend([0x90, 0x00]);
+        let mut command = vec![0xe0, 0x02, 0x00, 0x00, 21, 5];
+        for index in [0x8000002c_u32, 0x8000003c, 0x80000000, 0, 0] {
+            command.extend(index.to_be_bytes());
+        }
+        let mock = Arc::new(MockTransport::new().expect(command, response));
+        let ledger =
+            LedgerClient::with_transport(mock.clone(), DerivationType::LedgerLive(0)).await?;
+
+        let hardware = ExcaliburSigner::from(ledger).with_chain_id(5_u64);
+        assert_eq!(hardware.address(), local.address());
+        assert_eq!(hardware.chain_id(), 5);
+        assert!(hardware.is_hardware());
+        assert!(mock.is_done());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn read_only_does_not_sign() {
+        let read_only = ExcaliburSigner::read_only(1).with_chain_id(5_u64);
+        assert_eq!(read_only.chain_id(), 5);
+
+        let tx: TypedTransaction = TransactionRequest::new().nonce(0).chain_id(5_u64).into();
+        assert!(matches!(
+            read_only.sign_transaction(&tx).await,
+            Err(ExcaliburSignerError::ReadOnly)
+        ));
+    }
+}
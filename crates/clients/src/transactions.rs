@@ -0,0 +1,694 @@
+//! Tracks sent transactions until they are final.
+//!
+//! The [`TransactionManager`] keeps every transaction it sends, or is asked to
+//! track, and polls the network for their status. A transaction is pending
+//! until it is mined, included until it has enough confirmations, and then
+//! confirmed. One that leaves the chain in a reorg goes back to waiting, one
+//! whose nonce is used by another transaction is replaced, and one that falls
+//! out of the mempool is dropped.
+//!
+//! A pending transaction can be sped up, by sending it again with higher fees,
+//! or cancelled, by sending a transfer of nothing to the sender at its nonce
+//! instead. Every change of status is broadcast as a [`TxEvent`], and the
+//! transactions are saved so they are still tracked after a restart.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use ethers::{
+    providers::Middleware,
+    types::{
+        transaction::eip2718::TypedTransaction, Address, BlockNumber, Eip1559TransactionRequest,
+        H256, U256,
+    },
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::gas::FeePreset;
+
+/// Blocks on top of its own for a transaction to be confirmed.
+pub const DEFAULT_CONFIRMATIONS: u64 = 3;
+
+/// How long a transaction can be missing from the mempool before it is
+/// considered dropped.
+pub const DEFAULT_DROP_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Replacements must raise fees by at least this much, in percent, for nodes
+/// to accept them.
+const REPLACEMENT_BUMP_PERCENT: u64 = 10;
+
+/// Gas of a plain transfer, which is what a cancellation is.
+const TRANSFER_GAS: u64 = 21_000;
+
+/// Capacity of the event channel. Subscribers that fall further behind miss
+/// events, but the manager still holds the latest status.
+const EVENT_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TxKind {
+    Send,
+    /// Sends an earlier transaction again with higher fees.
+    SpeedUp,
+    /// Sends nothing to the sender at the nonce of an earlier transaction.
+    Cancel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TxStatus {
+    /// Waiting to be mined.
+    Pending,
+    /// Mined, without enough confirmations yet.
+    Included {
+        block_number: u64,
+        block_hash: H256,
+        success: bool,
+    },
+    /// Mined with enough confirmations.
+    Confirmed {
+        block_number: u64,
+        block_hash: H256,
+        success: bool,
+    },
+    /// Was mined, but its block was reorged out. Waits to be mined again.
+    Reorged,
+    /// Another transaction was mined at its nonce, the one tracked as `by` if
+    /// it was sent from here.
+    Replaced { by: Option<H256> },
+    /// Disappeared from the mempool without being mined.
+    Dropped,
+}
+
+impl TxStatus {
+    /// Whether the status can't change anymore.
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            TxStatus::Confirmed { .. } | TxStatus::Replaced { .. } | TxStatus::Dropped
+        )
+    }
+
+    fn is_mined(&self) -> bool {
+        matches!(self, TxStatus::Included { .. } | TxStatus::Confirmed { .. })
+    }
+}
+
+impl std::fmt::Display for TxStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TxStatus::Pending => write!(f, "Pending"),
+            TxStatus::Included {
+                block_number,
+                success,
+                ..
+            } => write!(
+                f,
+                "Included in block {}{}",
+                block_number,
+                if *success { "" } else { ", reverted" }
+            ),
+            TxStatus::Confirmed {
+                block_number,
+                success,
+                ..
+            } => write!(
+                f,
+                "Confirmed in block {}{}",
+                block_number,
+                if *success { "" } else { ", reverted" }
+            ),
+            TxStatus::Reorged => write!(f, "Reorged out"),
+            TxStatus::Replaced { by: Some(by) } => write!(f, "Replaced by {:?}", by),
+            TxStatus::Replaced { by: None } => write!(f, "Replaced"),
+            TxStatus::Dropped => write!(f, "Dropped"),
+        }
+    }
+}
+
+/// A transaction sent to the network, and where it is at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackedTransaction {
+    pub hash: H256,
+    pub from: Address,
+    pub nonce: U256,
+    pub tx: TypedTransaction,
+    pub kind: TxKind,
+    /// Transaction this one is sent in place of, for speed-ups and cancels.
+    pub replaces: Option<H256>,
+    pub status: TxStatus,
+    /// Unix time the transaction was sent at.
+    pub submitted_at: u64,
+    /// Unix time the transaction was last seen in the mempool or a block.
+    pub last_seen_at: u64,
+}
+
+/// A change of status of a tracked transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxEvent {
+    pub hash: H256,
+    pub from: Address,
+    pub nonce: U256,
+    pub status: TxStatus,
+}
+
+pub struct TransactionManager<M> {
+    client: Arc<M>,
+    transactions: Vec<TrackedTransaction>,
+    events: broadcast::Sender<TxEvent>,
+    /// Where the transactions are saved, if they are persisted.
+    path: Option<PathBuf>,
+    confirmations: u64,
+    drop_timeout: Duration,
+}
+
+impl<M: Middleware + 'static> TransactionManager<M> {
+    pub fn new(client: Arc<M>) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        Self {
+            client,
+            transactions: Vec::new(),
+            events,
+            path: None,
+            confirmations: DEFAULT_CONFIRMATIONS,
+            drop_timeout: DEFAULT_DROP_TIMEOUT,
+        }
+    }
+
+    /// Persists the transactions at `path`, loading the ones saved there
+    /// before.
+    pub fn with_store(mut self, path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        self.transactions = load_transactions(&path)?;
+        self.path = Some(path);
+        Ok(self)
+    }
+
+    pub fn with_confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations.max(1);
+        self
+    }
+
+    pub fn with_drop_timeout(mut self, timeout: Duration) -> Self {
+        self.drop_timeout = timeout;
+        self
+    }
+
+    /// Receives every change of status from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<TxEvent> {
+        self.events.subscribe()
+    }
+
+    /// The sending half of the event channel, to subscribe from elsewhere.
+    pub fn events(&self) -> broadcast::Sender<TxEvent> {
+        self.events.clone()
+    }
+
+    pub fn transactions(&self) -> &[TrackedTransaction] {
+        &self.transactions
+    }
+
+    pub fn get(&self, hash: H256) -> Option<&TrackedTransaction> {
+        self.transactions.iter().find(|tx| tx.hash == hash)
+    }
+
+    /// Transactions that aren't final yet.
+    pub fn pending(&self) -> impl Iterator<Item = &TrackedTransaction> {
+        self.transactions.iter().filter(|tx| !tx.status.is_final())
+    }
+
+    /// Fills in and sends `tx`, and tracks it.
+    pub async fn send(&mut self, mut tx: TypedTransaction) -> anyhow::Result<H256> {
+        self.client
+            .fill_transaction(&mut tx, None)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to fill transaction: {}", e))?;
+        self.submit(tx, TxKind::Send, None).await
+    }
+
+    /// Tracks a transaction sent as `hash` without the manager. `tx` must have
+    /// its sender and nonce set.
+    pub fn track(&mut self, hash: H256, tx: TypedTransaction) -> anyhow::Result<()> {
+        let (Some(from), Some(nonce)) = (tx.from().copied(), tx.nonce().copied()) else {
+            anyhow::bail!("Tracked transactions need a sender and nonce");
+        };
+        let now = now();
+        self.insert(TrackedTransaction {
+            hash,
+            from,
+            nonce,
+            tx,
+            kind: TxKind::Send,
+            replaces: None,
+            status: TxStatus::Pending,
+            submitted_at: now,
+            last_seen_at: now,
+        })
+    }
+
+    /// Sends the pending transaction `hash` again with higher fees: those of
+    /// `fees` if given, and at least enough more than the current ones for
+    /// nodes to accept the replacement.
+    pub async fn speed_up(&mut self, hash: H256, fees: Option<FeePreset>) -> anyhow::Result<H256> {
+        let original = self.replaceable(hash)?;
+        let mut tx = original.tx.clone();
+        bump_fees(&original.tx, &mut tx, fees);
+        self.submit(tx, TxKind::SpeedUp, Some(hash)).await
+    }
+
+    /// Replaces the pending transaction `hash` with a transfer of nothing from
+    /// its sender to itself, at higher fees like [`Self::speed_up`].
+    pub async fn cancel(&mut self, hash: H256, fees: Option<FeePreset>) -> anyhow::Result<H256> {
+        let original = self.replaceable(hash)?;
+        let mut tx = TypedTransaction::Eip1559(Eip1559TransactionRequest {
+            from: Some(original.from),
+            to: Some(original.from.into()),
+            value: Some(U256::zero()),
+            gas: Some(TRANSFER_GAS.into()),
+            nonce: Some(original.nonce),
+            chain_id: original.tx.chain_id(),
+            ..Default::default()
+        });
+        bump_fees(&original.tx, &mut tx, fees);
+        self.submit(tx, TxKind::Cancel, Some(hash)).await
+    }
+
+    /// Checks the status of every transaction that isn't final, returning the
+    /// changes.
+    pub async fn poll(&mut self) -> anyhow::Result<Vec<TxEvent>> {
+        let latest = self
+            .client
+            .get_block_number()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to fetch block number: {}", e))?
+            .as_u64();
+
+        // A transaction that can't be checked keeps its status until the next
+        // poll, so the changes already broadcast are still saved.
+        let mut events = Vec::new();
+        for index in 0..self.transactions.len() {
+            if self.transactions[index].status.is_final() {
+                continue;
+            }
+            let status = match self.check(index, latest).await {
+                Ok(status) => status,
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to check transaction {:?}: {}",
+                        self.transactions[index].hash,
+                        e
+                    );
+                    continue;
+                }
+            };
+            if let Some(event) = self.set_status(index, status) {
+                events.push(event);
+            }
+        }
+
+        // Once a transaction is mined, the others at its nonce never will be.
+        for index in 0..self.transactions.len() {
+            let tx = &self.transactions[index];
+            if !tx.status.is_mined() {
+                continue;
+            }
+            let (hash, from, nonce) = (tx.hash, tx.from, tx.nonce);
+            for other in 0..self.transactions.len() {
+                let sibling = &self.transactions[other];
+                if other != index
+                    && sibling.from == from
+                    && sibling.nonce == nonce
+                    && !sibling.status.is_final()
+                    && !sibling.status.is_mined()
+                {
+                    let status = TxStatus::Replaced { by: Some(hash) };
+                    if let Some(event) = self.set_status(other, status) {
+                        events.push(event);
+                    }
+                }
+            }
+        }
+
+        if !events.is_empty() {
+            self.save()?;
+        }
+        Ok(events)
+    }
+
+    /// Saves the transactions, if they are persisted.
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(path) = &self.path {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, serde_json::to_string_pretty(&self.transactions)?)?;
+        }
+        Ok(())
+    }
+
+    /// Works out the status of the transaction at `index`, given the latest
+    /// block.
+    async fn check(&mut self, index: usize, latest: u64) -> anyhow::Result<TxStatus> {
+        let tx = &self.transactions[index];
+        let (hash, from, nonce, status) = (tx.hash, tx.from, tx.nonce, tx.status);
+
+        let receipt = self
+            .client
+            .get_transaction_receipt(hash)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to fetch receipt of {:?}: {}", hash, e))?;
+        if let Some(receipt) = receipt {
+            if let (Some(block_number), Some(block_hash)) =
+                (receipt.block_number, receipt.block_hash)
+            {
+                self.transactions[index].last_seen_at = now();
+                let block_number = block_number.as_u64();
+                let success = receipt.status == Some(1.into());
+                let confirmations = latest.saturating_sub(block_number) + 1;
+                return Ok(if confirmations >= self.confirmations {
+                    TxStatus::Confirmed {
+                        block_number,
+                        block_hash,
+                        success,
+                    }
+                } else {
+                    TxStatus::Included {
+                        block_number,
+                        block_hash,
+                        success,
+                    }
+                });
+            }
+        }
+
+        // Not in a block anymore, so its block was reorged out.
+        if status.is_mined() {
+            return Ok(TxStatus::Reorged);
+        }
+
+        // Another transaction took the nonce. A tracked one that was mined is
+        // picked up by the sibling check, which names it as the replacement.
+        // Otherwise an untracked transaction took it.
+        let mined_nonce = self
+            .client
+            .get_transaction_count(from, Some(BlockNumber::Latest.into()))
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to fetch nonce of {:?}: {}", from, e))?;
+        if mined_nonce > nonce {
+            let siblings = self
+                .transactions
+                .iter()
+                .filter(|other| other.hash != hash && other.from == from && other.nonce == nonce)
+                .map(|other| other.hash)
+                .collect::<Vec<_>>();
+            for sibling in siblings {
+                let receipt = self
+                    .client
+                    .get_transaction_receipt(sibling)
+                    .await
+                    .map_err(|e| {
+                        anyhow::anyhow!("Failed to fetch receipt of {:?}: {}", sibling, e)
+                    })?;
+                if receipt.is_some_and(|receipt| receipt.block_number.is_some()) {
+                    return Ok(status);
+                }
+            }
+            return Ok(TxStatus::Replaced { by: None });
+        }
+
+        let in_mempool = self
+            .client
+            .get_transaction(hash)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to fetch transaction {:?}: {}", hash, e))?
+            .is_some();
+        let tx = &mut self.transactions[index];
+        if in_mempool {
+            tx.last_seen_at = now();
+            return Ok(status);
+        }
+        if now().saturating_sub(tx.last_seen_at) >= self.drop_timeout.as_secs() {
+            return Ok(TxStatus::Dropped);
+        }
+        Ok(status)
+    }
+
+    /// Sets the status of the transaction at `index`, broadcasting it if it
+    /// changed.
+    fn set_status(&mut self, index: usize, status: TxStatus) -> Option<TxEvent> {
+        let tx = &mut self.transactions[index];
+        if tx.status == status {
+            return None;
+        }
+        tracing::debug!("Transaction {:?}: {}", tx.hash, status);
+        tx.status = status;
+        let event = TxEvent {
+            hash: tx.hash,
+            from: tx.from,
+            nonce: tx.nonce,
+            status,
+        };
+        // Nobody may be listening, which is fine.
+        let _ = self.events.send(event.clone());
+        Some(event)
+    }
+
+    /// The transaction `hash`, if it can still be replaced.
+    fn replaceable(&self, hash: H256) -> anyhow::Result<TrackedTransaction> {
+        let tx = self
+            .get(hash)
+            .ok_or_else(|| anyhow::anyhow!("Transaction {:?} is not tracked", hash))?;
+        match tx.status {
+            TxStatus::Pending | TxStatus::Reorged => Ok(tx.clone()),
+            status => Err(anyhow::anyhow!(
+                "Transaction {:?} can't be replaced: {}",
+                hash,
+                status
+            )),
+        }
+    }
+
+    async fn submit(
+        &mut self,
+        tx: TypedTransaction,
+        kind: TxKind,
+        replaces: Option<H256>,
+    ) -> anyhow::Result<H256> {
+        let from = tx
+            .from()
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("Transaction has no sender"))?;
+        let nonce = tx
+            .nonce()
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("Transaction has no nonce"))?;
+
+        let hash = self
+            .client
+            .send_transaction(tx.clone(), None)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to send transaction: {}", e))?
+            .tx_hash();
+        tracing::debug!("Sent transaction {:?} ({:?})", hash, kind);
+
+        let now = now();
+        self.insert(TrackedTransaction {
+            hash,
+            from,
+            nonce,
+            tx,
+            kind,
+            replaces,
+            status: TxStatus::Pending,
+            submitted_at: now,
+            last_seen_at: now,
+        })?;
+        Ok(hash)
+    }
+
+    fn insert(&mut self, tx: TrackedTransaction) -> anyhow::Result<()> {
+        let event = TxEvent {
+            hash: tx.hash,
+            from: tx.from,
+            nonce: tx.nonce,
+            status: tx.status,
+        };
+        self.transactions.retain(|other| other.hash != tx.hash);
+        self.transactions.push(tx);
+        let _ = self.events.send(event);
+        self.save()
+    }
+}
+
+/// Reads the transactions a manager saved at `path`, or none if it hasn't
+/// saved any.
+pub fn load_transactions(path: impl AsRef<Path>) -> anyhow::Result<Vec<TrackedTransaction>> {
+    let path = path.as_ref();
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let data = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&data)?)
+}
+
+/// Sets the fees of `tx`, which replaces `original`, to those of `fees` but at
+/// least enough above the fees of `original` for the replacement to be
+/// accepted.
+fn bump_fees(original: &TypedTransaction, tx: &mut TypedTransaction, fees: Option<FeePreset>) {
+    let bump = |fee: Option<U256>| {
+        fee.map(|fee| fee * (100 + REPLACEMENT_BUMP_PERCENT) / 100 + 1)
+            .unwrap_or_default()
+    };
+    let (max_fee, priority_fee) = match original {
+        TypedTransaction::Eip1559(original) => (
+            bump(original.max_fee_per_gas),
+            bump(original.max_priority_fee_per_gas),
+        ),
+        original => {
+            let gas_price = bump(original.gas_price());
+            (gas_price, gas_price)
+        }
+    };
+    let fees = fees.unwrap_or_default();
+    FeePreset {
+        speed: fees.speed,
+        max_fee_per_gas: fees.max_fee_per_gas.max(max_fee),
+        max_priority_fee_per_gas: fees.max_priority_fee_per_gas.max(priority_fee),
+    }
+    .apply(tx);
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::{
+        prelude::*,
+        utils::{parse_ether, Anvil},
+    };
+
+    use super::*;
+
+    #[test]
+    fn bumps_replacement_fees() {
+        let original = TypedTransaction::Eip1559(
+            Eip1559TransactionRequest::new()
+                .max_fee_per_gas(100)
+                .max_priority_fee_per_gas(10),
+        );
+        let mut tx = original.clone();
+        bump_fees(&original, &mut tx, None);
+        let TypedTransaction::Eip1559(tx) = tx else {
+            unreachable!()
+        };
+        assert_eq!(tx.max_fee_per_gas, Some(111.into()));
+        assert_eq!(tx.max_priority_fee_per_gas, Some(12.into()));
+
+        // Higher suggested fees are kept as they are.
+        let mut tx = original.clone();
+        let fees = FeePreset {
+            max_fee_per_gas: 500.into(),
+            max_priority_fee_per_gas: 50.into(),
+            ..Default::default()
+        };
+        bump_fees(&original, &mut tx, Some(fees));
+        let TypedTransaction::Eip1559(tx) = tx else {
+            unreachable!()
+        };
+        assert_eq!(tx.max_fee_per_gas, Some(500.into()));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_speed_up_and_cancel() -> anyhow::Result<()> {
+        // Transactions stay in the mempool until a block is mined by hand.
+        let anvil = Anvil::default()
+            .arg("--no-mining")
+            .chain_id(31337_u64)
+            .spawn();
+        let wallet: LocalWallet = anvil.keys()[0].clone().into();
+        let wallet = wallet.with_chain_id(anvil.chain_id());
+        let provider = Provider::<Http>::try_from(anvil.endpoint())?;
+        let client = Arc::new(SignerMiddleware::new(provider.clone(), wallet));
+        let recipient = anvil.addresses()[1];
+
+        let dir = std::env::temp_dir().join("excalibur_transactions_test");
+        let store = dir.join("transactions.json");
+        let _ = fs::remove_file(&store);
+        let mut manager = TransactionManager::new(client.clone())
+            .with_store(&store)?
+            .with_confirmations(1);
+        let mut events = manager.subscribe();
+
+        let transfer = TypedTransaction::Eip1559(
+            Eip1559TransactionRequest::new()
+                .to(recipient)
+                .value(parse_ether(1)?),
+        );
+        let first = manager.send(transfer.clone()).await?;
+        assert_eq!(events.recv().await?.status, TxStatus::Pending);
+        assert!(manager.poll().await?.is_empty());
+
+        // Speeding up replaces the original once mined.
+        let faster = manager.speed_up(first, None).await?;
+        provider.request::<_, U256>("evm_mine", ()).await?;
+        manager.poll().await?;
+        assert!(matches!(
+            manager.get(faster).unwrap().status,
+            TxStatus::Confirmed { success: true, .. }
+        ));
+        assert_eq!(
+            manager.get(first).unwrap().status,
+            TxStatus::Replaced { by: Some(faster) }
+        );
+
+        // Cancelling sends nothing to the sender at the same nonce.
+        let second = manager.send(transfer.clone()).await?;
+        let cancel = manager.cancel(second, None).await?;
+        provider.request::<_, U256>("evm_mine", ()).await?;
+        manager.poll().await?;
+        let cancelled = manager.get(cancel).unwrap();
+        assert_eq!(cancelled.kind, TxKind::Cancel);
+        assert_eq!(cancelled.tx.to_addr(), Some(&client.address()));
+        assert!(cancelled.status.is_final());
+        assert!(matches!(
+            manager.get(second).unwrap().status,
+            TxStatus::Replaced { .. }
+        ));
+
+        // A transaction sent without the manager at the same nonce replaces
+        // both the original and its speed-up.
+        let third = manager.send(transfer).await?;
+        let third_faster = manager.speed_up(third, None).await?;
+        let elsewhere = TypedTransaction::Eip1559(
+            Eip1559TransactionRequest::new()
+                .to(recipient)
+                .nonce(manager.get(third).unwrap().nonce)
+                .max_fee_per_gas(1_000_000_000_000_u64)
+                .max_priority_fee_per_gas(1_000_000_000_000_u64),
+        );
+        client.send_transaction(elsewhere, None).await?;
+        provider.request::<_, U256>("evm_mine", ()).await?;
+        manager.poll().await?;
+        for hash in [third, third_faster] {
+            assert_eq!(
+                manager.get(hash).unwrap().status,
+                TxStatus::Replaced { by: None }
+            );
+        }
+
+        // The transactions are still tracked after a restart.
+        let restored = TransactionManager::new(client).with_store(&store)?;
+        assert_eq!(restored.transactions(), manager.transactions());
+        fs::remove_dir_all(dir)?;
+
+        Ok(())
+    }
+}
@@ -3,7 +3,8 @@ pub mod portfolio_management_agents;
 
 use base_agents::{block_admin::*, price_changer::*, token_admin::*};
 use portfolio_management_agents::{
-    base::parameter_manager::*, g3m::g3m_liquidity_provider::*, lognormal::ln_liquidity_provider::*,
+    base::parameter_manager::*, g3m::g3m_liquidity_provider::*,
+    lognormal::ln_liquidity_provider::*, v3::v3_reference::*,
 };
 
 use super::*;
@@ -16,6 +17,7 @@ pub enum AgentParameters<P: Parameterized> {
     TokenAdmin(TokenAdminParameters),
     PriceChanger(PriceChangerParameters<P>),
     ParameterManager(ParameterManagerParameters<P>),
+    V3Reference(V3ReferenceParameters<P>),
 }
 
 impl From<AgentParameters<Multiple>> for Vec<AgentParameters<Single>> {
@@ -50,6 +52,13 @@ impl From<AgentParameters<Multiple>> for Vec<AgentParameters<Single>> {
                     .map(AgentParameters::PriceChanger)
                     .collect()
             }
+            AgentParameters::V3Reference(parameters) => {
+                let parameters: Vec<V3ReferenceParameters<Single>> = parameters.into();
+                parameters
+                    .into_iter()
+                    .map(AgentParameters::V3Reference)
+                    .collect()
+            }
             AgentParameters::BlockAdmin(parameters) => {
                 vec![AgentParameters::BlockAdmin(parameters)]
             }
@@ -46,8 +46,9 @@ impl Arbitrageur {
         let liquid_exchange = LiquidExchange::new(liquid_exchange_address, client.clone());
 
         let solver = match kind {
-            PoolKind::G3M => protocol_client.g_solver.address(),
+            PoolKind::G3M => protocol_client.g_solver()?.address(),
             PoolKind::LogNormal => protocol_client.ln_solver.address(),
+            PoolKind::StableSwap => bail!("No solver available for StableSwap pools"),
         };
 
         // Deploy the arbitrageur's atomic contract to atomically swap between
@@ -1,7 +1,8 @@
 use std::sync::Arc;
 
 use arbiter_bindings::bindings::liquid_exchange::LiquidExchange;
-use clients::protocol::{PoolParams, ProtocolClient};
+use cfmm_math::trading_functions::tau::TauMode;
+use clients::protocol::{pool::PoolKind, PoolParams, ProtocolClient};
 use ethers::types::Address;
 use itertools::iproduct;
 
@@ -38,6 +39,8 @@ pub struct ParameterManager {
     pub sensitivity: f64,
     pub data: PositionData,
     pub pool_id: U256,
+    /// Rolls `tau` of a LogNormal pool towards expiry if set.
+    pub tau_mode: Option<TauMode>,
 }
 
 #[async_trait::async_trait]
@@ -48,6 +51,9 @@ impl Agent for ParameterManager {
         let portfolio_price = self.get_portfolio_price().await?;
         if time >= self.next_update_time {
             self.next_update_time = time + self.update_frequency;
+            if let Some(tau_mode) = self.tau_mode {
+                self.roll_tau(tau_mode, time, self.next_update_time).await?;
+            }
             self.update_position_data(portfolio_price, asset_price, time);
             self.calculate_rv()?;
             self.execute_smooth_rebalance().await?;
@@ -80,6 +86,14 @@ impl ParameterManager {
 
         if let Some(AgentParameters::ParameterManager(params)) = config.agent_parameters.get(&label)
         {
+            let tau_mode = match params.tau_decay {
+                Some(tau_decay) => Some(TauMode::Expiry {
+                    initial_tau: tau_decay.initial_tau,
+                    start: client.get_block_timestamp().await?.as_u64(),
+                    duration: tau_decay.duration,
+                }),
+                None => None,
+            };
             match params.specialty {
                 Specialty::VolatilityTargeting(parameters) => Ok(Self {
                     client,
@@ -92,6 +106,7 @@ impl ParameterManager {
                     max_change: parameters.max_change.0,
                     data: PositionData::new()?,
                     pool_id,
+                    tau_mode,
                 }),
             }
         } else {
@@ -116,6 +131,25 @@ impl ParameterManager {
         Ok(rx * self.get_asset_price().await? + ry)
     }
 
+    /// Moves `tau` towards where it should be at the next update, so the curve
+    /// decays with the block timestamp. Once the position reaches expiry, `tau`
+    /// is reset and the position rolls into the next period.
+    async fn roll_tau(&self, tau_mode: TauMode, time: u64, target_time: u64) -> Result<()> {
+        let pool = self.protocol_client.get_pool(self.pool_id).await?;
+        if !matches!(pool.kind, PoolKind::LogNormal) {
+            return Ok(());
+        }
+
+        let target_tau = tau_mode.tau_at(target_time);
+        if tau_mode.expired_between(time, target_time) {
+            tracing::info!("Pool expired, resetting tau to: {:?}", target_tau);
+        }
+        self.protocol_client
+            .set_tau(self.pool_id, target_tau, target_time)
+            .await?;
+        Ok(())
+    }
+
     fn update_position_data(&mut self, portfolio_price: f64, asset_price: f64, timestamp: u64) {
         if self.data.portfolio_prices.is_empty() {
             self.data.portfolio_prices.push((portfolio_price, 0));
@@ -177,6 +211,9 @@ impl ParameterManager {
                     .await?;
                 Ok(())
             }
+            PoolParams::StableSwap(_) => {
+                bail!("Volatility targeting is not supported for StableSwap pools")
+            }
         }
     }
 
@@ -215,6 +252,17 @@ impl ParameterManager {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ParameterManagerParameters<P: Parameterized> {
     pub specialty: Specialty<P>,
+    /// Decays `tau` with the block timestamp instead of keeping it fixed.
+    #[serde(default)]
+    pub tau_decay: Option<TauDecayParameters>,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct TauDecayParameters {
+    /// The value `tau` is reset to at the start of each period.
+    pub initial_tau: f64,
+    /// The length of a period in seconds, after which the position expires.
+    pub duration: u64,
 }
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
@@ -226,7 +274,10 @@ impl From<ParameterManagerParameters<Multiple>> for Vec<ParameterManagerParamete
     fn from(item: ParameterManagerParameters<Multiple>) -> Self {
         let specialties: Vec<Specialty<Single>> = item.specialty.into();
         iproduct!(specialties)
-            .map(|specialty| ParameterManagerParameters { specialty })
+            .map(|specialty| ParameterManagerParameters {
+                specialty,
+                tau_decay: item.tau_decay,
+            })
             .collect()
     }
 }
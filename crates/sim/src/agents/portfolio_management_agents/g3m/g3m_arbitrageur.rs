@@ -60,6 +60,7 @@ impl G3mArbitrageur {
         let (wx, wy, swap_fee) = match pool_params {
             PoolParams::G3M(g3m_params) => (g3m_params.w_x, g3m_params.w_y, g3m_params.swap_fee),
             PoolParams::LogNormal(_) => bail!("Failed to parse G3M params, received LogNormal"),
+            PoolParams::StableSwap(_) => bail!("Failed to parse G3M params, received StableSwap"),
         };
 
         let (wx, wy, swap_fee) = (
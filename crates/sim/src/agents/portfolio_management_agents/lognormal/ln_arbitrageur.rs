@@ -62,6 +62,9 @@ impl LnArbitrageur {
                 ln_params.swap_fee,
             ),
             PoolParams::G3M(_) => bail!("Failed to parse LogNormal params, received G3M"),
+            PoolParams::StableSwap(_) => {
+                bail!("Failed to parse LogNormal params, received StableSwap")
+            }
         };
 
         info!("strike: {:?}", format_ether(strike));
@@ -97,18 +100,31 @@ impl LnArbitrageur {
         })
     }
 
+    /// Computes `sigma * sqrt(tau)` in WAD units, so the arbitrage math follows
+    /// the curve as `tau` decays towards expiry.
+    pub async fn get_sigma_sqrt_tau(&self, sigma: I256, tau: I256) -> Result<I256> {
+        let sqrt_tau = self
+            .0
+            .atomic_arbitrage
+            .sqrt(tau.into_raw() * WAD)
+            .call()
+            .await?;
+        Ok(sigma * I256::from_raw(sqrt_tau) / I256::from_raw(WAD))
+    }
+
     pub async fn get_dx(&self) -> Result<I256> {
         let ArbInputs {
             i_wad,
             target_price_wad,
             strike,
             sigma,
-            tau: _,
+            tau,
             gamma,
             rx,
             ry: _,
             liq,
         } = self.get_arb_inputs().await?;
+        let sigma_sqrt_tau = self.get_sigma_sqrt_tau(sigma, tau).await?;
 
         let log_p = self
             .0
@@ -116,7 +132,7 @@ impl LnArbitrageur {
             .log(target_price_wad * i_wad / strike)
             .call()
             .await?;
-        let inner_p = log_p * i_wad / sigma + (sigma / 2);
+        let inner_p = log_p * i_wad / sigma_sqrt_tau + (sigma_sqrt_tau / 2);
         let cdf_p = self.0.atomic_arbitrage.cdf(inner_p).call().await?;
         let delta = liq * (i_wad - cdf_p) / i_wad;
         let dx = (delta - rx) * i_wad * i_wad
@@ -132,12 +148,13 @@ impl LnArbitrageur {
             target_price_wad,
             strike,
             sigma,
-            tau: _,
+            tau,
             gamma,
             rx: _,
             ry,
             liq,
         } = self.get_arb_inputs().await?;
+        let sigma_sqrt_tau = self.get_sigma_sqrt_tau(sigma, tau).await?;
 
         let log_p = self
             .0
@@ -145,7 +162,7 @@ impl LnArbitrageur {
             .log(target_price_wad * i_wad / strike)
             .call()
             .await?;
-        let inner_p = log_p * i_wad / sigma - (sigma / 2);
+        let inner_p = log_p * i_wad / sigma_sqrt_tau - (sigma_sqrt_tau / 2);
         let cdf_p = self.0.atomic_arbitrage.cdf(inner_p).call().await?;
         let delta = (liq * strike) / i_wad * (cdf_p) / i_wad;
         let dy = (delta - ry) * i_wad * i_wad
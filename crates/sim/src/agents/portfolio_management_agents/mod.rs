@@ -2,4 +2,5 @@ use super::*;
 pub mod base;
 pub mod g3m;
 pub mod lognormal;
+pub mod v3;
 use bindings;
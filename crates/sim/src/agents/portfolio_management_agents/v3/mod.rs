@@ -0,0 +1,3 @@
+use super::*;
+
+pub mod v3_reference;
@@ -0,0 +1,154 @@
+use std::{fs, path::Path, sync::Arc};
+
+use arbiter_bindings::bindings::liquid_exchange::LiquidExchange;
+use cfmm_math::trading_functions::concentrated_liquidity::{
+    compute_liquidity_given_x, RangePosition,
+};
+use ethers::types::Address;
+
+use super::{agent::*, *};
+
+/// Value of the reference position at one step of the simulation.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct V3ReferenceRecord {
+    pub timestamp: u64,
+    pub asset_price: f64,
+    pub position_value: f64,
+    pub fees_x: f64,
+    pub fees_y: f64,
+}
+
+/// Tracks a Uniswap v3 range position along the `LiquidExchange` price
+/// trajectory, as a benchmark for the DFMM pools of the same run. The position
+/// only exists off-chain and is assumed to be arbitraged to the exchange price
+/// every step.
+#[derive(Debug, Clone)]
+pub struct V3Reference {
+    pub client: Arc<RevmMiddleware>,
+    pub lex: LiquidExchange<RevmMiddleware>,
+    pub position: RangePosition,
+    pub records: Vec<V3ReferenceRecord>,
+    output_path: String,
+}
+
+#[async_trait::async_trait]
+impl Agent for V3Reference {
+    async fn init(&mut self) -> Result<()> {
+        self.record().await
+    }
+
+    async fn step(&mut self) -> Result<()> {
+        let price = parse_ether_to_f64(self.lex.price().call().await?)?;
+        self.position.move_to_price(price);
+        self.record().await
+    }
+
+    async fn exit(&mut self) -> Result<()> {
+        if let Some(parent) = Path::new(&self.output_path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.output_path, serde_json::to_string(&self.records)?)?;
+        Ok(())
+    }
+
+    fn client(&self) -> Arc<RevmMiddleware> {
+        self.client.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl V3Reference {
+    pub async fn new(
+        environment: &Environment,
+        config: &SimulationConfig<Single>,
+        label: impl Into<String>,
+        liquid_exchange_address: Address,
+    ) -> Result<Self> {
+        let label: String = label.into();
+        let client = RevmMiddleware::new(environment, Some(&label))?;
+        let lex = LiquidExchange::new(liquid_exchange_address, client.clone());
+
+        if let Some(AgentParameters::V3Reference(params)) = config.agent_parameters.get(&label) {
+            let price = params.initial_price.0;
+            let (lower, upper) = (params.lower_price.0, params.upper_price.0);
+            if lower >= upper || price >= upper {
+                bail!("`V3Reference` must start below the upper price of its range");
+            }
+
+            // Deposit the same amount of `x` as the DFMM liquidity providers,
+            // with whatever amount of `y` the range requires.
+            let liquidity =
+                compute_liquidity_given_x(params.initial_x_amount.0, price, lower, upper);
+            let position = RangePosition {
+                lower,
+                upper,
+                liquidity,
+                swap_fee: params.swap_fee.0,
+                price,
+                fees_x: 0.0,
+                fees_y: 0.0,
+            };
+
+            let output_path = format!(
+                "{}/{}_v3_reference.json",
+                config.output_directory,
+                config.output_file_name.clone().unwrap_or_default()
+            );
+
+            Ok(Self {
+                client,
+                lex,
+                position,
+                records: Vec::new(),
+                output_path,
+            })
+        } else {
+            bail!("No parameters found for `V3Reference`")
+        }
+    }
+
+    async fn record(&mut self) -> Result<()> {
+        let timestamp = self.client.get_block_timestamp().await?.as_u64();
+        let price = self.position.price;
+        self.records.push(V3ReferenceRecord {
+            timestamp,
+            asset_price: price,
+            position_value: self.position.value_at(price),
+            fees_x: self.position.fees_x,
+            fees_y: self.position.fees_y,
+        });
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct V3ReferenceParameters<P: Parameterized> {
+    pub initial_x_amount: P,
+    pub initial_price: P,
+    pub lower_price: P,
+    pub upper_price: P,
+    pub swap_fee: P,
+}
+
+impl From<V3ReferenceParameters<Multiple>> for Vec<V3ReferenceParameters<Single>> {
+    fn from(params: V3ReferenceParameters<Multiple>) -> Self {
+        itertools::iproduct!(
+            params.initial_x_amount.parameters(),
+            params.initial_price.parameters(),
+            params.lower_price.parameters(),
+            params.upper_price.parameters(),
+            params.swap_fee.parameters()
+        )
+        .map(|(ixa, ip, lp, up, fee)| V3ReferenceParameters {
+            initial_x_amount: Single(ixa),
+            initial_price: Single(ip),
+            lower_price: Single(lp),
+            upper_price: Single(up),
+            swap_fee: Single(fee),
+        })
+        .collect()
+    }
+}
@@ -130,12 +130,13 @@ impl From<SnapshotDB> for CacheDB<EmptyDB> {
             .accounts
             .iter()
             .map(|(k, v)| {
-                let db_account: DbAccount = v.clone().into();
+                let mut db_account: DbAccount = v.clone().into();
+                // Contracts lose their state without their storage.
+                if let Some(storage) = snapshot.storage.get(k) {
+                    db_account.storage = storage.clone();
+                }
 
-                (
-                    revm_primitives::Address::from(k.into_array()),
-                    db_account.clone(),
-                )
+                (revm_primitives::Address::from(k.into_array()), db_account)
             })
             .collect();
 
@@ -477,4 +478,27 @@ mod tests {
         assert_eq!(instance.config.agent_parameters.len(), 1);
         assert!(account.is_some());
     }
+
+    #[test]
+    fn test_snapshot_keeps_storage() {
+        let address = revm_primitives::Address::repeat_byte(1);
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_info(
+            address,
+            AccountInfo {
+                nonce: 1,
+                ..Default::default()
+            },
+        );
+        db.insert_account_storage(address, U256::from(2), U256::from(3))
+            .unwrap();
+
+        let snapshot = SnapshotDB::new(&db);
+        let serialized = serde_json::to_string(&snapshot).unwrap();
+        let restored = CacheDB::from(serde_json::from_str::<SnapshotDB>(&serialized).unwrap());
+
+        let account = restored.accounts.get(&address).unwrap();
+        assert_eq!(account.info.nonce, 1);
+        assert_eq!(account.storage.get(&U256::from(2)), Some(&U256::from(3)));
+    }
 }
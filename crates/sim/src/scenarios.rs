@@ -2,7 +2,9 @@ use arbiter_core::data_collection::EventLogger;
 use clients::protocol::ProtocolClient;
 use revm::db::{CacheDB, EmptyDB};
 
-use self::agents::portfolio_management_agents::{g3m::g3m_setup, lognormal::ln_setup};
+use self::agents::portfolio_management_agents::{
+    g3m::g3m_setup, lognormal::ln_setup, v3::v3_reference::V3Reference,
+};
 use super::*;
 use crate::{
     agent::Agents,
@@ -71,6 +73,13 @@ impl Scenario for DFMMScenario {
         let lex_events = price_changer.liquid_exchange.events();
         agents.add(price_changer);
 
+        // The v3 benchmark is optional, and steps right after the price changer
+        // so it sees every new price.
+        if config.agent_parameters.contains_key("v3_reference") {
+            let v3_reference = V3Reference::new(&environment, &config, "v3_reference", lex).await?;
+            agents.add(v3_reference);
+        }
+
         let base_client = RevmMiddleware::new(&environment, "base".into()).unwrap();
         let base_protocol_client = ProtocolClient::new(
             base_client.clone(),